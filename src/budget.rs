@@ -0,0 +1,561 @@
+//! Budget guardrails for the monitor proxy
+//!
+//! Limits are read from `~/.config/claudev/budget.json` and checked before
+//! each API request is forwarded. Spend is recorded per day and per session
+//! in `~/.claudev/budget_state.json` so limits survive proxy restarts.
+//!
+//! ```json
+//! {
+//!   "allowed_models": ["sonnet", "haiku"],
+//!   "limits": [
+//!     { "period": "daily", "max_cost_usd": 20.0, "action": "block" },
+//!     { "period": "session", "max_tokens": 2000000, "action": "header" }
+//!   ]
+//! }
+//! ```
+
+use crate::traffic::{ApiRequest, Usage};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Days of spend history kept in the state file
+const KEEP_DAYS: i64 = 35;
+/// Sessions kept in the state file (most recently active first)
+const KEEP_SESSIONS: usize = 200;
+/// Warn once spend crosses this share of a limit
+const DEFAULT_WARN_RATIO: f64 = 0.8;
+
+/// Window a limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetPeriod {
+    Daily,
+    Weekly,
+    Session,
+}
+
+impl BudgetPeriod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BudgetPeriod::Daily => "daily",
+            BudgetPeriod::Weekly => "weekly",
+            BudgetPeriod::Session => "session",
+        }
+    }
+}
+
+/// What the proxy does when a limit is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Emit a warning event only
+    #[default]
+    Warn,
+    /// Warn and add an `x-claudev-budget` header to the response
+    Header,
+    /// Reject the request with an error response
+    Block,
+}
+
+/// A single cost or token limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetLimit {
+    pub period: BudgetPeriod,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cost_usd: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub action: BudgetAction,
+    /// Share of the limit at which to start warning (default 0.8)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warn_ratio: Option<f64>,
+}
+
+/// Budget configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    #[serde(default)]
+    pub limits: Vec<BudgetLimit>,
+    /// Substrings of allowed model ids; empty allows every model
+    #[serde(default)]
+    pub allowed_models: Vec<String>,
+}
+
+impl BudgetConfig {
+    /// Load the user's budget file, or an empty config if there is none
+    pub fn load() -> Result<Self> {
+        let path = budget_config_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.limits.is_empty() && self.allowed_models.is_empty()
+    }
+
+    pub fn model_allowed(&self, model: &str) -> bool {
+        let model = model.to_lowercase();
+        self.allowed_models.is_empty()
+            || self
+                .allowed_models
+                .iter()
+                .any(|m| model.contains(&m.to_lowercase()))
+    }
+}
+
+/// Accumulated spend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Spend {
+    pub cost_usd: f64,
+    pub tokens: u64,
+    pub requests: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<DateTime<Utc>>,
+}
+
+impl Spend {
+    fn add(&mut self, other: &Spend) {
+        self.cost_usd += other.cost_usd;
+        self.tokens += other.tokens;
+        self.requests += other.requests;
+    }
+}
+
+/// Spend history persisted across restarts
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BudgetState {
+    #[serde(default)]
+    pub days: BTreeMap<NaiveDate, Spend>,
+    #[serde(default)]
+    pub sessions: HashMap<String, Spend>,
+}
+
+impl BudgetState {
+    pub fn load() -> Result<Self> {
+        let path = budget_state_path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = budget_state_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Spend in the period containing `now`
+    pub fn spent(&self, period: BudgetPeriod, session: &str, now: DateTime<Utc>) -> Spend {
+        let today = now.date_naive();
+        match period {
+            BudgetPeriod::Daily => self.days.get(&today).cloned().unwrap_or_default(),
+            BudgetPeriod::Weekly => {
+                let week_start = today.week(Weekday::Mon).first_day();
+                let mut total = Spend::default();
+                for (_, spend) in self.days.range(week_start..=today) {
+                    total.add(spend);
+                }
+                total
+            }
+            BudgetPeriod::Session => self.sessions.get(session).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn record(&mut self, session: &str, cost_usd: f64, tokens: u64, now: DateTime<Utc>) {
+        let spend = Spend {
+            cost_usd,
+            tokens,
+            requests: 1,
+            last_seen: Some(now),
+        };
+        self.days.entry(now.date_naive()).or_default().add(&spend);
+        let session_spend = self.sessions.entry(session.to_string()).or_default();
+        session_spend.add(&spend);
+        session_spend.last_seen = Some(now);
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now.date_naive() - Duration::days(KEEP_DAYS);
+        self.days.retain(|day, _| *day >= cutoff);
+
+        if self.sessions.len() > KEEP_SESSIONS {
+            let mut by_age: Vec<(String, Option<DateTime<Utc>>)> = self
+                .sessions
+                .iter()
+                .map(|(k, v)| (k.clone(), v.last_seen))
+                .collect();
+            by_age.sort_by_key(|(_, last_seen)| std::cmp::Reverse(*last_seen));
+            for (key, _) in by_age.into_iter().skip(KEEP_SESSIONS) {
+                self.sessions.remove(&key);
+            }
+        }
+    }
+}
+
+/// A limit that was reached or is close to being reached
+#[derive(Debug, Clone)]
+pub struct BudgetViolation {
+    pub action: BudgetAction,
+    pub message: String,
+}
+
+/// Outcome of checking a request against the budget
+#[derive(Debug, Clone, Default)]
+pub struct BudgetCheck {
+    pub violations: Vec<BudgetViolation>,
+}
+
+impl BudgetCheck {
+    /// The violation that blocks the request, if any
+    pub fn blocking(&self) -> Option<&BudgetViolation> {
+        self.violations
+            .iter()
+            .find(|v| v.action == BudgetAction::Block)
+    }
+
+    /// Value for the `x-claudev-budget` response header, if any limit asks for one
+    pub fn header_value(&self) -> Option<String> {
+        let messages: Vec<&str> = self
+            .violations
+            .iter()
+            .filter(|v| v.action == BudgetAction::Header)
+            .map(|v| v.message.as_str())
+            .collect();
+        if messages.is_empty() {
+            None
+        } else {
+            Some(messages.join("; "))
+        }
+    }
+}
+
+/// Usage of one limit, for status reporting
+#[derive(Debug, Clone)]
+pub struct LimitStatus {
+    pub limit: BudgetLimit,
+    pub spent: Spend,
+}
+
+impl LimitStatus {
+    /// Highest share of the limit used (cost or tokens)
+    pub fn used_ratio(&self) -> f64 {
+        limit_ratio(&self.limit, &self.spent)
+    }
+}
+
+fn limit_ratio(limit: &BudgetLimit, spent: &Spend) -> f64 {
+    let cost = limit
+        .max_cost_usd
+        .filter(|max| *max > 0.0)
+        .map(|max| spent.cost_usd / max)
+        .unwrap_or(0.0);
+    let tokens = limit
+        .max_tokens
+        .filter(|max| *max > 0)
+        .map(|max| spent.tokens as f64 / max as f64)
+        .unwrap_or(0.0);
+    cost.max(tokens)
+}
+
+fn describe_limit(limit: &BudgetLimit) -> String {
+    let mut parts = Vec::new();
+    if let Some(max) = limit.max_cost_usd {
+        parts.push(format!("${:.2}", max));
+    }
+    if let Some(max) = limit.max_tokens {
+        parts.push(format!("{} tokens", max));
+    }
+    format!("{} limit of {}", limit.period.label(), parts.join(" / "))
+}
+
+/// Budget configuration plus live spend, shared by proxy connections
+pub struct BudgetGuard {
+    config: BudgetConfig,
+    state: Mutex<BudgetState>,
+    persist: bool,
+}
+
+impl BudgetGuard {
+    /// Load config and persisted state from their default locations
+    pub fn load() -> Result<Self> {
+        Ok(Self {
+            config: BudgetConfig::load()?,
+            state: Mutex::new(BudgetState::load()?),
+            persist: true,
+        })
+    }
+
+    /// Guard that keeps its state in memory only
+    #[cfg(test)]
+    pub fn in_memory(config: BudgetConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BudgetState::default()),
+            persist: false,
+        }
+    }
+
+    pub fn config(&self) -> &BudgetConfig {
+        &self.config
+    }
+
+    /// Check a request before it is forwarded
+    pub fn check(&self, request: &ApiRequest, session: &str, now: DateTime<Utc>) -> BudgetCheck {
+        let mut check = BudgetCheck::default();
+
+        if !self.config.model_allowed(&request.model) {
+            check.violations.push(BudgetViolation {
+                action: BudgetAction::Block,
+                message: format!("model {} is not in the allow-list", request.model),
+            });
+        }
+
+        let state = self.state.lock().unwrap();
+        for limit in &self.config.limits {
+            let spent = state.spent(limit.period, session, now);
+            let ratio = limit_ratio(limit, &spent);
+            let warn_ratio = limit.warn_ratio.unwrap_or(DEFAULT_WARN_RATIO);
+
+            if ratio >= 1.0 {
+                check.violations.push(BudgetViolation {
+                    action: limit.action,
+                    message: format!("{} reached ({:.0}% used)", describe_limit(limit), ratio * 100.0),
+                });
+            } else if ratio >= warn_ratio {
+                check.violations.push(BudgetViolation {
+                    action: BudgetAction::Warn,
+                    message: format!("{} at {:.0}%", describe_limit(limit), ratio * 100.0),
+                });
+            }
+        }
+
+        check
+    }
+
+    /// Record a completed request's spend
+    pub fn record(&self, session: &str, model: &str, usage: &Usage, now: DateTime<Utc>) {
+        let cost = crate::traffic::calculate_cost_at(model, usage, now);
+        let tokens = usage.input_tokens
+            + usage.output_tokens
+            + usage.cache_creation_input_tokens
+            + usage.cache_read_input_tokens;
+
+        let mut state = self.state.lock().unwrap();
+        state.record(session, cost, tokens, now);
+        if self.persist {
+            if let Err(e) = state.save() {
+                tracing::warn!("Failed to save budget state: {}", e);
+            }
+        }
+    }
+
+    /// Current spend against every configured limit
+    pub fn status(&self, session: &str, now: DateTime<Utc>) -> Vec<LimitStatus> {
+        let state = self.state.lock().unwrap();
+        self.config
+            .limits
+            .iter()
+            .map(|limit| LimitStatus {
+                limit: limit.clone(),
+                spent: state.spent(limit.period, session, now),
+            })
+            .collect()
+    }
+
+    /// Most recently active session, used when reporting session limits
+    pub fn latest_session(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .iter()
+            .max_by_key(|(_, spend)| spend.last_seen)
+            .map(|(key, _)| key.clone())
+    }
+}
+
+/// Path of the budget configuration file
+pub fn budget_config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("claudev")
+        .join("budget.json")
+}
+
+/// Path of the persisted spend state
+pub fn budget_state_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claudev")
+        .join("budget_state.json")
+}
+
+/// Print budget status for `claudev monitor --status`
+pub fn print_status() -> Result<()> {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    let guard = BudgetGuard::load()?;
+    let now = Utc::now();
+
+    println!("{}", "Budget Status".cyan().bold());
+    println!("Config: {}", budget_config_path().display());
+    println!("State:  {}\n", budget_state_path().display());
+
+    if guard.config().is_empty() {
+        println!("No budgets configured.");
+        return Ok(());
+    }
+
+    if !guard.config().allowed_models.is_empty() {
+        println!("Allowed models: {}\n", guard.config().allowed_models.join(", "));
+    }
+
+    let session = guard.latest_session().unwrap_or_default();
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Period").fg(Color::Cyan),
+            Cell::new("Spent").fg(Color::Cyan),
+            Cell::new("Tokens").fg(Color::Cyan),
+            Cell::new("Limit").fg(Color::Cyan),
+            Cell::new("Used").fg(Color::Cyan),
+            Cell::new("Action").fg(Color::Cyan),
+        ]);
+
+    for status in guard.status(&session, now) {
+        let ratio = status.used_ratio();
+        let color = if ratio >= 1.0 {
+            Color::Red
+        } else if ratio >= status.limit.warn_ratio.unwrap_or(DEFAULT_WARN_RATIO) {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+        table.add_row(vec![
+            Cell::new(status.limit.period.label()),
+            Cell::new(format!("${:.2}", status.spent.cost_usd)),
+            Cell::new(status.spent.tokens),
+            Cell::new(describe_limit(&status.limit)),
+            Cell::new(format!("{:.0}%", ratio * 100.0)).fg(color),
+            Cell::new(format!("{:?}", status.limit.action).to_lowercase()),
+        ]);
+    }
+
+    println!("{}", table);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn request(model: &str) -> ApiRequest {
+        ApiRequest {
+            model: model.to_string(),
+            max_tokens: None,
+            messages: vec![],
+            system: None,
+            stream: false,
+            tools: None,
+            raw_body: None,
+        }
+    }
+
+    fn usage(input: u64, output: u64) -> Usage {
+        Usage {
+            input_tokens: input,
+            output_tokens: output,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_model_allow_list() {
+        let guard = BudgetGuard::in_memory(BudgetConfig {
+            limits: vec![],
+            allowed_models: vec!["haiku".to_string()],
+        });
+        let now = Utc::now();
+        assert!(guard.check(&request("claude-3-5-haiku"), "s", now).blocking().is_none());
+        assert!(guard.check(&request("claude-opus-4"), "s", now).blocking().is_some());
+    }
+
+    #[test]
+    fn test_token_limit_actions() {
+        let guard = BudgetGuard::in_memory(BudgetConfig {
+            limits: vec![
+                BudgetLimit {
+                    period: BudgetPeriod::Session,
+                    max_cost_usd: None,
+                    max_tokens: Some(1000),
+                    action: BudgetAction::Block,
+                    warn_ratio: None,
+                },
+                BudgetLimit {
+                    period: BudgetPeriod::Daily,
+                    max_cost_usd: None,
+                    max_tokens: Some(1000),
+                    action: BudgetAction::Header,
+                    warn_ratio: None,
+                },
+            ],
+            allowed_models: vec![],
+        });
+        let now = Utc::now();
+        let req = request("claude-sonnet-4");
+
+        guard.record("a", "claude-sonnet-4", &usage(800, 50), now);
+        let check = guard.check(&req, "a", now);
+        assert!(check.blocking().is_none());
+        assert_eq!(check.violations.len(), 2);
+        assert!(check.violations.iter().all(|v| v.action == BudgetAction::Warn));
+
+        guard.record("a", "claude-sonnet-4", &usage(200, 0), now);
+        let check = guard.check(&req, "a", now);
+        assert!(check.blocking().is_some());
+        assert!(check.header_value().unwrap().contains("daily"));
+
+        // Another session only hits the daily header limit
+        let check = guard.check(&req, "b", now);
+        assert!(check.blocking().is_none());
+        assert!(check.header_value().is_some());
+    }
+
+    #[test]
+    fn test_weekly_window() {
+        let mut state = BudgetState::default();
+        // Wednesday 2025-01-15; the week starts on Monday 2025-01-13
+        let wed = Utc.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        state.record("s", 1.0, 10, Utc.with_ymd_and_hms(2025, 1, 12, 12, 0, 0).unwrap());
+        state.record("s", 2.0, 20, Utc.with_ymd_and_hms(2025, 1, 13, 12, 0, 0).unwrap());
+        state.record("s", 4.0, 40, wed);
+
+        let weekly = state.spent(BudgetPeriod::Weekly, "s", wed);
+        assert!((weekly.cost_usd - 6.0).abs() < 1e-9);
+        assert_eq!(weekly.tokens, 60);
+        assert_eq!(state.spent(BudgetPeriod::Daily, "s", wed).requests, 1);
+        assert_eq!(state.spent(BudgetPeriod::Session, "s", wed).requests, 3);
+    }
+}
@@ -1,6 +1,7 @@
 // Claude configuration management module
 // Handles switching between different Claude Code API providers
 
+use crate::secret_store::{SecretHandle, SecretStore};
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -74,7 +75,13 @@ impl ClaudeProvider {
 pub struct ClaudeConfig {
     pub provider: ClaudeProvider,
     pub endpoint: String,
+    /// Plaintext key: held in memory until stored, or read from a config
+    /// written before keys moved to the secret store. Never saved.
+    #[serde(default, skip_serializing)]
     pub api_key: String,
+    /// Where the API key is kept in the secret store
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_ref: Option<SecretHandle>,
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub organization_id: Option<String>,
@@ -88,6 +95,7 @@ impl ClaudeConfig {
             model: provider.default_model().to_string(),
             provider,
             api_key,
+            api_key_ref: None,
             organization_id: None,
         }
     }
@@ -98,6 +106,7 @@ impl ClaudeConfig {
             provider: ClaudeProvider::Custom,
             endpoint,
             api_key,
+            api_key_ref: None,
             model,
             organization_id: None,
         }
@@ -117,8 +126,62 @@ impl ClaudeConfig {
         Ok(config)
     }
 
-    /// Save configuration to file
+    /// Secret store name of this provider's API key
+    pub fn secret_name(&self) -> String {
+        format!("claude/{}", self.provider.name())
+    }
+
+    /// Move the in-memory API key into the secret store, keeping only its handle
+    pub fn store_api_key(&mut self, store: &SecretStore) -> Result<SecretHandle> {
+        if self.api_key.is_empty() {
+            return self
+                .api_key_ref
+                .clone()
+                .ok_or_else(|| anyhow!("No API key to store"));
+        }
+        let handle = store.store(&self.secret_name(), &self.api_key)?;
+        self.api_key_ref = Some(handle.clone());
+        self.api_key.clear();
+        Ok(handle)
+    }
+
+    /// Whether this config was loaded with a plaintext key that still needs migrating
+    pub fn has_plaintext_key(&self) -> bool {
+        !self.api_key.is_empty() && self.api_key_ref.is_none()
+    }
+
+    /// Move a plaintext key from an older config file into the secret store
+    /// and rewrite the file without it. Returns the new handle, if any.
+    pub fn migrate_plaintext_key(store: &SecretStore) -> Result<Option<SecretHandle>> {
+        let mut config = Self::load()?;
+        if !config.has_plaintext_key() {
+            return Ok(None);
+        }
+        let handle = config.store_api_key(store)?;
+        config.save()?;
+        Ok(Some(handle))
+    }
+
+    /// Resolve the API key, from memory or the secret store
+    pub fn resolve_api_key(&self, store: &SecretStore) -> Result<String> {
+        if !self.api_key.is_empty() {
+            return Ok(self.api_key.clone());
+        }
+        let handle = self
+            .api_key_ref
+            .as_ref()
+            .ok_or_else(|| anyhow!("No API key configured"))?;
+        store.get(handle)
+    }
+
+    /// Save configuration to file. The API key itself is never written, only
+    /// its secret store handle; call `store_api_key` first.
     pub fn save(&self) -> Result<()> {
+        if self.has_plaintext_key() {
+            return Err(anyhow!(
+                "API key has not been moved to the secret store; refusing to save it in plaintext"
+            ));
+        }
         let config_path = Self::config_file_path();
 
         // Create parent directory if it doesn't exist
@@ -144,15 +207,14 @@ impl ClaudeConfig {
     pub fn write_claude_code_config(&self) -> Result<()> {
         // Claude Code stores its config in different locations based on OS
         let claude_config_paths = Self::get_claude_code_config_paths();
+        let config_content = self.generate_claude_code_config()?;
 
         let mut wrote_any = false;
         for path in claude_config_paths {
             if let Some(parent) = path.parent() {
                 if parent.exists() || self.try_create_claude_dir(parent) {
-                    // Create a simple config structure for Claude Code
-                    let config_content = self.generate_claude_code_config();
 
-                    match fs::write(&path, config_content) {
+                    match fs::write(&path, &config_content) {
                         Ok(_) => {
                             println!("✓ Updated Claude Code config: {}", path.display());
                             wrote_any = true;
@@ -172,6 +234,25 @@ impl ClaudeConfig {
         }
     }
 
+    /// Rewrite Claude Code config files that still hold a plaintext `apiKey`
+    /// so they use the secret store helper instead
+    pub fn scrub_claude_code_configs(&self) -> Result<Vec<PathBuf>> {
+        let mut rewritten = Vec::new();
+        for path in Self::get_claude_code_config_paths() {
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let has_plaintext = serde_json::from_str::<serde_json::Value>(&content)
+                .map(|v| v.get("apiKey").is_some())
+                .unwrap_or(false);
+            if has_plaintext {
+                fs::write(&path, self.generate_claude_code_config()?)?;
+                rewritten.push(path);
+            }
+        }
+        Ok(rewritten)
+    }
+
     fn try_create_claude_dir(&self, path: &std::path::Path) -> bool {
         fs::create_dir_all(path).is_ok()
     }
@@ -201,26 +282,45 @@ impl ClaudeConfig {
         paths
     }
 
-    /// Generate Claude Code compatible configuration
-    fn generate_claude_code_config(&self) -> String {
+    /// Generate Claude Code compatible configuration. The key is referenced
+    /// through an `apiKeyHelper` command that reads it from the secret store.
+    fn generate_claude_code_config(&self) -> Result<String> {
         #[derive(serde::Serialize)]
         struct ClaudeCodeConfig<'a> {
             #[serde(rename = "apiUrl")]
             api_url: &'a str,
-            #[serde(rename = "apiKey")]
-            api_key: &'a str,
+            #[serde(rename = "apiKeyHelper")]
+            api_key_helper: String,
             model: &'a str,
             provider: &'a str,
         }
 
+        let handle = self.api_key_ref.as_ref().ok_or_else(|| {
+            anyhow!("API key is not in the secret store. Run 'claudev claude migrate' first.")
+        })?;
+        let exe = std::env::current_exe()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| "claudev".to_string());
+
         let config = ClaudeCodeConfig {
             api_url: &self.endpoint,
-            api_key: &self.api_key,
+            api_key_helper: format!("{} secret get {}", shell_quote(&exe), handle),
             model: &self.model,
             provider: self.provider.name(),
         };
 
-        serde_json::to_string(&config).unwrap_or_default()
+        Ok(serde_json::to_string(&config)?)
+    }
+}
+
+/// Quote a path for the `apiKeyHelper` shell command
+fn shell_quote(s: &str) -> String {
+    if s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-+".contains(c))
+    {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
     }
 }
 
@@ -275,13 +375,15 @@ pub fn show_current_config() -> Result<()> {
             println!("  Provider: {}", config.provider.name().green());
             println!("  Endpoint: {}", config.endpoint);
             println!("  Model: {}", config.model);
-            if config.api_key.len() > 4 {
+            if let Some(handle) = &config.api_key_ref {
+                println!("  API Key: {}", handle.to_string().green());
+            } else if config.has_plaintext_key() {
                 println!(
-                    "  API Key: ****{}",
-                    &config.api_key[config.api_key.len() - 4..]
+                    "  API Key: {}",
+                    "stored in plaintext - run 'claudev claude migrate'".yellow()
                 );
             } else {
-                println!("  API Key: ****");
+                println!("  API Key: (none)");
             }
             if let Some(org_id) = &config.organization_id {
                 println!("  Organization ID: {}", org_id);
@@ -363,4 +465,28 @@ mod tests {
         assert_eq!(config.api_key, "test-key-123");
         assert!(config.model.contains("claude"));
     }
+
+    #[test]
+    fn test_plaintext_key_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SecretStore::for_tests(dir.path().join("secrets.age"), "passphrase");
+
+        let legacy = r#"{"provider":"z.ai","endpoint":"https://api.z.ai/v1","api_key":"sk-old-1234","model":"glm-4"}"#;
+        let mut config: ClaudeConfig = serde_json::from_str(legacy).unwrap();
+        assert!(config.has_plaintext_key());
+        assert!(config.generate_claude_code_config().is_err());
+
+        let handle = config.store_api_key(&store).unwrap();
+        assert_eq!(handle.to_string(), "file:claude/z.ai");
+        assert!(!config.has_plaintext_key());
+        assert_eq!(config.resolve_api_key(&store).unwrap(), "sk-old-1234");
+
+        let saved = serde_json::to_string(&config).unwrap();
+        assert!(!saved.contains("sk-old-1234"));
+        assert!(saved.contains("\"api_key_ref\":\"file:claude/z.ai\""));
+
+        let claude_code = config.generate_claude_code_config().unwrap();
+        assert!(!claude_code.contains("sk-old-1234"));
+        assert!(claude_code.contains("secret get file:claude/z.ai"));
+    }
 }
@@ -0,0 +1,597 @@
+// Provider health checks for `claudev claude doctor`
+// Validates the configured endpoint step by step (config, DNS, proxy, auth,
+// model) and reports each step as a structured check
+
+use crate::claude_config::{ClaudeConfig, ClaudeProvider};
+use anyhow::Result;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Per-request timeout of the authenticated call
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// Outcome of one diagnostic step
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+impl Check {
+    fn new(name: &str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            detail: detail.into(),
+            latency_ms: None,
+        }
+    }
+
+    fn timed(mut self, elapsed: Duration) -> Self {
+        self.latency_ms = Some(elapsed.as_millis() as u64);
+        self
+    }
+}
+
+/// Everything `claudev claude doctor` found
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub provider: String,
+    pub endpoint: String,
+    pub model: String,
+    /// Proxy the request went through, from the environment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    pub checks: Vec<Check>,
+}
+
+impl DoctorReport {
+    #[cfg(test)]
+    pub fn check(&self, name: &str) -> Option<&Check> {
+        self.checks.iter().find(|c| c.name == name)
+    }
+
+    /// True when no check failed
+    pub fn healthy(&self) -> bool {
+        self.checks.iter().all(|c| c.status != CheckStatus::Fail)
+    }
+}
+
+/// HTTP client used for the checks; honours the proxy environment like Claude Code does
+pub fn default_client() -> Result<reqwest::Client> {
+    Ok(reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?)
+}
+
+/// Run all checks against a provider config. `api_key` is the resolved key,
+/// or why it could not be read.
+pub async fn run(
+    config: &ClaudeConfig,
+    api_key: Result<&str, String>,
+    client: &reqwest::Client,
+) -> DoctorReport {
+    let mut report = DoctorReport {
+        provider: config.provider.name().to_string(),
+        endpoint: config.endpoint.clone(),
+        model: config.model.clone(),
+        proxy: None,
+        checks: Vec::new(),
+    };
+
+    // Config shape
+    let url = Url::parse(&config.endpoint)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https") && u.host_str().is_some());
+    let mut problems = Vec::new();
+    if url.is_none() {
+        problems.push(format!(
+            "endpoint '{}' is not an http(s) URL",
+            config.endpoint
+        ));
+    }
+    if config.model.trim().is_empty() {
+        problems.push("no model set".to_string());
+    }
+    if let Err(e) = &api_key {
+        problems.push(format!("API key unavailable: {}", e));
+    } else if api_key.as_ref().is_ok_and(|k| k.trim().is_empty()) {
+        problems.push("API key is empty".to_string());
+    }
+    let config_ok = problems.is_empty();
+    report.checks.push(if config_ok {
+        Check::new(
+            "config",
+            CheckStatus::Pass,
+            "endpoint, model and API key present",
+        )
+    } else {
+        Check::new("config", CheckStatus::Fail, problems.join("; "))
+    });
+
+    let Some(url) = url else {
+        for name in ["dns", "proxy", "auth", "model"] {
+            report
+                .checks
+                .push(Check::new(name, CheckStatus::Skip, "invalid endpoint"));
+        }
+        return report;
+    };
+
+    // DNS
+    let host = url.host_str().unwrap_or_default().to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let started = Instant::now();
+    let dns = match tokio::net::lookup_host((host.trim_matches(['[', ']']), port)).await {
+        Ok(addrs) => {
+            let addrs: Vec<String> = addrs.map(|a| a.ip().to_string()).collect();
+            Check::new(
+                "dns",
+                CheckStatus::Pass,
+                format!("{} -> {}", host, addrs.join(", ")),
+            )
+        }
+        Err(e) => Check::new(
+            "dns",
+            CheckStatus::Fail,
+            format!("{} did not resolve: {}", host, e),
+        ),
+    };
+    let dns_ok = dns.status == CheckStatus::Pass;
+    report.checks.push(dns.timed(started.elapsed()));
+
+    // Proxy settings that apply to this endpoint
+    report.proxy = proxy_for(&url);
+    report.checks.push(match &report.proxy {
+        Some(proxy) => Check::new(
+            "proxy",
+            CheckStatus::Warn,
+            format!("requests go through {} (from the environment)", proxy),
+        ),
+        None => Check::new("proxy", CheckStatus::Pass, "direct connection"),
+    });
+
+    let Ok(key) = api_key else {
+        report
+            .checks
+            .push(Check::new("auth", CheckStatus::Skip, "no API key"));
+        report
+            .checks
+            .push(Check::new("model", CheckStatus::Skip, "no API key"));
+        return report;
+    };
+    if !dns_ok && report.proxy.is_none() {
+        report.checks.push(Check::new(
+            "auth",
+            CheckStatus::Skip,
+            "host did not resolve",
+        ));
+        report.checks.push(Check::new(
+            "model",
+            CheckStatus::Skip,
+            "host did not resolve",
+        ));
+        return report;
+    }
+
+    // Minimal authenticated call: list models
+    let models_url = models_url(&url);
+    let mut request = client
+        .get(models_url.clone())
+        .header("authorization", format!("Bearer {}", key));
+    if config.provider == ClaudeProvider::Custom {
+        // Custom endpoints may speak the Anthropic API instead
+        request = request
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01");
+    }
+
+    let started = Instant::now();
+    let response = request.send().await;
+    let elapsed = started.elapsed();
+
+    let (auth, models) = match response {
+        Err(e) => (
+            Check::new(
+                "auth",
+                CheckStatus::Fail,
+                describe_request_error(&e, &report.proxy),
+            ),
+            None,
+        ),
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let via = response
+                .headers()
+                .get("via")
+                .and_then(|v| v.to_str().ok())
+                .map(String::from);
+            let body = response.text().await.unwrap_or_default();
+            let json: Option<Value> = serde_json::from_str(&body).ok();
+            let message = json
+                .as_ref()
+                .and_then(|j| j.pointer("/error/message").and_then(Value::as_str))
+                .map(|m| format!(": {}", m))
+                .unwrap_or_default();
+
+            let auth = match status {
+                200..=299 if json.is_none() => Check::new(
+                    "auth",
+                    CheckStatus::Warn,
+                    format!(
+                        "HTTP {} but the body is not JSON; a captive portal or proxy may have answered",
+                        status
+                    ),
+                ),
+                200..=299 => Check::new(
+                    "auth",
+                    CheckStatus::Pass,
+                    format!("HTTP {} from {}", status, models_url),
+                ),
+                401 | 403 => Check::new(
+                    "auth",
+                    CheckStatus::Fail,
+                    format!("HTTP {}: API key rejected{}", status, message),
+                ),
+                407 => Check::new(
+                    "auth",
+                    CheckStatus::Fail,
+                    "HTTP 407: the proxy requires authentication",
+                ),
+                404 | 405 => Check::new(
+                    "auth",
+                    CheckStatus::Warn,
+                    format!("HTTP {}: {} is not offered, key unverified", status, models_url),
+                ),
+                429 => Check::new(
+                    "auth",
+                    CheckStatus::Warn,
+                    "HTTP 429: rate limited, key accepted",
+                ),
+                _ => Check::new("auth", CheckStatus::Fail, format!("HTTP {}{}", status, message)),
+            };
+            let auth = match (via, auth.status) {
+                (Some(via), CheckStatus::Pass) => Check::new(
+                    "auth",
+                    CheckStatus::Warn,
+                    format!(
+                        "{}; response passed through a proxy (Via: {})",
+                        auth.detail, via
+                    ),
+                ),
+                _ => auth,
+            };
+            let models = (auth.status == CheckStatus::Pass)
+                .then(|| json.as_ref().map(model_ids))
+                .flatten();
+            (auth, models)
+        }
+    };
+    report.checks.push(auth.timed(elapsed));
+
+    // Model availability
+    report.checks.push(match models {
+        None => Check::new("model", CheckStatus::Skip, "model list unavailable"),
+        Some(ids) if ids.contains(&config.model) => Check::new(
+            "model",
+            CheckStatus::Pass,
+            format!(
+                "{} is available ({} models listed)",
+                config.model,
+                ids.len()
+            ),
+        ),
+        Some(ids) => {
+            let similar = similar_models(&config.model, &ids);
+            let hint = if similar.is_empty() {
+                String::new()
+            } else {
+                format!("; similar: {}", similar.join(", "))
+            };
+            Check::new(
+                "model",
+                CheckStatus::Fail,
+                format!(
+                    "{} is not among the {} listed models{}",
+                    config.model,
+                    ids.len(),
+                    hint
+                ),
+            )
+        }
+    });
+
+    report
+}
+
+/// `/models` under the configured base, or `/v1/models` for a bare host
+fn models_url(base: &Url) -> Url {
+    let mut url = base.clone();
+    let path = base.path().trim_end_matches('/');
+    let path = if path.is_empty() {
+        "/v1/models".to_string()
+    } else {
+        format!("{}/models", path)
+    };
+    url.set_path(&path);
+    url.set_query(None);
+    url
+}
+
+/// IDs from an OpenAI or Anthropic style model list
+fn model_ids(json: &Value) -> Vec<String> {
+    json.get("data")
+        .and_then(Value::as_array)
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|m| m.get("id").and_then(Value::as_str).map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Listed models sharing the most name parts with the configured model
+fn similar_models(model: &str, ids: &[String]) -> Vec<String> {
+    let lower = model.to_lowercase();
+    let parts: Vec<&str> = lower
+        .split(['/', '-', '.', ':'])
+        .filter(|part| part.len() > 2 && !part.chars().all(|c| c.is_ascii_digit()))
+        .collect();
+    let score = |id: &String| {
+        let id = id.to_lowercase();
+        parts.iter().filter(|part| id.contains(*part)).count()
+    };
+
+    let best = ids.iter().map(score).max().unwrap_or(0);
+    if best == 0 {
+        return Vec::new();
+    }
+    ids.iter()
+        .filter(|id| score(id) == best)
+        .take(3)
+        .cloned()
+        .collect()
+}
+
+/// Proxy from the environment that applies to `url`, honouring `NO_PROXY`
+fn proxy_for(url: &Url) -> Option<String> {
+    let env = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    };
+
+    let host = url.host_str()?.trim_matches(['[', ']']).to_lowercase();
+    if let Some(no_proxy) = env(&["NO_PROXY", "no_proxy"]) {
+        let bypass = no_proxy.split(',').map(str::trim).any(|rule| {
+            let rule = rule.trim_start_matches('.').to_lowercase();
+            rule == "*" || host == rule || host.ends_with(&format!(".{}", rule))
+        });
+        if bypass {
+            return None;
+        }
+    }
+
+    match url.scheme() {
+        "https" => env(&["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]),
+        _ => env(&["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]),
+    }
+}
+
+/// Explain a failed request, pointing at the proxy where it is the likely cause
+fn describe_request_error(error: &reqwest::Error, proxy: &Option<String>) -> String {
+    let mut chain = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(e) = source {
+        chain.push_str(": ");
+        chain.push_str(&e.to_string());
+        source = e.source();
+    }
+
+    let lower = chain.to_lowercase();
+    let cause = if lower.contains("certificate") || lower.contains("unknownissuer") {
+        match proxy {
+            Some(proxy) => format!(
+                "TLS certificate not trusted; {} may be intercepting HTTPS",
+                proxy
+            ),
+            None => "TLS certificate not trusted; something may be intercepting HTTPS".to_string(),
+        }
+    } else if error.is_timeout() {
+        format!("timed out after {}s", REQUEST_TIMEOUT.as_secs())
+    } else if error.is_connect() {
+        match proxy {
+            Some(proxy) => format!("could not connect through proxy {}", proxy),
+            None => "connection refused or unreachable".to_string(),
+        }
+    } else {
+        "request failed".to_string()
+    };
+    format!("{} ({})", cause, chain)
+}
+
+/// Print a doctor report as a checklist
+pub fn print_report(report: &DoctorReport) {
+    use colored::Colorize;
+
+    println!("\n{}", "Claude Code Provider Doctor".cyan().bold());
+    println!();
+    println!("  Provider: {}", report.provider.green());
+    println!("  Endpoint: {}", report.endpoint);
+    println!("  Model: {}", report.model);
+    println!();
+
+    for check in &report.checks {
+        let status = match check.status {
+            CheckStatus::Pass => "PASS".green().bold(),
+            CheckStatus::Warn => "WARN".yellow().bold(),
+            CheckStatus::Fail => "FAIL".red().bold(),
+            CheckStatus::Skip => "SKIP".dimmed(),
+        };
+        let latency = check
+            .latency_ms
+            .map(|ms| format!(" ({} ms)", ms))
+            .unwrap_or_default();
+        println!(
+            "  {}  {:<8}{}{}",
+            status,
+            check.name,
+            check.detail,
+            latency.dimmed()
+        );
+    }
+    println!();
+
+    if report.healthy() {
+        println!("{}", "Provider looks healthy.".green());
+    } else {
+        println!(
+            "{}",
+            "Provider has problems; see the failed checks above.".red()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::{self, MockConfig};
+
+    async fn mock(api_key: &str) -> mock_server::MockHandle {
+        mock_server::start(MockConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            api_key: Some(api_key.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn statuses(report: &DoctorReport) -> Vec<(&str, CheckStatus)> {
+        report
+            .checks
+            .iter()
+            .map(|c| (c.name.as_str(), c.status))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_healthy_endpoint() {
+        let server = mock("sk-good").await;
+        let config = ClaudeConfig::custom(
+            server.url(),
+            String::new(),
+            "claude-sonnet-4-20250514".to_string(),
+        );
+
+        let report = run(&config, Ok("sk-good"), &client()).await;
+        assert_eq!(report.check("config").unwrap().status, CheckStatus::Pass);
+        assert_eq!(report.check("dns").unwrap().status, CheckStatus::Pass);
+        let auth = report.check("auth").unwrap();
+        assert_eq!(auth.status, CheckStatus::Pass, "{}", auth.detail);
+        assert!(auth.latency_ms.is_some());
+        assert!(auth.detail.contains("/v1/models"));
+        assert_eq!(report.check("model").unwrap().status, CheckStatus::Pass);
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_model_errors() {
+        let server = mock("sk-good").await;
+        let mut config = ClaudeConfig::custom(
+            server.url(),
+            String::new(),
+            "claude-sonnet-4-20250514".to_string(),
+        );
+
+        let report = run(&config, Ok("sk-wrong"), &client()).await;
+        let auth = report.check("auth").unwrap();
+        assert_eq!(auth.status, CheckStatus::Fail);
+        assert!(auth.detail.contains("401"), "{}", auth.detail);
+        assert_eq!(report.check("model").unwrap().status, CheckStatus::Skip);
+        assert!(!report.healthy());
+
+        config.model = "claude-sonnet-5".to_string();
+        let report = run(&config, Ok("sk-good"), &client()).await;
+        let model = report.check("model").unwrap();
+        assert_eq!(model.status, CheckStatus::Fail);
+        assert!(
+            model.detail.contains("similar: claude-sonnet-4-20250514"),
+            "{}",
+            model.detail
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_config_and_unreachable_endpoint() {
+        let config = ClaudeConfig::custom("ftp://example".into(), String::new(), String::new());
+        let report = run(&config, Err("not found".into()), &client()).await;
+        assert_eq!(
+            statuses(&report),
+            vec![
+                ("config", CheckStatus::Fail),
+                ("dns", CheckStatus::Skip),
+                ("proxy", CheckStatus::Skip),
+                ("auth", CheckStatus::Skip),
+                ("model", CheckStatus::Skip),
+            ]
+        );
+        assert!(report
+            .check("config")
+            .unwrap()
+            .detail
+            .contains("not an http(s) URL"));
+
+        // Nothing listens on a port we just released
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = ClaudeConfig::custom(
+            format!("http://127.0.0.1:{}/v1", port),
+            String::new(),
+            "m".into(),
+        );
+        let report = run(&config, Ok("key"), &client()).await;
+        let auth = report.check("auth").unwrap();
+        assert_eq!(auth.status, CheckStatus::Fail);
+        assert!(
+            auth.detail.contains("connection refused"),
+            "{}",
+            auth.detail
+        );
+    }
+
+    #[test]
+    fn test_models_url() {
+        let url = |s: &str| models_url(&Url::parse(s).unwrap()).to_string();
+        assert_eq!(url("https://api.z.ai/v1"), "https://api.z.ai/v1/models");
+        assert_eq!(
+            url("https://openrouter.ai/api/v1/"),
+            "https://openrouter.ai/api/v1/models"
+        );
+        assert_eq!(
+            url("http://localhost:4000"),
+            "http://localhost:4000/v1/models"
+        );
+    }
+}
@@ -0,0 +1,703 @@
+// Claude Code settings profiles
+// Named sets of env vars, permissions, hooks, MCP servers and model that can
+// be switched into ~/.claude/settings.json
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Settings backups kept before older ones are pruned
+const MAX_BACKUPS: usize = 20;
+
+/// Permission rules set by a profile
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProfilePermissions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl ProfilePermissions {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+/// A named Claude Code settings profile. Only the sections a profile sets are
+/// touched when it is used; everything else in settings.json is kept.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaudeProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "ProfilePermissions::is_empty")]
+    pub permissions: ProfilePermissions,
+    /// Hook lists keyed by event, e.g. `PreToolUse`
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub hooks: Map<String, Value>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub mcp_servers: Map<String, Value>,
+}
+
+impl ClaudeProfile {
+    /// Capture the profile-managed sections of an existing settings.json
+    pub fn from_settings(settings: &Value) -> Self {
+        let section = |key: &str| settings.get(key).and_then(Value::as_object).cloned();
+        let rules = |kind: &str| -> Vec<String> {
+            settings
+                .pointer(&format!("/permissions/{}", kind))
+                .and_then(Value::as_array)
+                .map(|rules| {
+                    rules
+                        .iter()
+                        .filter_map(|r| r.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        Self {
+            description: None,
+            model: settings
+                .get("model")
+                .and_then(Value::as_str)
+                .map(String::from),
+            env: section("env")
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(k, v)| match v {
+                    Value::String(s) => (k, s),
+                    other => (k, other.to_string()),
+                })
+                .collect(),
+            permissions: ProfilePermissions {
+                allow: rules("allow"),
+                deny: rules("deny"),
+            },
+            hooks: section("hooks").unwrap_or_default(),
+            mcp_servers: section("mcpServers").unwrap_or_default(),
+        }
+    }
+
+    /// The settings.json fragment this profile writes
+    pub fn fragment(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_else(|_| Value::Object(Map::new()));
+        if let Some(map) = value.as_object_mut() {
+            map.remove("description");
+        }
+        value
+    }
+}
+
+/// One semantic difference between two settings files
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingChange {
+    Added {
+        path: String,
+        value: Value,
+    },
+    Removed {
+        path: String,
+        value: Value,
+    },
+    Changed {
+        path: String,
+        before: Value,
+        after: Value,
+    },
+}
+
+/// Outcome of `claudev claude profile use`
+#[derive(Debug)]
+pub struct ProfileSwitch {
+    pub settings_path: PathBuf,
+    pub changes: Vec<SettingChange>,
+    /// Copy of settings.json taken before it was rewritten
+    pub backup: Option<PathBuf>,
+}
+
+/// What the last `use` wrote, so the next switch can take it back out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ActiveProfile {
+    name: String,
+    applied: Value,
+}
+
+/// Profiles stored as `<dir>/<name>.json`
+pub struct ProfileStore {
+    dir: PathBuf,
+}
+
+impl ProfileStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn open_default() -> Self {
+        Self::new(profiles_dir())
+    }
+
+    fn profile_path(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !name.starts_with('.');
+        if !valid {
+            return Err(anyhow!(
+                "Invalid profile name '{}': use letters, digits, '-', '_' and '.'",
+                name
+            ));
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    fn active_path(&self) -> PathBuf {
+        self.dir.join(".active.json")
+    }
+
+    fn backups_dir(&self) -> PathBuf {
+        self.dir.join("backups")
+    }
+
+    pub fn list(&self) -> Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names: Vec<String> = fs::read_dir(&self.dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let name = name.strip_suffix(".json")?;
+                (!name.starts_with('.')).then(|| name.to_string())
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load(&self, name: &str) -> Result<ClaudeProfile> {
+        let path = self.profile_path(name)?;
+        if !path.exists() {
+            return Err(anyhow!(
+                "No profile named '{}'. Create it with 'claudev claude profile save {}'",
+                name,
+                name
+            ));
+        }
+        let content = fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid profile {}", path.display()))
+    }
+
+    pub fn save(&self, name: &str, profile: &ClaudeProfile) -> Result<PathBuf> {
+        let path = self.profile_path(name)?;
+        fs::create_dir_all(&self.dir)?;
+        fs::write(&path, serde_json::to_string_pretty(profile)?)?;
+        Ok(path)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.profile_path(name)?;
+        fs::remove_file(&path).with_context(|| format!("No profile named '{}'", name))?;
+        if self.active_name().as_deref() == Some(name) {
+            let _ = fs::remove_file(self.active_path());
+        }
+        Ok(())
+    }
+
+    fn active(&self) -> Option<ActiveProfile> {
+        let content = fs::read_to_string(self.active_path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Name of the profile last switched to
+    pub fn active_name(&self) -> Option<String> {
+        self.active().map(|a| a.name)
+    }
+
+    /// Settings.json as it would look after switching to `name`
+    pub fn preview(&self, name: &str, settings_path: &Path) -> Result<(Value, Value)> {
+        let (current, merged, _) = self.plan(name, settings_path)?;
+        Ok((current, merged))
+    }
+
+    /// Current settings, settings after the switch, and what the switch adds
+    fn plan(&self, name: &str, settings_path: &Path) -> Result<(Value, Value, Value)> {
+        let fragment = self.load(name)?.fragment();
+        let current = read_settings(settings_path)?;
+        let previous = self.active().map(|a| a.applied);
+        let base = merge_profile(&current, previous.as_ref(), &Value::Object(Map::new()));
+        let merged = merge_profile(&base, None, &fragment);
+        let applied = added_by(&base, &fragment);
+        Ok((current, merged, applied))
+    }
+
+    /// Switch settings.json to a profile, backing up the previous version
+    pub fn use_profile(&self, name: &str, settings_path: &Path) -> Result<ProfileSwitch> {
+        let (current, merged, applied) = self.plan(name, settings_path)?;
+        let changes = diff_settings(&current, &merged);
+
+        let mut backup = None;
+        if !changes.is_empty() {
+            if settings_path.exists() {
+                backup = Some(self.backup(settings_path)?);
+            }
+            write_atomic(settings_path, &serde_json::to_string_pretty(&merged)?)?;
+        }
+
+        let active = ActiveProfile {
+            name: name.to_string(),
+            applied,
+        };
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.active_path(), serde_json::to_string_pretty(&active)?)?;
+
+        Ok(ProfileSwitch {
+            settings_path: settings_path.to_path_buf(),
+            changes,
+            backup,
+        })
+    }
+
+    fn backup(&self, settings_path: &Path) -> Result<PathBuf> {
+        let dir = self.backups_dir();
+        fs::create_dir_all(&dir)?;
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+        let backup = dir.join(format!("settings-{}.json", stamp));
+        fs::copy(settings_path, &backup)
+            .with_context(|| format!("Failed to back up {}", settings_path.display()))?;
+
+        let mut backups: Vec<PathBuf> = fs::read_dir(&dir)?.flatten().map(|e| e.path()).collect();
+        backups.sort();
+        let excess = backups.len().saturating_sub(MAX_BACKUPS);
+        for old in &backups[..excess] {
+            let _ = fs::remove_file(old);
+        }
+        Ok(backup)
+    }
+}
+
+/// Default profile directory under the claudev config dir
+pub fn profiles_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("claudev")
+        .join("profiles")
+}
+
+/// Claude Code's user settings, honouring `CLAUDE_CONFIG_DIR`
+pub fn settings_path() -> PathBuf {
+    std::env::var_os("CLAUDE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|h| h.join(".claude")))
+        .unwrap_or_else(|| PathBuf::from(".claude"))
+        .join("settings.json")
+}
+
+/// Read settings.json, treating a missing file as empty. Invalid JSON is an
+/// error so we never overwrite a file we could not understand.
+pub fn read_settings(path: &Path) -> Result<Value> {
+    if !path.exists() {
+        return Ok(Value::Object(Map::new()));
+    }
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(Value::Object(Map::new()));
+    }
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    if !value.is_object() {
+        return Err(anyhow!("{} is not a JSON object", path.display()));
+    }
+    Ok(value)
+}
+
+fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".claudev-tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// Merge a profile fragment into settings. Object sections (`env`, `hooks`,
+/// `mcpServers`, `permissions`) are merged entry by entry and lists within
+/// them (e.g. `permissions.allow`) are unioned; entries and list items the
+/// previous profile wrote are dropped first unless they were edited since.
+pub fn merge_profile(settings: &Value, previous: Option<&Value>, fragment: &Value) -> Value {
+    let mut merged = settings.as_object().cloned().unwrap_or_default();
+
+    if let Some(previous) = previous.and_then(Value::as_object) {
+        for (key, applied) in previous {
+            match (merged.get_mut(key), applied) {
+                (Some(Value::Object(section)), Value::Object(entries)) => {
+                    for (entry, value) in entries {
+                        match (section.get_mut(entry), value) {
+                            (Some(Value::Array(items)), Value::Array(added)) => {
+                                items.retain(|item| !added.contains(item));
+                                if items.is_empty() {
+                                    section.remove(entry);
+                                }
+                            }
+                            (Some(current), value) if current == value => {
+                                section.remove(entry);
+                            }
+                            _ => {}
+                        }
+                    }
+                    if section.is_empty() {
+                        merged.remove(key);
+                    }
+                }
+                (Some(current), applied) if current == applied => {
+                    merged.remove(key);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(fragment) = fragment.as_object() {
+        for (key, value) in fragment {
+            match (merged.get_mut(key), value) {
+                (Some(Value::Object(section)), Value::Object(entries)) => {
+                    for (entry, value) in entries {
+                        match (section.get_mut(entry), value) {
+                            (Some(Value::Array(items)), Value::Array(added)) => {
+                                for item in added {
+                                    if !items.contains(item) {
+                                        items.push(item.clone());
+                                    }
+                                }
+                            }
+                            _ => {
+                                section.insert(entry.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    merged.insert(key.clone(), value.clone());
+                }
+            }
+        }
+    }
+
+    Value::Object(merged)
+}
+
+/// The part of a fragment that merging it into `settings` adds: list items
+/// already present are left out so switching away does not remove them
+fn added_by(settings: &Value, fragment: &Value) -> Value {
+    let mut applied = fragment.clone();
+    let Some(sections) = applied.as_object_mut() else {
+        return applied;
+    };
+    for (key, section) in sections.iter_mut() {
+        let Some(entries) = section.as_object_mut() else {
+            continue;
+        };
+        for (entry, value) in entries.iter_mut() {
+            let existing = settings.get(key).and_then(|s| s.get(entry));
+            if let (Value::Array(items), Some(Value::Array(existing))) = (value, existing) {
+                items.retain(|item| !existing.contains(item));
+            }
+        }
+    }
+    applied
+}
+
+/// Semantic diff of two settings values: objects are compared key by key and
+/// string lists element by element, so reordering is not a change
+pub fn diff_settings(before: &Value, after: &Value) -> Vec<SettingChange> {
+    let mut changes = Vec::new();
+    diff_at("", before, after, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, before: &Value, after: &Value, changes: &mut Vec<SettingChange>) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    match (before, after) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                match b.get(key) {
+                    Some(other) => diff_at(&child(key), value, other, changes),
+                    None => changes.push(SettingChange::Removed {
+                        path: child(key),
+                        value: value.clone(),
+                    }),
+                }
+            }
+            for (key, value) in b {
+                if !a.contains_key(key) {
+                    changes.push(SettingChange::Added {
+                        path: child(key),
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.iter().chain(b).all(Value::is_string) && a != b => {
+            let list = format!("{}[]", path);
+            for item in a.iter().filter(|item| !b.contains(item)) {
+                changes.push(SettingChange::Removed {
+                    path: list.clone(),
+                    value: item.clone(),
+                });
+            }
+            for item in b.iter().filter(|item| !a.contains(item)) {
+                changes.push(SettingChange::Added {
+                    path: list.clone(),
+                    value: item.clone(),
+                });
+            }
+        }
+        (a, b) if a != b => changes.push(SettingChange::Changed {
+            path: path.to_string(),
+            before: a.clone(),
+            after: b.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Print a settings diff, masking values of secret-looking env vars
+pub fn print_changes(changes: &[SettingChange]) {
+    use colored::Colorize;
+
+    if changes.is_empty() {
+        println!("  (no changes)");
+        return;
+    }
+    for change in changes {
+        match change {
+            SettingChange::Added { path, value } => {
+                println!(
+                    "  {} {} = {}",
+                    "+".green(),
+                    path,
+                    show_value(path, value).green()
+                )
+            }
+            SettingChange::Removed { path, value } => {
+                println!(
+                    "  {} {} = {}",
+                    "-".red(),
+                    path,
+                    show_value(path, value).red()
+                )
+            }
+            SettingChange::Changed {
+                path,
+                before,
+                after,
+            } => println!(
+                "  {} {}: {} -> {}",
+                "~".yellow(),
+                path,
+                show_value(path, before).red(),
+                show_value(path, after).green()
+            ),
+        }
+    }
+}
+
+fn show_value(path: &str, value: &Value) -> String {
+    let upper = path.to_uppercase();
+    let secret = upper.starts_with("ENV.")
+        && ["KEY", "TOKEN", "SECRET", "PASSWORD"]
+            .iter()
+            .any(|word| upper.contains(word));
+    match value {
+        Value::String(s) if secret && s.chars().count() > 4 => {
+            let mut tail: Vec<char> = s.chars().rev().take(4).collect();
+            tail.reverse();
+            format!("\"****{}\"", tail.into_iter().collect::<String>())
+        }
+        Value::String(_) if secret => "\"****\"".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_switching_profiles_preserves_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProfileStore::new(dir.path().join("profiles"));
+        let settings = dir.path().join("settings.json");
+        fs::write(
+            &settings,
+            json!({
+                "theme": "dark",
+                "env": {"EDITOR": "vim"},
+                "permissions": {"allow": ["Read"], "defaultMode": "acceptEdits"}
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let mut work = ClaudeProfile {
+            model: Some("opus".to_string()),
+            ..Default::default()
+        };
+        work.env
+            .insert("ANTHROPIC_BASE_URL".into(), "http://127.0.0.1:1338".into());
+        work.permissions.deny.push("Bash(rm:*)".into());
+        work.mcp_servers
+            .insert("github".into(), json!({"command": "gh-mcp"}));
+        store.save("work", &work).unwrap();
+
+        let mut personal = ClaudeProfile::default();
+        personal.env.insert("DISABLE_TELEMETRY".into(), "1".into());
+        store.save("personal", &personal).unwrap();
+
+        let switch = store.use_profile("work", &settings).unwrap();
+        assert!(switch.backup.as_ref().unwrap().exists());
+        assert!(switch.changes.contains(&SettingChange::Added {
+            path: "permissions.deny".into(),
+            value: json!(["Bash(rm:*)"]),
+        }));
+        let value = read_settings(&settings).unwrap();
+        assert_eq!(value["env"]["EDITOR"], "vim");
+        assert_eq!(value["permissions"]["allow"], json!(["Read"]));
+        assert_eq!(value["mcpServers"]["github"]["command"], "gh-mcp");
+
+        store.use_profile("personal", &settings).unwrap();
+        let value = read_settings(&settings).unwrap();
+        assert_eq!(value["theme"], "dark");
+        assert_eq!(
+            value["env"],
+            json!({"EDITOR": "vim", "DISABLE_TELEMETRY": "1"})
+        );
+        assert_eq!(value["permissions"]["defaultMode"], "acceptEdits");
+        assert!(value["permissions"].get("deny").is_none());
+        assert!(value.get("model").is_none());
+        assert!(value.get("mcpServers").is_none());
+        assert_eq!(store.active_name().as_deref(), Some("personal"));
+
+        // Switching to the active profile again changes nothing
+        assert!(store
+            .use_profile("personal", &settings)
+            .unwrap()
+            .changes
+            .is_empty());
+    }
+
+    #[test]
+    fn test_semantic_diff() {
+        let before = json!({
+            "model": "sonnet",
+            "permissions": {"allow": ["Read", "Bash(ls:*)"]},
+            "env": {"A": "1"}
+        });
+        let after = json!({
+            "model": "opus",
+            "permissions": {"allow": ["Bash(ls:*)", "Read", "Edit"]},
+            "env": {"B": "2"}
+        });
+
+        let changes = diff_settings(&before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                SettingChange::Removed {
+                    path: "env.A".into(),
+                    value: json!("1")
+                },
+                SettingChange::Added {
+                    path: "env.B".into(),
+                    value: json!("2")
+                },
+                SettingChange::Changed {
+                    path: "model".into(),
+                    before: json!("sonnet"),
+                    after: json!("opus")
+                },
+                SettingChange::Added {
+                    path: "permissions.allow[]".into(),
+                    value: json!("Edit")
+                },
+            ]
+        );
+        assert_eq!(
+            show_value("env.ANTHROPIC_API_KEY", &json!("sk-ant-1234")),
+            "\"****1234\""
+        );
+        assert_eq!(
+            show_value("env.API_TOKEN", &json!("jeton-clé-été")),
+            "\"****-été\""
+        );
+    }
+
+    #[test]
+    fn test_permission_lists_are_unioned_and_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProfileStore::new(dir.path().join("profiles"));
+        let settings = dir.path().join("settings.json");
+        fs::write(
+            &settings,
+            json!({"permissions": {"allow": ["Read", "Bash(git:*)"]}}).to_string(),
+        )
+        .unwrap();
+
+        let mut strict = ClaudeProfile::default();
+        strict.permissions.allow = vec!["Read".into(), "Edit".into()];
+        strict.permissions.deny.push("WebFetch".into());
+        store.save("strict", &strict).unwrap();
+        store.save("plain", &ClaudeProfile::default()).unwrap();
+
+        store.use_profile("strict", &settings).unwrap();
+        let value = read_settings(&settings).unwrap();
+        assert_eq!(
+            value["permissions"]["allow"],
+            json!(["Read", "Bash(git:*)", "Edit"])
+        );
+        assert_eq!(value["permissions"]["deny"], json!(["WebFetch"]));
+
+        // Only what the profile added is taken back out, "Read" was the user's
+        store.use_profile("plain", &settings).unwrap();
+        let value = read_settings(&settings).unwrap();
+        assert_eq!(
+            value["permissions"],
+            json!({"allow": ["Read", "Bash(git:*)"]})
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_settings_and_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ProfileStore::new(dir.path().to_path_buf());
+        assert!(store.save("../escape", &ClaudeProfile::default()).is_err());
+
+        let settings = dir.path().join("settings.json");
+        fs::write(&settings, "{ not json").unwrap();
+        store.save("empty", &ClaudeProfile::default()).unwrap();
+        assert!(store.use_profile("empty", &settings).is_err());
+        assert_eq!(fs::read_to_string(&settings).unwrap(), "{ not json");
+    }
+}
@@ -0,0 +1,224 @@
+//! Client attribution for proxied connections
+//!
+//! Several Claude Code instances (or other tools) can share one monitor. Each
+//! connection is attributed to a client from, in order of precedence, an
+//! explicit `x-claudev-session` header, the process owning the source port
+//! (found through `/proc/net/tcp` on Linux) and the user-agent.
+
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+/// Header a client can set to name its own session
+pub const SESSION_HEADER: &str = "x-claudev-session";
+
+/// Who sent a request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    /// Product from the user-agent, e.g. `claude-cli`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Value of the `x-claudev-session` header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    /// Repository (or directory) name the process runs in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+impl ClientInfo {
+    /// Short name used to group and filter traffic
+    pub fn label(&self) -> String {
+        if let Some(session) = &self.session {
+            return session.clone();
+        }
+        let name = self.name.as_deref().unwrap_or("unknown");
+        match (&self.project, self.pid) {
+            (Some(project), Some(pid)) => format!("{}@{} ({})", name, project, pid),
+            (Some(project), None) => format!("{}@{}", name, project),
+            (None, Some(pid)) => format!("{} ({})", name, pid),
+            (None, None) => name.to_string(),
+        }
+    }
+}
+
+/// Identify the client behind a connection from `peer` to our `local` address
+pub fn identify(peer: SocketAddr, local: SocketAddr, headers: &[(String, String)]) -> ClientInfo {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    let user_agent = header("user-agent");
+    let mut info = ClientInfo {
+        name: user_agent.as_deref().map(product_name),
+        session: header(SESSION_HEADER),
+        user_agent,
+        ..Default::default()
+    };
+
+    // Only local processes can be looked up
+    if peer.ip().is_loopback() {
+        if let Some(pid) = socket_owner(peer, local) {
+            info.pid = Some(pid);
+            info.cwd = std::fs::read_link(format!("/proc/{}/cwd", pid)).ok();
+            info.project = info.cwd.as_deref().map(project_name);
+        }
+    }
+
+    info
+}
+
+/// `claude-cli/1.0.3 (external, cli)` -> `claude-cli`
+fn product_name(user_agent: &str) -> String {
+    let product = user_agent.split_whitespace().next().unwrap_or(user_agent);
+    product.split('/').next().unwrap_or(product).to_string()
+}
+
+/// Name of the git repository containing `cwd`, or of `cwd` itself
+fn project_name(cwd: &Path) -> String {
+    let root = cwd
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd);
+    root.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.display().to_string())
+}
+
+/// PID of the process owning the client end of a local TCP connection
+#[cfg(target_os = "linux")]
+fn socket_owner(peer: SocketAddr, local: SocketAddr) -> Option<u32> {
+    let inode = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .find_map(|table| find_socket_inode(&table, peer, local))?;
+    let target = format!("socket:[{}]", inode);
+
+    std::fs::read_dir("/proc")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .find(|pid| {
+            let Ok(fds) = std::fs::read_dir(format!("/proc/{}/fd", pid)) else {
+                return false;
+            };
+            fds.flatten().any(|fd| {
+                std::fs::read_link(fd.path()).is_ok_and(|link| link.to_string_lossy() == target)
+            })
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn socket_owner(_peer: SocketAddr, _local: SocketAddr) -> Option<u32> {
+    None
+}
+
+/// Inode of the socket bound to `local_addr` and connected to `remote_addr`,
+/// from the contents of `/proc/net/tcp` or `/proc/net/tcp6`
+fn find_socket_inode(table: &str, local_addr: SocketAddr, remote_addr: SocketAddr) -> Option<u64> {
+    table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 10 {
+            return None;
+        }
+        let local = parse_proc_addr(fields[1])?;
+        let remote = parse_proc_addr(fields[2])?;
+        (same_endpoint(local, local_addr) && same_endpoint(remote, remote_addr))
+            .then(|| fields[9].parse().ok())
+            .flatten()
+    })
+}
+
+/// Compare endpoints, treating IPv4-mapped IPv6 addresses as IPv4
+fn same_endpoint(a: SocketAddr, b: SocketAddr) -> bool {
+    let canonical = |ip: IpAddr| match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    };
+    a.port() == b.port() && canonical(a.ip()) == canonical(b.ip())
+}
+
+/// Parse a `/proc/net/tcp` address: hex IP in host byte order, hex port
+fn parse_proc_addr(field: &str) -> Option<SocketAddr> {
+    let (ip, port) = field.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    let ip = match ip.len() {
+        8 => IpAddr::V4(Ipv4Addr::from(
+            u32::from_str_radix(ip, 16).ok()?.to_le_bytes(),
+        )),
+        32 => {
+            let mut octets = [0u8; 16];
+            for (i, word) in octets.chunks_mut(4).enumerate() {
+                let value = u32::from_str_radix(&ip[i * 8..i * 8 + 8], 16).ok()?;
+                word.copy_from_slice(&value.to_le_bytes());
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCP_TABLE: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0539 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41235 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:D431 0100007F:0539 01 00000000:00000000 00:00000000 00000000  1000        0 41240 1 0000000000000000 20 4 30 10 -1
+";
+
+    #[test]
+    fn test_find_socket_inode() {
+        let client: SocketAddr = "127.0.0.1:54321".parse().unwrap();
+        let proxy: SocketAddr = "127.0.0.1:1337".parse().unwrap();
+        assert_eq!(find_socket_inode(TCP_TABLE, client, proxy), Some(41240));
+        assert_eq!(find_socket_inode(TCP_TABLE, proxy, client), None);
+
+        let mapped = parse_proc_addr("0000000000000000FFFF00000100007F:D431").unwrap();
+        assert!(same_endpoint(mapped, client));
+    }
+
+    #[test]
+    fn test_label_precedence() {
+        let headers = vec![(
+            "User-Agent".to_string(),
+            "claude-cli/1.0.3 (external, cli)".to_string(),
+        )];
+        let peer: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+        let local: SocketAddr = "10.0.0.1:1338".parse().unwrap();
+
+        let mut info = identify(peer, local, &headers);
+        assert_eq!(info.label(), "claude-cli");
+
+        info.project = Some("crate".to_string());
+        info.pid = Some(42);
+        assert_eq!(info.label(), "claude-cli@crate (42)");
+
+        let mut headers = headers;
+        headers.push((SESSION_HEADER.to_string(), "refactor".to_string()));
+        assert_eq!(identify(peer, local, &headers).label(), "refactor");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_identifies_own_process() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (_server, peer) = listener.accept().unwrap();
+
+        let info = identify(peer, stream.peer_addr().unwrap(), &[]);
+        assert_eq!(info.pid, Some(std::process::id()));
+        assert!(info.cwd.is_some());
+    }
+}
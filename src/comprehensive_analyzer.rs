@@ -1,5 +1,7 @@
 use crate::advanced_analytics::{AdvancedAnalytics, AdvancedAnalyzer};
 use crate::claude_code_parser::{ClaudeCodeParser, ClaudeCodeStats};
+use crate::pricing;
+use crate::traffic::Usage;
 use crate::viral_insights::{ViralAnalyzer, ViralInsights};
 use crate::work_hours_analyzer::{WorkHoursAnalysis, WorkHoursAnalyzer};
 use anyhow::Result;
@@ -10,6 +12,9 @@ use std::path::PathBuf;
 use tracing::info;
 use walkdir::WalkDir;
 
+/// Model whose rates price token counts that don't record one
+const DEFAULT_MODEL: &str = "claude-3-5-sonnet";
+
 /// Context for parsing conversation tasks - groups mutable state to reduce function arguments
 struct ParseContext<'a> {
     total_conversations: &'a mut usize,
@@ -72,6 +77,15 @@ pub struct TokenUsage {
     pub total_tokens: u64,
     pub by_tool: HashMap<String, ToolTokens>,
     pub by_model: HashMap<String, u64>,
+    /// Cost of the tokens in `by_model`, per model
+    #[serde(skip)]
+    pub cost_by_model: HashMap<String, f64>,
+    /// Priced at the rates in effect when each task ran
+    #[serde(skip)]
+    pub cost_usd: f64,
+    /// The same tokens at batch API rates
+    #[serde(skip)]
+    pub batch_cost_usd: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -79,6 +93,8 @@ pub struct ToolTokens {
     pub input: u64,
     pub output: u64,
     pub total: u64,
+    #[serde(skip)]
+    pub cost_usd: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -96,6 +112,8 @@ pub struct CostAnalysis {
     pub by_model: HashMap<String, ModelCost>,
     pub monthly_estimate: f64,
     pub potential_savings: f64,
+    /// What the same tokens would have cost through the batch API
+    pub batch_cost_usd: f64,
 }
 
 #[derive(Debug, Serialize)]
@@ -219,9 +237,24 @@ impl ComprehensiveAnalyzer {
         conv_analysis.total_messages += claude_stats.total_messages;
         conv_analysis.user_messages += claude_stats.user_messages;
         conv_analysis.assistant_messages += claude_stats.assistant_messages;
+        let claude_input = (claude_stats.estimated_tokens as f64 * 0.6) as u64;
+        let claude_output = (claude_stats.estimated_tokens as f64 * 0.4) as u64;
         token_usage.total_tokens += claude_stats.estimated_tokens;
-        token_usage.total_input_tokens += (claude_stats.estimated_tokens as f64 * 0.6) as u64;
-        token_usage.total_output_tokens += (claude_stats.estimated_tokens as f64 * 0.4) as u64;
+        token_usage.total_input_tokens += claude_input;
+        token_usage.total_output_tokens += claude_output;
+        // The estimate is undated, so it is priced at the current rate
+        let claude_usage = Usage {
+            input_tokens: claude_input,
+            output_tokens: claude_output,
+            ..Default::default()
+        };
+        let price = pricing::global().lookup(DEFAULT_MODEL, None);
+        let claude_cost = price.cost(&claude_usage);
+        *token_usage.by_model.entry(price.model.clone()).or_insert(0) +=
+            claude_stats.estimated_tokens;
+        *token_usage.cost_by_model.entry(price.model.clone()).or_insert(0.0) += claude_cost;
+        token_usage.cost_usd += claude_cost;
+        token_usage.batch_cost_usd += price.cost_batch(&claude_usage);
 
         let code_attribution = self.analyze_code_attribution()?;
         let cost_analysis = self.calculate_costs(&token_usage)?;
@@ -408,10 +441,15 @@ impl ComprehensiveAnalyzer {
     fn analyze_tokens(&self) -> Result<TokenUsage> {
         info!("💰 Analyzing token usage (estimating from message lengths)...");
 
+        let pricing = pricing::global();
         let mut total_input = 0u64;
         let mut total_output = 0u64;
+        let mut total_cost = 0.0;
+        let mut batch_cost = 0.0;
         let mut by_tool: HashMap<String, ToolTokens> = HashMap::new();
+        // Messages don't name their model, so they are all priced as the default
         let mut by_model: HashMap<String, u64> = HashMap::new();
+        let mut cost_by_model: HashMap<String, f64> = HashMap::new();
 
         // Parse token data from API conversation history
         let patterns = vec![
@@ -438,6 +476,14 @@ impl ComprehensiveAnalyzer {
                 if !api_history.exists() {
                     continue;
                 }
+                // Task directories are named after their creation time in milliseconds
+                let task_date = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.parse::<i64>().ok())
+                    .and_then(chrono::DateTime::from_timestamp_millis)
+                    .map(|t| t.date_naive());
+                let price = pricing.lookup(DEFAULT_MODEL, task_date);
 
                 if let Ok(content) = fs::read_to_string(&api_history) {
                     if let Ok(messages) = serde_json::from_str::<Vec<ClineMessage>>(&content) {
@@ -470,34 +516,47 @@ impl ComprehensiveAnalyzer {
                                 }
                             };
 
+                            let usage = Usage {
+                                input_tokens,
+                                output_tokens,
+                                ..Default::default()
+                            };
+                            let cost = price.cost(&usage);
                             total_input += input_tokens;
                             total_output += output_tokens;
+                            total_cost += cost;
+                            batch_cost += price.cost_batch(&usage);
+                            *by_model.entry(price.model.clone()).or_insert(0) +=
+                                input_tokens + output_tokens;
+                            *cost_by_model.entry(price.model.clone()).or_insert(0.0) += cost;
 
                             let tool_tokens =
                                 by_tool.entry(tool_name.to_string()).or_insert(ToolTokens {
                                     input: 0,
                                     output: 0,
                                     total: 0,
+                                    cost_usd: 0.0,
                                 });
 
                             tool_tokens.input += input_tokens;
                             tool_tokens.output += output_tokens;
                             tool_tokens.total += input_tokens + output_tokens;
+                            tool_tokens.cost_usd += cost;
                         }
                     }
                 }
             }
         }
 
-        // Assume Claude Sonnet 3.5 as default model
-        *by_model.entry("claude-sonnet-3.5".to_string()).or_insert(0) = total_input + total_output;
-
         Ok(TokenUsage {
             total_input_tokens: total_input,
             total_output_tokens: total_output,
             total_tokens: total_input + total_output,
             by_tool,
             by_model,
+            cost_by_model,
+            cost_usd: total_cost,
+            batch_cost_usd: batch_cost,
         })
     }
 
@@ -575,36 +634,24 @@ impl ComprehensiveAnalyzer {
     }
 
     fn calculate_costs(&self, token_usage: &TokenUsage) -> Result<CostAnalysis> {
-        info!("💵 Calculating costs (Claude Sonnet 3.5 pricing)...");
-
-        // Claude 3.5 Sonnet pricing (as of Jan 2025)
-        let input_cost_per_mtok = 3.0; // $3 per million input tokens
-        let output_cost_per_mtok = 15.0; // $15 per million output tokens
+        info!("💵 Calculating costs from pricing table...");
 
-        let input_cost =
-            (token_usage.total_input_tokens as f64 / 1_000_000.0) * input_cost_per_mtok;
-        let output_cost =
-            (token_usage.total_output_tokens as f64 / 1_000_000.0) * output_cost_per_mtok;
-        let total_cost = input_cost + output_cost;
+        // Tokens were priced per task date while they were counted
+        let total_cost = token_usage.cost_usd;
 
         let mut by_tool = HashMap::new();
         let mut by_model = HashMap::new();
 
         for (tool, tokens) in &token_usage.by_tool {
-            let tool_input_cost = (tokens.input as f64 / 1_000_000.0) * input_cost_per_mtok;
-            let tool_output_cost = (tokens.output as f64 / 1_000_000.0) * output_cost_per_mtok;
-            by_tool.insert(tool.clone(), tool_input_cost + tool_output_cost);
+            by_tool.insert(tool.clone(), tokens.cost_usd);
         }
 
         for (model, tokens) in &token_usage.by_model {
-            // Calculate cost based on assumed 50/50 input/output split
-            let estimated_cost = (*tokens as f64 / 1_000_000.0)
-                * ((input_cost_per_mtok + output_cost_per_mtok) / 2.0);
             by_model.insert(
                 model.clone(),
                 ModelCost {
                     tokens: *tokens,
-                    cost: estimated_cost,
+                    cost: token_usage.cost_by_model.get(model).copied().unwrap_or(0.0),
                 },
             );
         }
@@ -618,6 +665,7 @@ impl ComprehensiveAnalyzer {
             by_model,
             monthly_estimate: total_cost, // This is historical total
             potential_savings,
+            batch_cost_usd: token_usage.batch_cost_usd,
         })
     }
 
@@ -0,0 +1,669 @@
+//! Context-window composition analysis
+//!
+//! Attributes the input tokens of each request to the part of the context
+//! they came from (system prompt, tool definitions, CLAUDE.md, file reads,
+//! other tool results, conversation history), tracks how that mix grows over
+//! a session, and flags requests dominated by stale tool results.
+//!
+//! Works on captured traffic entries and on Claude Code transcripts
+//! (`~/.claude/projects/<project>/<session>.jsonl`), which lack the system
+//! prompt and tool definitions but carry the same messages and usage.
+
+use crate::prompt_cache::session_key;
+use crate::traffic::{ContentBlock, Message, MessageContent, TrafficEntry, Usage};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Tool results older than this many messages count as stale
+const STALE_AFTER_MESSAGES: usize = 6;
+/// Requests whose stale tool results exceed this share of the context get flagged
+const STALE_DOMINANCE: f64 = 0.5;
+/// Tools whose results are file contents
+const FILE_READ_TOOLS: &[&str] = &["Read", "NotebookRead", "read_file", "View"];
+
+/// Where a slice of the context came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ContextBucket {
+    SystemPrompt,
+    ToolDefinitions,
+    ClaudeMd,
+    FileReads,
+    ToolResults,
+    History,
+}
+
+impl ContextBucket {
+    pub const ALL: [ContextBucket; 6] = [
+        ContextBucket::SystemPrompt,
+        ContextBucket::ToolDefinitions,
+        ContextBucket::ClaudeMd,
+        ContextBucket::FileReads,
+        ContextBucket::ToolResults,
+        ContextBucket::History,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ContextBucket::SystemPrompt => "System",
+            ContextBucket::ToolDefinitions => "Tools",
+            ContextBucket::ClaudeMd => "CLAUDE.md",
+            ContextBucket::FileReads => "File reads",
+            ContextBucket::ToolResults => "Tool results",
+            ContextBucket::History => "History",
+        }
+    }
+}
+
+/// Token counts per context bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ContextComposition {
+    pub system_prompt: u64,
+    pub tool_definitions: u64,
+    pub claude_md: u64,
+    pub file_reads: u64,
+    pub tool_results: u64,
+    pub history: u64,
+}
+
+impl ContextComposition {
+    pub fn get(&self, bucket: ContextBucket) -> u64 {
+        match bucket {
+            ContextBucket::SystemPrompt => self.system_prompt,
+            ContextBucket::ToolDefinitions => self.tool_definitions,
+            ContextBucket::ClaudeMd => self.claude_md,
+            ContextBucket::FileReads => self.file_reads,
+            ContextBucket::ToolResults => self.tool_results,
+            ContextBucket::History => self.history,
+        }
+    }
+
+    fn get_mut(&mut self, bucket: ContextBucket) -> &mut u64 {
+        match bucket {
+            ContextBucket::SystemPrompt => &mut self.system_prompt,
+            ContextBucket::ToolDefinitions => &mut self.tool_definitions,
+            ContextBucket::ClaudeMd => &mut self.claude_md,
+            ContextBucket::FileReads => &mut self.file_reads,
+            ContextBucket::ToolResults => &mut self.tool_results,
+            ContextBucket::History => &mut self.history,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        ContextBucket::ALL.iter().map(|b| self.get(*b)).sum()
+    }
+
+    /// Fraction of the context taken by a bucket
+    pub fn share(&self, bucket: ContextBucket) -> f64 {
+        let total = self.total();
+        if total == 0 {
+            0.0
+        } else {
+            self.get(bucket) as f64 / total as f64
+        }
+    }
+
+    fn scaled(&self, factor: f64) -> Self {
+        let mut scaled = Self::default();
+        for bucket in ContextBucket::ALL {
+            *scaled.get_mut(bucket) = (self.get(bucket) as f64 * factor).round() as u64;
+        }
+        scaled
+    }
+}
+
+/// Composition of one request's context
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestComposition {
+    pub id: u64,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub session: String,
+    pub composition: ContextComposition,
+    /// Prompt tokens reported by the API (input + cache read + cache write)
+    pub actual_prompt_tokens: Option<u64>,
+    /// Tokens of tool results older than `STALE_AFTER_MESSAGES` messages
+    pub stale_tool_result_tokens: u64,
+    /// Set when stale tool results dominate the context
+    pub flagged: bool,
+}
+
+impl RequestComposition {
+    pub fn stale_share(&self) -> f64 {
+        let total = self.composition.total();
+        if total == 0 {
+            0.0
+        } else {
+            self.stale_tool_result_tokens as f64 / total as f64
+        }
+    }
+}
+
+/// How a session's context grew from its first to its last request
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionGrowth {
+    pub session: String,
+    pub requests: usize,
+    pub first: ContextComposition,
+    pub last: ContextComposition,
+    pub peak_tokens: u64,
+    pub flagged_requests: usize,
+}
+
+impl SessionGrowth {
+    /// Bucket that grew the most over the session
+    pub fn fastest_growing(&self) -> Option<ContextBucket> {
+        ContextBucket::ALL
+            .iter()
+            .copied()
+            .max_by_key(|b| self.last.get(*b).saturating_sub(self.first.get(*b)))
+            .filter(|b| self.last.get(*b) > self.first.get(*b))
+    }
+}
+
+/// Context composition over a set of requests
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CompositionReport {
+    pub requests: Vec<RequestComposition>,
+    pub sessions: Vec<SessionGrowth>,
+}
+
+impl CompositionReport {
+    pub fn flagged(&self) -> impl Iterator<Item = &RequestComposition> {
+        self.requests.iter().filter(|r| r.flagged)
+    }
+
+    fn from_requests(requests: Vec<RequestComposition>) -> Self {
+        let mut order: Vec<String> = Vec::new();
+        let mut by_session: HashMap<String, Vec<&RequestComposition>> = HashMap::new();
+        for request in &requests {
+            if !by_session.contains_key(&request.session) {
+                order.push(request.session.clone());
+            }
+            by_session
+                .entry(request.session.clone())
+                .or_default()
+                .push(request);
+        }
+
+        let sessions = order
+            .iter()
+            .filter_map(|key| {
+                let reqs = by_session.get(key)?;
+                Some(SessionGrowth {
+                    session: key.clone(),
+                    requests: reqs.len(),
+                    first: reqs.first()?.composition.clone(),
+                    last: reqs.last()?.composition.clone(),
+                    peak_tokens: reqs.iter().map(|r| r.composition.total()).max()?,
+                    flagged_requests: reqs.iter().filter(|r| r.flagged).count(),
+                })
+            })
+            .collect();
+
+        Self { requests, sessions }
+    }
+}
+
+pub(crate) fn estimate_tokens(chars: usize) -> u64 {
+    (chars / 4) as u64
+}
+
+/// Length of the text in a tool result's `content` (string or blocks)
+pub(crate) fn value_text_len(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item.get("text").and_then(|t| t.as_str()) {
+                Some(text) => text.len(),
+                None => item.to_string().len(),
+            })
+            .sum(),
+        other => other.to_string().len(),
+    }
+}
+
+fn is_claude_md(text: &str) -> bool {
+    text.contains("# claudeMd") || (text.contains("Contents of ") && text.contains("CLAUDE.md"))
+}
+
+fn text_bucket(text: &str, default: ContextBucket) -> ContextBucket {
+    if is_claude_md(text) {
+        ContextBucket::ClaudeMd
+    } else {
+        default
+    }
+}
+
+/// Estimate the composition of a request from its parts
+fn compose(
+    system: Option<&MessageContent>,
+    tools: Option<&Vec<Value>>,
+    messages: &[Message],
+) -> (ContextComposition, u64) {
+    let mut composition = ContextComposition::default();
+    let mut stale = 0u64;
+
+    if let Some(system) = system {
+        match system {
+            MessageContent::Text(text) => {
+                let bucket = text_bucket(text, ContextBucket::SystemPrompt);
+                *composition.get_mut(bucket) += estimate_tokens(text.len());
+            }
+            MessageContent::Blocks(blocks) => {
+                for block in blocks {
+                    let text = block.text.as_deref().unwrap_or("");
+                    let bucket = text_bucket(text, ContextBucket::SystemPrompt);
+                    *composition.get_mut(bucket) += estimate_tokens(text.len());
+                }
+            }
+        }
+    }
+
+    if let Some(tools) = tools {
+        let json = serde_json::to_string(tools).unwrap_or_default();
+        composition.tool_definitions += estimate_tokens(json.len());
+    }
+
+    // Map tool_use ids to tool names so results can be attributed
+    let tool_names: HashMap<&str, &str> = messages
+        .iter()
+        .filter_map(|m| match &m.content {
+            MessageContent::Blocks(blocks) => Some(blocks),
+            MessageContent::Text(_) => None,
+        })
+        .flatten()
+        .filter(|b| b.block_type == "tool_use")
+        .filter_map(|b| Some((b.id.as_deref()?, b.name.as_deref()?)))
+        .collect();
+
+    let stale_before = messages.len().saturating_sub(STALE_AFTER_MESSAGES);
+
+    for (index, message) in messages.iter().enumerate() {
+        match &message.content {
+            MessageContent::Text(text) => {
+                let bucket = text_bucket(text, ContextBucket::History);
+                *composition.get_mut(bucket) += estimate_tokens(text.len());
+            }
+            MessageContent::Blocks(blocks) => {
+                for block in blocks {
+                    let (bucket, tokens) = block_bucket(block, &tool_names);
+                    *composition.get_mut(bucket) += tokens;
+                    if index < stale_before
+                        && matches!(bucket, ContextBucket::FileReads | ContextBucket::ToolResults)
+                    {
+                        stale += tokens;
+                    }
+                }
+            }
+        }
+    }
+
+    (composition, stale)
+}
+
+fn block_bucket(block: &ContentBlock, tool_names: &HashMap<&str, &str>) -> (ContextBucket, u64) {
+    match block.block_type.as_str() {
+        "tool_result" => {
+            let len = block.content.as_ref().map(value_text_len).unwrap_or(0);
+            let tool = block
+                .tool_use_id
+                .as_deref()
+                .and_then(|id| tool_names.get(id));
+            let bucket = match tool {
+                Some(name) if FILE_READ_TOOLS.contains(name) => ContextBucket::FileReads,
+                _ => ContextBucket::ToolResults,
+            };
+            (bucket, estimate_tokens(len))
+        }
+        "tool_use" => {
+            let len = block.input.as_ref().map(|i| i.to_string().len()).unwrap_or(0);
+            (ContextBucket::History, estimate_tokens(len))
+        }
+        _ => {
+            let text = block.text.as_deref().unwrap_or("");
+            (
+                text_bucket(text, ContextBucket::History),
+                estimate_tokens(text.len()),
+            )
+        }
+    }
+}
+
+fn prompt_tokens(usage: &Usage) -> u64 {
+    usage.input_tokens + usage.cache_read_input_tokens + usage.cache_creation_input_tokens
+}
+
+/// Build a request composition, scaling estimates to the reported token count
+fn request_composition(
+    id: u64,
+    timestamp: Option<DateTime<Utc>>,
+    session: String,
+    estimated: (ContextComposition, u64),
+    usage: Option<&Usage>,
+) -> RequestComposition {
+    let (mut composition, mut stale) = estimated;
+    let actual = usage.map(prompt_tokens).filter(|t| *t > 0);
+
+    let estimated_total = composition.total();
+    if let Some(actual) = actual {
+        if estimated_total > 0 {
+            let factor = actual as f64 / estimated_total as f64;
+            composition = composition.scaled(factor);
+            stale = (stale as f64 * factor).round() as u64;
+        }
+    }
+
+    let mut request = RequestComposition {
+        id,
+        timestamp,
+        session,
+        composition,
+        actual_prompt_tokens: actual,
+        stale_tool_result_tokens: stale,
+        flagged: false,
+    };
+    request.flagged = request.stale_share() > STALE_DOMINANCE;
+    request
+}
+
+/// Analyze captured traffic entries (oldest first)
+pub fn analyze_traffic(entries: &[TrafficEntry]) -> CompositionReport {
+    let requests = entries
+        .iter()
+        .map(|entry| {
+            let req = &entry.request;
+            request_composition(
+                entry.id,
+                Some(entry.timestamp),
+                session_key(req),
+                compose(req.system.as_ref(), req.tools.as_ref(), &req.messages),
+                entry.response.as_ref().and_then(|r| r.usage.as_ref()),
+            )
+        })
+        .collect();
+
+    CompositionReport::from_requests(requests)
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptLine {
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(default, rename = "isSidechain")]
+    is_sidechain: bool,
+    #[serde(default)]
+    message: Option<TranscriptMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptMessage {
+    #[serde(default)]
+    id: Option<String>,
+    role: String,
+    content: MessageContent,
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+/// Analyze a Claude Code transcript; each assistant reply marks one request
+pub fn analyze_transcript(path: &Path) -> Result<CompositionReport> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read transcript {}", path.display()))?;
+    let session = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("transcript")
+        .to_string();
+
+    let mut messages: Vec<Message> = Vec::new();
+    let mut seen_replies: HashSet<String> = HashSet::new();
+    let mut requests = Vec::new();
+
+    for line in content.lines() {
+        let parsed: TranscriptLine = match serde_json::from_str(line) {
+            Ok(parsed) => parsed,
+            Err(_) => continue,
+        };
+        if parsed.is_sidechain {
+            continue;
+        }
+        let message = match parsed.message {
+            Some(m) => m,
+            None => continue,
+        };
+
+        // Assistant replies are logged once per content block; the first marks the request
+        let new_reply = message.role == "assistant"
+            && message
+                .id
+                .as_ref()
+                .is_none_or(|id| seen_replies.insert(id.clone()));
+
+        if new_reply && !messages.is_empty() {
+            requests.push(request_composition(
+                requests.len() as u64 + 1,
+                parsed.timestamp,
+                session.clone(),
+                compose(None, None, &messages),
+                message.usage.as_ref(),
+            ));
+        }
+
+        messages.push(Message {
+            role: message.role,
+            content: message.content,
+        });
+    }
+
+    Ok(CompositionReport::from_requests(requests))
+}
+
+/// Read a file of traffic entries (JSONL). The content is returned as well so
+/// callers can fall back to reading it as a transcript when it holds no entries.
+pub fn load_traffic_file(path: &Path) -> Result<(String, Vec<TrafficEntry>)> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let entries = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    Ok((content, entries))
+}
+
+/// Load a report from a file of traffic entries (JSONL) or a Claude Code transcript
+pub fn analyze_file(path: &Path) -> Result<CompositionReport> {
+    let (_, entries) = load_traffic_file(path)?;
+
+    if entries.is_empty() {
+        analyze_transcript(path)
+    } else {
+        Ok(analyze_traffic(&entries))
+    }
+}
+
+/// Print a report as tables
+pub fn print_report(report: &CompositionReport, flagged_only: bool) {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+
+    let mut header = vec![Cell::new("Req").fg(Color::Cyan), Cell::new("Total").fg(Color::Cyan)];
+    header.extend(
+        ContextBucket::ALL
+            .iter()
+            .map(|b| Cell::new(b.label()).fg(Color::Cyan)),
+    );
+    header.push(Cell::new("Stale").fg(Color::Cyan));
+    table.set_header(header);
+
+    let requests: Vec<&RequestComposition> = if flagged_only {
+        report.flagged().collect()
+    } else {
+        report.requests.iter().collect()
+    };
+
+    for request in requests {
+        let composition = &request.composition;
+        let mut row = vec![
+            Cell::new(format!("#{}", request.id)),
+            Cell::new(composition.total()),
+        ];
+        row.extend(ContextBucket::ALL.iter().map(|b| {
+            Cell::new(format!(
+                "{} ({:.0}%)",
+                composition.get(*b),
+                composition.share(*b) * 100.0
+            ))
+        }));
+        let stale = Cell::new(format!("{:.0}%", request.stale_share() * 100.0));
+        row.push(if request.flagged { stale.fg(Color::Red) } else { stale });
+        table.add_row(row);
+    }
+
+    println!("{}", table);
+    println!();
+
+    for session in &report.sessions {
+        let first = session.first.total();
+        let last = session.last.total();
+        let growth = if first > 0 {
+            (last as f64 / first as f64 - 1.0) * 100.0
+        } else {
+            0.0
+        };
+        println!(
+            "{} {}: {} requests, {} → {} tokens ({:+.0}%), peak {}",
+            "Session".cyan().bold(),
+            session.session,
+            session.requests,
+            first,
+            last,
+            growth,
+            session.peak_tokens
+        );
+        if let Some(bucket) = session.fastest_growing() {
+            println!("   Fastest growing: {}", bucket.label().yellow());
+        }
+        if session.flagged_requests > 0 {
+            println!(
+                "   {} requests dominated by stale tool results",
+                session.flagged_requests.to_string().red()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn blocks(value: Value) -> MessageContent {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_attributes_file_reads_and_tool_results() {
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: MessageContent::Text("Fix the bug".to_string()),
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: blocks(json!([
+                    {"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "a.rs"}},
+                    {"type": "tool_use", "id": "t2", "name": "Bash", "input": {"command": "ls"}}
+                ])),
+            },
+            Message {
+                role: "user".to_string(),
+                content: blocks(json!([
+                    {"type": "tool_result", "tool_use_id": "t1", "content": "x".repeat(400)},
+                    {"type": "tool_result", "tool_use_id": "t2", "content": [{"type": "text", "text": "y".repeat(200)}]}
+                ])),
+            },
+        ];
+        let system = MessageContent::Text("s".repeat(800));
+
+        let (composition, stale) = compose(Some(&system), None, &messages);
+        assert_eq!(composition.system_prompt, 200);
+        assert_eq!(composition.file_reads, 100);
+        assert_eq!(composition.tool_results, 50);
+        assert_eq!(stale, 0);
+    }
+
+    #[test]
+    fn test_detects_claude_md() {
+        let text = "<system-reminder>Contents of /repo/CLAUDE.md (project instructions):\nUse tabs";
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Text(text.to_string()),
+        }];
+        let (composition, _) = compose(None, None, &messages);
+        assert!(composition.claude_md > 0);
+        assert_eq!(composition.history, 0);
+    }
+
+    #[test]
+    fn test_flags_stale_tool_results() {
+        let mut messages = vec![
+            Message {
+                role: "assistant".to_string(),
+                content: blocks(json!([{"type": "tool_use", "id": "t1", "name": "Bash", "input": {}}])),
+            },
+            Message {
+                role: "user".to_string(),
+                content: blocks(json!([{"type": "tool_result", "tool_use_id": "t1", "content": "z".repeat(40_000)}])),
+            },
+        ];
+        for i in 0..STALE_AFTER_MESSAGES {
+            messages.push(Message {
+                role: if i % 2 == 0 { "assistant" } else { "user" }.to_string(),
+                content: MessageContent::Text("short".to_string()),
+            });
+        }
+
+        let request = request_composition(
+            1,
+            None,
+            "s".to_string(),
+            compose(None, None, &messages),
+            None,
+        );
+        assert!(request.flagged);
+        assert!(request.stale_share() > 0.9);
+    }
+
+    #[test]
+    fn test_scales_to_reported_usage() {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: MessageContent::Text("a".repeat(400)),
+        }];
+        let usage = Usage {
+            input_tokens: 150,
+            cache_read_input_tokens: 50,
+            ..Default::default()
+        };
+        let request = request_composition(
+            1,
+            None,
+            "s".to_string(),
+            compose(None, None, &messages),
+            Some(&usage),
+        );
+        assert_eq!(request.composition.history, 200);
+        assert_eq!(request.actual_prompt_tokens, Some(200));
+    }
+}
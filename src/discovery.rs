@@ -201,6 +201,7 @@ impl LogDiscovery {
             ("debug", LogType::Debug),
             ("file-history", LogType::FileHistory),
             ("history.jsonl", LogType::History),
+            ("projects", LogType::Session), // Claude Code conversation transcripts
             ("sessions", LogType::Session),
             ("session-env", LogType::Session),
             ("telemetry", LogType::Telemetry),
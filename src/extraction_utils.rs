@@ -1,7 +1,9 @@
 #![allow(dead_code)]
 
+use crate::tool_analytics::{self, ToolInvocation};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -179,16 +181,41 @@ fn parse_claude_code_conversation(path: &Path) -> Result<Conversation> {
     let content = fs::read_to_string(path)?;
     let mut messages = Vec::new();
 
+    // Tool results arrive on later lines, so pair them up front
+    let invocations: HashMap<String, ToolInvocation> =
+        tool_analytics::pair_tool_calls(tool_analytics::transcript_events(&content))
+            .into_iter()
+            .map(|i| (i.id.clone(), i))
+            .collect();
+
     for line in content.lines() {
         if line.trim().is_empty() {
             continue;
         }
 
         if let Ok(msg) = serde_json::from_str::<serde_json::Value>(line) {
-            if let Some(role) = msg.get("role").and_then(|r| r.as_str()) {
-                let content = msg
-                    .get("content")
-                    .map(|c| c.to_string())
+            // Current transcripts nest the API message under `message`
+            let inner = msg.get("message").unwrap_or(&msg);
+            if let Some(role) = inner.get("role").and_then(|r| r.as_str()) {
+                let body = inner.get("content");
+                let content = body.map(|c| c.to_string()).unwrap_or_default();
+
+                let tool_calls = body
+                    .and_then(|c| c.as_array())
+                    .map(|blocks| {
+                        blocks
+                            .iter()
+                            .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                            .filter_map(|b| b.get("id").and_then(|id| id.as_str()))
+                            .filter_map(|id| invocations.get(id))
+                            .map(|i| ToolCall {
+                                tool: i.name.clone(),
+                                parameters: i.input.clone(),
+                                result: i.result_preview.clone(),
+                                success: i.completed && !i.is_error,
+                            })
+                            .collect()
+                    })
                     .unwrap_or_default();
 
                 messages.push(Message {
@@ -198,7 +225,7 @@ fn parse_claude_code_conversation(path: &Path) -> Result<Conversation> {
                         .get("timestamp")
                         .and_then(|t| t.as_str())
                         .map(|s| s.to_string()),
-                    tool_calls: Vec::new(),
+                    tool_calls,
                     tokens: None,
                 });
             }
@@ -137,6 +137,11 @@ impl AgenticToolUseExtractor {
                     && msg.tool_calls.iter().any(|t| t.success)
                 {
                     "success"
+                } else if !msg.tool_calls.is_empty()
+                    && msg.tool_calls.iter().all(|t| t.result.is_some())
+                {
+                    // Every tool answered, and every answer was an error
+                    "failure"
                 } else {
                     "partial"
                 }
@@ -147,7 +152,9 @@ impl AgenticToolUseExtractor {
 
             // Track files that might have been modified
             for tool_call in &msg.tool_calls {
-                if tool_call.tool == "write" || tool_call.tool == "edit" {
+                if tool_call.tool.eq_ignore_ascii_case("write")
+                    || tool_call.tool.eq_ignore_ascii_case("edit")
+                {
                     if let Some(file) = tool_call
                         .parameters
                         .get("file_path")
@@ -238,12 +245,15 @@ impl AgenticToolUseExtractor {
             .filter(|s| s.step_outcome == "failure")
             .count();
 
+        // Several tool calls in one response run in parallel
+        let parallel_tool_use = trajectory.iter().any(|s| s.tool_calls.len() > 1);
+
         SequenceFeatures {
             planning_quality: if has_planning { 0.8 } else { 0.3 },
             tool_diversity,
             error_recovery_count: error_recovery,
-            context_switches: 0,       // TODO: Detect context switches
-            parallel_tool_use: false,  // TODO: Detect parallel calls
+            context_switches: 0, // TODO: Detect context switches
+            parallel_tool_use,
             reads_before_writes: true, // TODO: Analyze read/write order
         }
     }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::fs;
 use std::path::PathBuf;
 use tracing::{info, Level};
@@ -29,6 +29,8 @@ mod workflow_correlation;
 // mod infographics;  // Temporarily disabled due to compilation errors
 mod cache;
 mod claude_config;
+mod claude_profiles;
+mod claude_doctor;
 mod daemon;
 mod dataset_extractor;
 mod deep_insights;
@@ -48,6 +50,19 @@ mod search;
 mod traffic;
 mod proxy;
 mod tui_traffic;
+mod prompt_cache;
+mod pricing;
+mod context_composition;
+mod budget;
+mod secret_scan;
+mod traffic_store;
+mod traffic_export;
+mod tool_analytics;
+mod client_id;
+mod mock_server;
+mod patch_manager;
+mod secret_store;
+mod mcp_audit;
 
 use analysis::Analyzer;
 use backup::BackupManager;
@@ -329,15 +344,19 @@ enum Commands {
         precision: String,
     },
 
-    /// Manage Claude Code provider configuration
+    /// Manage Claude Code provider configuration and settings profiles
     Claude {
-        /// Action: list, show, set
+        /// Action: list, show, set, migrate, doctor, profile
         #[arg(default_value = "show")]
         action: String,
 
-        /// Provider name (z.ai, openrouter, chatgpt, litellm, custom)
+        /// Provider name (z.ai, openrouter, chatgpt, litellm, custom), or for
+        /// `profile`: list, show, save, diff, use, delete
         provider: Option<String>,
 
+        /// Profile name (for `profile` actions)
+        name: Option<String>,
+
         /// API key for the provider
         #[arg(short, long)]
         api_key: Option<String>,
@@ -357,6 +376,10 @@ enum Commands {
         /// Apply configuration to Claude Code's config files
         #[arg(long)]
         apply: bool,
+
+        /// Print doctor results as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Generate coding journey timeline visualization
@@ -414,7 +437,10 @@ enum Commands {
     },
 
     /// Search indexed logs with full-text and filters
-    Search(search::cli::SearchArgs),
+    Search(Box<search::cli::SearchArgs>),
+
+    /// Check saved searches for matches logged since the last check
+    Alerts(search::cli::AlertsArgs),
 
     /// Demo beautiful ASCII charts (showcase all visualization types)
     DemoCharts,
@@ -425,7 +451,7 @@ enum Commands {
         #[arg(short, long, default_value = "8080")]
         port: u16,
 
-        /// Export traffic to JSONL file
+        /// Export captured traffic on exit (.har for HAR, .otlp for OTLP/JSON, otherwise JSONL)
         #[arg(short, long)]
         export: Option<PathBuf>,
 
@@ -436,6 +462,129 @@ enum Commands {
         /// Show setup instructions
         #[arg(long)]
         setup: bool,
+
+        /// Show budget usage against configured limits and exit
+        #[arg(long)]
+        status: bool,
+
+        /// Show the log of secrets detected in outbound requests and exit
+        #[arg(long)]
+        secrets: bool,
+
+        /// Replace detected secrets in requests before forwarding them
+        #[arg(long)]
+        redact_secrets: bool,
+
+        /// Days of traffic to keep in the on-disk store
+        #[arg(long, default_value_t = traffic_store::DEFAULT_RETENTION_DAYS)]
+        retention_days: u32,
+
+        /// API base URL to forward plaintext requests to, e.g. a `claudev mock-server`
+        #[arg(long)]
+        upstream: Option<String>,
+    },
+
+    /// Serve a mock Anthropic Messages API for offline testing and demos
+    MockServer {
+        /// Port to listen on
+        #[arg(short, long, default_value = "1339")]
+        port: u16,
+
+        /// Replies to serve in order: a JSON fixture array or recorded traffic (.jsonl, .jsonl.gz)
+        #[arg(short, long)]
+        script: Option<PathBuf>,
+
+        /// Delay before each response in milliseconds
+        #[arg(long, default_value = "0")]
+        latency: u64,
+
+        /// Delay between streamed events in milliseconds
+        #[arg(long, default_value = "20")]
+        chunk_delay: u64,
+
+        /// Fail every n-th request
+        #[arg(long)]
+        error_every: Option<usize>,
+
+        /// HTTP status of injected failures
+        #[arg(long, default_value = "529")]
+        error_status: u16,
+
+        /// Input tokens reported for unscripted usage
+        #[arg(long)]
+        input_tokens: Option<u64>,
+
+        /// Output tokens reported for unscripted usage
+        #[arg(long)]
+        output_tokens: Option<u64>,
+
+        /// Require this API key on every request
+        #[arg(long)]
+        api_key: Option<String>,
+    },
+
+    /// Query or export stored API traffic
+    Traffic(Box<TrafficArgs>),
+
+    /// Break down context-window usage of captured traffic or a Claude Code transcript
+    Context {
+        /// Traffic entries (JSONL) or Claude Code transcript; defaults to today's traffic store records
+        path: Option<PathBuf>,
+
+        /// Only show requests dominated by stale tool results
+        #[arg(long)]
+        flagged: bool,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Tool-call latency, failures and retry loops
+    Tools {
+        /// Traffic entries (JSONL) or Claude Code transcript; defaults to today's traffic store records
+        path: Option<PathBuf>,
+
+        /// Maximum number of tools to list
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Audit configured MCP servers against their use in transcripts
+    Mcp {
+        /// Action: audit, list
+        #[arg(default_value = "audit")]
+        action: String,
+
+        /// Only include servers and transcripts of this project
+        #[arg(short, long)]
+        project: Option<PathBuf>,
+
+        /// Days of captured traffic to measure tool definitions from
+        #[arg(long, default_value = "7")]
+        days: i64,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Manage API keys in the OS keyring or the encrypted secrets file
+    Secret {
+        /// Action: status, get, set, delete
+        #[arg(default_value = "status")]
+        action: String,
+
+        /// Secret handle (get, delete), e.g. keyring:claude/z.ai, or name (set)
+        name: Option<String>,
+
+        /// Backend for `set`: keyring or file (default: keyring when available)
+        #[arg(long)]
+        backend: Option<String>,
     },
 
     /// Patch Claude to route traffic through claudev monitor
@@ -448,7 +597,7 @@ enum Commands {
         #[arg(long)]
         restore: bool,
 
-        /// Show current patch status
+        /// Show current patch status, with a diff against the original
         #[arg(long)]
         status: bool,
 
@@ -459,6 +608,74 @@ enum Commands {
     },
 }
 
+/// Arguments of `claudev traffic`
+#[derive(Args)]
+struct TrafficArgs {
+    /// Action: query, export, rotate
+    #[arg(default_value = "query")]
+    action: String,
+
+    /// Only requests whose model contains this string
+    #[arg(short, long)]
+    model: Option<String>,
+
+    /// Only requests with this status (success, error, pending, streaming)
+    #[arg(short, long)]
+    status: Option<String>,
+
+    /// Only requests from clients whose label contains this string
+    #[arg(long)]
+    client: Option<String>,
+
+    /// Start time: YYYY-MM-DD, RFC 3339, or relative (30m, 24h, 7d, 2w)
+    #[arg(long)]
+    since: Option<String>,
+
+    /// End time, same formats as --since
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Minimum request cost in USD
+    #[arg(long)]
+    min_cost: Option<f64>,
+
+    /// Maximum request cost in USD
+    #[arg(long)]
+    max_cost: Option<f64>,
+
+    /// Minimum latency in milliseconds
+    #[arg(long)]
+    min_latency: Option<u64>,
+
+    /// Maximum latency in milliseconds
+    #[arg(long)]
+    max_latency: Option<u64>,
+
+    /// Aggregate by model, day, hour, status or client
+    #[arg(short, long)]
+    group_by: Option<String>,
+
+    /// Maximum number of requests to list
+    #[arg(short, long, default_value = "50")]
+    limit: usize,
+
+    /// Days of traffic to keep (for rotate)
+    #[arg(long, default_value_t = traffic_store::DEFAULT_RETENTION_DAYS)]
+    retention_days: u32,
+
+    /// Export format: har, otlp, jsonl (for export)
+    #[arg(short, long, default_value = "har")]
+    format: String,
+
+    /// Output file (for export; prints to stdout if omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// OTLP/HTTP collector to send spans to, e.g. http://localhost:4318 (for export)
+    #[arg(long)]
+    endpoint: Option<String>,
+}
+
 /// Load analysis data from a directory (JSON files, reports, datasets)
 fn load_analysis_data(path: &PathBuf) -> Result<String> {
     use std::fs;
@@ -545,9 +762,11 @@ async fn main() -> Result<()> {
     } else {
         Level::INFO
     };
+    // Logs go to stderr so JSON output on stdout stays parseable
     let subscriber = FmtSubscriber::builder()
         .with_max_level(level)
         .with_target(false)
+        .with_writer(std::io::stderr)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
@@ -1099,6 +1318,10 @@ async fn main() -> Result<()> {
                 "  Potential savings: ${:.2}",
                 insights.cost_analysis.potential_savings
             );
+            println!(
+                "  Via batch API: ${:.2}",
+                insights.cost_analysis.batch_cost_usd
+            );
 
             println!("\n⏱️  Work Hours:");
             println!(
@@ -1997,11 +2220,13 @@ async fn main() -> Result<()> {
         Commands::Claude {
             action,
             provider,
+            name,
             api_key,
             endpoint,
             model,
             organization_id,
             apply,
+            json,
         } => {
             use colored::Colorize;
 
@@ -2052,13 +2277,16 @@ async fn main() -> Result<()> {
                         config.organization_id = Some(org_id);
                     }
 
-                    // Save configuration
+                    // Keep the key in the secret store, then save the config with its handle
+                    let store = secret_store::SecretStore::open_default()?;
+                    let handle = config.store_api_key(&store)?;
                     config.save()?;
                     println!("{} Configuration saved!", "Success:".green().bold());
                     println!();
                     println!("  Provider: {}", config.provider.name().green());
                     println!("  Endpoint: {}", config.endpoint);
                     println!("  Model: {}", config.model);
+                    println!("  API Key: {}", handle);
                     println!();
 
                     // Apply to Claude Code config files if requested
@@ -2076,12 +2304,166 @@ async fn main() -> Result<()> {
                     }
                 }
 
+                "doctor" | "check" => {
+                    // Check the saved config, or a provider given on the command line
+                    let mut config = match &provider {
+                        Some(provider_name) => {
+                            let provider_enum = claude_config::ClaudeProvider::from_str(provider_name)?;
+                            claude_config::ClaudeConfig::load()
+                                .ok()
+                                .filter(|c| c.provider == provider_enum)
+                                .unwrap_or_else(|| claude_config::ClaudeConfig::new(provider_enum, String::new()))
+                        }
+                        None => claude_config::ClaudeConfig::load()?,
+                    };
+                    if let Some(custom_endpoint) = endpoint {
+                        config.endpoint = custom_endpoint;
+                    }
+                    if let Some(custom_model) = model {
+                        config.model = custom_model;
+                    }
+                    if let Some(key) = api_key {
+                        config.api_key = key;
+                    }
+
+                    let key = secret_store::SecretStore::open_default()
+                        .and_then(|store| config.resolve_api_key(&store))
+                        .map_err(|e| e.to_string());
+                    let client = claude_doctor::default_client()?;
+                    let report = claude_doctor::run(&config, key.as_deref().map_err(Clone::clone), &client).await;
+
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        claude_doctor::print_report(&report);
+                    }
+                    if !report.healthy() {
+                        std::process::exit(1);
+                    }
+                }
+
+                "migrate" => {
+                    let store = secret_store::SecretStore::open_default()?;
+                    match claude_config::ClaudeConfig::migrate_plaintext_key(&store)? {
+                        Some(handle) => println!(
+                            "{} Moved the plaintext API key to {}",
+                            "Success:".green().bold(),
+                            handle
+                        ),
+                        None => println!("No plaintext API key to migrate."),
+                    }
+
+                    // Earlier versions also wrote the key into Claude Code's config files
+                    let config = claude_config::ClaudeConfig::load()?;
+                    for path in config.scrub_claude_code_configs()? {
+                        println!("✓ Replaced plaintext key in {}", path.display());
+                    }
+                }
+
+                "profile" | "profiles" => {
+                    let store = claude_profiles::ProfileStore::open_default();
+                    let settings_path = claude_profiles::settings_path();
+                    let profile_action = provider.as_deref().unwrap_or("list");
+                    let require_name = || {
+                        name.clone().ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Please specify a profile name: claudev claude profile {} <name>",
+                                profile_action
+                            )
+                        })
+                    };
+
+                    match profile_action {
+                        "list" => {
+                            let names = store.list()?;
+                            if names.is_empty() {
+                                println!("No profiles yet. Snapshot your current settings with 'claudev claude profile save <name>'.");
+                            }
+                            let active = store.active_name();
+                            for profile_name in names {
+                                let profile = store.load(&profile_name)?;
+                                let marker = if active.as_deref() == Some(profile_name.as_str()) {
+                                    "*".green().bold()
+                                } else {
+                                    " ".normal()
+                                };
+                                println!(
+                                    "{} {}  {}",
+                                    marker,
+                                    profile_name.green(),
+                                    profile.description.unwrap_or_default().dimmed()
+                                );
+                            }
+                        }
+
+                        "show" => {
+                            let profile_name = require_name()?;
+                            let profile = store.load(&profile_name)?;
+                            println!("{}", serde_json::to_string_pretty(&profile)?);
+                        }
+
+                        "save" => {
+                            // Snapshot the profile-managed sections of the current settings
+                            let profile_name = require_name()?;
+                            let settings = claude_profiles::read_settings(&settings_path)?;
+                            let mut profile = claude_profiles::ClaudeProfile::from_settings(&settings);
+                            if let Some(custom_model) = model {
+                                profile.model = Some(custom_model);
+                            }
+                            let path = store.save(&profile_name, &profile)?;
+                            println!("{} Saved profile '{}' from {}", "Success:".green().bold(), profile_name, settings_path.display());
+                            println!("Edit {} to change it.", path.display());
+                        }
+
+                        "diff" => {
+                            let profile_name = require_name()?;
+                            let (current, merged) = store.preview(&profile_name, &settings_path)?;
+                            println!("Switching to '{}' would change {}:", profile_name, settings_path.display());
+                            claude_profiles::print_changes(&claude_profiles::diff_settings(&current, &merged));
+                        }
+
+                        "use" | "switch" => {
+                            let profile_name = require_name()?;
+                            let switch = store.use_profile(&profile_name, &settings_path)?;
+                            println!(
+                                "{} Switched {} to profile '{}'",
+                                "Success:".green().bold(),
+                                switch.settings_path.display(),
+                                profile_name
+                            );
+                            println!();
+                            claude_profiles::print_changes(&switch.changes);
+                            println!();
+                            if let Some(backup) = &switch.backup {
+                                println!("Previous settings backed up to {}", backup.display());
+                            }
+                            println!("Restart Claude Code for changes to take effect.");
+                        }
+
+                        "delete" | "rm" => {
+                            let profile_name = require_name()?;
+                            store.delete(&profile_name)?;
+                            println!("Deleted profile '{}'", profile_name);
+                        }
+
+                        other => {
+                            anyhow::bail!(
+                                "Unknown profile action '{}'. Use list, show, save, diff, use or delete",
+                                other
+                            );
+                        }
+                    }
+                }
+
                 _ => {
                     println!("{}: Unknown action '{}'\n", "Error".red().bold(), action);
                     println!("Available actions:");
                     println!("  list     - List all supported providers");
                     println!("  show     - Show current configuration");
                     println!("  set      - Set provider configuration");
+                    println!("  migrate  - Move a plaintext API key into the secret store");
+                    println!("  doctor   - Check connectivity, auth and model of the configured provider");
+                    println!("  profile  - Manage Claude Code settings profiles");
                     println!();
                     println!("Examples:");
                     println!("  vibedev claude list");
@@ -2089,6 +2471,8 @@ async fn main() -> Result<()> {
                     println!("  vibedev claude set z.ai --api-key sk-xxx --apply");
                     println!("  vibedev claude set openrouter --api-key sk-or-xxx --apply");
                     println!("  vibedev claude set custom --endpoint https://api.example.com --model gpt-4 --api-key xxx");
+                    println!("  claudev claude profile save work");
+                    println!("  claudev claude profile use work");
                 }
             }
 
@@ -2311,7 +2695,12 @@ async fn main() -> Result<()> {
         }
 
         Commands::Search(args) => {
-            search::cli::handle_search(args)?;
+            search::cli::handle_search(*args)?;
+            Ok(())
+        }
+
+        Commands::Alerts(args) => {
+            search::cli::handle_alerts(args)?;
             Ok(())
         }
 
@@ -2868,7 +3257,7 @@ async fn main() -> Result<()> {
             Ok(())
         }
 
-        Commands::Monitor { port, export, init_ca, setup } => {
+        Commands::Monitor { port, export, init_ca, setup, status, secrets, redact_secrets, retention_days, upstream } => {
             use proxy::{MitmProxy, ProxyConfig, ProxyEvent};
             use traffic::TrafficLog;
             use tokio::sync::mpsc;
@@ -2878,16 +3267,30 @@ async fn main() -> Result<()> {
                 return Ok(());
             }
 
+            if status {
+                return budget::print_status();
+            }
+
+            if secrets {
+                return secret_scan::print_audit_summary();
+            }
+
             let traffic_log = TrafficLog::new();
             traffic_log.enable_file_logging()?;
+            traffic_log.enable_store(traffic_store::TrafficStore::open_default(retention_days));
 
-            let config = ProxyConfig {
+            let mut config = ProxyConfig {
                 listen_addr: format!("127.0.0.1:{}", port).parse()?,
                 ..Default::default()
             };
+            if let Some(upstream) = upstream {
+                config.upstream = upstream;
+            }
 
             let (event_tx, event_rx) = mpsc::unbounded_channel::<ProxyEvent>();
-            let mut proxy = MitmProxy::new(config, traffic_log.clone(), event_tx);
+            let mut proxy = MitmProxy::new(config, traffic_log.clone(), event_tx)
+                .with_budget(budget::BudgetGuard::load()?)
+                .with_secret_scanner(secret_scan::SecretScanner::new(redact_secrets));
 
             if init_ca {
                 proxy.init_ca()?;
@@ -2910,19 +3313,302 @@ async fn main() -> Result<()> {
             });
 
             // Run TUI
-            tui_traffic::run_traffic_monitor(traffic_log, event_rx).await?;
+            tui_traffic::run_traffic_monitor(traffic_log.clone(), event_rx).await?;
 
             // Cleanup
             proxy_handle.abort();
 
             if let Some(export_path) = export {
-                // Export would be handled here
+                let format = traffic_export::ExportFormat::from_path(&export_path);
+                let rendered = traffic_export::render(&traffic_log.get_all(), format)?;
+                std::fs::write(&export_path, rendered)?;
                 println!("Exported traffic to: {:?}", export_path);
             }
 
             Ok(())
         }
 
+        Commands::MockServer {
+            port,
+            script,
+            latency,
+            chunk_delay,
+            error_every,
+            error_status,
+            input_tokens,
+            output_tokens,
+            api_key,
+        } => {
+            use colored::Colorize;
+
+            let script = match &script {
+                Some(path) => mock_server::load_script(path)?,
+                None => Vec::new(),
+            };
+            let usage = (input_tokens.is_some() || output_tokens.is_some()).then(|| traffic::Usage {
+                input_tokens: input_tokens.unwrap_or(0),
+                output_tokens: output_tokens.unwrap_or(0),
+                ..Default::default()
+            });
+
+            let replies = script.len();
+            let mut server = mock_server::start(mock_server::MockConfig {
+                listen_addr: format!("127.0.0.1:{}", port).parse()?,
+                script,
+                latency_ms: latency,
+                chunk_delay_ms: chunk_delay,
+                error_every,
+                error_status,
+                usage,
+                api_key,
+                ..Default::default()
+            })
+            .await?;
+
+            println!("{} {}", "Mock Anthropic API listening on".green().bold(), server.url());
+            if replies > 0 {
+                println!("Serving {} scripted replies in order", replies);
+            } else {
+                println!("Echoing the last user message of each request");
+            }
+            println!(
+                "\nPoint the monitor at it: claudev monitor -p 1338 --upstream {}",
+                server.url()
+            );
+            println!("Or call it directly: ANTHROPIC_BASE_URL={} claude", server.url());
+
+            let result = tokio::select! {
+                result = server.wait() => result,
+                _ = tokio::signal::ctrl_c() => Ok(()),
+            };
+            println!("\nServed {} requests", server.requests().len());
+            result
+        }
+
+        Commands::Traffic(args) => {
+            let TrafficArgs {
+                action,
+                model,
+                status,
+                client,
+                since,
+                until,
+                min_cost,
+                max_cost,
+                min_latency,
+                max_latency,
+                group_by,
+                limit,
+                retention_days,
+                format,
+                output,
+                endpoint,
+            } = *args;
+            use traffic_store::{GroupBy, TrafficQuery, TrafficStore};
+
+            let store = TrafficStore::open_default(retention_days);
+            let now = chrono::Utc::now();
+
+            let query = TrafficQuery {
+                model,
+                status,
+                since: since.map(|s| traffic_store::parse_time(&s, now)).transpose()?,
+                until: until.map(|s| traffic_store::parse_time(&s, now)).transpose()?,
+                min_cost,
+                max_cost,
+                min_latency_ms: min_latency,
+                max_latency_ms: max_latency,
+                client,
+            };
+
+            match action.as_str() {
+                "query" => {
+                    let group_by = group_by.map(|g| g.parse::<GroupBy>()).transpose()?;
+
+                    let entries = query.run(&store)?;
+                    if entries.is_empty() {
+                        println!("No matching requests in {}", store.dir().display());
+                        return Ok(());
+                    }
+                    traffic_store::print_results(&entries, group_by, limit);
+                }
+                "export" => {
+                    let entries = query.run(&store)?;
+
+                    if let Some(endpoint) = endpoint {
+                        traffic_export::send_otlp(&entries, &endpoint).await?;
+                        eprintln!("Sent {} spans to {}", entries.len(), endpoint);
+                        return Ok(());
+                    }
+
+                    let rendered = traffic_export::render(&entries, format.parse()?)?;
+                    match output {
+                        Some(path) => {
+                            std::fs::write(&path, rendered)?;
+                            eprintln!("Exported {} requests to {}", entries.len(), path.display());
+                        }
+                        None => println!("{}", rendered),
+                    }
+                }
+                "rotate" => {
+                    store.rotate(now.date_naive())?;
+                    println!(
+                        "Rotated traffic store {} (keeping {} days)",
+                        store.dir().display(),
+                        retention_days
+                    );
+                }
+                other => anyhow::bail!("Unknown action: {} (expected query, export or rotate)", other),
+            }
+
+            Ok(())
+        }
+
+        Commands::Context { path, flagged, json } => {
+            let (report, source) = match path {
+                Some(path) => (context_composition::analyze_file(&path)?, path),
+                None => {
+                    let store = traffic_store::TrafficStore::open_default(traffic_store::DEFAULT_RETENTION_DAYS);
+                    let today = chrono::Utc::now().date_naive();
+                    let entries = store.load(Some(today), None)?;
+                    (context_composition::analyze_traffic(&entries), store.dir().to_path_buf())
+                }
+            };
+
+            if report.requests.is_empty() {
+                println!("No requests found in {}", source.display());
+                return Ok(());
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                context_composition::print_report(&report, flagged);
+            }
+
+            Ok(())
+        }
+
+        Commands::Tools { path, limit, json } => {
+            let (report, source) = match path {
+                Some(path) => (tool_analytics::analyze_file(&path)?, path),
+                None => {
+                    let store = traffic_store::TrafficStore::open_default(traffic_store::DEFAULT_RETENTION_DAYS);
+                    let today = chrono::Utc::now().date_naive();
+                    let entries = store.load(Some(today), None)?;
+                    (tool_analytics::analyze_traffic(&entries), store.dir().to_path_buf())
+                }
+            };
+
+            if report.invocations.is_empty() {
+                println!("No tool calls found in {}", source.display());
+                return Ok(());
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                tool_analytics::print_report(&report, limit);
+            }
+
+            Ok(())
+        }
+
+        Commands::Mcp { action, project, days, json } => {
+            let project = project.map(std::fs::canonicalize).transpose()?;
+
+            match action.as_str() {
+                "list" => {
+                    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+                    let extra: Vec<PathBuf> = project.iter().cloned().collect();
+                    let servers = mcp_audit::inventory(&home, &extra);
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&servers)?);
+                        return Ok(());
+                    }
+                    if servers.is_empty() {
+                        println!("No MCP servers configured");
+                    }
+                    for server in &servers {
+                        println!(
+                            "{:<24} {:<8} {:<6} {}{}",
+                            server.name,
+                            server.scope.label(),
+                            server.transport,
+                            server.target,
+                            if server.disabled { " (disabled)" } else { "" }
+                        );
+                    }
+                }
+                "audit" => {
+                    let store = traffic_store::TrafficStore::open_default(traffic_store::DEFAULT_RETENTION_DAYS);
+                    let since = (chrono::Utc::now() - chrono::Duration::days(days)).date_naive();
+                    let traffic = store.load(Some(since), None).unwrap_or_default();
+                    let audit = mcp_audit::audit_default(project.as_deref(), &traffic)?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&audit)?);
+                    } else {
+                        mcp_audit::print_audit(&audit);
+                    }
+                }
+                other => anyhow::bail!("Unknown action: {} (expected audit or list)", other),
+            }
+
+            Ok(())
+        }
+
+        Commands::Secret { action, name, backend } => {
+            use colored::Colorize;
+
+            let mut store = secret_store::SecretStore::open_default()?;
+            let require_name = || {
+                name.clone().ok_or_else(|| {
+                    anyhow::anyhow!("Please specify a secret: claudev secret {} <name>", action)
+                })
+            };
+
+            match action.as_str() {
+                "status" => {
+                    let keyring = if secret_store::keyring_available() {
+                        "available".green()
+                    } else {
+                        "unavailable".yellow()
+                    };
+                    println!("Keyring:        {}", keyring);
+                    println!("Encrypted file: {}", store.file_path().display());
+                    println!("New secrets go to: {}", store.preferred().name().bold());
+                }
+
+                "get" => {
+                    // Used as Claude Code's apiKeyHelper, so print only the value
+                    let handle = secret_store::SecretHandle::parse(&require_name()?)?;
+                    println!("{}", store.get(&handle)?);
+                }
+
+                "set" => {
+                    let secret_name = require_name()?;
+                    if let Some(backend) = backend {
+                        store.set_preferred(secret_store::Backend::from_str(&backend)?);
+                    }
+                    let value = secret_store::read_secret_value(&format!("Value for {}: ", secret_name))?;
+                    let handle = store.store(&secret_name, &value)?;
+                    println!("{} Stored as {}", "Success:".green().bold(), handle);
+                }
+
+                "delete" | "rm" => {
+                    let handle = secret_store::SecretHandle::parse(&require_name()?)?;
+                    store.delete(&handle)?;
+                    println!("Deleted {}", handle);
+                }
+
+                other => {
+                    anyhow::bail!("Unknown secret action '{}'. Use status, get, set or delete", other);
+                }
+            }
+
+            Ok(())
+        }
+
         Commands::Patch { claude_path, restore, status, binary } => {
             use std::os::unix::fs::PermissionsExt;
 
@@ -2933,39 +3619,19 @@ async fn main() -> Result<()> {
                     .join(".local/bin/claude")
             });
 
-            // Resolve symlink to actual binary
-            let real_binary = if claude_bin.is_symlink() {
-                std::fs::read_link(&claude_bin)?
-            } else {
-                claude_bin.clone()
-            };
-
-            let backup_path = real_binary.with_extension("backup");
+            let manager = patch_manager::PatchManager::open_default();
+            let real_binary = patch_manager::resolve_binary(&claude_bin);
             let wrapper_path = claude_bin.with_extension("wrapper");
 
             if status {
-                // Check binary patch status
-                if backup_path.exists() {
-                    println!("Status: BINARY PATCHED");
-                    println!("Patched binary: {:?}", real_binary);
-                    println!("Original backup: {:?}", backup_path);
-                } else if wrapper_path.exists() {
-                    println!("Status: WRAPPER PATCHED");
-                    println!("Wrapper: {:?}", claude_bin);
-                    println!("Original: {:?}", wrapper_path);
-                } else {
-                    println!("Status: UNPATCHED");
-                    println!("Binary: {:?}", real_binary);
-                }
+                patch_manager::print_status(&manager.status(&claude_bin)?)?;
                 return Ok(());
             }
 
             if restore {
                 // Restore binary patch first
-                if backup_path.exists() {
-                    std::fs::remove_file(&real_binary)?;
-                    std::fs::rename(&backup_path, &real_binary)?;
-                    println!("Restored original Claude binary from backup");
+                if let Some(restored) = manager.restore(&claude_bin)? {
+                    println!("Restored original Claude binary at {:?}", restored);
                     return Ok(());
                 }
                 // Restore wrapper patch
@@ -2981,62 +3647,19 @@ async fn main() -> Result<()> {
 
             if binary {
                 // Binary patch: replace API URL directly in the binary
-                const ORIGINAL_URL: &[u8] = b"https://api.anthropic.com";
-                const PATCHED_URL: &[u8] = b"http://127.0.0.1:1338/api";  // Same length: 26 chars
-
-                if !real_binary.exists() {
-                    anyhow::bail!("Claude binary not found at {:?}", real_binary);
-                }
-
-                // Read the binary
-                let mut binary_data = std::fs::read(&real_binary)?;
-                let original_len = binary_data.len();
-
-                // Count occurrences
-                let mut count = 0;
-                let mut pos = 0;
-                while let Some(idx) = binary_data[pos..].windows(ORIGINAL_URL.len())
-                    .position(|w| w == ORIGINAL_URL)
-                {
-                    count += 1;
-                    pos += idx + ORIGINAL_URL.len();
-                }
-
-                if count == 0 {
-                    if backup_path.exists() {
-                        println!("Binary already patched (backup exists)");
-                    } else {
-                        anyhow::bail!("Could not find API URL in binary - may already be patched or different version");
-                    }
-                    return Ok(());
-                }
-
-                println!("Found {} occurrences of API URL in binary", count);
-
-                // Backup original
-                if !backup_path.exists() {
-                    std::fs::copy(&real_binary, &backup_path)?;
-                    println!("Backed up original to {:?}", backup_path);
-                }
-
-                // Replace all occurrences
-                let mut patched = 0;
-                pos = 0;
-                while let Some(idx) = binary_data[pos..].windows(ORIGINAL_URL.len())
-                    .position(|w| w == ORIGINAL_URL)
-                {
-                    let abs_pos = pos + idx;
-                    binary_data[abs_pos..abs_pos + PATCHED_URL.len()].copy_from_slice(PATCHED_URL);
-                    patched += 1;
-                    pos = abs_pos + PATCHED_URL.len();
-                }
-
-                // Write patched binary
-                std::fs::write(&real_binary, &binary_data)?;
-                println!("Patched {} URL(s) in binary", patched);
+                let record = manager.apply(&claude_bin)?;
+                println!(
+                    "Patched {:?} ({}) at offset 0x{:x}",
+                    record.binary,
+                    record.version.as_deref().unwrap_or("unknown version"),
+                    record.offset
+                );
+                println!("Original SHA-256: {}", record.original_sha256);
+                println!("Backed up original to {:?}", record.backup);
                 println!("\nClaude will now send HTTP requests to localhost:1338");
                 println!("Run 'claudev monitor -p 1338' to see plaintext API traffic!");
-                println!("\nTo restore: claudev patch --restore");
+                println!("\nCheck with: claudev patch --status");
+                println!("To restore: claudev patch --restore");
 
                 return Ok(());
             }
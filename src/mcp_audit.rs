@@ -0,0 +1,679 @@
+//! MCP server inventory and usage audit
+//!
+//! Collects the MCP servers Claude Code is configured with, from the user
+//! and per-project entries of `~/.claude.json`, `~/.claude/settings.json`
+//! and each project's `.mcp.json`, then joins them with the
+//! `mcp__<server>__<tool>` calls found in transcripts. Servers whose tools
+//! are never called still have their tool definitions sent with every
+//! request; captured traffic tells us how many tokens that costs.
+
+use crate::context_composition::estimate_tokens;
+use crate::tool_analytics::{self, ToolInvocation};
+use crate::traffic::TrafficEntry;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefix of MCP tool names in Claude Code
+const MCP_PREFIX: &str = "mcp__";
+
+/// Where a server is configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpScope {
+    /// `mcpServers` in `~/.claude.json` or `~/.claude/settings.json`
+    User,
+    /// A project entry in `~/.claude.json`
+    Local,
+    /// A project's `.mcp.json`
+    Project,
+}
+
+impl McpScope {
+    pub fn label(&self) -> &'static str {
+        match self {
+            McpScope::User => "user",
+            McpScope::Local => "local",
+            McpScope::Project => "project",
+        }
+    }
+}
+
+/// One configured MCP server
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServer {
+    pub name: String,
+    pub scope: McpScope,
+    pub source: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<PathBuf>,
+    /// `stdio`, `http` or `sse`
+    pub transport: String,
+    /// Command line or URL
+    pub target: String,
+    /// Listed in `disabledMcpjsonServers`
+    pub disabled: bool,
+}
+
+/// Calls to one server's tools
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct McpUsage {
+    pub calls: usize,
+    pub errors: usize,
+    /// Estimated tokens of tool results fed back into the context
+    pub result_tokens: u64,
+    pub tools: BTreeMap<String, usize>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+impl McpUsage {
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+}
+
+/// A server with everything known about it
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerReport {
+    pub name: String,
+    /// Empty for servers seen in transcripts but no longer configured
+    pub configs: Vec<McpServer>,
+    pub usage: McpUsage,
+    /// Estimated tokens of the server's tool definitions per request, from captured traffic
+    pub definition_tokens: Option<u64>,
+}
+
+impl ServerReport {
+    pub fn enabled(&self) -> bool {
+        self.configs.iter().any(|c| !c.disabled)
+    }
+}
+
+/// Inventory joined with usage
+#[derive(Debug, Clone, Serialize)]
+pub struct McpAudit {
+    pub servers: Vec<ServerReport>,
+    pub transcripts: usize,
+    /// Requests in captured traffic that defined tools
+    pub traffic_requests: usize,
+}
+
+impl McpAudit {
+    /// Enabled servers whose tools were never called
+    pub fn unused(&self) -> Vec<&ServerReport> {
+        self.servers
+            .iter()
+            .filter(|s| s.enabled() && s.usage.calls == 0)
+            .collect()
+    }
+
+    /// Servers called in transcripts but no longer configured
+    pub fn unconfigured(&self) -> Vec<&ServerReport> {
+        self.servers
+            .iter()
+            .filter(|s| s.configs.is_empty())
+            .collect()
+    }
+}
+
+/// Split `mcp__github__create_issue` into `("github", "create_issue")`
+pub fn split_tool_name(name: &str) -> Option<(&str, &str)> {
+    let rest = name.strip_prefix(MCP_PREFIX)?;
+    let (server, tool) = rest.split_once("__")?;
+    (!server.is_empty()).then_some((server, tool))
+}
+
+/// Server name as it appears in tool names: characters outside
+/// `[A-Za-z0-9_-]` become `_`
+pub fn normalize_server_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn string_list(value: Option<&Value>) -> BTreeSet<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Servers in an `mcpServers` object
+fn servers_in(
+    value: Option<&Value>,
+    scope: McpScope,
+    source: &Path,
+    project: Option<&Path>,
+    disabled: &BTreeSet<String>,
+) -> Vec<McpServer> {
+    let Some(servers) = value.and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    servers
+        .iter()
+        .map(|(name, config)| {
+            let url = config.get("url").and_then(Value::as_str);
+            let transport = config
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or(if url.is_some() { "http" } else { "stdio" })
+                .to_string();
+            let target = match url {
+                Some(url) => url.to_string(),
+                None => std::iter::once(config.get("command"))
+                    .chain(
+                        config
+                            .get("args")
+                            .and_then(Value::as_array)
+                            .into_iter()
+                            .flatten()
+                            .map(Some),
+                    )
+                    .flatten()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+            McpServer {
+                name: name.clone(),
+                scope,
+                source: source.to_path_buf(),
+                project: project.map(Path::to_path_buf),
+                transport,
+                target,
+                disabled: disabled.contains(name),
+            }
+        })
+        .collect()
+}
+
+/// All MCP servers configured under `home`, plus those of `extra_projects`
+pub fn inventory(home: &Path, extra_projects: &[PathBuf]) -> Vec<McpServer> {
+    let mut servers = Vec::new();
+    let none = BTreeSet::new();
+
+    let claude_json_path = home.join(".claude.json");
+    let claude_json = read_json(&claude_json_path);
+    let settings_path = home.join(".claude").join("settings.json");
+    let settings = read_json(&settings_path);
+
+    if let Some(config) = &claude_json {
+        servers.extend(servers_in(
+            config.get("mcpServers"),
+            McpScope::User,
+            &claude_json_path,
+            None,
+            &none,
+        ));
+    }
+    if let Some(config) = &settings {
+        servers.extend(servers_in(
+            config.get("mcpServers"),
+            McpScope::User,
+            &settings_path,
+            None,
+            &none,
+        ));
+    }
+
+    // Projects Claude Code has been used in, and the ones asked about
+    let project_entries = claude_json
+        .as_ref()
+        .and_then(|c| c.get("projects"))
+        .and_then(Value::as_object);
+    let mut projects: BTreeSet<PathBuf> = project_entries
+        .map(|p| p.keys().map(PathBuf::from).collect())
+        .unwrap_or_default();
+    projects.extend(extra_projects.iter().cloned());
+
+    for project in &projects {
+        let entry = project_entries.and_then(|p| p.get(&project.to_string_lossy().to_string()));
+        let mut disabled = string_list(entry.and_then(|e| e.get("disabledMcpjsonServers")));
+        for name in ["settings.json", "settings.local.json"] {
+            let local = read_json(&project.join(".claude").join(name));
+            disabled.extend(string_list(
+                local.as_ref().and_then(|l| l.get("disabledMcpjsonServers")),
+            ));
+        }
+
+        servers.extend(servers_in(
+            entry.and_then(|e| e.get("mcpServers")),
+            McpScope::Local,
+            &claude_json_path,
+            Some(project),
+            &none,
+        ));
+        let mcp_json_path = project.join(".mcp.json");
+        if let Some(mcp_json) = read_json(&mcp_json_path) {
+            servers.extend(servers_in(
+                mcp_json.get("mcpServers"),
+                McpScope::Project,
+                &mcp_json_path,
+                Some(project),
+                &disabled,
+            ));
+        }
+    }
+
+    servers
+}
+
+/// MCP usage per (normalized) server name
+pub fn usage_by_server(invocations: &[ToolInvocation]) -> BTreeMap<String, McpUsage> {
+    let mut usage: BTreeMap<String, McpUsage> = BTreeMap::new();
+    for invocation in invocations {
+        let Some((server, tool)) = split_tool_name(&invocation.name) else {
+            continue;
+        };
+        let entry = usage.entry(server.to_string()).or_default();
+        entry.calls += 1;
+        if invocation.is_error {
+            entry.errors += 1;
+        }
+        entry.result_tokens += estimate_tokens(invocation.result_chars);
+        *entry.tools.entry(tool.to_string()).or_default() += 1;
+        if invocation.requested_at > entry.last_used {
+            entry.last_used = invocation.requested_at;
+        }
+    }
+    usage
+}
+
+/// Tokens of each server's tool definitions in the latest request that carried them
+pub fn definition_tokens(entries: &[TrafficEntry]) -> (BTreeMap<String, u64>, usize) {
+    let mut sorted: Vec<&TrafficEntry> = entries
+        .iter()
+        .filter(|e| e.request.tools.as_ref().is_some_and(|t| !t.is_empty()))
+        .collect();
+    sorted.sort_by_key(|e| e.timestamp);
+
+    let mut tokens = BTreeMap::new();
+    for entry in &sorted {
+        let mut per_server: BTreeMap<String, u64> = BTreeMap::new();
+        for tool in entry.request.tools.iter().flatten() {
+            let name = tool.get("name").and_then(Value::as_str).unwrap_or_default();
+            if let Some((server, _)) = split_tool_name(name) {
+                *per_server.entry(server.to_string()).or_default() +=
+                    estimate_tokens(tool.to_string().len());
+            }
+        }
+        tokens.extend(per_server);
+    }
+    (tokens, sorted.len())
+}
+
+/// Join an inventory with transcript usage and traffic definition sizes
+pub fn audit(
+    inventory: Vec<McpServer>,
+    invocations: &[ToolInvocation],
+    transcripts: usize,
+    traffic: &[TrafficEntry],
+) -> McpAudit {
+    let mut usage = usage_by_server(invocations);
+    let (mut definitions, traffic_requests) = definition_tokens(traffic);
+
+    let mut by_name: BTreeMap<String, Vec<McpServer>> = BTreeMap::new();
+    for server in inventory {
+        by_name
+            .entry(normalize_server_name(&server.name))
+            .or_default()
+            .push(server);
+    }
+    for name in usage.keys() {
+        by_name.entry(name.clone()).or_default();
+    }
+
+    let mut servers: Vec<ServerReport> = by_name
+        .into_iter()
+        .map(|(name, configs)| ServerReport {
+            usage: usage.remove(&name).unwrap_or_default(),
+            definition_tokens: definitions.remove(&name),
+            name,
+            configs,
+        })
+        .collect();
+    servers.sort_by(|a, b| b.usage.calls.cmp(&a.usage.calls).then(a.name.cmp(&b.name)));
+
+    McpAudit {
+        servers,
+        transcripts,
+        traffic_requests,
+    }
+}
+
+/// Claude Code's directory name for a project's transcripts
+fn transcript_dir_name(project: &Path) -> String {
+    project
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Transcripts under `~/.claude/projects`, optionally only those of one project
+pub fn transcript_files(home: &Path, project: Option<&Path>) -> Vec<PathBuf> {
+    let projects_dir = home.join(".claude").join("projects");
+    let wanted = project.map(transcript_dir_name);
+    walkdir::WalkDir::new(&projects_dir)
+        .max_depth(2)
+        .into_iter()
+        .flatten()
+        .filter(|e| e.file_type().is_file() && e.path().extension().is_some_and(|x| x == "jsonl"))
+        .filter(|e| match &wanted {
+            Some(wanted) => e
+                .path()
+                .parent()
+                .and_then(|p| p.file_name())
+                .is_some_and(|dir| dir.to_string_lossy() == *wanted),
+            None => true,
+        })
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Audit the current user's configuration, transcripts and captured traffic
+pub fn audit_default(project: Option<&Path>, traffic: &[TrafficEntry]) -> Result<McpAudit> {
+    let home = dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Could not find home directory"))?;
+    let extra: Vec<PathBuf> = project.map(Path::to_path_buf).into_iter().collect();
+    let mut servers = inventory(&home, &extra);
+    if let Some(project) = project {
+        servers.retain(|s| s.project.is_none() || s.project.as_deref() == Some(project));
+    }
+
+    let transcripts = transcript_files(&home, project);
+    let mut invocations = Vec::new();
+    for path in &transcripts {
+        if let Ok(content) = fs::read_to_string(path) {
+            invocations.extend(tool_analytics::pair_tool_calls(
+                tool_analytics::transcript_events(&content),
+            ));
+        }
+    }
+
+    Ok(audit(servers, &invocations, transcripts.len(), traffic))
+}
+
+/// Print the audit as tables
+pub fn print_audit(audit: &McpAudit) {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    println!("\n{}", "MCP Server Audit".cyan().bold());
+    println!(
+        "{} servers, {} transcripts, {} captured requests with tools\n",
+        audit.servers.len(),
+        audit.transcripts,
+        audit.traffic_requests
+    );
+    if audit.servers.is_empty() {
+        println!("No MCP servers configured or used.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            "Server",
+            "Scope",
+            "Transport",
+            "Calls",
+            "Errors",
+            "Error %",
+            "Result tokens",
+            "Definition tokens",
+            "Last used",
+        ]);
+    for server in &audit.servers {
+        let scope = if server.configs.is_empty() {
+            "not configured".to_string()
+        } else {
+            server
+                .configs
+                .iter()
+                .map(|c| {
+                    if c.disabled {
+                        format!("{} (disabled)", c.scope.label())
+                    } else {
+                        c.scope.label().to_string()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let transport = server
+            .configs
+            .first()
+            .map(|c| c.transport.clone())
+            .unwrap_or_else(|| "-".to_string());
+        let error_rate = server.usage.error_rate();
+        let error_color = if error_rate >= 0.2 {
+            Color::Red
+        } else if error_rate > 0.0 {
+            Color::Yellow
+        } else {
+            Color::Reset
+        };
+        table.add_row(vec![
+            Cell::new(&server.name),
+            Cell::new(scope),
+            Cell::new(transport),
+            Cell::new(server.usage.calls),
+            Cell::new(server.usage.errors),
+            Cell::new(format!("{:.0}%", error_rate * 100.0)).fg(error_color),
+            Cell::new(server.usage.result_tokens),
+            Cell::new(
+                server
+                    .definition_tokens
+                    .map(|t| t.to_string())
+                    .unwrap_or_else(|| "-".to_string()),
+            ),
+            Cell::new(
+                server
+                    .usage
+                    .last_used
+                    .map(|t| t.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| "never".to_string()),
+            ),
+        ]);
+    }
+    println!("{}", table);
+
+    let unused = audit.unused();
+    if !unused.is_empty() {
+        println!("\n{}", "Never used but still loaded".yellow().bold());
+        let mut measured = 0;
+        for server in &unused {
+            match server.definition_tokens {
+                Some(tokens) => {
+                    measured += tokens;
+                    println!(
+                        "  {}  ~{} tokens of tool definitions per request",
+                        server.name, tokens
+                    );
+                }
+                None => println!("  {}  (definition size unknown)", server.name),
+            }
+        }
+        if measured > 0 {
+            println!(
+                "  Removing them would save ~{} tokens per request.",
+                measured
+            );
+        } else {
+            println!("  Capture traffic with 'claudev monitor' to measure what they cost.");
+        }
+    }
+
+    let unconfigured = audit.unconfigured();
+    if !unconfigured.is_empty() {
+        println!("\n{}", "Used but not configured here".dimmed());
+        for server in unconfigured {
+            println!("  {} ({} calls)", server.name, server.usage.calls);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{ApiRequest, TrafficStatus};
+    use serde_json::json;
+
+    fn write(path: &Path, value: Value) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, value.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_inventory_across_scopes() {
+        let home = tempfile::tempdir().unwrap();
+        let project = home.path().join("work/app");
+        write(
+            &home.path().join(".claude.json"),
+            json!({
+                "mcpServers": { "github": { "type": "stdio", "command": "npx", "args": ["-y", "gh-mcp"] } },
+                "projects": {
+                    project.to_string_lossy(): {
+                        "mcpServers": { "db": { "command": "pg-mcp" } },
+                        "disabledMcpjsonServers": ["old"]
+                    }
+                }
+            }),
+        );
+        write(
+            &home.path().join(".claude/settings.json"),
+            json!({ "mcpServers": { "fs": { "command": "fs-mcp" } } }),
+        );
+        write(
+            &project.join(".mcp.json"),
+            json!({ "mcpServers": {
+                "linear": { "type": "sse", "url": "https://mcp.linear.app/sse" },
+                "old": { "command": "old-mcp" }
+            } }),
+        );
+
+        let servers = inventory(home.path(), &[]);
+        let find = |name: &str| servers.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(servers.len(), 5);
+        assert_eq!(find("github").scope, McpScope::User);
+        assert_eq!(find("github").target, "npx -y gh-mcp");
+        assert_eq!(find("fs").source, home.path().join(".claude/settings.json"));
+        assert_eq!(find("db").scope, McpScope::Local);
+        assert_eq!(find("db").project.as_deref(), Some(project.as_path()));
+        assert_eq!(find("linear").transport, "sse");
+        assert!(find("old").disabled);
+        assert!(!find("linear").disabled);
+    }
+
+    #[test]
+    fn test_audit_joins_usage_and_cost() {
+        let home = tempfile::tempdir().unwrap();
+        write(
+            &home.path().join(".claude.json"),
+            json!({ "mcpServers": {
+                "github": { "command": "gh-mcp" },
+                "my.db": { "command": "db-mcp" },
+                "idle": { "command": "idle-mcp" }
+            } }),
+        );
+
+        let transcript = [
+            json!({"timestamp": "2025-06-01T10:00:00Z", "message": {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "t1", "name": "mcp__github__create_issue", "input": {}},
+                {"type": "tool_use", "id": "t2", "name": "mcp__my_db__query", "input": {"sql": "select 1"}},
+                {"type": "tool_use", "id": "t3", "name": "mcp__claude_ai_Gmail__search", "input": {}}
+            ]}}),
+            json!({"timestamp": "2025-06-01T10:00:05Z", "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "x".repeat(400)},
+                {"type": "tool_result", "tool_use_id": "t2", "content": "permission denied", "is_error": true},
+                {"type": "tool_result", "tool_use_id": "t3", "content": "ok"}
+            ]}}),
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+        let invocations =
+            tool_analytics::pair_tool_calls(tool_analytics::transcript_events(&transcript));
+
+        let idle_tool = json!({"name": "mcp__idle__do_things", "description": "d".repeat(800), "input_schema": {}});
+        let entry = TrafficEntry {
+            id: 1,
+            timestamp: Utc::now(),
+            request: ApiRequest {
+                model: "claude-sonnet-4".into(),
+                max_tokens: None,
+                messages: Vec::new(),
+                system: None,
+                stream: false,
+                tools: Some(vec![idle_tool, json!({"name": "Bash"})]),
+                raw_body: None,
+            },
+            response: None,
+            status: TrafficStatus::Success,
+            latency_ms: None,
+            client: None,
+        };
+
+        let audit = audit(inventory(home.path(), &[]), &invocations, 1, &[entry]);
+        let server = |name: &str| audit.servers.iter().find(|s| s.name == name).unwrap();
+
+        assert_eq!(server("github").usage.calls, 1);
+        assert_eq!(server("github").usage.result_tokens, 100);
+        assert_eq!(server("my_db").usage.error_rate(), 1.0);
+        assert_eq!(server("my_db").configs[0].name, "my.db");
+        assert_eq!(
+            audit
+                .unused()
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["idle"]
+        );
+        assert!(server("idle").definition_tokens.unwrap() > 200);
+        assert_eq!(
+            audit
+                .unconfigured()
+                .iter()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["claude_ai_Gmail"]
+        );
+    }
+
+    #[test]
+    fn test_split_tool_name() {
+        assert_eq!(
+            split_tool_name("mcp__github__create_issue"),
+            Some(("github", "create_issue"))
+        );
+        assert_eq!(split_tool_name("Bash"), None);
+        assert_eq!(split_tool_name("mcp____x"), None);
+    }
+}
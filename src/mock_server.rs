@@ -0,0 +1,686 @@
+//! Mock Anthropic Messages API for offline testing and demos
+//!
+//! Serves `POST /v1/messages` (streaming and non-streaming) on plain HTTP so
+//! the monitor proxy, budget guard and traffic TUI can be exercised without
+//! credentials. Responses come from a script — a JSON fixture file or traffic
+//! recorded by `claudev monitor` — and are served in order, wrapping around.
+//! With no script the last user message is echoed back.
+
+use crate::proxy::{api_path, read_request, write_error_response};
+use crate::traffic::{self, ApiRequest, ContentBlock, TrafficEntry, TrafficStatus, Usage};
+use crate::traffic_store;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// Words sent per streamed text delta
+const WORDS_PER_DELTA: usize = 3;
+
+/// One scripted reply
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MockResponse {
+    /// Shorthand for a single text block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content: Vec<ContentBlock>,
+    /// Estimated from the request and reply when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    /// Overrides [`MockConfig::latency_ms`] for this reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    /// Reply with an API error instead of a message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<MockError>,
+}
+
+/// An API error reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MockError {
+    pub status: u16,
+    #[serde(rename = "type", default = "default_error_type")]
+    pub error_type: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+fn default_error_type() -> String {
+    "api_error".to_string()
+}
+
+impl MockError {
+    /// Error the real API returns for a status code
+    pub fn for_status(status: u16) -> Self {
+        let (error_type, message) = match status {
+            400 => ("invalid_request_error", "Invalid request"),
+            401 => ("authentication_error", "Invalid API key"),
+            403 => ("permission_error", "Permission denied"),
+            404 => ("not_found_error", "Not found"),
+            413 => ("request_too_large", "Request too large"),
+            429 => ("rate_limit_error", "Rate limited"),
+            529 => ("overloaded_error", "Overloaded"),
+            _ => ("api_error", "Internal server error"),
+        };
+        Self {
+            status,
+            error_type: error_type.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl MockResponse {
+    /// Reply replaying a captured exchange
+    pub fn from_entry(entry: &TrafficEntry) -> Self {
+        let error = match &entry.status {
+            TrafficStatus::Error(message) => {
+                // Failures from upstream are logged as "HTTP <status>: <body>"
+                let status = message
+                    .strip_prefix("HTTP ")
+                    .and_then(|rest| rest.split(':').next())
+                    .and_then(|code| code.parse().ok())
+                    .unwrap_or(500);
+                Some(MockError {
+                    message: message.clone(),
+                    ..MockError::for_status(status)
+                })
+            }
+            _ => None,
+        };
+        let response = entry.response.as_ref();
+        Self {
+            text: None,
+            content: response.map(|r| r.content.clone()).unwrap_or_default(),
+            usage: response.and_then(|r| r.usage.clone()),
+            stop_reason: response.and_then(|r| r.stop_reason.clone()),
+            latency_ms: entry.latency_ms,
+            error,
+        }
+    }
+
+    fn blocks(&self) -> Vec<ContentBlock> {
+        let mut blocks = self.content.clone();
+        if let Some(text) = &self.text {
+            blocks.insert(0, text_block(text));
+        }
+        blocks
+    }
+}
+
+/// Load a script: a JSON array of [`MockResponse`], or traffic JSONL (optionally gzipped)
+pub fn load_script(path: &Path) -> Result<Vec<MockResponse>> {
+    let is_json = path.extension().is_some_and(|ext| ext == "json");
+    if is_json {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        return serde_json::from_str(&content)
+            .with_context(|| format!("Invalid fixture file {}", path.display()));
+    }
+
+    let entries = traffic_store::read_file(path)?;
+    Ok(entries
+        .iter()
+        .filter(|e| e.response.is_some() || matches!(e.status, TrafficStatus::Error(_)))
+        .map(MockResponse::from_entry)
+        .collect())
+}
+
+/// Models the mock lists unless configured otherwise
+const DEFAULT_MODELS: &[&str] = &[
+    "claude-opus-4-20250514",
+    "claude-sonnet-4-20250514",
+    "claude-3-5-haiku-20241022",
+];
+
+/// Mock server settings
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub listen_addr: SocketAddr,
+    /// Replies served in order, wrapping around
+    pub script: Vec<MockResponse>,
+    /// Delay before the response starts
+    pub latency_ms: u64,
+    /// Delay between streamed events
+    pub chunk_delay_ms: u64,
+    /// Fail every n-th request with `error_status`
+    pub error_every: Option<usize>,
+    pub error_status: u16,
+    /// Usage reported for replies that do not script their own
+    pub usage: Option<Usage>,
+    /// Models listed by `GET /v1/models`
+    pub models: Vec<String>,
+    /// Reject requests that do not carry this key (`x-api-key` or bearer token)
+    pub api_key: Option<String>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            listen_addr: "127.0.0.1:1339".parse().unwrap(),
+            script: Vec::new(),
+            latency_ms: 0,
+            chunk_delay_ms: 0,
+            error_every: None,
+            error_status: 529,
+            usage: None,
+            models: DEFAULT_MODELS.iter().map(|m| m.to_string()).collect(),
+            api_key: None,
+        }
+    }
+}
+
+struct MockState {
+    config: MockConfig,
+    served: AtomicUsize,
+    requests: Mutex<Vec<ApiRequest>>,
+}
+
+/// A mock server running in the background
+pub struct MockHandle {
+    pub addr: SocketAddr,
+    state: Arc<MockState>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl MockHandle {
+    /// Base URL to use as the proxy upstream
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Messages requests received so far
+    pub fn requests(&self) -> Vec<ApiRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Wait for the server to stop
+    pub async fn wait(&mut self) -> Result<()> {
+        (&mut self.task).await?
+    }
+}
+
+impl Drop for MockHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Bind `config.listen_addr` (port 0 picks a free port) and serve in the background
+pub async fn start(config: MockConfig) -> Result<MockHandle> {
+    let listener = TcpListener::bind(config.listen_addr)
+        .await
+        .with_context(|| format!("Failed to bind {}", config.listen_addr))?;
+    let addr = listener.local_addr()?;
+    let state = Arc::new(MockState {
+        config,
+        served: AtomicUsize::new(0),
+        requests: Mutex::new(Vec::new()),
+    });
+
+    let task = tokio::spawn(serve(listener, state.clone()));
+    Ok(MockHandle { addr, state, task })
+}
+
+async fn serve(listener: TcpListener, state: Arc<MockState>) -> Result<()> {
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, state).await {
+                tracing::debug!("Mock connection error from {}: {}", addr, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<MockState>) -> Result<()> {
+    let request = read_request(&mut stream, &[]).await?;
+    let path = api_path(&request.target)
+        .unwrap_or(&request.target)
+        .to_string();
+    let body = String::from_utf8_lossy(&request.body);
+
+    if let Some(key) = &state.config.api_key {
+        let presented = request.headers.iter().find_map(|(name, value)| {
+            if name.eq_ignore_ascii_case("x-api-key") {
+                Some(value.as_str())
+            } else if name.eq_ignore_ascii_case("authorization") {
+                value.strip_prefix("Bearer ")
+            } else {
+                None
+            }
+        });
+        if presented != Some(key.as_str()) {
+            return write_error_response(
+                &mut stream,
+                401,
+                "authentication_error",
+                "invalid x-api-key",
+            )
+            .await;
+        }
+    }
+
+    match (
+        request.method.as_str(),
+        path.split('?').next().unwrap_or_default(),
+    ) {
+        ("POST", "/v1/messages") => {
+            let api_request = match traffic::parse_request(&body) {
+                Ok(api_request) => api_request,
+                Err(e) => {
+                    return write_error_response(
+                        &mut stream,
+                        400,
+                        "invalid_request_error",
+                        &e.to_string(),
+                    )
+                    .await
+                }
+            };
+            state.requests.lock().unwrap().push(api_request.clone());
+            handle_messages(stream, &state, &api_request).await
+        }
+        ("GET", "/v1/models") => {
+            let data: Vec<Value> = state
+                .config
+                .models
+                .iter()
+                .map(|id| json!({ "type": "model", "id": id, "display_name": id }))
+                .collect();
+            write_json(
+                &mut stream,
+                &json!({
+                    "data": data,
+                    "has_more": false,
+                    "first_id": state.config.models.first(),
+                    "last_id": state.config.models.last(),
+                }),
+            )
+            .await
+        }
+        ("POST", "/v1/messages/count_tokens") => {
+            let tokens = traffic::parse_request(&body)
+                .map(|r| estimate_input_tokens(&r))
+                .unwrap_or(0);
+            write_json(&mut stream, &json!({ "input_tokens": tokens })).await
+        }
+        _ => {
+            write_error_response(
+                &mut stream,
+                404,
+                "not_found_error",
+                &format!("No mock for {}", path),
+            )
+            .await
+        }
+    }
+}
+
+async fn handle_messages(
+    mut stream: TcpStream,
+    state: &MockState,
+    request: &ApiRequest,
+) -> Result<()> {
+    let config = &state.config;
+    let n = state.served.fetch_add(1, Ordering::SeqCst);
+
+    let scripted = if config.script.is_empty() {
+        None
+    } else {
+        Some(&config.script[n % config.script.len()])
+    };
+
+    let latency = scripted
+        .and_then(|r| r.latency_ms)
+        .unwrap_or(config.latency_ms);
+    tokio::time::sleep(Duration::from_millis(latency)).await;
+
+    let injected = config
+        .error_every
+        .filter(|every| *every > 0 && (n + 1) % every == 0)
+        .map(|_| MockError::for_status(config.error_status));
+    if let Some(error) = injected.or_else(|| scripted.and_then(|r| r.error.clone())) {
+        return write_error_response(&mut stream, error.status, &error.error_type, &error.message)
+            .await;
+    }
+
+    let blocks = match scripted {
+        Some(response) => response.blocks(),
+        None => vec![text_block(&echo(request))],
+    };
+    let usage = scripted
+        .and_then(|r| r.usage.clone())
+        .or_else(|| config.usage.clone())
+        .unwrap_or_else(|| Usage {
+            input_tokens: estimate_input_tokens(request),
+            output_tokens: estimate_output_tokens(&blocks),
+            ..Default::default()
+        });
+    let stop_reason = scripted
+        .and_then(|r| r.stop_reason.clone())
+        .unwrap_or_else(|| {
+            if blocks.iter().any(|b| b.block_type == "tool_use") {
+                "tool_use".to_string()
+            } else {
+                "end_turn".to_string()
+            }
+        });
+
+    let message = json!({
+        "id": format!("msg_mock_{:04}", n + 1),
+        "type": "message",
+        "role": "assistant",
+        "model": request.model,
+        "content": blocks,
+        "stop_reason": stop_reason,
+        "stop_sequence": null,
+        "usage": usage,
+    });
+
+    if request.stream {
+        stream_message(&mut stream, message, config.chunk_delay_ms).await
+    } else {
+        write_json(&mut stream, &message).await
+    }
+}
+
+async fn write_json(stream: &mut TcpStream, value: &Value) -> Result<()> {
+    let body = value.to_string();
+    let head = format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Send a message as the SSE event sequence of the streaming API
+async fn stream_message(stream: &mut TcpStream, message: Value, delay_ms: u64) -> Result<()> {
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncache-control: no-cache\r\nconnection: close\r\n\r\n",
+        )
+        .await?;
+
+    let usage = &message["usage"];
+    let mut start = message.clone();
+    start["content"] = json!([]);
+    start["stop_reason"] = Value::Null;
+    start["usage"] = json!({
+        "input_tokens": usage["input_tokens"],
+        "cache_creation_input_tokens": usage["cache_creation_input_tokens"],
+        "cache_read_input_tokens": usage["cache_read_input_tokens"],
+        "output_tokens": 1,
+    });
+
+    let mut events = vec![("message_start", json!({ "message": start }))];
+    for (index, block) in message["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        match block["type"].as_str() {
+            Some("tool_use") => {
+                events.push((
+                    "content_block_start",
+                    json!({ "index": index, "content_block": {
+                        "type": "tool_use", "id": block["id"], "name": block["name"], "input": {}
+                    }}),
+                ));
+                events.push((
+                    "content_block_delta",
+                    json!({ "index": index, "delta": {
+                        "type": "input_json_delta", "partial_json": block["input"].to_string()
+                    }}),
+                ));
+            }
+            _ => {
+                events.push((
+                    "content_block_start",
+                    json!({ "index": index, "content_block": { "type": "text", "text": "" } }),
+                ));
+                let text = block["text"].as_str().unwrap_or_default();
+                let words: Vec<&str> = text.split_inclusive(' ').collect();
+                for chunk in words.chunks(WORDS_PER_DELTA) {
+                    events.push((
+                        "content_block_delta",
+                        json!({ "index": index, "delta": { "type": "text_delta", "text": chunk.concat() } }),
+                    ));
+                }
+            }
+        }
+        events.push(("content_block_stop", json!({ "index": index })));
+    }
+    events.push((
+        "message_delta",
+        json!({
+            "delta": { "stop_reason": message["stop_reason"], "stop_sequence": null },
+            "usage": { "output_tokens": usage["output_tokens"] },
+        }),
+    ));
+    events.push(("message_stop", json!({})));
+
+    for (event_type, mut data) in events {
+        data["type"] = json!(event_type);
+        let frame = format!("event: {}\ndata: {}\n\n", event_type, data);
+        stream.write_all(frame.as_bytes()).await?;
+        if delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
+
+fn text_block(text: &str) -> ContentBlock {
+    ContentBlock {
+        block_type: "text".to_string(),
+        text: Some(text.to_string()),
+        id: None,
+        name: None,
+        input: None,
+        tool_use_id: None,
+        content: None,
+        is_error: None,
+    }
+}
+
+fn echo(request: &ApiRequest) -> String {
+    let last = request
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.text())
+        .unwrap_or_default();
+    format!("Mock reply to: {}", last)
+}
+
+/// Roughly four characters per token, as the API's own estimates
+fn estimate_input_tokens(request: &ApiRequest) -> u64 {
+    let body = request
+        .raw_body
+        .clone()
+        .unwrap_or_else(|| serde_json::to_string(&request.messages).unwrap_or_default());
+    (body.len() as u64 / 4).max(1)
+}
+
+fn estimate_output_tokens(blocks: &[ContentBlock]) -> u64 {
+    let chars: usize = blocks
+        .iter()
+        .map(|b| {
+            b.text.as_ref().map_or(0, |t| t.len())
+                + b.input.as_ref().map_or(0, |i| i.to_string().len())
+        })
+        .sum();
+    (chars as u64 / 4).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::{MitmProxy, ProxyConfig, ProxyEvent};
+    use crate::traffic::TrafficLog;
+    use tokio::sync::mpsc;
+
+    fn config(script: Vec<MockResponse>) -> MockConfig {
+        MockConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            script,
+            ..Default::default()
+        }
+    }
+
+    async fn post(url: &str, body: Value) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/v1/messages", url))
+            .json(&body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scripted_replies_and_errors() {
+        let script: Vec<MockResponse> = serde_json::from_value(json!([
+            { "text": "first", "usage": { "input_tokens": 100, "output_tokens": 7 } },
+            { "error": { "status": 429, "type": "rate_limit_error", "message": "slow down" } },
+        ]))
+        .unwrap();
+        let mock = start(config(script)).await.unwrap();
+        let body = json!({ "model": "claude-sonnet-4", "max_tokens": 10, "messages": [{ "role": "user", "content": "hi" }] });
+
+        let first: Value = post(&mock.url(), body.clone()).await.json().await.unwrap();
+        assert_eq!(first["content"][0]["text"], "first");
+        assert_eq!(first["usage"]["output_tokens"], 7);
+        assert_eq!(first["model"], "claude-sonnet-4");
+
+        let second = post(&mock.url(), body.clone()).await;
+        assert_eq!(second.status().as_u16(), 429);
+
+        // The script wraps around
+        let third: Value = post(&mock.url(), body).await.json().await.unwrap();
+        assert_eq!(third["content"][0]["text"], "first");
+        assert_eq!(mock.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_back() {
+        let script: Vec<MockResponse> = serde_json::from_value(json!([{
+            "text": "Let me check the build output for you",
+            "content": [{ "type": "tool_use", "id": "toolu_1", "name": "Bash", "input": { "command": "cargo build" } }],
+        }]))
+        .unwrap();
+        let mock = start(config(script)).await.unwrap();
+        let body = json!({ "model": "claude-sonnet-4", "max_tokens": 10, "stream": true, "messages": [{ "role": "user", "content": "build it" }] });
+
+        let text = post(&mock.url(), body).await.text().await.unwrap();
+        let response = traffic::response_from_stream(&text);
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(
+            response.content[0].text.as_deref(),
+            Some("Let me check the build output for you")
+        );
+        assert_eq!(
+            response.content[1].input,
+            Some(json!({ "command": "cargo build" }))
+        );
+        assert!(response.usage.unwrap().output_tokens > 1);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_against_mock() {
+        let mock = start(MockConfig {
+            usage: Some(Usage {
+                input_tokens: 1200,
+                output_tokens: 40,
+                ..Default::default()
+            }),
+            error_every: Some(2),
+            ..config(vec![])
+        })
+        .await
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}/api", listener.local_addr().unwrap());
+        let traffic_log = TrafficLog::new();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let proxy = MitmProxy::new(
+            ProxyConfig {
+                upstream: mock.url(),
+                ..Default::default()
+            },
+            traffic_log.clone(),
+            event_tx,
+        );
+        tokio::spawn(async move { proxy.serve(listener).await });
+
+        let body = json!({ "model": "claude-haiku-4-5", "max_tokens": 10, "stream": true, "messages": [{ "role": "user", "content": "ping" }] });
+        let ok = post(&proxy_url, body.clone()).await;
+        assert_eq!(ok.status().as_u16(), 200);
+        assert!(ok.text().await.unwrap().contains("message_stop"));
+        let failed = post(&proxy_url, body).await;
+        assert_eq!(failed.status().as_u16(), 529);
+
+        let mut completed = 0;
+        let mut failures = 0;
+        while completed + failures < 2 {
+            match event_rx.recv().await.unwrap() {
+                ProxyEvent::RequestCompleted {
+                    tokens_in,
+                    tokens_out,
+                    ..
+                } => {
+                    assert_eq!((tokens_in, tokens_out), (1200, 40));
+                    completed += 1;
+                }
+                ProxyEvent::RequestFailed { .. } => failures += 1,
+                _ => {}
+            }
+        }
+        assert_eq!((completed, failures), (1, 1));
+
+        let entries = traffic_log.get_all();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].status, TrafficStatus::Success);
+        let reply = &entries[0].response.as_ref().unwrap().content[0];
+        assert_eq!(reply.text.as_deref(), Some("Mock reply to: ping"));
+        assert!(matches!(&entries[1].status, TrafficStatus::Error(e) if e.starts_with("HTTP 529")));
+    }
+
+    #[test]
+    fn test_replay_from_traffic() {
+        let entry: TrafficEntry = serde_json::from_value(json!({
+            "id": 1,
+            "timestamp": "2025-06-01T12:00:00Z",
+            "request": { "model": "claude-sonnet-4", "max_tokens": 10, "messages": [], "system": null, "stream": false, "tools": null },
+            "response": null,
+            "latency_ms": 80,
+            "status": { "Error": "HTTP 529: {\"type\":\"error\"}" },
+        }))
+        .unwrap();
+        let reply = MockResponse::from_entry(&entry);
+        assert_eq!(reply.latency_ms, Some(80));
+        let error = reply.error.unwrap();
+        assert_eq!(
+            (error.status, error.error_type.as_str()),
+            (529, "overloaded_error")
+        );
+    }
+}
@@ -71,6 +71,33 @@ impl AiTool {
         }
     }
 
+    /// The tool with this display name, as given by `name`
+    pub fn from_name(name: &str) -> Self {
+        [
+            AiTool::ClaudeCode,
+            AiTool::Cline,
+            AiTool::Cursor,
+            AiTool::Kiro,
+            AiTool::RooCode,
+            AiTool::Kilo,
+            AiTool::VSCode,
+            AiTool::Copilot,
+            AiTool::Tabnine,
+            AiTool::CodeWhisperer,
+            AiTool::Windsurf,
+            AiTool::Continue,
+            AiTool::Aider,
+            AiTool::Cody,
+            AiTool::CodeGPT,
+            AiTool::BitoAI,
+            AiTool::AmazonQ,
+            AiTool::Supermaven,
+        ]
+        .into_iter()
+        .find(|tool| tool.name() == name)
+        .unwrap_or_else(|| AiTool::Other(name.to_string()))
+    }
+
     pub fn name(&self) -> &str {
         match self {
             AiTool::ClaudeCode => "Claude Code",
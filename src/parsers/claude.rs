@@ -20,26 +20,20 @@ impl LogParser for ClaudeParser {
         let mut oldest: Option<DateTime<Utc>> = None;
         let mut newest: Option<DateTime<Utc>> = None;
 
-        // Check if this is history.jsonl
-        if path.file_name().and_then(|n| n.to_str()) == Some("history.jsonl") {
+        if self.parses_lines(path) {
+            // history.jsonl and conversation transcripts: one entry per line, in order
             let file = fs::File::open(path)?;
             let reader = BufReader::new(file);
 
             for line in reader.lines() {
                 let line = line?;
-                if line.trim().is_empty() {
-                    continue;
-                }
-
-                if let Ok(json) = serde_json::from_str::<Value>(&line) {
-                    if let Some(entry) = parse_history_entry(&json) {
-                        // Update date range
-                        if let Some(ts) = entry.timestamp {
-                            oldest = Some(oldest.map_or(ts, |o| o.min(ts)));
-                            newest = Some(newest.map_or(ts, |n| n.max(ts)));
-                        }
-                        entries.push(entry);
+                if let Some(entry) = self.parse_line(path, &line) {
+                    // Update date range
+                    if let Some(ts) = entry.timestamp {
+                        oldest = Some(oldest.map_or(ts, |o| o.min(ts)));
+                        newest = Some(newest.map_or(ts, |n| n.max(ts)));
                     }
+                    entries.push(entry);
                 }
             }
         } else if path.is_dir() && path.join("history.jsonl").exists() {
@@ -60,6 +54,23 @@ impl LogParser for ClaudeParser {
             },
         })
     }
+
+    fn parses_lines(&self, path: &Path) -> bool {
+        path.file_name().and_then(|n| n.to_str()) == Some("history.jsonl")
+            || is_transcript_path(path)
+    }
+
+    fn parse_line(&self, path: &Path, line: &str) -> Option<LogEntry> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let json = serde_json::from_str::<Value>(line).ok()?;
+        if is_transcript_path(path) {
+            parse_transcript_entry(&json)
+        } else {
+            parse_history_entry(&json)
+        }
+    }
 }
 
 fn parse_history_entry(json: &Value) -> Option<LogEntry> {
@@ -111,6 +122,107 @@ fn parse_history_entry(json: &Value) -> Option<LogEntry> {
     })
 }
 
+/// Whether `path` is a Claude Code conversation transcript
+/// (`~/.claude/projects/<project>/<session-id>.jsonl`)
+pub fn is_transcript(path: &Path) -> bool {
+    path.is_file() && is_transcript_path(path)
+}
+
+/// Whether `path` is shaped like a transcript path, without looking at the
+/// file system; also true for transcripts read from a backup archive
+pub fn is_transcript_path(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "jsonl")
+        && path
+            .parent()
+            .and_then(|p| p.parent())
+            .and_then(|p| p.file_name())
+            .is_some_and(|n| n == "projects")
+}
+
+/// Turns a transcript line into an entry; lines without message text are skipped
+fn parse_transcript_entry(json: &Value) -> Option<LogEntry> {
+    if json.get("isMeta").and_then(|v| v.as_bool()) == Some(true) {
+        return None;
+    }
+    let message = json.get("message")?;
+    let role = message
+        .get("role")
+        .or_else(|| json.get("type"))
+        .and_then(|v| v.as_str())?;
+
+    let timestamp = json
+        .get("timestamp")
+        .and_then(|v| v.as_str())
+        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    let mut text = Vec::new();
+    let mut tool_parts = Vec::new();
+    let mut is_error = false;
+    match message.get("content") {
+        Some(Value::String(s)) => text.push(s.clone()),
+        Some(Value::Array(blocks)) => {
+            for block in blocks {
+                match block.get("type").and_then(|t| t.as_str()) {
+                    Some("text") => {
+                        if let Some(t) = block.get("text").and_then(|t| t.as_str()) {
+                            text.push(t.to_string());
+                        }
+                    }
+                    Some("tool_use") => {
+                        let name = block.get("name").and_then(|n| n.as_str()).unwrap_or("tool");
+                        let input = block.get("input").map(|i| i.to_string()).unwrap_or_default();
+                        tool_parts.push(format!("[{}] {}", name, input));
+                    }
+                    Some("tool_result") => {
+                        is_error |= block.get("is_error").and_then(|e| e.as_bool()) == Some(true);
+                        match block.get("content") {
+                            Some(Value::String(s)) => tool_parts.push(s.clone()),
+                            Some(Value::Array(parts)) => tool_parts.extend(
+                                parts
+                                    .iter()
+                                    .filter_map(|p| p.get("text").and_then(|t| t.as_str()))
+                                    .map(String::from),
+                            ),
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let category = if text.is_empty() {
+        EntryCategory::ToolUse
+    } else if role == "user" {
+        EntryCategory::UserPrompt
+    } else {
+        EntryCategory::AssistantResponse
+    };
+    text.extend(tool_parts);
+    let message = text.join("\n");
+    if message.trim().is_empty() {
+        return None;
+    }
+
+    let level = if is_error {
+        LogLevel::Error
+    } else if category == EntryCategory::UserPrompt {
+        LogLevel::Info
+    } else {
+        LogLevel::Debug
+    };
+
+    Some(LogEntry {
+        timestamp,
+        level,
+        message,
+        category,
+    })
+}
+
 pub fn analyze_claude_logs(claude_dir: &Path) -> Result<ClaudeAnalysis> {
     let mut analysis = ClaudeAnalysis::default();
 
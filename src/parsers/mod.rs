@@ -12,6 +12,17 @@ use std::path::Path;
 pub trait LogParser: Send + Sync {
     fn can_parse(&self, path: &Path) -> bool;
     fn parse(&self, path: &Path) -> Result<ParsedLog>;
+
+    /// Whether `path` holds one entry per line, so lines appended later can
+    /// be parsed on their own with `parse_line`
+    fn parses_lines(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Parses one line of a line-oriented file; `None` for lines without an entry
+    fn parse_line(&self, _path: &Path, _line: &str) -> Option<LogEntry> {
+        None
+    }
 }
 
 #[derive(Debug, Clone)]
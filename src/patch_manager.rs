@@ -0,0 +1,703 @@
+//! Reversible binary patching of the Claude CLI
+//!
+//! `claudev patch --binary` rewrites the API base URL inside the Claude
+//! binary so it talks plaintext HTTP to `claudev monitor`. Every patch is
+//! recorded with the SHA-256 and version of the original, and the original is
+//! kept under `~/.claudev/patches/<version>-<sha>/`. The record lets us tell a
+//! patched binary from one an upgrade has silently replaced, and restoring is
+//! verified against the recorded checksum.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// API base URL compiled into the Claude binary
+pub const ORIGINAL_URL: &[u8] = b"https://api.anthropic.com";
+/// Replacement pointing at `claudev monitor -p 1338`; must be the same length
+pub const PATCHED_URL: &[u8] = b"http://127.0.0.1:1338/api";
+const _: () = assert!(ORIGINAL_URL.len() == PATCHED_URL.len());
+
+/// How long `claude --version` may take before we give up on it
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Differing runs closer than this are shown as one hunk
+const DIFF_MERGE_GAP: usize = 8;
+
+/// A binary patch we applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchRecord {
+    /// Binary that was rewritten (the launcher symlink resolved)
+    pub binary: PathBuf,
+    pub version: Option<String>,
+    pub original_sha256: String,
+    pub patched_sha256: String,
+    /// Byte-identical copy of the original
+    pub backup: PathBuf,
+    /// Where the URL was replaced
+    pub offset: u64,
+    pub patched_at: DateTime<Utc>,
+}
+
+/// Where a launcher stands relative to our records
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchState {
+    Unpatched,
+    Patched,
+    /// The recorded binary holds the original bytes again, e.g. after a reinstall
+    Reverted,
+    /// The launcher points at another binary, or the patched binary was
+    /// overwritten by a different release
+    Upgraded {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// The recorded binary is neither the original nor our patch
+    Modified,
+    Missing,
+}
+
+/// Result of `claudev patch --status`
+#[derive(Debug, Clone)]
+pub struct PatchStatus {
+    pub launcher: PathBuf,
+    pub binary: PathBuf,
+    pub version: Option<String>,
+    pub sha256: Option<String>,
+    pub state: PatchState,
+    pub record: Option<PatchRecord>,
+    /// `.backup` left by earlier versions of claudev, without a checksum
+    pub legacy_backup: Option<PathBuf>,
+    /// Original launcher moved aside by the wrapper patch
+    pub wrapper_original: Option<PathBuf>,
+}
+
+/// A run of bytes that differ between two files
+#[derive(Debug, Clone, PartialEq)]
+pub struct ByteDiff {
+    pub offset: usize,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+/// Applies, checks and reverts binary patches
+pub struct PatchManager {
+    dir: PathBuf,
+}
+
+impl PatchManager {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub fn open_default() -> Self {
+        Self::new(patches_dir())
+    }
+
+    fn records_path(&self) -> PathBuf {
+        self.dir.join("patches.json")
+    }
+
+    fn load_records(&self) -> Result<Vec<PatchRecord>> {
+        let path = self.records_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    fn save_records(&self, records: &[PatchRecord]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.records_path(), serde_json::to_string_pretty(records)?)?;
+        Ok(())
+    }
+
+    /// Latest record for the binary behind a launcher, or for the launcher itself
+    fn find_record(&self, launcher: &Path, binary: &Path) -> Result<Option<PatchRecord>> {
+        let records = self.load_records()?;
+        let exact = records.iter().rev().find(|r| r.binary == binary);
+        // After an upgrade the launcher points elsewhere; fall back to any
+        // record for a binary that sat next to it
+        let sibling = || {
+            records.iter().rev().find(|r| {
+                r.binary.parent() == binary.parent() || r.binary.parent() == launcher.parent()
+            })
+        };
+        Ok(exact.or_else(sibling).cloned())
+    }
+
+    pub fn status(&self, launcher: &Path) -> Result<PatchStatus> {
+        let binary = resolve_binary(launcher);
+        let exists = binary.exists();
+        let sha256 = if exists {
+            Some(sha256_file(&binary)?)
+        } else {
+            None
+        };
+        let record = self.find_record(launcher, &binary)?;
+        let version = if exists {
+            detect_version(&binary)
+        } else {
+            None
+        };
+
+        let upgraded = |record: &PatchRecord| PatchState::Upgraded {
+            from: record.version.clone(),
+            to: version.clone(),
+        };
+        let state = match (&record, &sha256) {
+            (_, None) => PatchState::Missing,
+            (None, _) => PatchState::Unpatched,
+            (Some(record), Some(_)) if record.binary != binary => upgraded(record),
+            (Some(record), Some(sha)) if *sha == record.patched_sha256 => PatchState::Patched,
+            (Some(record), Some(sha)) if *sha == record.original_sha256 => PatchState::Reverted,
+            (Some(record), Some(_)) if is_new_release(record, &binary, &version)? => {
+                upgraded(record)
+            }
+            (Some(_), Some(_)) => PatchState::Modified,
+        };
+
+        let legacy_backup = Some(binary.with_extension("backup")).filter(|p| p.exists());
+        let wrapper_original = Some(launcher.with_extension("wrapper")).filter(|p| p.exists());
+
+        Ok(PatchStatus {
+            launcher: launcher.to_path_buf(),
+            binary,
+            version,
+            sha256,
+            state,
+            record,
+            legacy_backup,
+            wrapper_original,
+        })
+    }
+
+    /// Patch the binary behind a launcher, backing up the original first
+    pub fn apply(&self, launcher: &Path) -> Result<PatchRecord> {
+        let status = self.status(launcher)?;
+        match &status.state {
+            PatchState::Missing => anyhow::bail!("Claude binary not found at {:?}", status.binary),
+            PatchState::Patched => anyhow::bail!(
+                "{:?} is already patched; restore it first with `claudev patch --restore`",
+                status.binary
+            ),
+            PatchState::Modified => anyhow::bail!(
+                "{:?} changed since it was patched and matches neither the original nor the patch; reinstall Claude before patching",
+                status.binary
+            ),
+            _ => {}
+        }
+        if let Some(legacy) = &status.legacy_backup {
+            anyhow::bail!(
+                "Found a backup from an older claudev at {:?}; run `claudev patch --restore` first",
+                legacy
+            );
+        }
+
+        let binary = &status.binary;
+        let mut data = fs::read(binary).with_context(|| format!("Failed to read {:?}", binary))?;
+        let original_sha256 = sha256_bytes(&data);
+
+        let offset = match find_all(&data, ORIGINAL_URL).as_slice() {
+            [offset] => *offset,
+            [] if !find_all(&data, PATCHED_URL).is_empty() => anyhow::bail!(
+                "{:?} already contains the patched URL but has no patch record; reinstall Claude to get a clean original",
+                binary
+            ),
+            [] => anyhow::bail!(
+                "API URL not found in {:?}; this Claude version is not supported",
+                binary
+            ),
+            matches => anyhow::bail!(
+                "API URL found {} times in {:?}; refusing to patch an ambiguous binary",
+                matches.len(),
+                binary
+            ),
+        };
+
+        // Back up and verify before touching the original
+        let version = status.version.clone();
+        let backup_dir = self.dir.join(format!(
+            "{}-{}",
+            version.as_deref().unwrap_or("unknown"),
+            &original_sha256[..12]
+        ));
+        fs::create_dir_all(&backup_dir)?;
+        let backup = backup_dir.join(binary.file_name().unwrap_or_else(|| "claude".as_ref()));
+        fs::write(&backup, &data)?;
+        if sha256_file(&backup)? != original_sha256 {
+            anyhow::bail!("Backup {:?} does not match the original; aborting", backup);
+        }
+
+        data[offset..offset + PATCHED_URL.len()].copy_from_slice(PATCHED_URL);
+        replace_file(binary, &data)?;
+
+        let record = PatchRecord {
+            binary: binary.clone(),
+            version,
+            original_sha256,
+            patched_sha256: sha256_bytes(&data),
+            backup,
+            offset: offset as u64,
+            patched_at: Utc::now(),
+        };
+        let mut records = self.load_records()?;
+        records.retain(|r| r.binary != record.binary);
+        records.push(record.clone());
+        self.save_records(&records)?;
+
+        // The backup of a release that was overwritten in place can never be restored
+        if let (PatchState::Upgraded { .. }, Some(stale)) = (&status.state, &status.record) {
+            if stale.binary == record.binary && stale.backup.parent() != record.backup.parent() {
+                if let Some(dir) = stale.backup.parent() {
+                    let _ = fs::remove_dir_all(dir);
+                }
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Put the original binary back, verified against its recorded checksum.
+    /// Returns the restored path, or `None` when there is no binary patch.
+    pub fn restore(&self, launcher: &Path) -> Result<Option<PathBuf>> {
+        let status = self.status(launcher)?;
+
+        let Some(record) = status.record else {
+            // Backups from before patches were recorded have no checksum
+            if let Some(legacy) = status.legacy_backup {
+                fs::rename(&legacy, &status.binary)?;
+                return Ok(Some(status.binary));
+            }
+            return Ok(None);
+        };
+
+        let backup_sha = sha256_file(&record.backup)
+            .with_context(|| format!("Backup {:?} is missing", record.backup))?;
+        if backup_sha != record.original_sha256 {
+            anyhow::bail!(
+                "Backup {:?} is corrupt (sha256 {}, expected {})",
+                record.backup,
+                backup_sha,
+                record.original_sha256
+            );
+        }
+
+        let current = if record.binary.exists() {
+            Some(sha256_file(&record.binary)?)
+        } else {
+            None
+        };
+        if let Some(sha) = &current {
+            let replaced = *sha != record.original_sha256 && *sha != record.patched_sha256;
+            let version = detect_version(&record.binary);
+            if replaced && is_new_release(&record, &record.binary, &version)? {
+                anyhow::bail!(
+                    "{:?} was replaced by Claude {} and is no longer patched; refusing to restore the backup of {} over it. Run `claudev patch --binary` to patch the new version",
+                    record.binary,
+                    version.as_deref().unwrap_or("(unknown version)"),
+                    record.version.as_deref().unwrap_or("an unknown version")
+                );
+            }
+        }
+        if current.as_deref() != Some(record.original_sha256.as_str()) {
+            let data = fs::read(&record.backup)?;
+            replace_file(&record.binary, &data)?;
+            let restored = sha256_file(&record.binary)?;
+            if restored != record.original_sha256 {
+                anyhow::bail!(
+                    "Restored {:?} does not match the original (sha256 {}); backup kept at {:?}",
+                    record.binary,
+                    restored,
+                    record.backup
+                );
+            }
+        }
+
+        // Only forget the patch once the original is verified in place
+        let mut records = self.load_records()?;
+        records.retain(|r| r.binary != record.binary);
+        self.save_records(&records)?;
+        if let Some(dir) = record.backup.parent() {
+            let _ = fs::remove_dir_all(dir);
+        }
+
+        Ok(Some(record.binary))
+    }
+}
+
+/// Whether a recorded binary that matches neither of our checksums is another
+/// Claude release rather than a damaged copy of the one we patched
+fn is_new_release(record: &PatchRecord, binary: &Path, version: &Option<String>) -> Result<bool> {
+    if version.is_some() && *version != record.version {
+        return Ok(true);
+    }
+    let data = fs::read(binary).with_context(|| format!("Failed to read {:?}", binary))?;
+    Ok(find_all(&data, ORIGINAL_URL).len() == 1)
+}
+
+/// Default directory of patch records and backups
+pub fn patches_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claudev")
+        .join("patches")
+}
+
+/// Follow a launcher symlink to the binary it runs
+pub fn resolve_binary(launcher: &Path) -> PathBuf {
+    fs::canonicalize(launcher).unwrap_or_else(|_| launcher.to_path_buf())
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn sha256_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Claude version from the install path (`versions/1.0.3`) or `--version`
+pub fn detect_version(binary: &Path) -> Option<String> {
+    let from_path = binary
+        .file_name()
+        .and_then(|n| n.to_str())
+        .and_then(parse_version);
+    from_path.or_else(|| {
+        let mut child = Command::new(binary)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let started = Instant::now();
+        while child.try_wait().ok()?.is_none() {
+            if started.elapsed() > VERSION_TIMEOUT {
+                let _ = child.kill();
+                return None;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let output = child.wait_with_output().ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .find_map(parse_version)
+    })
+}
+
+/// `1.0.3` or `v1.0.3`, nothing else
+fn parse_version(s: &str) -> Option<String> {
+    let s = s.strip_prefix('v').unwrap_or(s);
+    let parts: Vec<&str> = s.split('.').collect();
+    let numeric = parts.len() >= 2
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()));
+    numeric.then(|| s.to_string())
+}
+
+fn find_all(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(offset, _)| offset)
+        .collect()
+}
+
+/// Write `data` next to `path` and rename it over, keeping permissions
+fn replace_file(path: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".claudev-tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, data).with_context(|| format!("Failed to write {:?}", tmp))?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&tmp, metadata.permissions())?;
+    } else {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o755))?;
+        }
+    }
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+/// Runs of differing bytes between two equally long buffers
+pub fn diff_bytes(before: &[u8], after: &[u8]) -> Vec<ByteDiff> {
+    let mut diffs: Vec<ByteDiff> = Vec::new();
+    let len = before.len().min(after.len());
+
+    for offset in (0..len).filter(|&i| before[i] != after[i]) {
+        match diffs.last_mut() {
+            Some(last) if offset <= last.offset + last.before.len() + DIFF_MERGE_GAP => {
+                let end = offset + 1;
+                last.before = before[last.offset..end].to_vec();
+                last.after = after[last.offset..end].to_vec();
+            }
+            _ => diffs.push(ByteDiff {
+                offset,
+                before: vec![before[offset]],
+                after: vec![after[offset]],
+            }),
+        }
+    }
+    if before.len() != after.len() {
+        diffs.push(ByteDiff {
+            offset: len,
+            before: before[len..].to_vec(),
+            after: after[len..].to_vec(),
+        });
+    }
+    diffs
+}
+
+/// Printable form of a byte run
+fn show_bytes(bytes: &[u8]) -> String {
+    const MAX: usize = 64;
+    let shown: String = bytes[..bytes.len().min(MAX)]
+        .iter()
+        .flat_map(|b| std::ascii::escape_default(*b))
+        .map(char::from)
+        .collect();
+    if bytes.len() > MAX {
+        format!("{}… ({} bytes)", shown, bytes.len())
+    } else {
+        shown
+    }
+}
+
+/// Print `claudev patch --status`, with the diff against the original when patched
+pub fn print_status(status: &PatchStatus) -> Result<()> {
+    use colored::Colorize;
+
+    let state = match &status.state {
+        PatchState::Unpatched if status.wrapper_original.is_some() => "WRAPPER PATCHED".green(),
+        PatchState::Unpatched if status.legacy_backup.is_some() => {
+            "BINARY PATCHED (legacy backup)".yellow()
+        }
+        PatchState::Unpatched => "UNPATCHED".normal(),
+        PatchState::Patched => "BINARY PATCHED".green(),
+        PatchState::Reverted => "PATCH UNDONE (original binary is back)".yellow(),
+        PatchState::Upgraded { .. } => "PATCH UNDONE (Claude was upgraded)".yellow(),
+        PatchState::Modified => "MODIFIED (matches neither original nor patch)".red(),
+        PatchState::Missing => "BINARY NOT FOUND".red(),
+    };
+    println!("Status:   {}", state.bold());
+    println!("Launcher: {:?}", status.launcher);
+    println!("Binary:   {:?}", status.binary);
+    if let Some(version) = &status.version {
+        println!("Version:  {}", version);
+    }
+    if let Some(sha) = &status.sha256 {
+        println!("SHA-256:  {}", sha);
+    }
+    if let Some(wrapper) = &status.wrapper_original {
+        println!("Original launcher: {:?}", wrapper);
+    }
+    if let Some(legacy) = &status.legacy_backup {
+        println!("Backup (no checksum): {:?}", legacy);
+    }
+
+    let Some(record) = &status.record else {
+        return Ok(());
+    };
+    println!();
+    println!(
+        "Patched {} at {}",
+        record.version.as_deref().unwrap_or("unknown version"),
+        record.patched_at.format("%Y-%m-%d %H:%M")
+    );
+    println!("Original SHA-256: {}", record.original_sha256);
+    println!("Patched SHA-256:  {}", record.patched_sha256);
+    println!("Backup: {:?}", record.backup);
+
+    match &status.state {
+        PatchState::Upgraded { from, to } => {
+            println!(
+                "\nClaude moved from {} to {}; the new binary is unpatched.",
+                from.as_deref().unwrap_or("?"),
+                to.as_deref().unwrap_or("?")
+            );
+            if record.binary == status.binary {
+                println!("Run `claudev patch --binary` to patch it.");
+            } else {
+                println!("Run `claudev patch --restore` then `claudev patch --binary` to patch it.");
+            }
+        }
+        PatchState::Reverted => {
+            println!("\nThe binary was replaced with the original. Run `claudev patch --binary` to re-apply.");
+        }
+        PatchState::Patched | PatchState::Modified => {
+            let before = fs::read(&record.backup)
+                .with_context(|| format!("Failed to read backup {:?}", record.backup))?;
+            let after = fs::read(&status.binary)?;
+            let diffs = diff_bytes(&before, &after);
+            println!(
+                "\n{} ({} hunks)",
+                "Diff against original".cyan().bold(),
+                diffs.len()
+            );
+            for diff in diffs.iter().take(10) {
+                println!("  @ 0x{:08x}", diff.offset);
+                println!("    {}", format!("- {}", show_bytes(&diff.before)).red());
+                println!("    {}", format!("+ {}", show_bytes(&diff.after)).green());
+            }
+            if diffs.len() > 10 {
+                println!("  … {} more", diffs.len() - 10);
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_binary(dir: &Path, name: &str, urls: usize) -> PathBuf {
+        let mut data = b"\x7fELF header bytes ".to_vec();
+        for _ in 0..urls {
+            data.extend_from_slice(b"const BASE=\"");
+            data.extend_from_slice(ORIGINAL_URL);
+            data.extend_from_slice(b"\";");
+        }
+        data.extend_from_slice(b" trailing code");
+        let path = dir.join(name);
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_patch_and_byte_identical_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PatchManager::new(dir.path().join("patches"));
+        let binary = fake_binary(dir.path(), "1.0.3", 1);
+        let original = fs::read(&binary).unwrap();
+
+        let record = manager.apply(&binary).unwrap();
+        assert_eq!(record.version.as_deref(), Some("1.0.3"));
+        let backup_dir = record.backup.parent().unwrap();
+        assert_eq!(backup_dir.parent().unwrap(), dir.path().join("patches"));
+        assert!(backup_dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("1.0.3-"));
+        let status = manager.status(&binary).unwrap();
+        assert_eq!(status.state, PatchState::Patched);
+
+        let patched = fs::read(&binary).unwrap();
+        let diffs = diff_bytes(&original, &patched);
+        assert_eq!(diffs.len(), 1);
+        // "https://" and "http://1" share their first four bytes
+        assert_eq!(diffs[0].offset, record.offset as usize + 4);
+        assert!(manager.apply(&binary).is_err());
+
+        assert_eq!(manager.restore(&binary).unwrap(), Some(binary.clone()));
+        assert_eq!(fs::read(&binary).unwrap(), original);
+        assert_eq!(
+            manager.status(&binary).unwrap().state,
+            PatchState::Unpatched
+        );
+        assert!(!record.backup.exists());
+    }
+
+    #[test]
+    fn test_refuses_ambiguous_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PatchManager::new(dir.path().join("patches"));
+        let binary = fake_binary(dir.path(), "claude", 2);
+        let original = fs::read(&binary).unwrap();
+
+        let err = manager.apply(&binary).unwrap_err().to_string();
+        assert!(err.contains("2 times"), "{}", err);
+        assert_eq!(fs::read(&binary).unwrap(), original);
+    }
+
+    #[test]
+    fn test_in_place_upgrade_is_repatched_not_downgraded() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PatchManager::new(dir.path().join("patches"));
+        let binary = fake_binary(dir.path(), "claude", 1);
+        let old_record = manager.apply(&binary).unwrap();
+
+        // An upgrade overwrites the launcher with a new, unpatched release
+        let mut upgraded = fs::read(&binary).unwrap();
+        upgraded.splice(0..0, b"new release ".iter().copied());
+        let start = find_all(&upgraded, PATCHED_URL)[0];
+        upgraded[start..start + ORIGINAL_URL.len()].copy_from_slice(ORIGINAL_URL);
+        fs::write(&binary, &upgraded).unwrap();
+        assert_eq!(
+            manager.status(&binary).unwrap().state,
+            PatchState::Upgraded { from: None, to: None }
+        );
+
+        let err = manager.restore(&binary).unwrap_err().to_string();
+        assert!(err.contains("refusing to restore"), "{}", err);
+        assert_eq!(fs::read(&binary).unwrap(), upgraded);
+
+        let record = manager.apply(&binary).unwrap();
+        assert_eq!(fs::read(&record.backup).unwrap(), upgraded);
+        assert!(!old_record.backup.exists());
+        assert_eq!(manager.status(&binary).unwrap().state, PatchState::Patched);
+
+        // A patched binary that was damaged is still reported as such
+        let mut damaged = fs::read(&binary).unwrap();
+        damaged.push(0);
+        fs::write(&binary, damaged).unwrap();
+        assert_eq!(manager.status(&binary).unwrap().state, PatchState::Modified);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detects_upgrade_and_reinstall() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PatchManager::new(dir.path().join("patches"));
+        let versions = dir.path().join("versions");
+        fs::create_dir(&versions).unwrap();
+        let old = fake_binary(&versions, "1.0.3", 1);
+        let launcher = dir.path().join("claude");
+        std::os::unix::fs::symlink(&old, &launcher).unwrap();
+
+        let record = manager.apply(&launcher).unwrap();
+
+        // Reinstalling the same version puts the original bytes back
+        fs::copy(&record.backup, &old).unwrap();
+        assert_eq!(
+            manager.status(&launcher).unwrap().state,
+            PatchState::Reverted
+        );
+
+        // An upgrade repoints the launcher at a new, unpatched binary
+        let new = fake_binary(&versions, "1.0.4", 1);
+        fs::remove_file(&launcher).unwrap();
+        std::os::unix::fs::symlink(&new, &launcher).unwrap();
+        assert_eq!(
+            manager.status(&launcher).unwrap().state,
+            PatchState::Upgraded {
+                from: Some("1.0.3".to_string()),
+                to: Some("1.0.4".to_string())
+            }
+        );
+
+        // Restore still targets the binary that was patched
+        assert_eq!(manager.restore(&launcher).unwrap(), Some(old));
+        assert_eq!(
+            manager.status(&launcher).unwrap().state,
+            PatchState::Unpatched
+        );
+    }
+}
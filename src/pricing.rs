@@ -0,0 +1,283 @@
+//! Model pricing table shared by all cost calculations
+//!
+//! Rates are loaded from a bundled `pricing.json` and can be overridden or
+//! extended by `~/.config/claudev/pricing.json`. Entries are keyed by provider,
+//! model and effective date so historic usage is priced at the rate that
+//! applied when it happened.
+
+use crate::traffic::Usage;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Pricing table shipped with the binary
+const BUNDLED_PRICING: &str = include_str!("pricing.json");
+
+/// Batch API requests are billed at half the regular rate unless listed
+const DEFAULT_BATCH_DISCOUNT: f64 = 0.5;
+/// Cache writes default to 125% of the input rate
+const DEFAULT_CACHE_WRITE_MULTIPLIER: f64 = 1.25;
+
+lazy_static::lazy_static! {
+    static ref GLOBAL_PRICING: PricingTable = PricingTable::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load pricing overrides, using bundled rates: {}", e);
+        PricingTable::bundled()
+    });
+}
+
+/// Rates for one model, in USD per million tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub provider: String,
+    pub model: String,
+    /// Substrings of API model ids this entry applies to (longest match wins)
+    #[serde(rename = "match", default)]
+    pub patterns: Vec<String>,
+    pub effective_from: NaiveDate,
+    pub input: f64,
+    pub output: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_write: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_read: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_input: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_output: Option<f64>,
+}
+
+impl ModelPrice {
+    pub fn cache_write_rate(&self) -> f64 {
+        self.cache_write.unwrap_or(self.input * DEFAULT_CACHE_WRITE_MULTIPLIER)
+    }
+
+    /// Providers without prompt caching bill cached tokens as regular input
+    pub fn cache_read_rate(&self) -> f64 {
+        self.cache_read.unwrap_or(self.input)
+    }
+
+    pub fn batch_input_rate(&self) -> f64 {
+        self.batch_input.unwrap_or(self.input * DEFAULT_BATCH_DISCOUNT)
+    }
+
+    pub fn batch_output_rate(&self) -> f64 {
+        self.batch_output.unwrap_or(self.output * DEFAULT_BATCH_DISCOUNT)
+    }
+
+    /// Cost of a regular (non-batch) request
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_creation_input_tokens as f64 * self.cache_write_rate()
+            + usage.cache_read_input_tokens as f64 * self.cache_read_rate())
+            / 1_000_000.0
+    }
+
+    /// Cost of a request submitted through the batch API. Cache rates get
+    /// the same discount as input.
+    pub fn cost_batch(&self, usage: &Usage) -> f64 {
+        let ratio = self.batch_input_rate() / self.input.max(f64::EPSILON);
+        (usage.input_tokens as f64 * self.batch_input_rate()
+            + usage.output_tokens as f64 * self.batch_output_rate()
+            + usage.cache_creation_input_tokens as f64 * self.cache_write_rate() * ratio
+            + usage.cache_read_input_tokens as f64 * self.cache_read_rate() * ratio)
+            / 1_000_000.0
+    }
+
+    /// Length of the longest pattern matching a model id, if any.
+    /// Entries without patterns match on their model name.
+    fn match_len(&self, model: &str) -> Option<usize> {
+        let model = model.to_lowercase();
+        let patterns = if self.patterns.is_empty() {
+            std::slice::from_ref(&self.model)
+        } else {
+            self.patterns.as_slice()
+        };
+        patterns
+            .iter()
+            .filter(|p| !p.is_empty() && model.contains(&p.to_lowercase()))
+            .map(|p| p.len())
+            .max()
+    }
+}
+
+/// Versioned pricing table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PricingTable {
+    #[serde(default)]
+    pub version: u32,
+    /// Model used when nothing matches
+    #[serde(default)]
+    pub fallback: String,
+    pub models: Vec<ModelPrice>,
+}
+
+impl PricingTable {
+    /// The table bundled with the binary
+    pub fn bundled() -> Self {
+        Self::from_json(BUNDLED_PRICING).expect("bundled pricing.json is valid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Failed to parse pricing table")
+    }
+
+    /// Bundled table merged with the user's overrides, if present
+    pub fn load() -> Result<Self> {
+        let mut table = Self::bundled();
+        let path = pricing_file_path();
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            table.merge(Self::from_json(&content)?);
+        }
+        Ok(table)
+    }
+
+    /// Overlay another table; entries with the same provider, model and date replace ours
+    pub fn merge(&mut self, other: PricingTable) {
+        self.version = self.version.max(other.version);
+        if !other.fallback.is_empty() {
+            self.fallback = other.fallback;
+        }
+        for price in other.models {
+            if let Some(existing) = self.models.iter_mut().find(|p| {
+                p.provider == price.provider
+                    && p.model == price.model
+                    && p.effective_from == price.effective_from
+            }) {
+                *existing = price;
+            } else {
+                self.models.push(price);
+            }
+        }
+    }
+
+    /// Find the price for a model on a given date (today if `None`)
+    pub fn lookup(&self, model: &str, date: Option<NaiveDate>) -> &ModelPrice {
+        self.find(model, date)
+            .or_else(|| self.find(&self.fallback, date))
+            .or_else(|| self.models.first())
+            .expect("pricing table has at least one model")
+    }
+
+    fn find(&self, model: &str, date: Option<NaiveDate>) -> Option<&ModelPrice> {
+        let date = date.unwrap_or_else(|| Utc::now().date_naive());
+
+        // OpenRouter-style ids ("anthropic/claude-3.5-sonnet") name the provider
+        let provider = model
+            .split_once('/')
+            .map(|(p, _)| p.to_lowercase())
+            .filter(|p| self.models.iter().any(|m| m.provider == *p));
+
+        let candidates: Vec<(usize, &ModelPrice)> = self
+            .models
+            .iter()
+            .filter(|p| provider.as_ref().is_none_or(|prov| p.provider == *prov))
+            .filter_map(|p| p.match_len(model).map(|len| (len, p)))
+            .collect();
+
+        let best_len = candidates.iter().map(|(len, _)| *len).max()?;
+        let mut best: Vec<&ModelPrice> = candidates
+            .into_iter()
+            .filter(|(len, _)| *len == best_len)
+            .map(|(_, p)| p)
+            .collect();
+        best.sort_by_key(|p| p.effective_from);
+
+        // Latest rate already in effect, or the earliest known one for older dates
+        best.iter()
+            .rev()
+            .find(|p| p.effective_from <= date)
+            .or_else(|| best.first())
+            .copied()
+    }
+
+    /// Cost of a request made at a specific time
+    pub fn cost_at(&self, model: &str, usage: &Usage, at: DateTime<Utc>) -> f64 {
+        self.lookup(model, Some(at.date_naive())).cost(usage)
+    }
+}
+
+/// Path of the user-editable pricing override file
+pub fn pricing_file_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("claudev")
+        .join("pricing.json")
+}
+
+/// Process-wide pricing table (bundled rates plus user overrides)
+pub fn global() -> &'static PricingTable {
+    &GLOBAL_PRICING
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(input: u64, output: u64, write: u64, read: u64) -> Usage {
+        Usage {
+            input_tokens: input,
+            output_tokens: output,
+            cache_creation_input_tokens: write,
+            cache_read_input_tokens: read,
+        }
+    }
+
+    #[test]
+    fn test_longest_match_wins() {
+        let table = PricingTable::bundled();
+        assert_eq!(table.lookup("claude-opus-4-5-20251101", None).input, 5.0);
+        assert_eq!(table.lookup("claude-opus-4-1-20250805", None).input, 15.0);
+        assert_eq!(table.lookup("claude-3-5-haiku-20241022", None).input, 0.8);
+        assert_eq!(table.lookup("claude-3-haiku-20240307", None).input, 0.25);
+        assert_eq!(table.lookup("anthropic/claude-3.5-sonnet", None).input, 3.0);
+    }
+
+    #[test]
+    fn test_unknown_model_uses_fallback() {
+        let table = PricingTable::bundled();
+        assert_eq!(table.lookup("some-new-model", None).model, "claude-sonnet-4");
+    }
+
+    #[test]
+    fn test_cache_rates_included() {
+        let table = PricingTable::bundled();
+        let price = table.lookup("claude-sonnet-4-20250514", None);
+        // 1M each of input, output, cache write and cache read
+        let cost = price.cost(&usage(1_000_000, 1_000_000, 1_000_000, 1_000_000));
+        assert!((cost - (3.0 + 15.0 + 3.75 + 0.3)).abs() < 1e-9);
+        let batch = price.cost_batch(&usage(1_000_000, 1_000_000, 0, 0));
+        assert!((batch - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_effective_dates_and_overrides() {
+        let mut table = PricingTable::bundled();
+        let override_json = r#"{
+            "version": 2,
+            "fallback": "claude-sonnet-4",
+            "models": [{
+                "provider": "anthropic",
+                "model": "claude-sonnet-4",
+                "match": ["sonnet"],
+                "effective_from": "2030-01-01",
+                "input": 1.0,
+                "output": 5.0
+            }]
+        }"#;
+        table.merge(PricingTable::from_json(override_json).unwrap());
+
+        let before = NaiveDate::from_ymd_opt(2029, 6, 1);
+        let after = NaiveDate::from_ymd_opt(2030, 6, 1);
+        assert_eq!(table.lookup("claude-sonnet-4-5", before).input, 3.0);
+        assert_eq!(table.lookup("claude-sonnet-4-5", after).input, 1.0);
+
+        // Dates before the first known rate use the earliest entry
+        let ancient = NaiveDate::from_ymd_opt(2020, 1, 1);
+        assert_eq!(table.lookup("claude-sonnet-4-5", ancient).input, 3.0);
+    }
+}
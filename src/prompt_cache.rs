@@ -0,0 +1,543 @@
+//! Prompt cache efficiency analysis
+//!
+//! Uses the cache counters in `Usage` together with captured request bodies to
+//! work out which request prefixes (tools, system prompt, early messages) were
+//! served from the prompt cache, how well each session hits the cache, and
+//! which changes between consecutive requests broke it.
+
+use crate::pricing;
+use crate::traffic::{ApiRequest, TrafficEntry, Usage};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Default cache TTL; gaps longer than this explain a miss on their own
+const CACHE_TTL_SECS: i64 = 300;
+/// A drop below this fraction of the previously cached prefix counts as a break
+const BREAK_THRESHOLD: f64 = 0.9;
+
+/// A cacheable part of a request, in the order the API builds the prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PrefixSegment {
+    Tools,
+    System,
+    Message(usize),
+}
+
+impl fmt::Display for PrefixSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefixSegment::Tools => write!(f, "tools"),
+            PrefixSegment::System => write!(f, "system"),
+            PrefixSegment::Message(i) => write!(f, "msg[{}]", i),
+        }
+    }
+}
+
+/// Why the cache stopped hitting between two requests of a session
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum BreakCause {
+    ToolsChanged,
+    SystemChanged,
+    MessageEdited(usize),
+    ModelChanged,
+    Expired,
+    Unknown,
+}
+
+impl fmt::Display for BreakCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakCause::ToolsChanged => write!(f, "tool definitions changed"),
+            BreakCause::SystemChanged => write!(f, "system prompt changed"),
+            BreakCause::MessageEdited(i) => write!(f, "message {} edited", i),
+            BreakCause::ModelChanged => write!(f, "model changed"),
+            BreakCause::Expired => write!(f, "cache TTL expired"),
+            BreakCause::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Cache usage for a single request
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestCacheInfo {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub session: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub hit_ratio: f64,
+    /// Prefix segments estimated to have been read from the cache
+    pub cached_segments: Vec<PrefixSegment>,
+}
+
+/// Cache efficiency for one session
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionCacheStats {
+    pub session: String,
+    pub requests: usize,
+    pub prompt_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub hit_ratio: f64,
+    pub saved_usd: f64,
+    pub write_premium_usd: f64,
+    pub lost_usd: f64,
+    pub breaks: usize,
+}
+
+/// A cache-breaking change between two consecutive requests
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheBreak {
+    pub session: String,
+    pub previous_id: u64,
+    pub request_id: u64,
+    pub cause: BreakCause,
+    pub tokens_lost: u64,
+    pub cost_usd: f64,
+}
+
+/// Full cache analysis over a set of traffic entries
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CacheReport {
+    pub requests: Vec<RequestCacheInfo>,
+    pub sessions: Vec<SessionCacheStats>,
+    pub breaks: Vec<CacheBreak>,
+    pub overall_hit_ratio: f64,
+    /// What cache reads saved compared to sending the same tokens uncached
+    pub saved_usd: f64,
+    /// Extra paid for writing prefixes to the cache
+    pub write_premium_usd: f64,
+    /// Extra paid because a break forced a cached prefix to be resent
+    pub lost_usd: f64,
+}
+
+impl CacheReport {
+    /// Savings after paying for cache writes
+    pub fn net_savings_usd(&self) -> f64 {
+        self.saved_usd - self.write_premium_usd
+    }
+}
+
+/// Fingerprint of one prefix segment
+#[derive(Debug, Clone)]
+struct SegmentFingerprint {
+    segment: PrefixSegment,
+    hash: u64,
+    est_tokens: u64,
+}
+
+fn fingerprint(segment: PrefixSegment, content: &str) -> SegmentFingerprint {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    SegmentFingerprint {
+        segment,
+        hash: hasher.finish(),
+        est_tokens: (content.len() / 4) as u64,
+    }
+}
+
+/// Split a request into its cacheable prefix segments
+fn request_segments(request: &ApiRequest) -> Vec<SegmentFingerprint> {
+    let mut segments = Vec::new();
+
+    if let Some(tools) = request.tools.as_ref().filter(|t| !t.is_empty()) {
+        let json = serde_json::to_string(tools).unwrap_or_default();
+        segments.push(fingerprint(PrefixSegment::Tools, &json));
+    }
+
+    if let Some(system) = request.system.as_ref() {
+        let json = serde_json::to_string(system).unwrap_or_default();
+        segments.push(fingerprint(PrefixSegment::System, &json));
+    }
+
+    for (i, message) in request.messages.iter().enumerate() {
+        let json = serde_json::to_string(message).unwrap_or_default();
+        segments.push(fingerprint(PrefixSegment::Message(i), &json));
+    }
+
+    segments
+}
+
+/// Session key: requests sharing a system prompt and opening message belong together
+pub fn session_key(request: &ApiRequest) -> String {
+    let mut hasher = DefaultHasher::new();
+    request
+        .system
+        .as_ref()
+        .map(|s| s.text())
+        .hash(&mut hasher);
+    if let Some(first) = request.messages.first() {
+        serde_json::to_string(first).unwrap_or_default().hash(&mut hasher);
+    }
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+fn hash_of<T: Hash>(value: T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A session still being followed, with its latest system prompt and
+/// opening message
+struct OpenSession {
+    key: String,
+    client: String,
+    system: u64,
+    opening: u64,
+}
+
+/// Groups requests into sessions by conversation lineage. A turn may change
+/// the system prompt or edit the opening message, but not both, so a request
+/// continues the latest session of the same client that shares either one.
+#[derive(Default)]
+struct SessionTracker {
+    open: Vec<OpenSession>,
+}
+
+impl SessionTracker {
+    fn assign(&mut self, entry: &TrafficEntry) -> String {
+        let client = entry.client.as_ref().map(|c| c.label()).unwrap_or_default();
+        let system = hash_of(entry.request.system.as_ref().map(|s| s.text()));
+        let opening = hash_of(
+            entry
+                .request
+                .messages
+                .first()
+                .map(|m| serde_json::to_string(m).unwrap_or_default()),
+        );
+
+        if let Some(session) = self.open.iter_mut().rev().find(|s| {
+            s.client == client && (s.system == system || s.opening == opening)
+        }) {
+            session.system = system;
+            session.opening = opening;
+            return session.key.clone();
+        }
+
+        let base = format!("{:08x}", hash_of((&client, system, opening)) as u32);
+        let mut key = base.clone();
+        let mut n = 1;
+        while self.open.iter().any(|s| s.key == key) {
+            n += 1;
+            key = format!("{}-{}", base, n);
+        }
+        self.open.push(OpenSession {
+            key: key.clone(),
+            client,
+            system,
+            opening,
+        });
+        key
+    }
+}
+
+/// Estimate which leading segments fit inside the cached token count
+fn cached_segments(segments: &[SegmentFingerprint], cached_tokens: u64) -> Vec<PrefixSegment> {
+    if cached_tokens == 0 {
+        return Vec::new();
+    }
+
+    // Token estimates are rough, so allow the prefix to overshoot a little
+    let budget = cached_tokens as f64 * 1.1;
+    let mut cumulative = 0u64;
+    let mut result = Vec::new();
+    for seg in segments {
+        cumulative += seg.est_tokens;
+        if cumulative as f64 > budget {
+            break;
+        }
+        result.push(seg.segment);
+    }
+    result
+}
+
+/// Find the first segment that differs between two requests
+fn first_changed_segment(
+    previous: &[SegmentFingerprint],
+    current: &[SegmentFingerprint],
+) -> Option<PrefixSegment> {
+    for prev in previous {
+        match current.iter().find(|c| c.segment == prev.segment) {
+            Some(cur) if cur.hash == prev.hash => continue,
+            _ => return Some(prev.segment),
+        }
+    }
+    None
+}
+
+fn hit_ratio(read: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        read as f64 / total as f64
+    }
+}
+
+/// Analyze prompt cache usage across traffic entries (oldest first)
+pub fn analyze_cache(entries: &[TrafficEntry]) -> CacheReport {
+    let mut report = CacheReport::default();
+    let mut sessions: HashMap<String, SessionCacheStats> = HashMap::new();
+    let mut session_order: Vec<String> = Vec::new();
+    let mut tracker = SessionTracker::default();
+    // Last request seen per session: (entry, segments, usage)
+    let mut last_seen: HashMap<String, (&TrafficEntry, Vec<SegmentFingerprint>, Usage)> =
+        HashMap::new();
+
+    let mut total_prompt = 0u64;
+    let mut total_read = 0u64;
+
+    for entry in entries {
+        let response = match &entry.response {
+            Some(r) => r,
+            None => continue,
+        };
+        let usage = match &response.usage {
+            Some(u) => u.clone(),
+            None => continue,
+        };
+
+        let model = response
+            .model
+            .clone()
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| entry.request.model.clone());
+        let price = pricing::global().lookup(&model, Some(entry.timestamp.date_naive()));
+        let input_rate = price.input / 1_000_000.0;
+        let read_rate = price.cache_read_rate() / 1_000_000.0;
+        let write_rate = price.cache_write_rate() / 1_000_000.0;
+        let session = tracker.assign(entry);
+        let segments = request_segments(&entry.request);
+
+        let prompt_tokens =
+            usage.input_tokens + usage.cache_read_input_tokens + usage.cache_creation_input_tokens;
+        total_prompt += prompt_tokens;
+        total_read += usage.cache_read_input_tokens;
+
+        let saved = usage.cache_read_input_tokens as f64 * (input_rate - read_rate);
+        let premium = usage.cache_creation_input_tokens as f64 * (write_rate - input_rate);
+        report.saved_usd += saved;
+        report.write_premium_usd += premium;
+
+        if !sessions.contains_key(&session) {
+            session_order.push(session.clone());
+        }
+        let stats = sessions
+            .entry(session.clone())
+            .or_insert_with(|| SessionCacheStats {
+                session: session.clone(),
+                ..Default::default()
+            });
+        stats.requests += 1;
+        stats.prompt_tokens += prompt_tokens;
+        stats.cache_read_tokens += usage.cache_read_input_tokens;
+        stats.cache_write_tokens += usage.cache_creation_input_tokens;
+        stats.saved_usd += saved;
+        stats.write_premium_usd += premium;
+
+        // Compare with the previous request of this session
+        if let Some((prev_entry, prev_segments, prev_usage)) = last_seen.get(&session) {
+            let prev_cached =
+                prev_usage.cache_read_input_tokens + prev_usage.cache_creation_input_tokens;
+            let threshold = (prev_cached as f64 * BREAK_THRESHOLD) as u64;
+
+            if prev_cached > 0 && usage.cache_read_input_tokens < threshold {
+                let prev_model = prev_entry
+                    .response
+                    .as_ref()
+                    .and_then(|r| r.model.clone())
+                    .filter(|m| !m.is_empty())
+                    .unwrap_or_else(|| prev_entry.request.model.clone());
+
+                let cause = if prev_model != model {
+                    BreakCause::ModelChanged
+                } else {
+                    match first_changed_segment(prev_segments, &segments) {
+                        Some(PrefixSegment::Tools) => BreakCause::ToolsChanged,
+                        Some(PrefixSegment::System) => BreakCause::SystemChanged,
+                        Some(PrefixSegment::Message(i)) => BreakCause::MessageEdited(i),
+                        None => {
+                            let gap = entry.timestamp - prev_entry.timestamp;
+                            if gap.num_seconds() > CACHE_TTL_SECS {
+                                BreakCause::Expired
+                            } else {
+                                BreakCause::Unknown
+                            }
+                        }
+                    }
+                };
+
+                let tokens_lost = prev_cached - usage.cache_read_input_tokens;
+                // Lost tokens were either rewritten to the cache or sent uncached
+                let resend_rate = if usage.cache_creation_input_tokens > 0 {
+                    write_rate
+                } else {
+                    input_rate
+                };
+                let cost_usd = tokens_lost as f64 * (resend_rate - read_rate);
+
+                stats.breaks += 1;
+                stats.lost_usd += cost_usd;
+                report.lost_usd += cost_usd;
+                report.breaks.push(CacheBreak {
+                    session: session.clone(),
+                    previous_id: prev_entry.id,
+                    request_id: entry.id,
+                    cause,
+                    tokens_lost,
+                    cost_usd,
+                });
+            }
+        }
+
+        report.requests.push(RequestCacheInfo {
+            id: entry.id,
+            timestamp: entry.timestamp,
+            session: session.clone(),
+            model,
+            input_tokens: usage.input_tokens,
+            cache_read_tokens: usage.cache_read_input_tokens,
+            cache_write_tokens: usage.cache_creation_input_tokens,
+            hit_ratio: hit_ratio(usage.cache_read_input_tokens, prompt_tokens),
+            cached_segments: cached_segments(&segments, usage.cache_read_input_tokens),
+        });
+
+        last_seen.insert(session, (entry, segments, usage));
+    }
+
+    report.sessions = session_order
+        .into_iter()
+        .filter_map(|key| sessions.remove(&key))
+        .map(|mut s| {
+            s.hit_ratio = hit_ratio(s.cache_read_tokens, s.prompt_tokens);
+            s
+        })
+        .collect();
+    report.overall_hit_ratio = hit_ratio(total_read, total_prompt);
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_id::ClientInfo;
+    use crate::traffic::{ApiResponse, Message, MessageContent, TrafficStatus};
+
+    fn entry(id: u64, system: &str, messages: &[&str], read: u64, write: u64) -> TrafficEntry {
+        TrafficEntry {
+            id,
+            timestamp: Utc::now(),
+            request: ApiRequest {
+                model: "claude-sonnet-4-20250514".to_string(),
+                max_tokens: Some(1000),
+                messages: messages
+                    .iter()
+                    .map(|m| Message {
+                        role: "user".to_string(),
+                        content: MessageContent::Text(m.to_string()),
+                    })
+                    .collect(),
+                system: Some(MessageContent::Text(system.to_string())),
+                stream: false,
+                tools: None,
+                raw_body: None,
+            },
+            response: Some(ApiResponse {
+                id: None,
+                model: Some("claude-sonnet-4-20250514".to_string()),
+                content: vec![],
+                usage: Some(Usage {
+                    input_tokens: 100,
+                    output_tokens: 50,
+                    cache_creation_input_tokens: write,
+                    cache_read_input_tokens: read,
+                }),
+                stop_reason: None,
+                raw_body: None,
+            }),
+            latency_ms: Some(100),
+            status: TrafficStatus::Success,
+            client: None,
+        }
+    }
+
+    #[test]
+    fn test_hit_ratio_and_savings() {
+        let entries = vec![
+            entry(1, "sys", &["hello"], 0, 10_000),
+            entry(2, "sys", &["hello", "more"], 10_000, 0),
+        ];
+        let report = analyze_cache(&entries);
+
+        assert_eq!(report.sessions.len(), 1);
+        assert_eq!(report.sessions[0].requests, 2);
+        assert!(report.breaks.is_empty());
+        // 10k read tokens at $3/MTok * 0.9
+        assert!((report.saved_usd - 0.027).abs() < 1e-9);
+        // 10k written tokens at $3/MTok * 0.25
+        assert!((report.write_premium_usd - 0.0075).abs() < 1e-9);
+        assert!(report.requests[1].hit_ratio > 0.9);
+    }
+
+    #[test]
+    fn test_detects_edited_message() {
+        let entries = vec![
+            entry(1, "sys", &["hello", "first"], 0, 10_000),
+            entry(2, "sys", &["hello", "edited"], 0, 10_000),
+        ];
+        let report = analyze_cache(&entries);
+
+        assert_eq!(report.breaks.len(), 1);
+        assert_eq!(report.breaks[0].cause, BreakCause::MessageEdited(1));
+        assert_eq!(report.breaks[0].tokens_lost, 10_000);
+        assert!(report.lost_usd > 0.0);
+    }
+
+    #[test]
+    fn test_detects_system_change() {
+        let entries = vec![
+            entry(1, "sys v1", &["hello"], 0, 10_000),
+            entry(2, "sys v2", &["hello", "more"], 0, 10_000),
+        ];
+        let report = analyze_cache(&entries);
+
+        assert_eq!(report.sessions.len(), 1);
+        assert_eq!(report.breaks.len(), 1);
+        assert_eq!(report.breaks[0].cause, BreakCause::SystemChanged);
+    }
+
+    #[test]
+    fn test_sessions_follow_client_and_lineage() {
+        let tagged = |id, session: &str| TrafficEntry {
+            client: Some(ClientInfo {
+                session: Some(session.to_string()),
+                ..Default::default()
+            }),
+            ..entry(id, "sys", &["hello"], 0, 5_000)
+        };
+        let entries = vec![tagged(1, "a"), tagged(2, "b"), tagged(3, "a")];
+        let report = analyze_cache(&entries);
+
+        assert_eq!(report.sessions.len(), 2);
+        assert_eq!(report.sessions[0].requests, 2);
+    }
+
+    #[test]
+    fn test_sessions_split_by_system_prompt() {
+        let entries = vec![
+            entry(1, "main agent", &["task"], 0, 5_000),
+            entry(2, "subagent", &["subtask"], 0, 5_000),
+        ];
+        let report = analyze_cache(&entries);
+
+        assert_eq!(report.sessions.len(), 2);
+        assert!(report.breaks.is_empty());
+    }
+}
@@ -2,13 +2,24 @@
 //!
 //! This module provides an HTTP/HTTPS proxy that intercepts traffic to
 //! api.anthropic.com and logs it for monitoring.
-
-use crate::traffic::TrafficLog;
+//!
+//! Plaintext API requests (from a binary-patched client pointing at
+//! `http://127.0.0.1:<port>/api`) are parsed, checked against the budget and
+//! forwarded to the upstream API over TLS.
+
+use crate::budget::BudgetGuard;
+use crate::client_id;
+use crate::prompt_cache::session_key;
+use crate::secret_scan::SecretScanner;
+use crate::traffic::{self, TrafficLog};
 use anyhow::Result;
+use chrono::Utc;
 use rcgen::{Certificate, CertificateParams, DistinguishedName, DnType, KeyPair, PKCS_ECDSA_P256_SHA256};
 use std::fs;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc;
@@ -20,13 +31,33 @@ pub enum ProxyEvent {
     RequestCompleted { id: u64, tokens_in: u64, tokens_out: u64, latency_ms: u64 },
     RequestFailed { id: u64, error: String },
     StreamChunk { id: u64, text: String },
+    BudgetAlert { message: String, blocked: bool },
+    SecretDetected { data_type: String, location: String, redacted: bool },
 }
 
+/// Upstream API that plaintext requests are forwarded to
+const DEFAULT_UPSTREAM: &str = "https://api.anthropic.com";
+/// Largest request head accepted before giving up
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+/// Headers that describe the client connection and are not forwarded
+const HOP_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "proxy-connection",
+    "keep-alive",
+    "content-length",
+    "transfer-encoding",
+    "accept-encoding",
+    client_id::SESSION_HEADER,
+];
+
 /// MITM Proxy configuration
 pub struct ProxyConfig {
     pub listen_addr: SocketAddr,
     pub ca_cert_path: PathBuf,
     pub ca_key_path: PathBuf,
+    /// Base URL plaintext API requests are forwarded to
+    pub upstream: String,
 }
 
 impl Default for ProxyConfig {
@@ -39,6 +70,7 @@ impl Default for ProxyConfig {
             listen_addr: "127.0.0.1:8080".parse().unwrap(),
             ca_cert_path: config_dir.join("ca.crt"),
             ca_key_path: config_dir.join("ca.key"),
+            upstream: DEFAULT_UPSTREAM.to_string(),
         }
     }
 }
@@ -54,6 +86,19 @@ pub struct MitmProxy {
     config: ProxyConfig,
     traffic_log: TrafficLog,
     event_tx: mpsc::UnboundedSender<ProxyEvent>,
+    budget: Option<Arc<BudgetGuard>>,
+    scanner: Option<Arc<SecretScanner>>,
+}
+
+/// State shared by all proxy connections
+#[derive(Clone)]
+struct ConnectionContext {
+    traffic_log: TrafficLog,
+    event_tx: mpsc::UnboundedSender<ProxyEvent>,
+    upstream: String,
+    budget: Option<Arc<BudgetGuard>>,
+    scanner: Option<Arc<SecretScanner>>,
+    client: reqwest::Client,
 }
 
 impl MitmProxy {
@@ -66,9 +111,23 @@ impl MitmProxy {
             config,
             traffic_log,
             event_tx,
+            budget: None,
+            scanner: None,
         }
     }
 
+    /// Enforce budgets and model allow-lists on forwarded API requests
+    pub fn with_budget(mut self, budget: BudgetGuard) -> Self {
+        self.budget = Some(Arc::new(budget));
+        self
+    }
+
+    /// Scan outbound API requests for secrets
+    pub fn with_secret_scanner(mut self, scanner: SecretScanner) -> Self {
+        self.scanner = Some(Arc::new(scanner));
+        self
+    }
+
     /// Initialize or load CA certificate
     pub fn init_ca(&mut self) -> Result<()> {
         // Create config directory if needed
@@ -102,14 +161,26 @@ impl MitmProxy {
     pub async fn run(&self) -> Result<()> {
         let listener = TcpListener::bind(&self.config.listen_addr).await?;
         tracing::info!("MITM Proxy listening on {}", self.config.listen_addr);
+        self.serve(listener).await
+    }
+
+    /// Accept connections on an already bound listener
+    pub async fn serve(&self, listener: TcpListener) -> Result<()> {
+        let ctx = ConnectionContext {
+            traffic_log: self.traffic_log.clone(),
+            event_tx: self.event_tx.clone(),
+            upstream: self.config.upstream.clone(),
+            budget: self.budget.clone(),
+            scanner: self.scanner.clone(),
+            client: reqwest::Client::new(),
+        };
 
         loop {
             let (stream, addr) = listener.accept().await?;
-            let traffic_log = self.traffic_log.clone();
-            let event_tx = self.event_tx.clone();
+            let ctx = ctx.clone();
 
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(stream, addr, traffic_log, event_tx).await {
+                if let Err(e) = handle_connection(stream, addr, ctx).await {
                     tracing::debug!("Connection error from {}: {}", addr, e);
                 }
             });
@@ -149,9 +220,8 @@ fn generate_ca() -> Result<CaCert> {
 /// Handle a single client connection
 async fn handle_connection(
     mut stream: TcpStream,
-    _addr: SocketAddr,
-    _traffic_log: TrafficLog,
-    _event_tx: mpsc::UnboundedSender<ProxyEvent>,
+    addr: SocketAddr,
+    ctx: ConnectionContext,
 ) -> Result<()> {
     let mut buf = vec![0u8; 8192];
     let n = stream.read(&mut buf).await?;
@@ -164,6 +234,13 @@ async fn handle_connection(
     // Check if this is a CONNECT request (HTTPS proxy)
     if request_line.starts_with("CONNECT ") {
         handle_connect(stream, &request_line).await
+    } else if request_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(api_path)
+        .is_some()
+    {
+        handle_api(stream, addr, &buf[..n], ctx).await
     } else {
         // Regular HTTP proxy - just forward
         handle_http(stream, &buf[..n]).await
@@ -277,6 +354,308 @@ async fn handle_http(
     Ok(())
 }
 
+/// Upstream path for a plaintext API request target, if it is one.
+/// Patched clients prefix API paths with `/api`.
+pub(crate) fn api_path(target: &str) -> Option<&str> {
+    let path = target.strip_prefix("/api").unwrap_or(target);
+    path.starts_with("/v1/").then_some(path)
+}
+
+/// A fully read HTTP/1.1 request
+pub(crate) struct HttpRequest {
+    pub method: String,
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Read the rest of a request whose first bytes have already been received
+pub(crate) async fn read_request(stream: &mut TcpStream, initial: &[u8]) -> Result<HttpRequest> {
+    let mut buf = initial.to_vec();
+    let mut chunk = vec![0u8; 8192];
+
+    let header_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() > MAX_HEADER_BYTES {
+            return Err(anyhow::anyhow!("Request headers too large"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow::anyhow!("Connection closed before headers were complete"));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default().to_string();
+    let headers = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .collect();
+
+    let mut request = HttpRequest {
+        method,
+        target,
+        headers,
+        body: buf[header_end..].to_vec(),
+    };
+
+    if request
+        .header("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        return Err(anyhow::anyhow!("Chunked request bodies are not supported"));
+    }
+
+    let content_length: usize = request
+        .header("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    while request.body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow::anyhow!("Connection closed before body was complete"));
+        }
+        request.body.extend_from_slice(&chunk[..n]);
+    }
+    request.body.truncate(content_length);
+
+    Ok(request)
+}
+
+/// Write an Anthropic-style JSON error response and close the connection
+pub(crate) async fn write_error_response(
+    stream: &mut TcpStream,
+    status: u16,
+    error_type: &str,
+    message: &str,
+) -> Result<()> {
+    let body = serde_json::json!({
+        "type": "error",
+        "error": { "type": error_type, "message": message }
+    })
+    .to_string();
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|s| s.canonical_reason())
+        .unwrap_or("Error");
+    let response = format!(
+        "HTTP/1.1 {} {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Forward a plaintext API request upstream, logging and budgeting Messages API calls
+async fn handle_api(
+    mut client_stream: TcpStream,
+    peer: SocketAddr,
+    initial_data: &[u8],
+    ctx: ConnectionContext,
+) -> Result<()> {
+    let mut request = read_request(&mut client_stream, initial_data).await?;
+    let path = api_path(&request.target).unwrap_or(&request.target).to_string();
+
+    let is_messages = request.method == "POST"
+        && path.starts_with("/v1/messages")
+        && !path.contains("count_tokens");
+
+    if let (true, Some(scanner)) = (is_messages, &ctx.scanner) {
+        match scanner.scan(&String::from_utf8_lossy(&request.body)) {
+            Ok(result) => {
+                for finding in &result.findings {
+                    let _ = ctx.event_tx.send(ProxyEvent::SecretDetected {
+                        data_type: finding.data_type.to_string(),
+                        location: finding.location.clone(),
+                        redacted: scanner.redacts(),
+                    });
+                }
+                if let Some(redacted) = result.redacted_body {
+                    request.body = redacted.into_bytes();
+                }
+            }
+            Err(e) => tracing::debug!("Secret scan skipped: {}", e),
+        }
+    }
+
+    let api_request = if is_messages {
+        traffic::parse_request(&String::from_utf8_lossy(&request.body)).ok()
+    } else {
+        None
+    };
+
+    // (entry id, session, model, streaming) for logged requests
+    let mut tracked = None;
+    let mut budget_header = None;
+
+    if let Some(api_request) = api_request {
+        let session = session_key(&api_request);
+
+        // Scanning /proc for the socket owner is blocking file I/O
+        let local = client_stream.local_addr()?;
+        let headers = request.headers.clone();
+        let client = tokio::task::spawn_blocking(move || client_id::identify(peer, local, &headers))
+            .await
+            .ok();
+
+        if let Some(budget) = &ctx.budget {
+            let check = budget.check(&api_request, &session, Utc::now());
+            for violation in &check.violations {
+                let _ = ctx.event_tx.send(ProxyEvent::BudgetAlert {
+                    message: violation.message.clone(),
+                    blocked: violation.action == crate::budget::BudgetAction::Block,
+                });
+            }
+
+            if let Some(blocking) = check.blocking() {
+                let message = format!("Blocked by claudev budget: {}", blocking.message);
+                let id = ctx.traffic_log.start_request(api_request, client);
+                ctx.traffic_log.fail_request(id, message.clone());
+                let _ = ctx.event_tx.send(ProxyEvent::RequestFailed {
+                    id,
+                    error: message.clone(),
+                });
+                return write_error_response(&mut client_stream, 429, "budget_exceeded_error", &message)
+                    .await;
+            }
+            budget_header = check.header_value();
+        }
+
+        let model = api_request.model.clone();
+        let stream = api_request.stream;
+        let id = ctx.traffic_log.start_request(api_request, client);
+        if stream {
+            ctx.traffic_log.mark_streaming(id);
+        }
+        let _ = ctx.event_tx.send(ProxyEvent::RequestStarted {
+            id,
+            model: model.clone(),
+            stream,
+        });
+        tracked = Some((id, session, model, stream));
+    }
+
+    let started = Instant::now();
+    let url = format!("{}{}", ctx.upstream.trim_end_matches('/'), path);
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())?;
+    let mut upstream = ctx.client.request(method, &url).body(request.body.clone());
+    for (name, value) in &request.headers {
+        if !HOP_HEADERS.contains(&name.to_lowercase().as_str()) {
+            upstream = upstream.header(name, value);
+        }
+    }
+
+    let mut response = match upstream.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            let message = format!("Upstream request failed: {}", e);
+            if let Some((id, ..)) = tracked {
+                ctx.traffic_log.fail_request(id, message.clone());
+                let _ = ctx.event_tx.send(ProxyEvent::RequestFailed {
+                    id,
+                    error: message.clone(),
+                });
+            }
+            return write_error_response(&mut client_stream, 502, "api_error", &message).await;
+        }
+    };
+
+    // Relay the response head; the body is delimited by closing the connection
+    let status = response.status();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    );
+    for (name, value) in response.headers() {
+        if !HOP_HEADERS.contains(&name.as_str()) {
+            head.push_str(&format!(
+                "{}: {}\r\n",
+                name,
+                String::from_utf8_lossy(value.as_bytes())
+            ));
+        }
+    }
+    if let Some(warning) = budget_header {
+        head.push_str(&format!("x-claudev-budget: {}\r\n", warning));
+    }
+    head.push_str("connection: close\r\n\r\n");
+    client_stream.write_all(head.as_bytes()).await?;
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        client_stream.write_all(&chunk).await?;
+        body.extend_from_slice(&chunk);
+    }
+    let _ = client_stream.shutdown().await;
+
+    let Some((id, session, model, stream)) = tracked else {
+        return Ok(());
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let text = String::from_utf8_lossy(&body);
+
+    if !status.is_success() {
+        let error = format!("HTTP {}: {}", status.as_u16(), text.chars().take(200).collect::<String>());
+        ctx.traffic_log.fail_request(id, error.clone());
+        let _ = ctx.event_tx.send(ProxyEvent::RequestFailed { id, error });
+        return Ok(());
+    }
+
+    let parsed = if stream {
+        Some(traffic::response_from_stream(&text))
+    } else {
+        traffic::parse_response(&text).ok()
+    };
+
+    match parsed {
+        Some(api_response) => {
+            let usage = api_response.usage.clone().unwrap_or_default();
+            let model = api_response
+                .model
+                .clone()
+                .filter(|m| !m.is_empty())
+                .unwrap_or(model);
+            ctx.traffic_log.complete_request(id, api_response, latency_ms);
+            if let Some(budget) = &ctx.budget {
+                budget.record(&session, &model, &usage, Utc::now());
+            }
+            let _ = ctx.event_tx.send(ProxyEvent::RequestCompleted {
+                id,
+                tokens_in: usage.input_tokens,
+                tokens_out: usage.output_tokens,
+                latency_ms,
+            });
+        }
+        None => {
+            let error = "Could not parse API response".to_string();
+            ctx.traffic_log.fail_request(id, error.clone());
+            let _ = ctx.event_tx.send(ProxyEvent::RequestFailed { id, error });
+        }
+    }
+
+    Ok(())
+}
+
 /// Get the CA certificate path
 pub fn get_ca_cert_path() -> PathBuf {
     ProxyConfig::default().ca_cert_path
@@ -312,5 +691,63 @@ mod tests {
     fn test_default_config() {
         let config = ProxyConfig::default();
         assert_eq!(config.listen_addr.port(), 8080);
+        assert_eq!(config.upstream, "https://api.anthropic.com");
+    }
+
+    #[test]
+    fn test_api_path() {
+        assert_eq!(api_path("/api/v1/messages"), Some("/v1/messages"));
+        assert_eq!(api_path("/v1/messages?beta=true"), Some("/v1/messages?beta=true"));
+        assert_eq!(api_path("http://example.com/"), None);
+        assert_eq!(api_path("/index.html"), None);
+    }
+
+    #[tokio::test]
+    async fn test_budget_blocks_request() {
+        use crate::budget::{BudgetConfig, BudgetGuard};
+
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+        let traffic_log = TrafficLog::new();
+        let ctx = ConnectionContext {
+            traffic_log: traffic_log.clone(),
+            event_tx,
+            // Never contacted: the request is blocked before forwarding
+            upstream: "http://127.0.0.1:9".to_string(),
+            budget: Some(Arc::new(BudgetGuard::in_memory(BudgetConfig {
+                limits: vec![],
+                allowed_models: vec!["haiku".to_string()],
+            }))),
+            scanner: None,
+            client: reqwest::Client::new(),
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, peer) = listener.accept().await.unwrap();
+            handle_connection(stream, peer, ctx).await.unwrap();
+        });
+
+        let body = r#"{"model":"claude-opus-4","max_tokens":10,"messages":[{"role":"user","content":"hi"}],"stream":false}"#;
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "POST /api/v1/messages HTTP/1.1\r\nhost: 127.0.0.1\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        client.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 429"));
+        assert!(response.contains("budget_exceeded_error"));
+
+        assert!(matches!(
+            event_rx.recv().await,
+            Some(ProxyEvent::BudgetAlert { blocked: true, .. })
+        ));
+        let entries = traffic_log.get_all();
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0].status, traffic::TrafficStatus::Error(_)));
     }
 }
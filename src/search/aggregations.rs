@@ -0,0 +1,515 @@
+// Grouped counts and token statistics over search matches
+
+use anyhow::Result;
+use chrono::DateTime;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tantivy::aggregation::agg_req::Aggregations;
+use tantivy::aggregation::agg_result::{
+    AggregationResult, AggregationResults, BucketEntries, BucketEntry, BucketResult, MetricResult,
+};
+use tantivy::aggregation::{AggregationCollector, AggregationLimits, Key};
+use tantivy::collector::Count;
+
+use super::query_executor::{OutputFormat, QueryExecutor, SearchQuery};
+use super::schema::*;
+
+/// Most periods a monthly histogram returns (a century)
+const MAX_MONTHS: usize = 1200;
+
+/// Width of the bars drawn in the table
+const BAR_WIDTH: usize = 24;
+
+/// Field matches are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Tool,
+    Project,
+    Category,
+    Level,
+}
+
+impl GroupBy {
+    fn field(&self) -> &'static str {
+        match self {
+            GroupBy::Tool => FIELD_TOOL,
+            GroupBy::Project => FIELD_PROJECT,
+            GroupBy::Category => FIELD_CATEGORY,
+            GroupBy::Level => FIELD_LEVEL,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            GroupBy::Tool => "Tool",
+            GroupBy::Project => "Project",
+            GroupBy::Category => "Category",
+            GroupBy::Level => "Level",
+        }
+    }
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "tool" => Ok(GroupBy::Tool),
+            "project" => Ok(GroupBy::Project),
+            "category" => Ok(GroupBy::Category),
+            "level" => Ok(GroupBy::Level),
+            other => anyhow::bail!(
+                "Unknown facet: {} (expected tool, project, category or level)",
+                other
+            ),
+        }
+    }
+}
+
+/// Length of the periods of a date histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Hour,
+    Day,
+    /// ISO weeks, starting on Monday
+    Week,
+    Month,
+}
+
+impl Interval {
+    fn label(&self) -> &'static str {
+        match self {
+            Interval::Hour => "Hour",
+            Interval::Day => "Day",
+            Interval::Week => "Week",
+            Interval::Month => "Month",
+        }
+    }
+
+    /// Sub-aggregation splitting matches into periods; `dense` includes
+    /// empty periods between the first and last match
+    fn request(&self, dense: bool) -> Value {
+        let fixed = |interval: &str, offset: &str| {
+            json!({
+                "date_histogram": {
+                    "field": FIELD_TIMESTAMP,
+                    "fixed_interval": interval,
+                    "offset": offset,
+                    "min_doc_count": if dense { 0 } else { 1 },
+                },
+                "aggs": token_stats_request(),
+            })
+        };
+        match self {
+            Interval::Hour => fixed("1h", "0d"),
+            Interval::Day => fixed("1d", "0d"),
+            // The epoch was a Thursday; shift the weeks to start on Monday
+            Interval::Week => fixed("7d", "-3d"),
+            Interval::Month => json!({
+                "terms": {
+                    "field": FIELD_MONTH,
+                    "size": MAX_MONTHS,
+                    "order": { "_key": "asc" },
+                },
+                "aggs": token_stats_request(),
+            }),
+        }
+    }
+
+    /// Name of the period starting at `millis` since the epoch
+    fn period_name(&self, millis: f64) -> String {
+        let Some(start) = DateTime::from_timestamp_millis(millis as i64) else {
+            return millis.to_string();
+        };
+        match self {
+            Interval::Hour => start.format("%Y-%m-%d %H:00").to_string(),
+            Interval::Day => start.format("%Y-%m-%d").to_string(),
+            Interval::Week => start.format("%G-W%V").to_string(),
+            Interval::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hour" | "hourly" => Ok(Interval::Hour),
+            "day" | "daily" => Ok(Interval::Day),
+            "week" | "weekly" => Ok(Interval::Week),
+            "month" | "monthly" => Ok(Interval::Month),
+            other => anyhow::bail!(
+                "Unknown histogram interval: {} (expected hour, day, week or month)",
+                other
+            ),
+        }
+    }
+}
+
+/// Estimated tokens of the matched messages; code blocks and tool calls
+/// are part of a message and not counted separately
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenStats {
+    pub messages: u64,
+    pub total: u64,
+    pub average: f64,
+    pub max: u64,
+}
+
+/// Matches sharing a group value or period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bucket {
+    pub key: String,
+    pub count: u64,
+    pub tokens: TokenStats,
+    /// The bucket's matches per period, when grouping and a histogram are combined
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub periods: Vec<Bucket>,
+}
+
+/// Matches of a query grouped by a field, by period, or by both
+#[derive(Debug, Clone, Serialize)]
+pub struct Aggregation {
+    pub query: String,
+    pub group_by: Option<String>,
+    pub interval: Option<String>,
+    pub total_found: usize,
+    pub tokens: TokenStats,
+    pub buckets: Vec<Bucket>,
+    pub search_time_ms: u64,
+}
+
+impl QueryExecutor {
+    /// Groups the matches of `query` by `group_by` (the `query.limit` largest
+    /// groups) and/or into `interval` periods, with token statistics for
+    /// every bucket. Both together give each group its own histogram.
+    pub fn aggregate(
+        &self,
+        query: &SearchQuery,
+        group_by: Option<GroupBy>,
+        interval: Option<Interval>,
+    ) -> Result<Aggregation> {
+        let start = std::time::Instant::now();
+        let searcher = self.index().reader()?.searcher();
+        let tantivy_query = self.build_query(query, true)?;
+
+        let mut request = token_stats_request();
+        match (group_by, interval) {
+            (Some(group_by), _) => {
+                let mut aggs = token_stats_request();
+                if let Some(interval) = interval {
+                    aggs["periods"] = interval.request(false);
+                }
+                request["groups"] = json!({
+                    "terms": { "field": group_by.field(), "size": query.limit.max(1) },
+                    "aggs": aggs,
+                });
+            }
+            (None, Some(interval)) => request["periods"] = interval.request(true),
+            (None, None) => {
+                anyhow::bail!("Aggregating needs a field to group by or a histogram interval")
+            }
+        }
+        let request: Aggregations = serde_json::from_value(request)?;
+        let collector = AggregationCollector::from_aggs(request, AggregationLimits::default());
+        let (total_found, results) = searcher.search(&*tantivy_query, &(Count, collector))?;
+
+        let buckets = if group_by.is_some() {
+            buckets_of(&results, "groups", interval)
+        } else {
+            buckets_of(&results, "periods", interval)
+        };
+
+        Ok(Aggregation {
+            query: query.text.clone(),
+            group_by: group_by.map(|g| g.label().to_lowercase()),
+            interval: interval.map(|i| i.label().to_lowercase()),
+            total_found,
+            tokens: token_stats_of(&results),
+            buckets,
+            search_time_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+fn token_stats_request() -> Value {
+    json!({ "tokens": { "stats": { "field": FIELD_TOKENS } } })
+}
+
+fn token_stats_of(results: &AggregationResults) -> TokenStats {
+    match results.0.get("tokens") {
+        Some(AggregationResult::MetricResult(MetricResult::Stats(stats))) => TokenStats {
+            messages: stats.count,
+            total: stats.sum as u64,
+            average: stats.avg.unwrap_or(0.0),
+            max: stats.max.unwrap_or(0.0) as u64,
+        },
+        _ => TokenStats::default(),
+    }
+}
+
+/// Buckets of the bucket aggregation `name`, with their periods
+fn buckets_of(results: &AggregationResults, name: &str, interval: Option<Interval>) -> Vec<Bucket> {
+    let entries: Vec<&BucketEntry> = match results.0.get(name) {
+        Some(AggregationResult::BucketResult(BucketResult::Terms { buckets, .. })) => buckets.iter().collect(),
+        Some(AggregationResult::BucketResult(BucketResult::Histogram { buckets })) => match buckets {
+            BucketEntries::Vec(buckets) => buckets.iter().collect(),
+            BucketEntries::HashMap(buckets) => buckets.values().collect(),
+        },
+        _ => Vec::new(),
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| Bucket {
+            key: match (&entry.key, interval) {
+                (Key::F64(millis), Some(interval)) => interval.period_name(*millis),
+                (Key::F64(value), None) => value.to_string(),
+                (Key::Str(value), _) => value.clone(),
+            },
+            count: entry.doc_count,
+            tokens: token_stats_of(&entry.sub_aggregation),
+            periods: buckets_of(&entry.sub_aggregation, "periods", interval),
+        })
+        .collect()
+}
+
+/// Renders aggregation results in the given format
+pub fn format_aggregation(results: &Aggregation, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(results).unwrap_or_default(),
+        OutputFormat::Markdown => format_aggregation_markdown(results),
+        OutputFormat::Table => format_aggregation_table(results),
+    }
+}
+
+/// Rows as (group, period, bucket, whether it is a group total)
+fn rows(results: &Aggregation) -> Vec<(String, String, &Bucket, bool)> {
+    let mut rows = Vec::new();
+    for bucket in &results.buckets {
+        match (&results.group_by, &results.interval) {
+            (Some(_), Some(_)) => {
+                rows.push((display_key(&bucket.key), "all".to_string(), bucket, true));
+                for period in &bucket.periods {
+                    rows.push((String::new(), period.key.clone(), period, false));
+                }
+            }
+            (Some(_), None) => rows.push((display_key(&bucket.key), String::new(), bucket, false)),
+            _ => rows.push((String::new(), bucket.key.clone(), bucket, false)),
+        }
+    }
+    rows
+}
+
+fn display_key(key: &str) -> String {
+    if key.is_empty() {
+        "(none)".to_string()
+    } else {
+        key.to_string()
+    }
+}
+
+fn headers(results: &Aggregation) -> Vec<String> {
+    let mut headers = Vec::new();
+    if let Some(group_by) = &results.group_by {
+        headers.push(capitalize(group_by));
+    }
+    if let Some(interval) = &results.interval {
+        headers.push(capitalize(interval));
+    }
+    headers.extend(["Matches", "Tokens", "Avg tokens"].map(String::from));
+    headers
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_aggregation_table(results: &Aggregation) -> String {
+    let rows = rows(results);
+    let max_count = rows
+        .iter()
+        .filter(|(_, _, _, total)| !total)
+        .map(|(_, _, bucket, _)| bucket.count)
+        .max()
+        .unwrap_or(0);
+
+    let mut table = Table::new();
+    let mut header: Vec<Cell> = headers(results).into_iter().map(|h| Cell::new(h).fg(Color::Cyan)).collect();
+    header.push(Cell::new(""));
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(header);
+
+    for (group, period, bucket, is_total) in rows {
+        let mut cells = Vec::new();
+        if results.group_by.is_some() {
+            cells.push(Cell::new(group).fg(Color::Yellow));
+        }
+        if results.interval.is_some() {
+            cells.push(Cell::new(period));
+        }
+        cells.push(Cell::new(bucket.count));
+        cells.push(Cell::new(bucket.tokens.total));
+        cells.push(Cell::new(format!("{:.0}", bucket.tokens.average)));
+        // Group totals would dwarf their periods' bars
+        let bar = if is_total || max_count == 0 {
+            String::new()
+        } else {
+            let width = (bucket.count as f64 / max_count as f64 * BAR_WIDTH as f64).ceil() as usize;
+            "█".repeat(width)
+        };
+        cells.push(Cell::new(bar).fg(Color::Green));
+        table.add_row(cells);
+    }
+
+    table.to_string()
+}
+
+fn format_aggregation_markdown(results: &Aggregation) -> String {
+    let headers = headers(results);
+    let mut output = String::from("# Search Aggregation\n\n");
+    output.push_str(&format!("Query: `{}`\n", results.query));
+    output.push_str(&format!(
+        "Found {} matches ({} estimated tokens) in {}ms\n\n",
+        results.total_found, results.tokens.total, results.search_time_ms
+    ));
+    output.push_str(&format!("| {} |\n", headers.join(" | ")));
+    output.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+
+    for (group, period, bucket, _) in rows(results) {
+        let mut cells = Vec::new();
+        if results.group_by.is_some() {
+            cells.push(group);
+        }
+        if results.interval.is_some() {
+            cells.push(period);
+        }
+        cells.push(bucket.count.to_string());
+        cells.push(bucket.tokens.total.to_string());
+        cells.push(format!("{:.0}", bucket.tokens.average));
+        output.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AiTool;
+    use crate::parsers::{EntryCategory, LogEntry, LogLevel};
+    use crate::search::code::CodeBlock;
+    use crate::search::LogEntryDocument;
+    use std::path::Path;
+    use tantivy::Index;
+
+    fn executor_with(entries: &[(&str, &str, &str)]) -> (tempfile::TempDir, QueryExecutor) {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = build_schema();
+        let index = Index::create_in_dir(dir.path(), schema.clone()).unwrap();
+        register_tokenizers(&index);
+        let mut writer = index.writer(15_000_000).unwrap();
+
+        for (i, (project, timestamp, message)) in entries.iter().enumerate() {
+            let entry = LogEntry {
+                timestamp: Some(timestamp.parse().unwrap()),
+                level: if message.contains("timeout") { LogLevel::Error } else { LogLevel::Info },
+                message: message.to_string(),
+                category: EntryCategory::AssistantResponse,
+            };
+            let path = format!("/home/u/.claude/projects/{}/s.jsonl", project);
+            let doc = LogEntryDocument::from_log_entry(
+                &entry,
+                i as u64 * 2 + 1,
+                &AiTool::ClaudeCode,
+                "Session",
+                Path::new(&path),
+            );
+            // Derived documents match too but add no tokens
+            let block = CodeBlock {
+                language: "text".to_string(),
+                code: message.to_string(),
+                file_path: None,
+            };
+            writer.add_document(doc.code_block(&block, i as u64 * 2 + 2).to_tantivy_document(&schema)).unwrap();
+            writer.add_document(doc.to_tantivy_document(&schema)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let executor = QueryExecutor::new(dir.path()).unwrap();
+        (dir, executor)
+    }
+
+    #[test]
+    fn test_group_by_project_per_week() {
+        let (_dir, executor) = executor_with(&[
+            // Monday and Sunday of ISO week 10, then week 11
+            ("api", "2026-03-02T10:00:00Z", "request timeout calling billing"),
+            ("api", "2026-03-08T23:00:00Z", "timeout again"),
+            ("api", "2026-03-09T08:00:00Z", "timeout after retry"),
+            ("web", "2026-03-03T12:00:00Z", "asset timeout"),
+            ("web", "2026-03-03T13:00:00Z", "build passed"),
+        ]);
+        let query = SearchQuery {
+            text: "timeout".into(),
+            facets: vec![("category".into(), "AssistantResponse".into())],
+            ..Default::default()
+        };
+
+        let results = executor
+            .aggregate(&query, Some(GroupBy::Project), Some(Interval::Week))
+            .unwrap();
+        assert_eq!(results.total_found, 4);
+        assert_eq!(results.tokens.messages, 4);
+
+        let api = &results.buckets[0];
+        assert_eq!((api.key.as_str(), api.count), ("api", 3));
+        let weeks: Vec<_> = api.periods.iter().map(|p| (p.key.as_str(), p.count)).collect();
+        assert_eq!(weeks, [("2026-W10", 2), ("2026-W11", 1)]);
+        let message = "request timeout calling billing";
+        assert_eq!(api.periods[0].tokens.max, (message.len() / 4) as u64);
+
+        let web = &results.buckets[1];
+        assert_eq!((web.key.as_str(), web.count), ("web", 1));
+
+        let table = format_aggregation(&results, OutputFormat::Table);
+        assert!(table.contains("2026-W11"));
+        let json: Value = serde_json::from_str(&format_aggregation(&results, OutputFormat::Json)).unwrap();
+        assert_eq!(json["buckets"][0]["periods"][1]["count"], 1);
+    }
+
+    #[test]
+    fn test_histograms_and_groups() {
+        let (_dir, executor) = executor_with(&[
+            ("api", "2026-01-15T10:00:00Z", "timeout"),
+            ("api", "2026-03-01T10:00:00Z", "timeout"),
+            ("web", "2026-03-20T10:00:00Z", "fine"),
+        ]);
+        let all = SearchQuery::default();
+
+        // Months without matches show up in a plain histogram
+        let days = executor.aggregate(&all, None, Some(Interval::Day)).unwrap();
+        assert_eq!(days.buckets.first().unwrap().key, "2026-01-15");
+        assert_eq!(days.buckets.last().unwrap().key, "2026-03-20");
+        assert_eq!(days.buckets.iter().map(|b| b.count).sum::<u64>(), 6);
+
+        let months = executor.aggregate(&all, None, Some(Interval::Month)).unwrap();
+        let months: Vec<_> = months.buckets.iter().map(|b| (b.key.as_str(), b.count)).collect();
+        assert_eq!(months, [("2026-01", 2), ("2026-03", 4)]);
+
+        let levels = executor.aggregate(&all, Some(GroupBy::Level), None).unwrap();
+        let levels: Vec<_> = levels.buckets.iter().map(|b| (b.key.as_str(), b.count)).collect();
+        assert_eq!(levels, [("Error", 4), ("Info", 2)]);
+
+        assert!(executor.aggregate(&all, None, None).is_err());
+        assert!("weekly".parse::<Interval>().is_ok());
+        assert!("session".parse::<GroupBy>().is_err());
+    }
+}
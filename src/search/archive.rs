@@ -0,0 +1,393 @@
+// Search indexes built straight from backup archives, one per archive
+
+use anyhow::{Context, Result};
+use colored::*;
+use md5::{Digest, Md5};
+use std::path::{Component, Path, PathBuf};
+
+use crate::models::{AiTool, LogType};
+
+use super::index_builder::IndexBuilder;
+use super::metadata::{ArchiveSource, IndexMetadata};
+use super::query_executor::{
+    Conversation, QueryExecutor, SearchQuery, SearchResult, SearchResults, SortOrder,
+};
+use super::schema::SCHEMA_VERSION;
+
+/// A log file inside a backup archive. `BackupManager::create_backup` stores
+/// the files of a log directory as `<tool>/<log type>/<path under home>` and
+/// single-file locations as `<tool>/<file name>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveMember {
+    pub tool: AiTool,
+    /// Log type as the live index records it (e.g. "Session")
+    pub log_type: String,
+    /// Path the file had under the home directory, or just its name for a
+    /// single-file location
+    pub source: PathBuf,
+    /// Whether the entry is a single-file location, archived without the
+    /// path its parser is usually chosen by
+    pub single_file: bool,
+}
+
+impl ArchiveMember {
+    /// Reads the layout of an archive entry path; `None` for paths the
+    /// backup does not write
+    pub fn from_entry_path(path: &Path) -> Option<Self> {
+        let parts: Vec<String> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect();
+
+        let tool = AiTool::from_name(&parts.first()?.replace('_', " "));
+        match parts.as_slice() {
+            [_, file] => Some(Self {
+                tool,
+                log_type: format!("{:?}", LogType::Unknown),
+                source: PathBuf::from(file),
+                single_file: true,
+            }),
+            [_, log_type, rest @ ..] if !rest.is_empty() => Some(Self {
+                tool,
+                log_type: format!("{:?}", log_type_from_dir(log_type)?),
+                source: rest.iter().collect(),
+                single_file: false,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The log type a backup names a directory after
+fn log_type_from_dir(name: &str) -> Option<LogType> {
+    [
+        LogType::Debug,
+        LogType::History,
+        LogType::FileHistory,
+        LogType::Session,
+        LogType::Telemetry,
+        LogType::ShellSnapshot,
+        LogType::Todo,
+        LogType::Cache,
+        LogType::Plugin,
+        LogType::Unknown,
+    ]
+    .into_iter()
+    .find(|log_type| format!("{:?}", log_type).to_lowercase() == name)
+}
+
+/// Directory of the search index of a backup archive, named after the
+/// archive and a hash of its absolute path
+pub fn archive_index_dir(archive: &Path) -> Result<PathBuf> {
+    let absolute = std::path::absolute(archive)?;
+    let hash = format!("{:x}", Md5::digest(absolute.to_string_lossy().as_bytes()));
+    let name = archive
+        .file_name()
+        .map(|name| {
+            name.to_string_lossy()
+                .trim_end_matches(".tar.gz")
+                .to_string()
+        })
+        .unwrap_or_default();
+
+    Ok(dirs::cache_dir()
+        .context("Could not determine cache directory")?
+        .join("vibedev")
+        .join("archives")
+        .join(format!("{}-{}", name, &hash[..12])))
+}
+
+/// Returns the index of a backup archive, building it first when there is
+/// none yet, it is outdated or `rebuild` is set
+pub fn open_archive_index(archive: &Path, rebuild: bool) -> Result<PathBuf> {
+    let archive = std::path::absolute(archive)?;
+    let current = ArchiveSource::of(&archive)?;
+    let index_dir = archive_index_dir(&archive)?;
+    let metadata_path = index_dir.join("metadata.json");
+
+    let up_to_date = index_dir.join("meta.json").exists()
+        && IndexMetadata::load(&metadata_path).is_ok_and(|metadata| {
+            metadata.version == SCHEMA_VERSION && metadata.archive.as_ref() == Some(&current)
+        });
+
+    if up_to_date && !rebuild {
+        let metadata = IndexMetadata::load(&metadata_path)?;
+        println!(
+            "{} Using index of {} ({} docs)",
+            "✓".green(),
+            archive.display().to_string().cyan(),
+            metadata.total_docs.to_string().cyan()
+        );
+        return Ok(index_dir);
+    }
+
+    if index_dir.exists() {
+        std::fs::remove_dir_all(&index_dir).context("Failed to remove old archive index")?;
+    }
+    println!(
+        "{} Indexing {}...",
+        "📦".yellow(),
+        archive.display().to_string().cyan()
+    );
+    IndexBuilder::new(&index_dir)?.build_from_archive(&archive)?;
+    Ok(index_dir)
+}
+
+/// Runs a query against the indexes of several archives as if they were one
+pub fn search_indexes(index_dirs: &[PathBuf], query: &SearchQuery) -> Result<SearchResults> {
+    // Every index supplies enough results to fill the requested page
+    let page = SearchQuery {
+        offset: 0,
+        limit: query.offset + query.limit,
+        ..query.clone()
+    };
+    let searches = index_dirs
+        .iter()
+        .map(|dir| QueryExecutor::new(dir)?.execute(&page))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(merge_results(query, searches))
+}
+
+/// Combines the results of one query run from offset 0 against several
+/// indexes. Scores of separate indexes are not comparable, so relevance order
+/// alternates between their rankings.
+fn merge_results(query: &SearchQuery, searches: Vec<SearchResults>) -> SearchResults {
+    let mut ranked: Vec<(usize, SearchResult)> = Vec::new();
+    let mut conversations = Vec::new();
+    let mut total_found = 0;
+    let mut search_time_ms = 0;
+
+    for results in searches {
+        total_found += results.total_found;
+        search_time_ms += results.search_time_ms;
+        ranked.extend(results.results.into_iter().enumerate());
+        conversations.extend(results.conversations);
+    }
+
+    match query.sort {
+        SortOrder::Relevance => ranked.sort_by_key(|(rank, _)| *rank),
+        // RFC 3339 timestamps in UTC order lexically; undated entries go last
+        SortOrder::Newest => ranked.sort_by(|(_, a), (_, b)| b.timestamp.cmp(&a.timestamp)),
+        SortOrder::Oldest => ranked.sort_by(|(_, a), (_, b)| match (&a.timestamp, &b.timestamp) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }),
+    }
+
+    let results: Vec<SearchResult> = ranked
+        .into_iter()
+        .map(|(_, result)| result)
+        .skip(query.offset)
+        .take(query.limit)
+        .collect();
+
+    // Keep the conversations of the matches still shown
+    conversations.retain(|conversation: &Conversation| {
+        results.iter().any(|result| {
+            result.session_id == conversation.session_id
+                && result.file_path == conversation.file_path
+        })
+    });
+
+    SearchResults {
+        query: query.text.clone(),
+        total_found,
+        showing: results.len(),
+        offset: query.offset,
+        results,
+        conversations,
+        search_time_ms,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::metadata::ArchiveSource;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+
+    /// Writes a backup archive laid out like `BackupManager::create_backup`'s
+    fn write_archive(path: &Path, files: &[(&str, String)]) {
+        let encoder = GzEncoder::new(std::fs::File::create(path).unwrap(), Compression::fast());
+        let mut tar = tar::Builder::new(encoder);
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(1_746_000_000);
+            tar.append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn transcript(lines: &[serde_json::Value]) -> String {
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn prompt(ts: &str, text: &str) -> serde_json::Value {
+        json!({"type": "user", "timestamp": ts, "message": {"role": "user", "content": text}})
+    }
+
+    fn build(archive: &Path, index_dir: &Path) -> QueryExecutor {
+        IndexBuilder::new(index_dir)
+            .unwrap()
+            .build_from_archive(archive)
+            .unwrap();
+        QueryExecutor::new(index_dir).unwrap()
+    }
+
+    #[test]
+    fn test_archive_member_layout() {
+        let member = ArchiveMember::from_entry_path(Path::new(
+            "Claude_Code/session/.claude/projects/-app/s.jsonl",
+        ))
+        .unwrap();
+        assert_eq!(member.tool, AiTool::ClaudeCode);
+        assert_eq!(member.log_type, "Session");
+        assert_eq!(member.source, Path::new(".claude/projects/-app/s.jsonl"));
+        assert!(!member.single_file);
+
+        let member =
+            ArchiveMember::from_entry_path(Path::new("Claude_Code/history.jsonl")).unwrap();
+        assert!(member.single_file);
+        assert_eq!(member.source, Path::new("history.jsonl"));
+
+        let member =
+            ArchiveMember::from_entry_path(Path::new("My_Tool/debug/.my/log.txt")).unwrap();
+        assert_eq!(member.tool, AiTool::Other("My Tool".to_string()));
+
+        assert!(ArchiveMember::from_entry_path(Path::new("README")).is_none());
+        assert!(ArchiveMember::from_entry_path(Path::new("Cursor/notatype/x.log")).is_none());
+    }
+
+    #[test]
+    fn test_index_built_from_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("ai-logs-backup.tar.gz");
+        write_archive(
+            &archive,
+            &[
+                (
+                    "Claude_Code/session/.claude/projects/-work-app/s1.jsonl",
+                    transcript(&[
+                        prompt("2025-04-30T10:00:00Z", "why does the migration fail"),
+                        json!({"type": "assistant", "timestamp": "2025-04-30T10:00:05Z",
+                            "message": {"role": "assistant", "content": [
+                                {"type": "tool_use", "id": "t1", "name": "Bash",
+                                 "input": {"command": "cargo test"}}]}}),
+                        json!({"type": "user", "timestamp": "2025-04-30T10:00:09Z",
+                            "message": {"role": "user", "content": [
+                                {"type": "tool_result", "tool_use_id": "t1",
+                                 "content": "Exit code 1\nmigration failed", "is_error": true}]}}),
+                    ]),
+                ),
+                (
+                    "Claude_Code/history.jsonl",
+                    transcript(&[json!({"prompt": "deploy the staging branch",
+                        "timestamp": "2025-04-29T08:00:00Z"})]),
+                ),
+                (
+                    "Aider/debug/.aider/chat.log",
+                    "2025-04-28T12:00:00Z user asked about flaky migration tests\n".to_string(),
+                ),
+            ],
+        );
+        let index_dir = dir.path().join("index");
+        let executor = build(&archive, &index_dir);
+
+        let search = |query: SearchQuery| executor.execute(&query).unwrap().results;
+
+        // Transcripts keep their sessions, turns and project inside the archive
+        let results = search(SearchQuery {
+            text: "migration".to_string(),
+            sort: SortOrder::Oldest,
+            ..Default::default()
+        });
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].tool, "Aider");
+        assert_eq!(results[1].session_id, "s1");
+        assert_eq!(results[2].turn, 2);
+        assert_eq!(results[1].project, "-work-app");
+        assert_eq!(results[1].log_type, "Session");
+        assert!(results[1]
+            .file_path
+            .starts_with(&archive.to_string_lossy().to_string()));
+
+        let calls = search(SearchQuery {
+            tool_name: Some("Bash".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].exit_status, Some(1));
+
+        // A single-file location is read by its tool's parser
+        let history = search(SearchQuery {
+            text: "staging".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].message, "deploy the staging branch");
+        assert_eq!(history[0].tool, "Claude Code");
+
+        let metadata = IndexMetadata::load(&index_dir.join("metadata.json")).unwrap();
+        assert_eq!(metadata.archive, Some(ArchiveSource::of(&archive).unwrap()));
+        assert_eq!(metadata.files.len(), 3);
+        assert_eq!(metadata.total_docs, 6);
+        assert!(!index_dir.join("scratch").exists());
+    }
+
+    #[test]
+    fn test_search_across_archives() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut index_dirs = Vec::new();
+        for (name, days) in [("old", ["01", "03"]), ("new", ["02", "04"])] {
+            let archive = dir.path().join(format!("{}.tar.gz", name));
+            let lines: Vec<_> = days
+                .iter()
+                .map(|day| {
+                    prompt(
+                        &format!("2025-01-{}T09:00:00Z", day),
+                        &format!("flaky test {}", day),
+                    )
+                })
+                .collect();
+            write_archive(
+                &archive,
+                &[(
+                    "Claude_Code/session/.claude/projects/-app/s.jsonl",
+                    transcript(&lines),
+                )],
+            );
+            let index_dir = dir.path().join(name);
+            build(&archive, &index_dir);
+            index_dirs.push(index_dir);
+        }
+
+        let query = SearchQuery {
+            text: "flaky".to_string(),
+            sort: SortOrder::Newest,
+            limit: 2,
+            offset: 1,
+            ..Default::default()
+        };
+        let results = search_indexes(&index_dirs, &query).unwrap();
+        assert_eq!(results.total_found, 4);
+        let messages: Vec<&str> = results.results.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["flaky test 03", "flaky test 02"]);
+
+        // Without comparable scores, relevance takes each archive's best in turn
+        let query = SearchQuery {
+            text: "flaky".to_string(),
+            ..Default::default()
+        };
+        let results = search_indexes(&index_dirs, &query).unwrap();
+        assert_eq!(results.results.len(), 4);
+        assert_ne!(results.results[0].file_path, results.results[1].file_path);
+    }
+}
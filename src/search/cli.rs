@@ -1,17 +1,31 @@
 use anyhow::{Context, Result};
-use clap::Args;
+use clap::{Args, Subcommand};
 use colored::*;
-use std::path::PathBuf;
-
-use super::index_builder::IndexBuilder;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::aggregations::{format_aggregation, GroupBy, Interval};
+use super::archive::{open_archive_index, search_indexes};
+use super::index_builder::{discover_logs, IndexBuilder};
 use super::metadata::IndexMetadata;
-use super::query_executor::{parse_relative_date, OutputFormat, QueryExecutor, SearchQuery};
+use super::schema::SCHEMA_VERSION;
+use super::embedder::SentenceEmbedder;
+use super::query_executor::{
+    parse_end_date, parse_relative_date, ExitStatusFilter, OutputFormat, QueryExecutor,
+    SearchMode, SearchQuery, SearchResults, SortOrder,
+};
+use super::saved::{check_alert, saved_searches_path, SavedSearch, SavedSearches};
+use super::vector_index::VectorIndex;
+use super::tui::run_search_tui;
+use super::watcher::IndexWatcher;
 
 /// Search command arguments
 #[derive(Debug, Args)]
 pub struct SearchArgs {
-    /// Search query text
-    #[arg(value_name = "QUERY")]
+    /// Search query text (may be empty when filtering by --lang, --symbol, --tool-name, --file or --exit-status)
+    #[arg(value_name = "QUERY", default_value = "")]
     pub query: String,
 
     /// Filter by AI tool (e.g., claude, cursor, cline)
@@ -34,19 +48,55 @@ pub struct SearchArgs {
     #[arg(long)]
     pub project: Option<String>,
 
+    /// Only code blocks in this language (e.g., rust, python, ts)
+    #[arg(long = "lang")]
+    pub language: Option<String>,
+
+    /// Only code blocks using this identifier (matches camelCase/snake_case parts too)
+    #[arg(long)]
+    pub symbol: Option<String>,
+
+    /// Only tool calls of this tool (e.g., Bash, Edit, Read)
+    #[arg(long)]
+    pub tool_name: Option<String>,
+
+    /// Only tool calls and code blocks touching this file (e.g., src/main.rs)
+    #[arg(long)]
+    pub file: Option<String>,
+
+    /// Only tool calls with this outcome (an exit code, success or failure)
+    #[arg(long)]
+    pub exit_status: Option<String>,
+
     /// Start date (YYYY-MM-DD or relative: 7d, 1m, 1y)
     #[arg(long)]
     pub from: Option<String>,
 
-    /// End date (YYYY-MM-DD or relative: 7d, 1m, 1y)
+    /// End date, inclusive (YYYY-MM-DD or relative: 7d, 1m, 1y)
     #[arg(long)]
     pub to: Option<String>,
 
+    /// Sort order (relevance, newest, oldest)
+    #[arg(long, default_value = "relevance")]
+    pub sort: String,
+
     /// Treat query as regex pattern
     #[arg(long)]
     pub regex: bool,
 
-    /// Maximum number of results
+    /// Match words by prefix, tolerating a typo in longer words
+    #[arg(long, conflicts_with = "regex")]
+    pub fuzzy: bool,
+
+    /// Match by meaning using local embeddings instead of words
+    #[arg(long, conflicts_with_all = ["regex", "hybrid"])]
+    pub semantic: bool,
+
+    /// Rank by both word matches and meaning
+    #[arg(long, conflicts_with = "regex")]
+    pub hybrid: bool,
+
+    /// Maximum number of results, or of groups with --facet
     #[arg(long, default_value = "100")]
     pub limit: usize,
 
@@ -58,7 +108,7 @@ pub struct SearchArgs {
     #[arg(long, default_value = "table")]
     pub format: String,
 
-    /// Number of context lines before/after match
+    /// Number of conversation turns to show before/after each match
     #[arg(long, default_value = "0")]
     pub context: usize,
 
@@ -69,50 +119,165 @@ pub struct SearchArgs {
     /// Update index before searching
     #[arg(long)]
     pub update: bool,
+
+    /// Keep the index updated as logs are written instead of searching
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Search interactively, refining results as you type
+    #[arg(short, long, conflicts_with_all = ["watch", "semantic", "hybrid", "regex"])]
+    pub interactive: bool,
+
+    /// Seconds between checks for new log lines when watching
+    #[arg(long, default_value = "5", requires = "watch")]
+    pub watch_interval: u64,
+
+    /// Count matches per value of a field instead of listing them (tool, project, category, level)
+    #[arg(long, value_name = "FIELD", conflicts_with_all = ["semantic", "hybrid", "interactive"])]
+    pub facet: Option<String>,
+
+    /// Count matches per period instead of listing them (hour, day, week, month)
+    #[arg(long, value_name = "INTERVAL", conflicts_with_all = ["semantic", "hybrid", "interactive"])]
+    pub histogram: Option<String>,
+
+    /// Run a saved search; query text and filters given here take precedence
+    #[arg(long, value_name = "NAME")]
+    pub saved: Option<String>,
+
+    /// Save this search's query and filters under a name
+    #[arg(long, value_name = "NAME")]
+    pub save: Option<String>,
+
+    /// Make the saved search an alert rule for `claudev alerts check`
+    #[arg(long, requires = "save")]
+    pub alert: bool,
+
+    /// Search a backup archive instead of the live logs, indexing it first
+    /// if needed (repeat to search several)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["update", "watch"])]
+    pub archive: Vec<PathBuf>,
 }
 
-/// Handles the search command
-pub fn handle_search(args: SearchArgs) -> Result<()> {
-    let cache_dir = dirs::cache_dir()
+/// Alerts command arguments
+#[derive(Debug, Args)]
+pub struct AlertsArgs {
+    #[command(subcommand)]
+    pub command: AlertsCommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AlertsCommand {
+    /// Report matches of alert rules indexed since the last check
+    Check {
+        /// Saved searches to check (default: every alert rule)
+        names: Vec<String>,
+
+        /// Output format (table, json)
+        #[arg(long, default_value = "table")]
+        format: String,
+
+        /// Maximum number of new matches shown per rule
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// List saved searches and alert rules
+    List,
+}
+
+/// Directory of the search index
+fn index_dir() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
         .context("Could not determine cache directory")?
         .join("vibedev")
-        .join("search_index");
+        .join("search_index"))
+}
 
-    let metadata_path = cache_dir.join("metadata.json");
+/// Fills in the query text and filters not given on the command line
+fn apply_saved_search(args: &mut SearchArgs, saved: &SavedSearch) {
+    if args.query.is_empty() {
+        args.query = saved.query.clone();
+    }
+    let fill = |arg: &mut Option<String>, value: &Option<String>| {
+        if arg.is_none() {
+            *arg = value.clone();
+        }
+    };
+    fill(&mut args.tool, &saved.tool);
+    fill(&mut args.log_type, &saved.log_type);
+    fill(&mut args.category, &saved.category);
+    fill(&mut args.level, &saved.level);
+    fill(&mut args.project, &saved.project);
+    fill(&mut args.language, &saved.language);
+    fill(&mut args.symbol, &saved.symbol);
+    fill(&mut args.tool_name, &saved.tool_name);
+    fill(&mut args.file, &saved.file);
+    fill(&mut args.exit_status, &saved.exit_status);
+    fill(&mut args.from, &saved.from);
+    fill(&mut args.to, &saved.to);
+    args.regex |= saved.regex && !args.fuzzy;
+    args.fuzzy |= saved.fuzzy && !args.regex;
+}
 
-    // Check if index exists
-    let index_exists = cache_dir.join("meta.json").exists();
+/// The query text and filters of a search, to be saved under `name`
+fn saved_search_from(args: &SearchArgs, name: &str, alert: bool) -> SavedSearch {
+    SavedSearch {
+        name: name.to_string(),
+        query: args.query.clone(),
+        tool: args.tool.clone(),
+        log_type: args.log_type.clone(),
+        category: args.category.clone(),
+        level: args.level.clone(),
+        project: args.project.clone(),
+        language: args.language.clone(),
+        symbol: args.symbol.clone(),
+        tool_name: args.tool_name.clone(),
+        file: args.file.clone(),
+        exit_status: args.exit_status.clone(),
+        from: args.from.clone(),
+        to: args.to.clone(),
+        regex: args.regex,
+        fuzzy: args.fuzzy,
+        alert,
+    }
+}
 
-    // Build or update index
-    if args.rebuild || !index_exists {
-        println!("{}", "🔍 No search index found. Building index...".yellow());
-        let mut builder = IndexBuilder::new(&cache_dir)?;
-        builder.build_initial_index()?;
-    } else if args.update {
-        let mut builder = IndexBuilder::new(&cache_dir)?;
-        builder.update_index()?;
-    } else {
-        // Show index info
-        if let Ok(metadata) = IndexMetadata::load(&metadata_path) {
-            let age = chrono::Utc::now()
-                .signed_duration_since(metadata.last_indexed)
-                .num_minutes();
+/// Handles the search command
+pub fn handle_search(mut args: SearchArgs) -> Result<()> {
+    let cache_dir = index_dir()?;
 
-            let age_str = if age < 60 {
-                format!("{}m ago", age)
-            } else if age < 1440 {
-                format!("{}h ago", age / 60)
-            } else {
-                format!("{}d ago", age / 1440)
-            };
+    if let Some(name) = args.saved.clone() {
+        let saved = SavedSearches::load()?;
+        apply_saved_search(&mut args, saved.get(&name)?);
+    }
 
-            println!(
-                "{} Using cached index ({} docs, last updated {})",
-                "✓".green(),
-                metadata.total_docs.to_string().cyan(),
-                age_str.yellow()
+    let index_dirs = if args.archive.is_empty() {
+        prepare_index(&cache_dir, args.rebuild, args.update)?;
+        vec![cache_dir]
+    } else {
+        let single_index_only = args.interactive
+            || args.semantic
+            || args.hybrid
+            || args.facet.is_some()
+            || args.histogram.is_some();
+        if args.archive.len() > 1 && single_index_only {
+            anyhow::bail!(
+                "Only one --archive can be searched with --interactive, --semantic, --hybrid, --facet or --histogram"
             );
         }
+        args.archive
+            .iter()
+            .map(|archive| open_archive_index(archive, args.rebuild))
+            .collect::<Result<Vec<_>>>()?
+    };
+    let cache_dir = index_dirs[0].clone();
+
+    if args.watch {
+        return watch_index(&cache_dir, Duration::from_secs(args.watch_interval.max(1)));
+    }
+
+    if args.interactive {
+        return run_search_tui(&cache_dir);
     }
 
     // Build search query
@@ -125,7 +290,36 @@ pub fn handle_search(args: SearchArgs) -> Result<()> {
     let to_date = args
         .to
         .as_ref()
-        .map(|s| parse_relative_date(s))
+        .map(|s| parse_end_date(s))
+        .transpose()?;
+
+    if let (Some(from), Some(to)) = (from_date, to_date) {
+        if from > to {
+            anyhow::bail!("--from must not be later than --to");
+        }
+    }
+
+    let sort: SortOrder = args.sort.parse()?;
+
+    if let Some(name) = &args.save {
+        if name.trim().is_empty() {
+            anyhow::bail!("--save needs a name");
+        }
+        let mut saved = SavedSearches::load()?;
+        saved.upsert(saved_search_from(&args, name, args.alert));
+        saved.save()?;
+        println!(
+            "{} Saved search {} in {}",
+            "✓".green(),
+            name.cyan(),
+            saved_searches_path().display()
+        );
+    }
+
+    let exit_status = args
+        .exit_status
+        .as_deref()
+        .map(str::parse::<ExitStatusFilter>)
         .transpose()?;
 
     let format = match args.format.to_lowercase().as_str() {
@@ -134,6 +328,18 @@ pub fn handle_search(args: SearchArgs) -> Result<()> {
         _ => OutputFormat::Table,
     };
 
+    let mode = if args.semantic {
+        SearchMode::Semantic
+    } else if args.hybrid {
+        SearchMode::Hybrid
+    } else {
+        SearchMode::Lexical
+    };
+
+    if mode != SearchMode::Lexical && args.query.trim().is_empty() {
+        anyhow::bail!("Semantic and hybrid search need query text");
+    }
+
     let search_query = SearchQuery {
         text: args.query.clone(),
         tool: args.tool,
@@ -141,20 +347,69 @@ pub fn handle_search(args: SearchArgs) -> Result<()> {
         category: args.category,
         level: args.level,
         project: args.project,
+        language: args.language,
+        symbol: args.symbol,
+        tool_name: args.tool_name,
+        file: args.file,
+        exit_status,
+        after_doc_id: None,
         from_date,
         to_date,
         regex: args.regex,
+        fuzzy: args.fuzzy,
+        facets: Vec::new(),
         limit: args.limit,
         offset: args.offset,
+        mode,
+        sort,
         format,
         context: args.context,
     };
 
-    // Execute search
-    println!("\n{} Searching...", "🔍".cyan());
-
     let executor = QueryExecutor::new(&cache_dir)?;
-    let results = executor.execute(&search_query)?;
+
+    if args.facet.is_some() || args.histogram.is_some() {
+        let group_by = args.facet.as_deref().map(str::parse::<GroupBy>).transpose()?;
+        let interval = args.histogram.as_deref().map(str::parse::<Interval>).transpose()?;
+
+        println!("\n{} Aggregating...", "📊".cyan());
+        let aggregation = executor.aggregate(&search_query, group_by, interval)?;
+        println!(
+            "   Found {} matches ({} estimated tokens) in {}ms\n",
+            aggregation.total_found.to_string().green(),
+            aggregation.tokens.total,
+            aggregation.search_time_ms
+        );
+        println!("{}", format_aggregation(&aggregation, format));
+        return Ok(());
+    }
+
+    let results = if mode == SearchMode::Lexical {
+        println!("\n{} Searching...", "🔍".cyan());
+        if index_dirs.len() > 1 {
+            search_indexes(&index_dirs, &search_query)?
+        } else {
+            executor.execute(&search_query)?
+        }
+    } else {
+        println!("\n{} Loading embedding model...", "🧠".cyan());
+        let embedder = SentenceEmbedder::load().context("Failed to load embedding model")?;
+
+        // Embed prompts and responses indexed since the last semantic search
+        let mut vectors = VectorIndex::open(&cache_dir, &embedder)?;
+        let added = vectors.sync(executor.index(), &embedder, true)?;
+        if added > 0 {
+            vectors.save(&cache_dir)?;
+            println!(
+                "   Embedded {} new messages ({} total)",
+                added.to_string().cyan(),
+                vectors.len()
+            );
+        }
+
+        println!("\n{} Searching...", "🔍".cyan());
+        executor.execute_semantic(&search_query, &vectors, &embedder)?
+    };
 
     println!(
         "   Found {} results in {}ms\n",
@@ -186,3 +441,234 @@ pub fn handle_search(args: SearchArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Builds the index of the live logs when missing, outdated or `rebuild`
+/// is set, and otherwise updates it if asked to
+fn prepare_index(cache_dir: &Path, rebuild: bool, update: bool) -> Result<()> {
+    let metadata_path = cache_dir.join("metadata.json");
+
+    // Check if index exists
+    let index_exists = cache_dir.join("meta.json").exists();
+
+    // Indexes written with an older schema lack fields and must be rebuilt
+    let stale = index_exists
+        && IndexMetadata::load(&metadata_path)
+            .map(|m| m.version != SCHEMA_VERSION)
+            .unwrap_or(true);
+
+    // Build or update index
+    if rebuild || !index_exists || stale {
+        if index_exists {
+            println!("{}", "🔍 Rebuilding search index...".yellow());
+            std::fs::remove_dir_all(cache_dir).context("Failed to remove old index")?;
+        } else {
+            println!("{}", "🔍 No search index found. Building index...".yellow());
+        }
+        let mut builder = IndexBuilder::new(cache_dir)?;
+        builder.build_initial_index()?;
+    } else if update {
+        let mut builder = IndexBuilder::new(cache_dir)?;
+        builder.update_index()?;
+    } else {
+        // Show index info
+        if let Ok(metadata) = IndexMetadata::load(&metadata_path) {
+            let age = chrono::Utc::now()
+                .signed_duration_since(metadata.last_indexed)
+                .num_minutes();
+
+            let age_str = if age < 60 {
+                format!("{}m ago", age)
+            } else if age < 1440 {
+                format!("{}h ago", age / 60)
+            } else {
+                format!("{}d ago", age / 1440)
+            };
+
+            println!(
+                "{} Using cached index ({} docs, last updated {})",
+                "✓".green(),
+                metadata.total_docs.to_string().cyan(),
+                age_str.yellow()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Indexes new log lines as they are written, until interrupted
+fn watch_index(cache_dir: &Path, interval: Duration) -> Result<()> {
+    println!(
+        "\n{} Watching logs for new entries every {}s (Ctrl-C to stop)",
+        "👀".cyan(),
+        interval.as_secs()
+    );
+
+    // Finish the update in progress on Ctrl-C rather than dying mid-commit
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_on_signal = Arc::clone(&stop);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            stop_on_signal.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let mut watcher = IndexWatcher::new(cache_dir, interval)?;
+    watcher.run(&stop);
+    println!("{} Stopped watching", "✓".green());
+    Ok(())
+}
+
+/// Handles the alerts command
+pub fn handle_alerts(args: AlertsArgs) -> Result<()> {
+    match args.command {
+        AlertsCommand::Check {
+            names,
+            format,
+            limit,
+        } => check_alerts(&names, &format, limit),
+        AlertsCommand::List => list_saved_searches(),
+    }
+}
+
+/// Checks alert rules against log lines written since the last check
+fn check_alerts(names: &[String], format: &str, limit: usize) -> Result<()> {
+    let saved = SavedSearches::load()?;
+    let rules: Vec<&SavedSearch> = if names.is_empty() {
+        saved.alerts().collect()
+    } else {
+        names.iter().map(|name| saved.get(name)).collect::<Result<_>>()?
+    };
+    let json = format.eq_ignore_ascii_case("json");
+    if rules.is_empty() {
+        if json {
+            println!("[]");
+        } else {
+            println!(
+                "No alert rules. Save one with: claudev search <QUERY> --save <NAME> --alert"
+            );
+        }
+        return Ok(());
+    }
+
+    let cache_dir = index_dir()?;
+    let metadata_path = cache_dir.join("metadata.json");
+    let current = cache_dir.join("meta.json").exists()
+        && IndexMetadata::load(&metadata_path)
+            .map(|m| m.version == SCHEMA_VERSION)
+            .unwrap_or(false);
+    if !current {
+        anyhow::bail!("Search index is missing or outdated; run `claudev search --rebuild` first");
+    }
+
+    // Index what was written since the last update, quietly so the output
+    // stays parseable
+    IndexBuilder::new(&cache_dir)?.update_locations(&discover_logs()?.locations)?;
+
+    let executor = QueryExecutor::new(&cache_dir)?;
+    let mut metadata = IndexMetadata::load(&metadata_path)?;
+    let now = chrono::Utc::now();
+    let reports = rules
+        .iter()
+        .map(|rule| check_alert(&executor, &mut metadata, rule, limit, now))
+        .collect::<Result<Vec<_>>>()?;
+    metadata.save(&metadata_path)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    let mut quiet = true;
+    for report in &reports {
+        if report.first_check {
+            quiet = false;
+            println!(
+                "{} {}: watching for new matches ({} already indexed)",
+                "👀".cyan(),
+                report.name.cyan().bold(),
+                report.hits
+            );
+        } else if report.hits > 0 {
+            quiet = false;
+            println!(
+                "\n{} {}: {} new {}",
+                "🔔".yellow(),
+                report.name.cyan().bold(),
+                report.hits.to_string().yellow().bold(),
+                if report.hits == 1 { "match" } else { "matches" }
+            );
+            let results = SearchResults {
+                query: report.name.clone(),
+                total_found: report.hits,
+                showing: report.results.len(),
+                offset: 0,
+                results: report.results.clone(),
+                conversations: Vec::new(),
+                search_time_ms: 0,
+            };
+            println!("{}", executor.format_results(&results, OutputFormat::Table));
+        }
+    }
+    if quiet {
+        println!(
+            "{} No new matches for {} alert {}",
+            "✓".green(),
+            reports.len(),
+            if reports.len() == 1 { "rule" } else { "rules" }
+        );
+    }
+    Ok(())
+}
+
+/// Prints the saved searches, marking alert rules
+fn list_saved_searches() -> Result<()> {
+    let saved = SavedSearches::load()?;
+    if saved.searches.is_empty() {
+        println!(
+            "No saved searches in {}. Save one with: claudev search <QUERY> --save <NAME>",
+            saved_searches_path().display()
+        );
+        return Ok(());
+    }
+
+    for search in &saved.searches {
+        let mut filters = Vec::new();
+        for (flag, value) in [
+            ("tool", &search.tool),
+            ("log-type", &search.log_type),
+            ("category", &search.category),
+            ("level", &search.level),
+            ("project", &search.project),
+            ("lang", &search.language),
+            ("symbol", &search.symbol),
+            ("tool-name", &search.tool_name),
+            ("file", &search.file),
+            ("exit-status", &search.exit_status),
+            ("from", &search.from),
+            ("to", &search.to),
+        ] {
+            if let Some(value) = value {
+                filters.push(format!("--{} {}", flag, value));
+            }
+        }
+        if search.regex {
+            filters.push("--regex".to_string());
+        }
+        if search.fuzzy {
+            filters.push("--fuzzy".to_string());
+        }
+        println!(
+            "{} {} {} {}",
+            if search.alert { "🔔" } else { "  " },
+            search.name.cyan().bold(),
+            if search.query.is_empty() {
+                String::new()
+            } else {
+                format!("{:?}", search.query)
+            },
+            filters.join(" ").dimmed()
+        );
+    }
+    Ok(())
+}
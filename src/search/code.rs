@@ -0,0 +1,390 @@
+// Code blocks in messages: extraction, language detection and identifier tokenizing
+
+use serde_json::Value;
+use tantivy::tokenizer::{Token, TokenStream, Tokenizer};
+use tantivy::Index;
+
+/// Name the code tokenizer is registered under
+pub const CODE_TOKENIZER: &str = "code";
+
+/// Tool calls whose input carries file contents, with the keys holding code
+const FILE_EDIT_TOOLS: [(&str, &[&str]); 4] = [
+    ("Write", &["content"]),
+    ("Edit", &["new_string"]),
+    ("MultiEdit", &["edits"]),
+    ("NotebookEdit", &["new_source"]),
+];
+
+/// A snippet of code found in a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    /// Normalized language name, empty when unknown
+    pub language: String,
+    /// File the code was written to or labelled with
+    pub file_path: Option<String>,
+    pub code: String,
+}
+
+/// Extracts fenced code blocks and file-edit tool calls from a message.
+/// Tool calls are the `[Tool] {input}` lines written by the Claude
+/// transcript parser.
+pub fn extract_code_blocks(message: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = message.lines();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            let (language, file_path) = parse_info_string(info);
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            let code = code.join("\n");
+            if !code.trim().is_empty() {
+                let language = match language {
+                    Some(language) => normalize_language(&language),
+                    None => file_path
+                        .as_deref()
+                        .map(language_for_path)
+                        .unwrap_or_default(),
+                };
+                blocks.push(CodeBlock {
+                    language,
+                    file_path,
+                    code,
+                });
+            }
+        } else if let Some(block) = parse_tool_edit(trimmed) {
+            blocks.push(block);
+        }
+    }
+
+    blocks
+}
+
+/// Language and file path from a fence info string such as `rust`,
+/// `rust:src/main.rs`, `src/main.rs` or `python title="app.py"`
+fn parse_info_string(info: &str) -> (Option<String>, Option<String>) {
+    let info = info.trim();
+    if info.is_empty() {
+        return (None, None);
+    }
+
+    let mut words = info.split_whitespace();
+    let first = words.next().unwrap_or_default();
+    let (language, mut path) = match first.split_once(':') {
+        Some((language, path)) => (Some(language), Some(path.to_string())),
+        None if looks_like_path(first) => (None, Some(first.to_string())),
+        None => (Some(first), None),
+    };
+
+    for word in words {
+        let value = word
+            .strip_prefix("title=")
+            .or_else(|| word.strip_prefix("file="))
+            .or_else(|| word.strip_prefix("filename="))
+            .or_else(|| looks_like_path(word).then_some(word));
+        if let Some(value) = value {
+            path = Some(value.trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+    }
+
+    (
+        language.filter(|l| !l.is_empty()).map(String::from),
+        path.filter(|p| !p.is_empty()),
+    )
+}
+
+fn looks_like_path(word: &str) -> bool {
+    word.contains('/')
+        || word
+            .rsplit_once('.')
+            .is_some_and(|(stem, ext)| !stem.is_empty() && !ext.is_empty())
+}
+
+/// Code written by a `[Write] {...}`-style tool call line
+fn parse_tool_edit(line: &str) -> Option<CodeBlock> {
+    let rest = line.strip_prefix('[')?;
+    let (tool, input) = rest.split_once("] ")?;
+    let (_, keys) = FILE_EDIT_TOOLS.iter().find(|(name, _)| *name == tool)?;
+    let input: Value = serde_json::from_str(input).ok()?;
+
+    let file_path = input
+        .get("file_path")
+        .or_else(|| input.get("notebook_path"))
+        .and_then(Value::as_str)
+        .map(String::from);
+
+    let mut parts = Vec::new();
+    for key in *keys {
+        match input.get(*key) {
+            Some(Value::String(code)) => parts.push(code.clone()),
+            // MultiEdit carries a list of edits
+            Some(Value::Array(edits)) => parts.extend(
+                edits
+                    .iter()
+                    .filter_map(|e| e.get("new_string").and_then(Value::as_str))
+                    .map(String::from),
+            ),
+            _ => {}
+        }
+    }
+    let code = parts.join("\n");
+    if code.trim().is_empty() {
+        return None;
+    }
+
+    Some(CodeBlock {
+        language: file_path
+            .as_deref()
+            .map(language_for_path)
+            .unwrap_or_default(),
+        file_path,
+        code,
+    })
+}
+
+/// Canonical language name for a fence tag or alias
+pub fn normalize_language(language: &str) -> String {
+    let language = language.trim().to_lowercase();
+    let canonical = match language.as_str() {
+        "rs" => "rust",
+        "py" | "python3" => "python",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "node" => "javascript",
+        "sh" | "bash" | "zsh" | "console" | "shell-session" => "shell",
+        "yml" => "yaml",
+        "c++" | "cc" | "hpp" | "cxx" => "cpp",
+        "rb" => "ruby",
+        "kt" => "kotlin",
+        "golang" => "go",
+        "md" => "markdown",
+        "cs" | "c#" => "csharp",
+        other => other,
+    };
+    canonical.to_string()
+}
+
+/// Language of a file, from its extension
+pub fn language_for_path(path: &str) -> String {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext)
+        .unwrap_or_default();
+    let language = match extension.to_lowercase().as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "go" => "go",
+        "java" => "java",
+        "kt" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "sh" | "bash" | "zsh" => "shell",
+        "toml" => "toml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "md" => "markdown",
+        "sql" => "sql",
+        "html" => "html",
+        "css" => "css",
+        _ => "",
+    };
+    language.to_string()
+}
+
+/// Splits an identifier into lowercase words at `_`, `-` and case changes:
+/// `parseHTTPResponse` gives `parse`, `http`, `response`
+pub fn identifier_parts(identifier: &str) -> Vec<String> {
+    let chars: Vec<char> = identifier.chars().collect();
+    let mut parts = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let boundary = i > 0
+            && c.is_uppercase()
+            && (chars[i - 1].is_lowercase()
+                || chars[i - 1].is_ascii_digit()
+                || (chars[i - 1].is_uppercase()
+                    && chars.get(i + 1).is_some_and(|n| n.is_lowercase())));
+        if boundary && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Tokenizer for code: each identifier is indexed whole (lowercased) and as
+/// its camelCase / snake_case parts, so `LogDiscovery` matches
+/// `logdiscovery`, `log` and `discovery`
+#[derive(Clone, Default)]
+pub struct CodeTokenizer {
+    token: Token,
+}
+
+pub struct CodeTokenStream<'a> {
+    tokens: std::vec::IntoIter<Token>,
+    current: &'a mut Token,
+}
+
+impl Tokenizer for CodeTokenizer {
+    type TokenStream<'a> = CodeTokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> CodeTokenStream<'a> {
+        let mut tokens = Vec::new();
+        let mut position = 0;
+
+        let mut start = None;
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ' ')))
+        {
+            let is_ident = c.is_alphanumeric() || c == '_';
+            match (start, is_ident) {
+                (None, true) => start = Some(i),
+                (Some(from), false) => {
+                    let word = &text[from..i];
+                    start = None;
+                    // Numbers and single letters only add noise
+                    if word.chars().count() < 2 || word.chars().all(|c| c.is_ascii_digit()) {
+                        continue;
+                    }
+
+                    let whole = word.to_lowercase();
+                    let parts = identifier_parts(word);
+                    tokens.push(Token {
+                        offset_from: from,
+                        offset_to: i,
+                        position,
+                        text: whole.clone(),
+                        position_length: 1,
+                    });
+                    if parts.len() > 1 {
+                        for part in parts.into_iter().filter(|p| *p != whole) {
+                            tokens.push(Token {
+                                offset_from: from,
+                                offset_to: i,
+                                position,
+                                text: part,
+                                position_length: 1,
+                            });
+                        }
+                    }
+                    position += 1;
+                }
+                _ => {}
+            }
+        }
+
+        self.token = Token::default();
+        CodeTokenStream {
+            tokens: tokens.into_iter(),
+            current: &mut self.token,
+        }
+    }
+}
+
+impl TokenStream for CodeTokenStream<'_> {
+    fn advance(&mut self) -> bool {
+        match self.tokens.next() {
+            Some(token) => {
+                *self.current = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        self.current
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.current
+    }
+}
+
+/// Makes the code tokenizer available to an index; needed whenever one is
+/// opened or created, since tokenizers are not persisted
+pub fn register_code_tokenizer(index: &Index) {
+    index
+        .tokenizers()
+        .register(CODE_TOKENIZER, CodeTokenizer::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_fenced_and_tool_blocks() {
+        let message = concat!(
+            "Here is the fix:\n",
+            "```rs title=\"src/discovery.rs\"\n",
+            "let discovery = LogDiscovery::new(home, true);\n",
+            "```\n",
+            "and a command\n",
+            "```\n",
+            "cargo test\n",
+            "```\n",
+            "[Edit] {\"file_path\":\"/repo/app.py\",\"old_string\":\"a\",\"new_string\":\"def parse_args(): pass\"}\n",
+            "[Bash] {\"command\":\"ls\"}\n",
+            "[MultiEdit] {\"file_path\":\"web/main.tsx\",\"edits\":[{\"new_string\":\"const a = 1\"},{\"new_string\":\"const b = 2\"}]}",
+        );
+
+        let blocks = extract_code_blocks(message);
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].language, "rust");
+        assert_eq!(blocks[0].file_path.as_deref(), Some("src/discovery.rs"));
+        assert_eq!(blocks[1].language, "");
+        assert_eq!(blocks[1].code, "cargo test");
+        assert_eq!(blocks[2].language, "python");
+        assert_eq!(blocks[2].code, "def parse_args(): pass");
+        assert_eq!(blocks[3].language, "typescript");
+        assert_eq!(blocks[3].code, "const a = 1\nconst b = 2");
+    }
+
+    #[test]
+    fn test_identifier_parts() {
+        assert_eq!(identifier_parts("LogDiscovery"), ["log", "discovery"]);
+        assert_eq!(
+            identifier_parts("parse_history_entry"),
+            ["parse", "history", "entry"]
+        );
+        assert_eq!(
+            identifier_parts("parseHTTPResponse"),
+            ["parse", "http", "response"]
+        );
+        assert_eq!(identifier_parts("utf8Decode"), ["utf8", "decode"]);
+    }
+
+    #[test]
+    fn test_code_tokenizer() {
+        let mut tokenizer = CodeTokenizer::default();
+        let mut stream = tokenizer.token_stream("LogDiscovery::new(x, 42)");
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        assert_eq!(texts, ["logdiscovery", "log", "discovery", "new"]);
+    }
+}
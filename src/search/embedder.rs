@@ -0,0 +1,121 @@
+// Local sentence embeddings for semantic search, run on CPU with candle
+
+use anyhow::{anyhow, Result};
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config as BertConfig, DTYPE};
+use hf_hub::{api::sync::Api, Repo, RepoType};
+use std::fs;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+/// Small sentence-embedding model (384 dimensions, ~90MB)
+pub const EMBEDDING_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// Longer inputs are truncated; the model was trained on 256-token sequences
+const MAX_SEQUENCE_TOKENS: usize = 256;
+
+/// Turns texts into unit-length vectors whose dot product measures similarity
+pub trait TextEmbedder {
+    /// Identifies the model; vectors from different models are not comparable
+    fn model_id(&self) -> &str;
+
+    fn dimensions(&self) -> usize;
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// BERT sentence embedder with mean pooling
+pub struct SentenceEmbedder {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+    dimensions: usize,
+}
+
+impl SentenceEmbedder {
+    /// Loads the model from the HuggingFace cache, downloading it on first use
+    pub fn load() -> Result<Self> {
+        let api = Api::new()?;
+        let repo = api.repo(Repo::new(EMBEDDING_MODEL.to_string(), RepoType::Model));
+
+        let config_path = repo.get("config.json")?;
+        let tokenizer_path = repo.get("tokenizer.json")?;
+        let weights_path = repo.get("model.safetensors")?;
+
+        let config: BertConfig = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer: {}", e))?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_SEQUENCE_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer: {}", e))?;
+
+        let device = Device::Cpu;
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DTYPE, &device)? };
+        let model = BertModel::load(vb, &config)?;
+
+        Ok(Self {
+            model,
+            tokenizer,
+            device,
+            dimensions: config.hidden_size,
+        })
+    }
+}
+
+impl TextEmbedder for SentenceEmbedder {
+    fn model_id(&self) -> &str {
+        EMBEDDING_MODEL
+    }
+
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Tokenization failed: {}", e))?;
+
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let masks = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+
+        let input_ids = Tensor::stack(&ids, 0)?;
+        let attention_mask = Tensor::stack(&masks, 0)?;
+        let token_type_ids = input_ids.zeros_like()?;
+
+        // (batch, tokens, hidden)
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+        // Mean over real tokens, ignoring padding
+        let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+        let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+        let counts = mask.sum(1)?;
+        let pooled = summed.broadcast_div(&counts)?;
+
+        let norms = pooled.sqr()?.sum_keepdim(1)?.sqrt()?;
+        let normalized = pooled.broadcast_div(&norms)?;
+
+        Ok(normalized.to_vec2::<f32>()?)
+    }
+}
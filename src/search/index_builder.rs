@@ -1,28 +1,40 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::collector::Count;
+use tantivy::query::{BooleanQuery, Occur, Query, RangeQuery, TermQuery};
 use tantivy::schema::*;
 use tantivy::{doc, Index, IndexWriter};
 
 use crate::discovery::LogDiscovery;
-use crate::models::{DiscoveryFindings, LogLocation};
+use crate::models::{AiTool, DiscoveryFindings, LogLocation};
 use crate::models::{format_bytes, LogType};
-use crate::parsers::claude::ClaudeParser;
+use crate::parsers::claude::{self, ClaudeParser};
 use crate::parsers::cline::ClineParser;
 use crate::parsers::cursor::CursorParser;
 use crate::parsers::generic::GenericParser;
-use crate::parsers::LogParser;
+use crate::parsers::{EntryCategory, LogEntry, LogParser};
 
-use super::metadata::{detect_changes, IndexMetadata, LocationMetadata};
-use super::schema::{build_schema, LogEntryDocument, FIELD_FILE_PATH};
+use super::archive::ArchiveMember;
+use super::metadata::{tail_hash, ArchiveSource, FileCheckpoint, IndexMetadata, LocationMetadata};
+use super::code::extract_code_blocks;
+use super::schema::{
+    build_schema, max_doc_id, register_tokenizers, LogEntryDocument, FIELD_FILE_PATH, FIELD_LINE,
+};
+use super::tool_calls::{tool_calls_by_turn, ToolCallRecord};
 
 const BATCH_SIZE: usize = 10_000;
 const MEMORY_BUDGET_MB: usize = 500;
+/// How long a tool call may wait for its result before it is indexed without one
+const PENDING_TOOL_CALL_SECS: i64 = 60 * 60;
 
 /// Statistics about indexing operation
 #[derive(Debug, Clone)]
@@ -34,6 +46,23 @@ pub struct IndexStats {
     pub duration_secs: f64,
 }
 
+/// Entries read from one source file
+struct SourceRead {
+    tool: AiTool,
+    entries: Vec<LogEntry>,
+    /// Line of the source each entry was parsed from
+    lines: Vec<u64>,
+    /// Line reading started at; 0 when the file was read from the start
+    first_line: u64,
+    /// Conversation turn of the first entry
+    first_turn: u64,
+    /// Tool calls requested by the entries, by index into `entries`
+    tool_calls: Vec<(usize, ToolCallRecord)>,
+    /// Where the next update resumes
+    checkpoint: FileCheckpoint,
+    bytes_read: u64,
+}
+
 /// Builds and manages the Tantivy search index
 pub struct IndexBuilder {
     index_path: PathBuf,
@@ -58,6 +87,7 @@ impl IndexBuilder {
             Index::create_in_dir(&index_path, schema.clone())
                 .context("Failed to create new index")?
         };
+        register_tokenizers(&index);
 
         Ok(Self {
             index_path,
@@ -74,9 +104,7 @@ impl IndexBuilder {
         println!("\n{}", "📊 Discovering logs...".cyan());
 
         // Run discovery
-        let home_dir = dirs::home_dir().context("Could not determine home directory")?;
-        let discovery = LogDiscovery::new(home_dir, true);
-        let findings = discovery.scan()?;
+        let findings = discover_logs()?;
 
         println!(
             "   Found: {} tools, {} locations, {}",
@@ -88,21 +116,9 @@ impl IndexBuilder {
         println!("\n{}", "🏗️  Building search index...".green().bold());
 
         // Index all locations
-        let stats = self.index_locations(&findings.locations, true)?;
-
-        // Save metadata
-        let mut metadata = IndexMetadata::new();
-        for location in &findings.locations {
-            if let Ok(loc_meta) = LocationMetadata::from_log_location(location) {
-                metadata.upsert_location(loc_meta);
-            }
-        }
-        metadata.update_total_docs();
-        metadata.save(&self.metadata_path)?;
-
-        let elapsed = start.elapsed().as_secs_f64();
-        let mut final_stats = stats;
-        final_stats.duration_secs = elapsed;
+        let mut final_stats =
+            self.sync_locations(&findings.locations, IndexMetadata::new(), true)?;
+        final_stats.duration_secs = start.elapsed().as_secs_f64();
 
         // Print summary
         self.print_stats(&final_stats);
@@ -110,86 +126,170 @@ impl IndexBuilder {
         Ok(final_stats)
     }
 
-    /// Updates the index with new/changed logs
+    /// Updates the index with lines appended to logs and with new or changed files
     pub fn update_index(&mut self) -> Result<IndexStats> {
         let start = std::time::Instant::now();
 
         println!("\n{}", "🔄 Checking for updates...".cyan());
 
-        // Load existing metadata
-        let metadata = if self.metadata_path.exists() {
-            IndexMetadata::load(&self.metadata_path)?
-        } else {
+        if !self.metadata_path.exists() {
             return self.build_initial_index();
-        };
-
-        // Run discovery
-        let home_dir = dirs::home_dir().context("Could not determine home directory")?;
-        let discovery = LogDiscovery::new(home_dir, true);
-        let findings = discovery.scan()?;
+        }
 
-        // Detect changes
-        let changed = detect_changes(&metadata, &findings.locations)?;
+        let findings = discover_logs()?;
+        let mut final_stats = self.update_locations(&findings.locations)?;
 
-        if changed.is_empty() {
+        if final_stats.total_files == 0 {
             println!("   {}", "No changes detected".green());
-            return Ok(IndexStats {
-                total_docs: metadata.total_docs,
-                total_files: metadata.indexed_locations.len(),
-                total_bytes: metadata
-                    .indexed_locations
-                    .iter()
-                    .map(|l| l.size_bytes)
-                    .sum(),
-                index_size_bytes: self.get_index_size()?,
-                duration_secs: 0.0,
-            });
+            return Ok(final_stats);
         }
 
         println!(
-            "   Found {} new/changed files",
-            changed.len().to_string().yellow()
+            "   Updated {} new/changed files",
+            final_stats.total_files.to_string().yellow()
         );
 
-        // Delete old docs for changed locations
-        self.delete_docs_by_paths(&changed)?;
+        final_stats.duration_secs = start.elapsed().as_secs_f64();
+        self.print_stats(&final_stats);
+
+        Ok(final_stats)
+    }
+
+    /// Builds the index from a backup archive made by `BackupManager`,
+    /// parsing each log as it is read from the archive instead of restoring
+    /// it first. Documents point into the archive
+    /// (`<archive>/<tool>/<log type>/...`) and the index metadata records
+    /// which archive it was built from.
+    pub fn build_from_archive(&mut self, archive: &Path) -> Result<IndexStats> {
+        let start = std::time::Instant::now();
+        let source = ArchiveSource::of(archive)?;
 
-        // Reindex changed locations
-        let stats = self.index_locations(&changed, false)?;
+        let pb = ProgressBar::new(source.size_bytes);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("   [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({msg}) ETA: {eta}")
+                .unwrap()
+                .progress_chars("█▓▒░ "),
+        );
 
-        // Update metadata
-        let mut updated_metadata = metadata;
-        for location in &changed {
-            if let Ok(mut loc_meta) = LocationMetadata::from_log_location(location) {
-                loc_meta.doc_count = stats.total_docs;
-                updated_metadata.upsert_location(loc_meta);
+        let file = File::open(archive)
+            .with_context(|| format!("Failed to open archive {}", archive.display()))?;
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(pb.wrap_read(file)));
+
+        let parsers = log_parsers();
+        // Logs only some parsers can read from disk are written here one at a time
+        let scratch = self.index_path.join("scratch");
+        let mut writer = self.writer()?;
+        let mut metadata = IndexMetadata::new();
+        let doc_id_counter = AtomicU64::new(self.next_doc_id(&metadata)?);
+        let mut total_docs = 0u64;
+        let mut bytes_processed = 0u64;
+        let mut files_indexed = Vec::new();
+
+        for entry in tar.entries().context("Failed to read archive")? {
+            let mut entry = entry.context("Failed to read archive")?;
+            if !entry.header().entry_type().is_file() {
+                continue;
             }
+            let entry_path = entry.path()?.into_owned();
+            let Some(member) = ArchiveMember::from_entry_path(&entry_path) else {
+                continue;
+            };
+            let modified = entry
+                .header()
+                .mtime()
+                .ok()
+                .and_then(|secs| DateTime::from_timestamp(secs as i64, 0))
+                .unwrap_or(source.last_modified);
+            let mut bytes = Vec::new();
+            entry.read_to_end(&mut bytes)?;
+
+            let path = archive.join(&entry_path);
+            let Some(read) =
+                read_archive_member(&parsers, &member, &path, &bytes, modified, &scratch)?
+            else {
+                continue;
+            };
+
+            total_docs +=
+                self.add_source(&writer, &read, &path, &member.log_type, &doc_id_counter)?;
+            bytes_processed += read.bytes_read;
+            files_indexed.push(path);
+            metadata.upsert_file(read.checkpoint);
+            pb.set_message(format!("{} files", files_indexed.len()));
         }
-        updated_metadata.last_indexed = chrono::Utc::now();
-        updated_metadata.update_total_docs();
-        updated_metadata.save(&self.metadata_path)?;
+        pb.finish_with_message(format!("{} files", files_indexed.len()));
+        let _ = fs::remove_dir_all(&scratch);
 
-        let elapsed = start.elapsed().as_secs_f64();
-        let mut final_stats = stats;
-        final_stats.duration_secs = elapsed;
+        writer.commit().context("Failed to commit index")?;
+        self.count_file_docs(&mut metadata, &files_indexed)?;
 
-        self.print_stats(&final_stats);
+        metadata.archive = Some(source);
+        metadata.next_doc_id = doc_id_counter.load(Ordering::SeqCst);
+        metadata.total_docs = metadata.files.iter().map(|file| file.doc_count).sum();
+        metadata.save(&self.metadata_path)?;
 
-        Ok(final_stats)
+        let stats = IndexStats {
+            total_docs,
+            total_files: files_indexed.len(),
+            total_bytes: bytes_processed,
+            index_size_bytes: self.get_index_size()?,
+            duration_secs: start.elapsed().as_secs_f64(),
+        };
+        self.print_stats(&stats);
+
+        Ok(stats)
     }
 
-    /// Indexes a list of log locations
+    /// Brings the index up to date with `locations` without printing:
+    /// appended lines are indexed from each file's checkpoint, other changed
+    /// files are reindexed and files that disappeared are dropped
+    pub fn update_locations(&mut self, locations: &[LogLocation]) -> Result<IndexStats> {
+        let metadata = IndexMetadata::load(&self.metadata_path).unwrap_or_default();
+        self.sync_locations(locations, metadata, false)
+    }
+
+    /// Indexes what changed in `locations` since `metadata` was saved, then
+    /// saves the updated metadata
+    fn sync_locations(
+        &mut self,
+        locations: &[LogLocation],
+        mut metadata: IndexMetadata,
+        show_progress: bool,
+    ) -> Result<IndexStats> {
+        let stats = self.index_locations(locations, &mut metadata, show_progress)?;
+
+        if stats.total_files > 0 || metadata.indexed_locations.len() != locations.len() {
+            metadata
+                .indexed_locations
+                .retain(|loc| locations.iter().any(|l| l.path == loc.path));
+            for location in locations {
+                if let Ok(mut loc_meta) = LocationMetadata::from_log_location(location) {
+                    loc_meta.doc_count = metadata.docs_under(&location.path);
+                    metadata.upsert_location(loc_meta);
+                }
+            }
+            metadata.last_indexed = chrono::Utc::now();
+            metadata.update_total_docs();
+            metadata.save(&self.metadata_path)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Indexes the files of a list of log locations that changed since their
+    /// checkpoint in `metadata`, updating the checkpoints
     fn index_locations(
         &mut self,
         locations: &[LogLocation],
+        metadata: &mut IndexMetadata,
         show_progress: bool,
     ) -> Result<IndexStats> {
-        let total_files = locations.len();
-        let total_bytes: u64 = locations.iter().map(|l| l.size_bytes).sum();
+        let total_locations = locations.len();
 
         // Create progress bar
         let pb = if show_progress {
-            let pb = ProgressBar::new(total_files as u64);
+            let pb = ProgressBar::new(total_locations as u64);
             pb.set_style(
                 ProgressStyle::default_bar()
                     .template(
@@ -203,75 +303,46 @@ impl IndexBuilder {
             None
         };
 
-        // Create index writer
-        let mut writer: IndexWriter<TantivyDocument> = self
-            .index
-            .writer(MEMORY_BUDGET_MB * 1_024 * 1_024)
-            .context("Failed to create index writer")?;
+        // Created once something changed; it holds the index lock and threads
+        let mut writer: Option<IndexWriter<TantivyDocument>> = None;
 
         // Track stats
         let doc_counter = Arc::new(AtomicU64::new(0));
-        let doc_id_counter = Arc::new(AtomicU64::new(self.get_current_max_doc_id()? + 1));
+        let doc_id_counter = Arc::new(AtomicU64::new(self.next_doc_id(metadata)?));
 
-        // Parsers chain
-        let parsers: Vec<Box<dyn LogParser>> = vec![
-            Box::new(ClaudeParser),
-            Box::new(ClineParser),
-            Box::new(CursorParser),
-            Box::new(GenericParser),
-        ];
+        let parsers = log_parsers();
 
         let mut bytes_processed = 0u64;
+        let mut files_indexed = Vec::new();
+        let mut all_sources = Vec::new();
 
         for location in locations {
-            // Try each parser
-            let parsed = parsers
-                .iter()
-                .find_map(|parser| {
-                    if parser.can_parse(&location.path) {
-                        parser.parse(&location.path).ok()
-                    } else {
-                        None
-                    }
-                });
-
-            if let Some(parsed_log) = parsed {
-                // Index entries in batches
-                let mut batch = Vec::new();
-
-                for entry in &parsed_log.entries {
-                    let doc_id = doc_id_counter.fetch_add(1, Ordering::SeqCst);
+            for source in source_files(&location.path) {
+                all_sources.push(source.clone());
 
-                    let log_entry_doc = LogEntryDocument::from_log_entry(
-                        entry,
-                        doc_id,
-                        &parsed_log.tool,
-                        &format!("{:?}", location.log_type),
-                        &location.path,
-                    );
-
-                    batch.push(log_entry_doc);
-
-                    // Commit batch
-                    if batch.len() >= BATCH_SIZE {
-                        for doc in &batch {
-                            writer.add_document(doc.to_tantivy_document(&self.schema))?;
-                        }
-                        doc_counter.fetch_add(batch.len() as u64, Ordering::SeqCst);
-                        batch.clear();
-                    }
-                }
+                let previous = metadata.find_file(&source).cloned();
+                let Some(read) = read_source(&parsers, location, &source, previous.as_ref())? else {
+                    continue;
+                };
+                let writer = match writer {
+                    Some(ref writer) => writer,
+                    None => writer.insert(self.writer()?),
+                };
 
-                // Commit remaining
-                if !batch.is_empty() {
-                    for doc in &batch {
-                        writer.add_document(doc.to_tantivy_document(&self.schema))?;
-                    }
-                    doc_counter.fetch_add(batch.len() as u64, Ordering::SeqCst);
-                }
-            }
+                // Drop what was indexed from the lines being read: the whole
+                // file when it is read from the start, otherwise lines after
+                // the checkpoint (left over if the last run stopped before
+                // saving it, or held back for a running tool call)
+                self.delete_from_line(writer, &source, read.first_line)?;
+
+                let log_type = format!("{:?}", location.log_type);
+                let added = self.add_source(writer, &read, &source, &log_type, &doc_id_counter)?;
+                doc_counter.fetch_add(added, Ordering::SeqCst);
 
-            bytes_processed += location.size_bytes;
+                bytes_processed += read.bytes_read;
+                files_indexed.push(source.clone());
+                metadata.upsert_file(read.checkpoint);
+            }
 
             if let Some(ref pb) = pb {
                 pb.inc(1);
@@ -279,53 +350,187 @@ impl IndexBuilder {
             }
         }
 
+        // Files indexed before that no longer exist
+        let removed: Vec<PathBuf> = metadata
+            .files
+            .iter()
+            .filter(|file| !all_sources.contains(&file.path))
+            .map(|file| file.path.clone())
+            .collect();
+        for path in &removed {
+            let writer = match writer {
+                Some(ref writer) => writer,
+                None => writer.insert(self.writer()?),
+            };
+            self.delete_from_line(writer, path, 0)?;
+            metadata.remove_file(path);
+        }
+
         if let Some(pb) = pb {
             pb.finish_with_message("Done!");
         }
 
+        let Some(mut writer) = writer else {
+            return Ok(IndexStats {
+                total_docs: 0,
+                total_files: 0,
+                total_bytes: 0,
+                index_size_bytes: self.get_index_size()?,
+                duration_secs: 0.0,
+            });
+        };
+
         // Commit index
         writer
             .commit()
             .context("Failed to commit index")?;
 
+        self.count_file_docs(metadata, &files_indexed)?;
+        metadata.next_doc_id = doc_id_counter.load(Ordering::SeqCst);
+
         let total_docs = doc_counter.load(Ordering::SeqCst);
         let index_size = self.get_index_size()?;
 
         Ok(IndexStats {
             total_docs,
-            total_files,
-            total_bytes,
+            total_files: files_indexed.len() + removed.len(),
+            total_bytes: bytes_processed,
             index_size_bytes: index_size,
             duration_secs: 0.0, // Set by caller
         })
     }
 
-    /// Deletes documents by file paths (for incremental updates)
-    fn delete_docs_by_paths(&mut self, locations: &[LogLocation]) -> Result<()> {
-        let mut writer: IndexWriter<TantivyDocument> = self
-            .index
-            .writer(MEMORY_BUDGET_MB * 1_024 * 1_024)?;
+    /// Adds the documents of the entries read from `source`: one per entry,
+    /// plus one per code block and tool call. Returns how many were added.
+    fn add_source(
+        &self,
+        writer: &IndexWriter<TantivyDocument>,
+        read: &SourceRead,
+        source: &Path,
+        log_type: &str,
+        doc_id_counter: &AtomicU64,
+    ) -> Result<u64> {
+        // Transcript entries are turns of the conversation named by the file
+        let session_id = claude::is_transcript_path(source)
+            .then(|| source.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .flatten();
 
-        let file_path_field = self.schema.get_field(FIELD_FILE_PATH)?;
+        // Tool calls of a transcript, keyed by the entry that made them
+        let mut tool_calls: HashMap<usize, Vec<&ToolCallRecord>> = HashMap::new();
+        for (index, call) in &read.tool_calls {
+            tool_calls.entry(*index).or_default().push(call);
+        }
 
-        for location in locations {
-            let path_str = location.path.to_string_lossy().to_string();
-            let term = Term::from_field_text(file_path_field, &path_str);
-            writer.delete_term(term);
+        // Index entries in batches
+        let mut added = 0u64;
+        let mut batch = Vec::new();
+
+        for (index, (entry, line)) in read.entries.iter().zip(&read.lines).enumerate() {
+            let doc_id = doc_id_counter.fetch_add(1, Ordering::SeqCst);
+
+            let mut log_entry_doc =
+                LogEntryDocument::from_log_entry(entry, doc_id, &read.tool, log_type, source)
+                    .at_line(*line);
+            if let Some(ref session_id) = session_id {
+                let turn = read.first_turn + index as u64;
+                log_entry_doc = log_entry_doc.with_turn(session_id, turn);
+            }
+
+            // Code the assistant wrote also gets a document per block
+            if matches!(
+                entry.category,
+                EntryCategory::AssistantResponse | EntryCategory::ToolUse
+            ) {
+                for block in extract_code_blocks(&entry.message) {
+                    let doc_id = doc_id_counter.fetch_add(1, Ordering::SeqCst);
+                    batch.push(log_entry_doc.code_block(&block, doc_id));
+                }
+            }
+
+            for call in tool_calls.get(&index).into_iter().flatten() {
+                let doc_id = doc_id_counter.fetch_add(1, Ordering::SeqCst);
+                batch.push(log_entry_doc.tool_call(call, doc_id));
+            }
+
+            batch.push(log_entry_doc);
+
+            // Commit batch
+            if batch.len() >= BATCH_SIZE {
+                for doc in &batch {
+                    writer.add_document(doc.to_tantivy_document(&self.schema))?;
+                }
+                added += batch.len() as u64;
+                batch.clear();
+            }
         }
 
-        writer.commit()?;
+        // Commit remaining
+        for doc in &batch {
+            writer.add_document(doc.to_tantivy_document(&self.schema))?;
+        }
+        added += batch.len() as u64;
+
+        Ok(added)
+    }
+
+    /// Records in each file's checkpoint how many documents it now has in
+    /// the committed index
+    fn count_file_docs(&self, metadata: &mut IndexMetadata, files: &[PathBuf]) -> Result<()> {
+        let searcher = self.index.reader()?.searcher();
+        let file_path_field = self.schema.get_field(FIELD_FILE_PATH)?;
+        for path in files {
+            let term = Term::from_field_text(file_path_field, &path.to_string_lossy());
+            let count = searcher.search(&TermQuery::new(term, IndexRecordOption::Basic), &Count)?;
+            if let Some(checkpoint) = metadata.files.iter_mut().find(|f| &f.path == path) {
+                checkpoint.doc_count = count as u64;
+            }
+        }
         Ok(())
     }
 
-    /// Gets the current maximum doc_id in the index
-    fn get_current_max_doc_id(&self) -> Result<u64> {
-        let reader = self.index.reader()?;
-        let searcher = reader.searcher();
+    fn writer(&self) -> Result<IndexWriter<TantivyDocument>> {
+        self.index
+            .writer(MEMORY_BUDGET_MB * 1_024 * 1_024)
+            .context("Failed to create index writer")
+    }
+
+    /// Deletes the documents of a source file from `line` on; line 0 deletes all
+    fn delete_from_line(
+        &self,
+        writer: &IndexWriter<TantivyDocument>,
+        path: &Path,
+        line: u64,
+    ) -> Result<()> {
+        let file_path_field = self.schema.get_field(FIELD_FILE_PATH)?;
+        let term = Term::from_field_text(file_path_field, &path.to_string_lossy());
 
-        // Simple approach: return the number of documents
-        // This assumes doc_ids are sequential
-        Ok(searcher.num_docs())
+        if line == 0 {
+            writer.delete_term(term);
+        } else {
+            let lines = BooleanQuery::new(vec![
+                (
+                    Occur::Must,
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>,
+                ),
+                (
+                    Occur::Must,
+                    Box::new(RangeQuery::new_u64_bounds(
+                        FIELD_LINE.to_string(),
+                        Bound::Included(line),
+                        Bound::Unbounded,
+                    )),
+                ),
+            ]);
+            writer.delete_query(Box::new(lines))?;
+        }
+        Ok(())
+    }
+
+    /// Id for the next document: the one recorded in `metadata`, or past the
+    /// highest in the index when the metadata predates recording it
+    fn next_doc_id(&self, metadata: &IndexMetadata) -> Result<u64> {
+        let max = max_doc_id(&self.index.reader()?.searcher())?;
+        Ok(metadata.next_doc_id.max(max + 1))
     }
 
     /// Gets the size of the index on disk
@@ -357,6 +562,325 @@ impl IndexBuilder {
     }
 }
 
+/// Parsers in the order they are tried; the generic one accepts anything
+fn log_parsers() -> Vec<Box<dyn LogParser>> {
+    vec![
+        Box::new(ClaudeParser),
+        Box::new(ClineParser),
+        Box::new(CursorParser),
+        Box::new(GenericParser),
+    ]
+}
+
+/// Runs log discovery over the home directory
+pub(crate) fn discover_logs() -> Result<DiscoveryFindings> {
+    let home_dir = dirs::home_dir().context("Could not determine home directory")?;
+    LogDiscovery::new(home_dir, true).scan()
+}
+
+/// Reads what changed in `source` since its checkpoint: the appended lines of
+/// a line-oriented file, or the whole file otherwise. `None` when nothing
+/// changed or no parser accepts it.
+fn read_source(
+    parsers: &[Box<dyn LogParser>],
+    location: &LogLocation,
+    source: &Path,
+    previous: Option<&FileCheckpoint>,
+) -> Result<Option<SourceRead>> {
+    let Ok(file_meta) = fs::metadata(source) else {
+        return Ok(None);
+    };
+    let size = if file_meta.is_dir() {
+        dir_size(source)
+    } else {
+        file_meta.len()
+    };
+    let last_modified: DateTime<Utc> = file_meta.modified()?.into();
+
+    if previous.is_some_and(|cp| cp.size_bytes == size && cp.last_modified == last_modified) {
+        return Ok(None);
+    }
+
+    let checkpoint = FileCheckpoint {
+        path: source.to_path_buf(),
+        size_bytes: size,
+        last_modified,
+        offset: 0,
+        line: 0,
+        turn: 0,
+        tail_hash: String::new(),
+        doc_count: 0, // Counted once the index is committed
+    };
+
+    if let Some(parser) = parsers.iter().find(|parser| parser.can_parse(source)) {
+        if parser.parses_lines(source) {
+            // Resume at the checkpoint only if the file was appended to, not rewritten
+            let resume = previous.filter(|cp| {
+                cp.offset <= size
+                    && tail_hash(source, cp.offset).is_ok_and(|hash| hash == cp.tail_hash)
+            });
+            return read_lines(parser.as_ref(), location, source, resume, checkpoint).map(Some);
+        }
+    }
+
+    // Try each parser
+    let parsed = parsers.iter().find_map(|parser| {
+        if parser.can_parse(source) {
+            parser.parse(source).ok()
+        } else {
+            None
+        }
+    });
+    let Some(parsed) = parsed else {
+        return Ok(None);
+    };
+
+    Ok(Some(SourceRead {
+        tool: parsed.tool,
+        lines: (0..parsed.entries.len() as u64).collect(),
+        entries: parsed.entries,
+        first_line: 0,
+        first_turn: 0,
+        tool_calls: Vec::new(),
+        checkpoint,
+        bytes_read: size,
+    }))
+}
+
+/// Parses the complete lines of `source` after `resume`, or all of them.
+/// A tool call still waiting for its result holds the checkpoint back at
+/// its line, so the call is indexed again once the result is logged. Calls
+/// the conversation moved on from, or that waited longer than
+/// `PENDING_TOOL_CALL_SECS`, are indexed without a result instead.
+fn read_lines(
+    parser: &dyn LogParser,
+    location: &LogLocation,
+    source: &Path,
+    resume: Option<&FileCheckpoint>,
+    mut checkpoint: FileCheckpoint,
+) -> Result<SourceRead> {
+    let (offset, first_line, first_turn) =
+        resume.map_or((0, 0, 0), |cp| (cp.offset, cp.line, cp.turn));
+
+    let mut file = File::open(source)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+
+    // A last line without a newline may still be being written
+    let complete = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+
+    let ParsedLines {
+        entries,
+        lines,
+        line_starts,
+        end_offset,
+        end_line,
+    } = parse_lines(parser, source, &bytes[..complete], offset, first_line);
+
+    checkpoint.offset = end_offset;
+    checkpoint.line = end_line;
+    checkpoint.turn = first_turn + entries.len() as u64;
+
+    let tool_calls = if claude::is_transcript(source) {
+        let content = String::from_utf8_lossy(&bytes[..complete]);
+        let calls = tool_calls_by_turn(&content, &entries);
+        let now = Utc::now();
+        let pending = calls.iter().find(|(index, call)| {
+            call.exit_status.is_none() && !abandoned(&entries, *index, now)
+        });
+        if let Some(&(index, _)) = pending {
+            checkpoint.offset = line_starts[index];
+            checkpoint.line = lines[index];
+            checkpoint.turn = first_turn + index as u64;
+        }
+        calls
+    } else {
+        Vec::new()
+    };
+    checkpoint.tail_hash = tail_hash(source, checkpoint.offset)?;
+
+    Ok(SourceRead {
+        tool: location.tool.clone(),
+        entries,
+        lines,
+        first_line,
+        first_turn,
+        tool_calls,
+        checkpoint,
+        bytes_read: complete as u64,
+    })
+}
+
+/// Whether the tool call at `entries[index]` will never get a result: a
+/// later prompt or reply means the session moved on (results and parallel
+/// calls are tool-only entries), and a call that is too old was interrupted
+fn abandoned(entries: &[LogEntry], index: usize, now: DateTime<Utc>) -> bool {
+    let moved_on = entries[index + 1..].iter().any(|e| {
+        matches!(
+            e.category,
+            EntryCategory::UserPrompt | EntryCategory::AssistantResponse
+        )
+    });
+    let expired = entries[index]
+        .timestamp
+        .is_some_and(|t| (now - t).num_seconds() > PENDING_TOOL_CALL_SECS);
+    moved_on || expired
+}
+
+/// Parses a log read from a backup archive, `path` being where it sits
+/// inside the archive. Line-oriented logs are parsed from memory; others are
+/// written to `scratch` for their parser, which reads from disk. `None` when
+/// no parser accepts it.
+fn read_archive_member(
+    parsers: &[Box<dyn LogParser>],
+    member: &ArchiveMember,
+    path: &Path,
+    bytes: &[u8],
+    modified: DateTime<Utc>,
+    scratch: &Path,
+) -> Result<Option<SourceRead>> {
+    // Single files are archived under their tool's name alone, so the tool
+    // chooses their parser rather than their path
+    let parser: &dyn LogParser = match (&member.tool, member.single_file) {
+        (AiTool::ClaudeCode, true) => &ClaudeParser,
+        (AiTool::Cline, true) => &ClineParser,
+        (AiTool::Cursor, true) => &CursorParser,
+        _ => match parsers.iter().find(|parser| parser.can_parse(&member.source)) {
+            Some(parser) => parser.as_ref(),
+            None => return Ok(None),
+        },
+    };
+
+    let mut checkpoint = FileCheckpoint {
+        path: path.to_path_buf(),
+        size_bytes: bytes.len() as u64,
+        last_modified: modified,
+        offset: bytes.len() as u64,
+        line: 0,
+        turn: 0,
+        tail_hash: String::new(), // Archived logs are never resumed
+        doc_count: 0,             // Counted once the index is committed
+    };
+
+    if parser.parses_lines(path) {
+        let parsed = parse_lines(parser, path, bytes, 0, 0);
+        checkpoint.line = parsed.end_line;
+        checkpoint.turn = parsed.entries.len() as u64;
+
+        let tool_calls = if claude::is_transcript_path(path) {
+            tool_calls_by_turn(&String::from_utf8_lossy(bytes), &parsed.entries)
+        } else {
+            Vec::new()
+        };
+        return Ok(Some(SourceRead {
+            tool: member.tool.clone(),
+            entries: parsed.entries,
+            lines: parsed.lines,
+            first_line: 0,
+            first_turn: 0,
+            tool_calls,
+            checkpoint,
+            bytes_read: bytes.len() as u64,
+        }));
+    }
+
+    // Keep the path under home, which some parsers look at
+    let file = scratch.join(&member.source);
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&file, bytes)?;
+    let parsed = parser.parse(&file);
+    fs::remove_file(&file)?;
+    let Ok(parsed) = parsed else {
+        return Ok(None);
+    };
+
+    Ok(Some(SourceRead {
+        tool: parsed.tool,
+        lines: (0..parsed.entries.len() as u64).collect(),
+        entries: parsed.entries,
+        first_line: 0,
+        first_turn: 0,
+        tool_calls: Vec::new(),
+        checkpoint,
+        bytes_read: bytes.len() as u64,
+    }))
+}
+
+/// Entries parsed from lines of a source
+struct ParsedLines {
+    entries: Vec<LogEntry>,
+    /// Line of the source each entry was parsed from
+    lines: Vec<u64>,
+    /// Offset of the line each entry was parsed from
+    line_starts: Vec<u64>,
+    /// Offset just past the last line
+    end_offset: u64,
+    /// Number of lines before `end_offset`
+    end_line: u64,
+}
+
+/// Parses each line of `bytes`, which start at `offset` and line
+/// `first_line` of `source`
+fn parse_lines(
+    parser: &dyn LogParser,
+    source: &Path,
+    bytes: &[u8],
+    offset: u64,
+    first_line: u64,
+) -> ParsedLines {
+    let mut parsed = ParsedLines {
+        entries: Vec::new(),
+        lines: Vec::new(),
+        line_starts: Vec::new(),
+        end_offset: offset,
+        end_line: first_line,
+    };
+    for raw in bytes.split_inclusive(|&b| b == b'\n') {
+        if let Some(entry) = parser.parse_line(source, String::from_utf8_lossy(raw).trim_end()) {
+            parsed.entries.push(entry);
+            parsed.lines.push(parsed.end_line);
+            parsed.line_starts.push(parsed.end_offset);
+        }
+        parsed.end_offset += raw.len() as u64;
+        parsed.end_line += 1;
+    }
+    parsed
+}
+
+/// Total size of the files below a directory
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+
+/// Files to parse for a location: each transcript of a conversation
+/// directory separately, otherwise the location itself
+fn source_files(path: &Path) -> Vec<PathBuf> {
+    if path.is_dir() {
+        let mut transcripts: Vec<PathBuf> = walkdir::WalkDir::new(path)
+            .max_depth(2)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| claude::is_transcript(p))
+            .collect();
+        if !transcripts.is_empty() {
+            transcripts.sort();
+            return transcripts;
+        }
+    }
+    vec![path.to_path_buf()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -374,4 +898,169 @@ mod tests {
         assert!(index_dir.exists());
         assert!(index_dir.join("meta.json").exists());
     }
+
+    #[test]
+    fn test_incremental_updates() {
+        use crate::search::query_executor::{QueryExecutor, SearchQuery};
+        use serde_json::json;
+        use std::io::Write;
+
+        let dir = tempdir().unwrap();
+        let projects = dir.path().join(".claude/projects");
+        let transcript = projects.join("-repo/s1.jsonl");
+        fs::create_dir_all(transcript.parent().unwrap()).unwrap();
+
+        let line = |value: serde_json::Value| format!("{}\n", value);
+        let prompt = |ts: &str, text: &str| {
+            line(json!({"type": "user", "timestamp": ts,
+                "message": {"role": "user", "content": text}}))
+        };
+        let append = |text: &str| {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&transcript)
+                .unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        let location = LogLocation {
+            tool: AiTool::ClaudeCode,
+            path: projects.clone(),
+            log_type: LogType::Session,
+            size_bytes: 0,
+            file_count: 1,
+            oldest_entry: None,
+            newest_entry: None,
+        };
+        let index_dir = dir.path().join("index");
+        let mut builder = IndexBuilder::new(&index_dir).unwrap();
+
+        let search = |query: SearchQuery| {
+            QueryExecutor::new(&index_dir)
+                .unwrap()
+                .execute(&query)
+                .unwrap()
+                .results
+        };
+        let all = || search(SearchQuery::default());
+        let metadata = || IndexMetadata::load(&index_dir.join("metadata.json")).unwrap();
+
+        append(&prompt("2026-05-01T10:00:00Z", "first question"));
+        let stats = builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert_eq!((stats.total_files, stats.total_docs), (1, 1));
+
+        // Nothing new: nothing read
+        let stats = builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert_eq!(stats.total_files, 0);
+
+        // Appended lines are indexed after the first, continuing the turns;
+        // a line still being written waits for its newline
+        append(&prompt("2026-05-01T10:01:00Z", "second question"));
+        append("{\"type\": \"user\"");
+        let stats = builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert_eq!(stats.total_docs, 1);
+        let mut turns: Vec<(u64, u64)> = all().iter().map(|r| (r.turn, r.doc_id)).collect();
+        turns.sort();
+        assert_eq!(turns.len(), 2);
+        assert_eq!(turns[1].0, 1);
+        assert_ne!(turns[0].1, turns[1].1);
+
+        // Finish the partial line (as a blank one) and start a tool call
+        append("}\n");
+        let recent = |secs_ago: i64| (Utc::now() - chrono::Duration::seconds(secs_ago)).to_rfc3339();
+        append(&line(json!({"type": "assistant", "timestamp": recent(60),
+            "message": {"role": "assistant", "content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "cargo test"}}]}})));
+        builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        let bash = || {
+            search(SearchQuery {
+                tool_name: Some("Bash".to_string()),
+                ..Default::default()
+            })
+        };
+        assert_eq!(bash().len(), 1);
+        assert_eq!(bash()[0].exit_status, None);
+
+        // The result arrives: the call is indexed again with its outcome, once
+        append(&line(json!({"type": "user", "timestamp": recent(30),
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "Exit code 101\nfailed", "is_error": true}]}})));
+        builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert_eq!(bash().len(), 1);
+        assert_eq!(bash()[0].exit_status, Some(101));
+        assert_eq!(all().len(), 5);
+        assert_eq!(metadata().total_docs, 5);
+        assert_eq!(metadata().find_file(&transcript).unwrap().doc_count, 5);
+
+        // A rewritten file is indexed again from the start, with ids that
+        // were never handed out before
+        let next_doc_id = metadata().next_doc_id;
+        fs::write(&transcript, prompt("2026-05-02T09:00:00Z", "compacted")).unwrap();
+        builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        let results = all();
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].message.as_str(), results[0].turn), ("compacted", 0));
+        assert_eq!(results[0].doc_id, next_doc_id);
+        assert_eq!(metadata().next_doc_id, next_doc_id + 1);
+        assert_eq!(metadata().total_docs, 1);
+
+        // Deleted files lose their documents
+        fs::remove_file(&transcript).unwrap();
+        builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert!(all().is_empty());
+        assert!(metadata().find_file(&transcript).is_none());
+        assert_eq!(metadata().total_docs, 0);
+    }
+
+    #[test]
+    fn test_unanswered_tool_call_releases_checkpoint() {
+        use serde_json::json;
+
+        let dir = tempdir().unwrap();
+        let projects = dir.path().join(".claude/projects");
+        let transcript = projects.join("-repo/s1.jsonl");
+        fs::create_dir_all(transcript.parent().unwrap()).unwrap();
+
+        let location = LogLocation {
+            tool: AiTool::ClaudeCode,
+            path: projects.clone(),
+            log_type: LogType::Session,
+            size_bytes: 0,
+            file_count: 1,
+            oldest_entry: None,
+            newest_entry: None,
+        };
+        let index_dir = dir.path().join("index");
+        let mut builder = IndexBuilder::new(&index_dir).unwrap();
+        let recent = |secs_ago: i64| (Utc::now() - chrono::Duration::seconds(secs_ago)).to_rfc3339();
+        let tool_use = |ts: String| {
+            json!({"type": "assistant", "timestamp": ts, "message": {"role": "assistant",
+                "content": [{"type": "tool_use", "id": "t1", "name": "Bash", "input": {}}]}})
+        };
+        let prompt = |ts: String| {
+            json!({"type": "user", "timestamp": ts,
+                "message": {"role": "user", "content": "never mind"}})
+        };
+        // Whether the checkpoint reached the end of the file after updating
+        let update = |builder: &mut IndexBuilder, lines: &[serde_json::Value]| {
+            let content: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+            fs::write(&transcript, content).unwrap();
+            builder.update_locations(std::slice::from_ref(&location)).unwrap();
+            let metadata = IndexMetadata::load(&index_dir.join("metadata.json")).unwrap();
+            let offset = metadata.find_file(&transcript).unwrap().offset;
+            offset == fs::metadata(&transcript).unwrap().len()
+        };
+
+        // A recent call with nothing after it may still get its result
+        assert!(!update(&mut builder, &[tool_use(recent(10))]));
+
+        // The session moved on without a result: the call is indexed as is
+        assert!(update(&mut builder, &[tool_use(recent(10)), prompt(recent(5))]));
+        let stats = builder.update_locations(std::slice::from_ref(&location)).unwrap();
+        assert_eq!(stats.total_files, 0);
+
+        // A call that waited too long is not held back either
+        assert!(update(&mut builder, &[tool_use(recent(2 * PENDING_TOOL_CALL_SECS))]));
+    }
 }
@@ -2,13 +2,14 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use std::fs::{self, File};
-use std::io::{Read, BufReader};
+use std::io::{Read, BufReader, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 
 use crate::models::LogLocation;
 
+use super::schema::SCHEMA_VERSION;
+
 /// Metadata about the search index
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexMetadata {
@@ -24,6 +25,23 @@ pub struct IndexMetadata {
     /// Total number of documents in the index
     pub total_docs: u64,
 
+    /// Id the next indexed document gets. Ids are never handed out twice,
+    /// even when the documents with the highest ids were deleted.
+    #[serde(default)]
+    pub next_doc_id: u64,
+
+    /// How far each source file has been indexed
+    #[serde(default)]
+    pub files: Vec<FileCheckpoint>,
+
+    /// Documents each alert rule has already reported on
+    #[serde(default)]
+    pub alerts: Vec<AlertCheckpoint>,
+
+    /// Backup archive the index was built from, instead of the live logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<ArchiveSource>,
+
     /// Whether semantic search is enabled
     pub semantic_enabled: bool,
 }
@@ -34,7 +52,7 @@ pub struct LocationMetadata {
     /// File path
     pub path: PathBuf,
 
-    /// Content hash (MD5 of first 1MB + last modified time)
+    /// Content hash (MD5 of first 1MB + last modified time); empty for directories
     pub hash: String,
 
     /// File size in bytes
@@ -47,18 +65,165 @@ pub struct LocationMetadata {
     pub doc_count: u64,
 }
 
+/// Indexing progress of a single source file. Line-oriented files (JSONL)
+/// are only appended to, so updates resume at `offset`; other sources are
+/// reindexed whole when their size or modification time changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    /// File path
+    pub path: PathBuf,
+
+    /// File size in bytes when last indexed
+    pub size_bytes: u64,
+
+    /// Last modified timestamp when last indexed
+    pub last_modified: DateTime<Utc>,
+
+    /// Bytes indexed so far; always the start of a line
+    pub offset: u64,
+
+    /// Number of lines before `offset`
+    pub line: u64,
+
+    /// Number of entries before `offset`; conversation turns continue from here
+    pub turn: u64,
+
+    /// MD5 of the bytes just before `offset`, telling an append from a rewrite
+    pub tail_hash: String,
+
+    /// Number of documents indexed from this file
+    pub doc_count: u64,
+}
+
+/// Progress of an alert rule. Hits with a higher id than `last_doc_id` were
+/// indexed after the last check, but lines read again (after a held tool
+/// call, or after the last run stopped early) get new ids too, so hits
+/// already reported from a line are kept in `seen` while it may be reread.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertCheckpoint {
+    /// Name of the saved search
+    pub name: String,
+
+    /// Highest document id handed out at the last check
+    pub last_doc_id: u64,
+
+    /// Lines reported on that may still be indexed again
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seen: Vec<AlertLine>,
+
+    /// When the rule was last checked
+    pub checked_at: DateTime<Utc>,
+}
+
+/// A source line an alert rule has reported on
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AlertLine {
+    pub file_path: String,
+    pub line: u64,
+}
+
+/// A backup archive an index was built from. Archives are not appended to,
+/// so a different size or modification time means the index must be rebuilt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchiveSource {
+    /// Archive path
+    pub path: PathBuf,
+
+    /// Archive size in bytes when indexed
+    pub size_bytes: u64,
+
+    /// Last modified timestamp when indexed
+    pub last_modified: DateTime<Utc>,
+}
+
+impl ArchiveSource {
+    /// Describes the archive at `path` as it is now
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)
+            .with_context(|| format!("Failed to read archive {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            size_bytes: metadata.len(),
+            last_modified: metadata.modified()?.into(),
+        })
+    }
+}
+
 impl IndexMetadata {
     /// Creates new empty metadata
     pub fn new() -> Self {
         Self {
-            version: "1.0".to_string(),
+            version: SCHEMA_VERSION.to_string(),
             last_indexed: Utc::now(),
             indexed_locations: Vec::new(),
             total_docs: 0,
+            next_doc_id: 0,
+            files: Vec::new(),
+            alerts: Vec::new(),
+            archive: None,
             semantic_enabled: false,
         }
     }
 
+    /// Finds a file checkpoint by path
+    pub fn find_file(&self, path: &Path) -> Option<&FileCheckpoint> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    /// Updates or adds a file checkpoint
+    pub fn upsert_file(&mut self, checkpoint: FileCheckpoint) {
+        if let Some(pos) = self.files.iter().position(|file| file.path == checkpoint.path) {
+            self.files[pos] = checkpoint;
+        } else {
+            self.files.push(checkpoint);
+        }
+    }
+
+    /// Removes a file checkpoint by path
+    pub fn remove_file(&mut self, path: &Path) {
+        self.files.retain(|file| file.path != path);
+    }
+
+    /// Number of documents indexed from files at or below `path`
+    pub fn docs_under(&self, path: &Path) -> u64 {
+        self.files
+            .iter()
+            .filter(|file| file.path.starts_with(path))
+            .map(|file| file.doc_count)
+            .sum()
+    }
+
+    /// Finds an alert rule's checkpoint by name
+    pub fn find_alert(&self, name: &str) -> Option<&AlertCheckpoint> {
+        self.alerts.iter().find(|alert| alert.name == name)
+    }
+
+    /// Records that an alert rule has seen every document up to `last_doc_id`
+    /// and the lines in `seen`. Lines before their file's checkpoint are
+    /// never read again, so they are dropped.
+    pub fn record_alert(
+        &mut self,
+        name: &str,
+        last_doc_id: u64,
+        mut seen: Vec<AlertLine>,
+        checked_at: DateTime<Utc>,
+    ) {
+        seen.retain(|hit| {
+            self.find_file(Path::new(&hit.file_path))
+                .is_some_and(|file| hit.line >= file.line)
+        });
+        let checkpoint = AlertCheckpoint {
+            name: name.to_string(),
+            last_doc_id,
+            seen,
+            checked_at,
+        };
+        match self.alerts.iter_mut().find(|alert| alert.name == name) {
+            Some(existing) => *existing = checkpoint,
+            None => self.alerts.push(checkpoint),
+        }
+    }
+
     /// Loads metadata from a file
     pub fn load(path: &Path) -> Result<Self> {
         let file = File::open(path).context("Failed to open metadata file")?;
@@ -75,8 +240,11 @@ impl IndexMetadata {
             fs::create_dir_all(parent)?;
         }
 
-        let file = File::create(path).context("Failed to create metadata file")?;
+        // Written aside and renamed, so a reader never sees half a file
+        let tmp_path = path.with_extension("json.tmp");
+        let file = File::create(&tmp_path).context("Failed to create metadata file")?;
         serde_json::to_writer_pretty(file, self).context("Failed to write metadata JSON")?;
+        fs::rename(&tmp_path, path).context("Failed to replace metadata file")?;
         Ok(())
     }
 
@@ -120,7 +288,12 @@ impl Default for IndexMetadata {
 impl LocationMetadata {
     /// Creates metadata from a LogLocation
     pub fn from_log_location(location: &LogLocation) -> Result<Self> {
-        let hash = compute_location_hash(&location.path)?;
+        // Directories are tracked through the checkpoints of their files
+        let hash = if location.path.is_dir() {
+            String::new()
+        } else {
+            compute_location_hash(&location.path)?
+        };
         let last_modified = fs::metadata(&location.path)?
             .modified()?
             .into();
@@ -133,20 +306,6 @@ impl LocationMetadata {
             doc_count: 0,  // Will be updated during indexing
         })
     }
-
-    /// Checks if this location has changed compared to current state
-    pub fn has_changed(&self) -> Result<bool> {
-        // Check if file still exists
-        if !self.path.exists() {
-            return Ok(true);
-        }
-
-        // Compute current hash
-        let current_hash = compute_location_hash(&self.path)?;
-
-        // Compare hashes
-        Ok(current_hash != self.hash)
-    }
 }
 
 /// Computes a hash for a log location to detect changes
@@ -173,35 +332,20 @@ pub fn compute_location_hash(path: &Path) -> Result<String> {
     Ok(format!("{:x}", result))
 }
 
-/// Detects which locations have changed since the last index
-pub fn detect_changes(
-    metadata: &IndexMetadata,
-    discovered_locations: &[LogLocation],
-) -> Result<Vec<LogLocation>> {
-    let mut changed = Vec::new();
-
-    // Build a map of existing locations for fast lookup
-    let existing: HashMap<PathBuf, &LocationMetadata> = metadata
-        .indexed_locations
-        .iter()
-        .map(|loc| (loc.path.clone(), loc))
-        .collect();
-
-    for location in discovered_locations {
-        let needs_reindex = if let Some(existing_loc) = existing.get(&location.path) {
-            // Check if it has changed
-            existing_loc.has_changed().unwrap_or(true)
-        } else {
-            // New location
-            true
-        };
-
-        if needs_reindex {
-            changed.push(location.clone());
-        }
+/// MD5 of up to 4KB of `path` ending at `offset`; unchanged as long as the
+/// file is only appended to
+pub fn tail_hash(path: &Path, offset: u64) -> Result<String> {
+    let start = offset.saturating_sub(4_096);
+    let mut file = File::open(path).context("Failed to open file for hashing")?;
+    file.seek(SeekFrom::Start(start))?;
+
+    let mut buffer = Vec::new();
+    file.take(offset - start).read_to_end(&mut buffer)?;
+    if (buffer.len() as u64) < offset - start {
+        anyhow::bail!("{} is shorter than {} bytes", path.display(), offset);
     }
 
-    Ok(changed)
+    Ok(format!("{:x}", Md5::digest(&buffer)))
 }
 
 #[cfg(test)]
@@ -213,7 +357,7 @@ mod tests {
     #[test]
     fn test_metadata_new() {
         let metadata = IndexMetadata::new();
-        assert_eq!(metadata.version, "1.0");
+        assert_eq!(metadata.version, SCHEMA_VERSION);
         assert_eq!(metadata.total_docs, 0);
         assert_eq!(metadata.indexed_locations.len(), 0);
         assert!(!metadata.semantic_enabled);
@@ -313,4 +457,74 @@ mod tests {
         metadata.update_total_docs();
         assert_eq!(metadata.total_docs, 125);
     }
+
+    #[test]
+    fn test_tail_hash_survives_appends() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("session.jsonl");
+        fs::write(&file_path, "{\"a\":1}\n").unwrap();
+
+        let offset = fs::metadata(&file_path).unwrap().len();
+        let hash = tail_hash(&file_path, offset).unwrap();
+
+        // Appending keeps the bytes before the checkpoint
+        let mut file = fs::OpenOptions::new().append(true).open(&file_path).unwrap();
+        file.write_all(b"{\"b\":2}\n").unwrap();
+        drop(file);
+        assert_eq!(tail_hash(&file_path, offset).unwrap(), hash);
+
+        // Rewriting does not
+        fs::write(&file_path, "{\"c\":3}\n{\"b\":2}\n").unwrap();
+        assert_ne!(tail_hash(&file_path, offset).unwrap(), hash);
+
+        // Truncating below the checkpoint is an error
+        fs::write(&file_path, "{}").unwrap();
+        assert!(tail_hash(&file_path, offset).is_err());
+    }
+
+    #[test]
+    fn test_file_checkpoints() {
+        let mut metadata = IndexMetadata::new();
+        let checkpoint = |path: &str, doc_count| FileCheckpoint {
+            path: PathBuf::from(path),
+            size_bytes: 10,
+            last_modified: Utc::now(),
+            offset: 10,
+            line: 1,
+            turn: 1,
+            tail_hash: String::new(),
+            doc_count,
+        };
+
+        metadata.upsert_file(checkpoint("/logs/projects/a/1.jsonl", 3));
+        metadata.upsert_file(checkpoint("/logs/projects/b/2.jsonl", 4));
+        metadata.upsert_file(checkpoint("/logs/history.jsonl", 5));
+        metadata.upsert_file(checkpoint("/logs/projects/a/1.jsonl", 6));
+
+        assert_eq!(metadata.files.len(), 3);
+        assert_eq!(metadata.docs_under(Path::new("/logs/projects")), 10);
+        assert_eq!(metadata.docs_under(Path::new("/logs/history.jsonl")), 5);
+
+        metadata.remove_file(Path::new("/logs/history.jsonl"));
+        assert!(metadata.find_file(Path::new("/logs/history.jsonl")).is_none());
+    }
+
+    #[test]
+    fn test_alert_checkpoints_persist() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.json");
+
+        let mut metadata = IndexMetadata::new();
+        metadata.record_alert("rate-limits", 10, Vec::new(), Utc::now());
+        metadata.record_alert("tool-errors", 12, Vec::new(), Utc::now());
+        metadata.record_alert("rate-limits", 15, Vec::new(), Utc::now());
+        metadata.next_doc_id = 16;
+        metadata.save(&path).unwrap();
+
+        let loaded = IndexMetadata::load(&path).unwrap();
+        assert_eq!(loaded.next_doc_id, 16);
+        assert_eq!(loaded.alerts.len(), 2);
+        assert_eq!(loaded.find_alert("rate-limits").unwrap().last_doc_id, 15);
+        assert!(loaded.find_alert("unknown").is_none());
+    }
 }
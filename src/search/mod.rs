@@ -4,6 +4,15 @@ pub mod schema;
 pub mod metadata;
 pub mod index_builder;
 pub mod query_executor;
+pub mod aggregations;
+pub mod archive;
+pub mod embedder;
+pub mod code;
+pub mod tool_calls;
+pub mod vector_index;
+pub mod watcher;
+pub mod tui;
+pub mod saved;
 pub mod cli;
 
 // Re-exports
@@ -2,13 +2,31 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, NaiveDate, Utc};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Bound;
 use std::path::Path;
-use tantivy::collector::TopDocs;
-use tantivy::query::{BooleanQuery, Occur, Query, QueryParser, RegexQuery, TermQuery};
+use tantivy::collector::{Count, FacetCollector, TopDocs};
+use tantivy::query::{
+    AllQuery, BooleanQuery, FuzzyTermQuery, Occur, Query, QueryParser, RangeQuery, RegexQuery,
+    TermQuery, TermSetQuery,
+};
 use tantivy::schema::*;
-use tantivy::{Index, TantivyDocument};
+use tantivy::{DocAddress, Index, Order, Searcher, TantivyDocument};
 
+use super::code::normalize_language;
+use super::embedder::TextEmbedder;
 use super::schema::*;
+use super::tool_calls::normalize_path;
+use super::vector_index::VectorIndex;
+
+/// Reciprocal rank fusion constant; larger values flatten the advantage of top ranks
+const RRF_K: f32 = 60.0;
+
+/// Fuzzy words at least this long tolerate one typo; shorter ones must match exactly
+const FUZZY_TYPO_MIN_LEN: usize = 5;
+
+/// Most turns loaded for one conversation
+const MAX_CONVERSATION_TURNS: u64 = 10_000;
 
 /// Search query parameters
 #[derive(Debug, Clone)]
@@ -31,6 +49,25 @@ pub struct SearchQuery {
     /// Filter by project
     pub project: Option<String>,
 
+    /// Only code blocks in this language
+    pub language: Option<String>,
+
+    /// Only code blocks using this identifier or identifier part
+    pub symbol: Option<String>,
+
+    /// Only tool calls of this tool (case-insensitive)
+    pub tool_name: Option<String>,
+
+    /// Only tool calls and code blocks touching this file; a relative path
+    /// or file name matches any absolute path ending with it
+    pub file: Option<String>,
+
+    /// Only tool calls that ended this way
+    pub exit_status: Option<ExitStatusFilter>,
+
+    /// Only documents indexed after the one with this id
+    pub after_doc_id: Option<u64>,
+
     /// Start date (inclusive)
     pub from_date: Option<DateTime<Utc>>,
 
@@ -40,16 +77,29 @@ pub struct SearchQuery {
     /// Use regex matching
     pub regex: bool,
 
+    /// Match every word as a prefix, allowing a typo in longer words
+    pub fuzzy: bool,
+
+    /// Only documents with these `(dimension, value)` facets; values of the
+    /// same dimension are alternatives, dimensions must all match
+    pub facets: Vec<(String, String)>,
+
     /// Maximum number of results
     pub limit: usize,
 
     /// Offset for pagination
     pub offset: usize,
 
+    /// Lexical, semantic or hybrid matching
+    pub mode: SearchMode,
+
+    /// Result ordering
+    pub sort: SortOrder,
+
     /// Output format
     pub format: OutputFormat,
 
-    /// Number of context lines before/after match
+    /// Number of conversation turns to show before/after each match
     pub context: usize,
 }
 
@@ -60,6 +110,71 @@ pub enum OutputFormat {
     Markdown,
 }
 
+/// How the query text is matched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Full-text match on words
+    Lexical,
+    /// Nearest neighbours of the query embedding
+    Semantic,
+    /// Lexical and semantic rankings fused
+    Hybrid,
+}
+
+/// How results are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Best match first
+    Relevance,
+    /// Most recent first
+    Newest,
+    /// Oldest first
+    Oldest,
+}
+
+impl std::str::FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "relevance" | "score" => Ok(SortOrder::Relevance),
+            "newest" | "time" | "desc" => Ok(SortOrder::Newest),
+            "oldest" | "asc" => Ok(SortOrder::Oldest),
+            other => anyhow::bail!("Unknown sort order: {} (expected relevance, newest or oldest)", other),
+        }
+    }
+}
+
+/// Which tool call outcomes to match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatusFilter {
+    /// Exactly this exit code
+    Code(i64),
+    /// Exit code 0
+    Success,
+    /// Any nonzero exit code
+    Failure,
+}
+
+impl std::str::FromStr for ExitStatusFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "ok" | "success" => Ok(ExitStatusFilter::Success),
+            "error" | "failed" | "failure" | "nonzero" => Ok(ExitStatusFilter::Failure),
+            other => match other.parse::<i64>() {
+                Ok(0) => Ok(ExitStatusFilter::Success),
+                Ok(code) => Ok(ExitStatusFilter::Code(code)),
+                Err(_) => anyhow::bail!(
+                    "Unknown exit status: {} (expected a number, success or failure)",
+                    other
+                ),
+            },
+        }
+    }
+}
+
 impl Default for SearchQuery {
     fn default() -> Self {
         Self {
@@ -69,11 +184,21 @@ impl Default for SearchQuery {
             category: None,
             level: None,
             project: None,
+            language: None,
+            symbol: None,
+            tool_name: None,
+            file: None,
+            exit_status: None,
+            after_doc_id: None,
             from_date: None,
             to_date: None,
             regex: false,
+            fuzzy: false,
+            facets: Vec::new(),
             limit: 100,
             offset: 0,
+            mode: SearchMode::Lexical,
+            sort: SortOrder::Relevance,
             format: OutputFormat::Table,
             context: 0,
         }
@@ -91,10 +216,59 @@ pub struct SearchResult {
     pub category: String,
     pub message: String,
     pub file_path: String,
+    /// Line of `file_path` the entry was read from
+    #[serde(default)]
+    pub line: u64,
     pub project: String,
+    /// Conversation the entry belongs to; empty for non-conversation logs
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub session_id: String,
+    #[serde(default)]
+    pub turn: u64,
+    #[serde(default)]
+    pub role: String,
+    /// Language of a code block result
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub language: String,
+    /// File a code block was written to or a tool call operated on
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_file: String,
+    /// Tool of a tool call result
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tool_name: String,
+    /// Exit status of a tool call result, when it finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_status: Option<i64>,
     pub score: f32,
 }
 
+/// One message shown as context around a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationTurn {
+    pub turn: u64,
+    pub role: String,
+    pub timestamp: Option<String>,
+    pub message: String,
+    /// Whether this turn matched the query
+    pub is_match: bool,
+}
+
+/// Matches of one conversation with their surrounding turns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    pub session_id: String,
+    pub project: String,
+    pub file_path: String,
+    pub turns: Vec<ConversationTurn>,
+}
+
+/// Number of matches per value of one facet dimension, most frequent first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FacetCounts {
+    pub dimension: String,
+    pub values: Vec<(String, u64)>,
+}
+
 /// Search results wrapper
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResults {
@@ -103,6 +277,9 @@ pub struct SearchResults {
     pub showing: usize,
     pub offset: usize,
     pub results: Vec<SearchResult>,
+    /// Matches grouped per conversation with `context` neighbouring turns
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conversations: Vec<Conversation>,
     pub search_time_ms: u64,
 }
 
@@ -117,10 +294,21 @@ impl QueryExecutor {
     pub fn new(index_path: &Path) -> Result<Self> {
         let schema = build_schema();
         let index = Index::open_in_dir(index_path).context("Failed to open index")?;
+        register_tokenizers(&index);
 
         Ok(Self { index, schema })
     }
 
+    /// The underlying tantivy index
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Id of the most recently indexed document, 0 for an empty index
+    pub fn last_doc_id(&self) -> Result<u64> {
+        max_doc_id(&self.index.reader()?.searcher())
+    }
+
     /// Executes a search query
     pub fn execute(&self, query: &SearchQuery) -> Result<SearchResults> {
         let start = std::time::Instant::now();
@@ -130,49 +318,328 @@ impl QueryExecutor {
         let searcher = reader.searcher();
 
         // Build the query
-        let tantivy_query = self.build_query(query)?;
-
-        // Execute search
-        let top_docs = searcher.search(
-            &*tantivy_query,
-            &TopDocs::with_limit(query.limit + query.offset),
-        )?;
+        let tantivy_query = self.build_query(query, true)?;
+
+        // Execute search, counting every match alongside the requested page
+        let top_docs = TopDocs::with_limit(query.limit.max(1)).and_offset(query.offset);
+        let (page, total_found) = match query.sort {
+            SortOrder::Relevance => searcher.search(&*tantivy_query, &(top_docs, Count))?,
+            SortOrder::Newest | SortOrder::Oldest => {
+                let order = if query.sort == SortOrder::Newest {
+                    Order::Desc
+                } else {
+                    Order::Asc
+                };
+                let collector =
+                    top_docs.order_by_fast_field::<tantivy::DateTime>(FIELD_TIMESTAMP, order);
+                let (docs, total) = searcher.search(&*tantivy_query, &(collector, Count))?;
+                // Time-ordered results carry no relevance score
+                let docs = docs.into_iter().map(|(_, address)| (0.0, address)).collect();
+                (docs, total)
+            }
+        };
 
-        // Skip offset
-        let results_to_show: Vec<_> = top_docs.into_iter().skip(query.offset).collect();
+        let mut results = self.collect_results(&searcher, page)?;
+        results.truncate(query.limit);
 
-        // Convert to SearchResult
-        let mut results = Vec::new();
-        for (score, doc_address) in results_to_show {
-            let retrieved_doc = searcher.doc(doc_address)?;
-            let result = self.doc_to_search_result(&retrieved_doc, score)?;
-            results.push(result);
-        }
+        let conversations = if query.context > 0 {
+            self.conversations(&searcher, &results, query.context)?
+        } else {
+            Vec::new()
+        };
 
         let search_time_ms = start.elapsed().as_millis() as u64;
 
         Ok(SearchResults {
             query: query.text.clone(),
-            total_found: results.len(),
+            total_found,
             showing: results.len(),
             offset: query.offset,
             results,
+            conversations,
             search_time_ms,
         })
     }
 
-    /// Builds a Tantivy query from SearchQuery
-    fn build_query(&self, query: &SearchQuery) -> Result<Box<dyn Query>> {
+    /// Executes a semantic or hybrid query; filters apply as for lexical
+    /// search, and `total_found` counts the candidates that passed them
+    pub fn execute_semantic(
+        &self,
+        query: &SearchQuery,
+        vectors: &VectorIndex,
+        embedder: &dyn TextEmbedder,
+    ) -> Result<SearchResults> {
+        let start = std::time::Instant::now();
+        let searcher = self.index.reader()?.searcher();
+
+        // Candidates per ranking, generous enough to survive filtering
+        let pool = ((query.offset + query.limit) * 4).max(50);
+
+        let embedding = embedder
+            .embed(&[query.text.as_str()])?
+            .pop()
+            .context("Embedder returned no vector")?;
+        let nearest = vectors.search(&embedding, pool, pool * 2);
+        let semantic = self.filter_hits(&searcher, query, &nearest)?;
+
+        let mut ranked = match query.mode {
+            SearchMode::Hybrid if !query.text.trim().is_empty() => {
+                let lexical_query = self.build_query(query, true)?;
+                let hits = searcher.search(&*lexical_query, &TopDocs::with_limit(pool))?;
+                let lexical = self.collect_results(&searcher, hits)?;
+                fuse_rankings(vec![lexical, semantic])
+            }
+            _ => semantic,
+        };
+
+        match query.sort {
+            SortOrder::Relevance => {}
+            // RFC 3339 timestamps in UTC order lexically; undated entries go last
+            SortOrder::Newest => ranked.sort_by(|a, b| b.timestamp.cmp(&a.timestamp)),
+            SortOrder::Oldest => ranked.sort_by(|a, b| match (&a.timestamp, &b.timestamp) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            }),
+        }
+
+        let total_found = ranked.len();
+        let results: Vec<SearchResult> = ranked
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .collect();
+
+        let conversations = if query.context > 0 {
+            self.conversations(&searcher, &results, query.context)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(SearchResults {
+            query: query.text.clone(),
+            total_found,
+            showing: results.len(),
+            offset: query.offset,
+            results,
+            conversations,
+            search_time_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Counts the documents matching `query` under each of the `FACET_DIMENSIONS`
+    pub fn facet_counts(&self, query: &SearchQuery) -> Result<Vec<FacetCounts>> {
+        let searcher = self.index.reader()?.searcher();
+        let tantivy_query = self.build_query(query, true)?;
+
+        let mut collector = FacetCollector::for_field(FIELD_FACETS);
+        for dimension in FACET_DIMENSIONS {
+            collector.add_facet(Facet::from_path([dimension]));
+        }
+        let counts = searcher.search(&*tantivy_query, &collector)?;
+
+        Ok(FACET_DIMENSIONS
+            .iter()
+            .map(|dimension| {
+                let mut values: Vec<(String, u64)> = counts
+                    .get(&format!("/{}", dimension))
+                    .filter_map(|(facet, count)| {
+                        facet.to_path().last().map(|value| (value.to_string(), count))
+                    })
+                    .collect();
+                values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+                FacetCounts {
+                    dimension: dimension.to_string(),
+                    values,
+                }
+            })
+            .collect())
+    }
+
+    /// Every turn of a conversation, or `None` if the session is not indexed
+    pub fn conversation(&self, session_id: &str) -> Result<Option<Conversation>> {
+        let searcher = self.index.reader()?.searcher();
+        let turns = self.session_turns(&searcher, session_id, 0, MAX_CONVERSATION_TURNS - 1)?;
+        let Some(first) = turns.first() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Conversation {
+            session_id: session_id.to_string(),
+            project: first.project.clone(),
+            file_path: first.file_path.clone(),
+            turns: turns
+                .iter()
+                .map(|r| ConversationTurn {
+                    turn: r.turn,
+                    role: r.role.clone(),
+                    timestamp: r.timestamp.clone(),
+                    message: r.message.clone(),
+                    is_match: false,
+                })
+                .collect(),
+        }))
+    }
+
+    /// Messages of a session between two turns, in turn order, one per turn;
+    /// code blocks and tool calls indexed from a message are left out
+    fn session_turns(
+        &self,
+        searcher: &Searcher,
+        session_id: &str,
+        lowest: u64,
+        highest: u64,
+    ) -> Result<Vec<SearchResult>> {
+        let session_field = self.schema.get_field(FIELD_SESSION_ID)?;
+        let category_field = self.schema.get_field(FIELD_CATEGORY)?;
+
+        let session = TermQuery::new(
+            Term::from_field_text(session_field, session_id),
+            IndexRecordOption::Basic,
+        );
+        let window = RangeQuery::new_u64_bounds(
+            FIELD_TURN.to_string(),
+            Bound::Included(lowest),
+            Bound::Included(highest),
+        );
+        let mut clauses = vec![
+            (Occur::Must, Box::new(session) as Box<dyn Query>),
+            (Occur::Must, Box::new(window)),
+        ];
+        for derived in [CODE_BLOCK_CATEGORY, TOOL_CALL_CATEGORY] {
+            let term = Term::from_field_text(category_field, derived);
+            clauses.push((
+                Occur::MustNot,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+
+        let limit = (highest - lowest + 1).min(MAX_CONVERSATION_TURNS) as usize;
+        let hits = searcher.search(&BooleanQuery::new(clauses), &TopDocs::with_limit(limit))?;
+        let mut turns = self.collect_results(searcher, hits)?;
+        turns.sort_by_key(|t| t.turn);
+        turns.dedup_by_key(|t| t.turn);
+        Ok(turns)
+    }
+
+    /// Loads the vector hits that pass the query's filters, keeping their
+    /// order and using the similarity as score
+    fn filter_hits(
+        &self,
+        searcher: &Searcher,
+        query: &SearchQuery,
+        hits: &[(u64, f32)],
+    ) -> Result<Vec<SearchResult>> {
+        if hits.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let doc_id_field = self.schema.get_field(FIELD_DOC_ID)?;
+        let ids = TermSetQuery::new(hits.iter().map(|(id, _)| Term::from_field_u64(doc_id_field, *id)));
+        let filtered = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(ids) as Box<dyn Query>),
+            (Occur::Must, self.build_query(query, false)?),
+        ]);
+        let docs = searcher.search(&filtered, &TopDocs::with_limit(hits.len()))?;
+
+        let mut by_id: HashMap<u64, SearchResult> = self
+            .collect_results(searcher, docs)?
+            .into_iter()
+            .map(|r| (r.doc_id, r))
+            .collect();
+        Ok(hits
+            .iter()
+            .filter_map(|(id, similarity)| {
+                by_id.remove(id).map(|mut result| {
+                    result.score = *similarity;
+                    result
+                })
+            })
+            .collect())
+    }
+
+    /// Loads the stored documents of a page of hits
+    fn collect_results(
+        &self,
+        searcher: &Searcher,
+        page: Vec<(f32, DocAddress)>,
+    ) -> Result<Vec<SearchResult>> {
+        page.into_iter()
+            .map(|(score, doc_address)| {
+                let retrieved_doc = searcher.doc(doc_address)?;
+                self.doc_to_search_result(&retrieved_doc, score)
+            })
+            .collect()
+    }
+
+    /// Groups conversation matches per session, each with `context` turns
+    /// before and after it; overlapping windows are merged
+    fn conversations(
+        &self,
+        searcher: &Searcher,
+        results: &[SearchResult],
+        context: usize,
+    ) -> Result<Vec<Conversation>> {
+        let context = context as u64;
+
+        // Sessions in order of their best match
+        let mut sessions: Vec<(&SearchResult, Vec<u64>)> = Vec::new();
+        for result in results.iter().filter(|r| !r.session_id.is_empty()) {
+            match sessions.iter_mut().find(|(first, _)| first.session_id == result.session_id) {
+                Some((_, turns)) => turns.push(result.turn),
+                None => sessions.push((result, vec![result.turn])),
+            }
+        }
+
+        let mut conversations = Vec::new();
+        for (first, matched) in sessions {
+            let lowest = matched.iter().min().copied().unwrap_or(0).saturating_sub(context);
+            let highest = matched.iter().max().copied().unwrap_or(0).saturating_add(context);
+
+            let turns: Vec<ConversationTurn> = self
+                .session_turns(searcher, &first.session_id, lowest, highest)?
+                .into_iter()
+                .filter(|r| matched.iter().any(|m| r.turn.abs_diff(*m) <= context))
+                .map(|r| ConversationTurn {
+                    is_match: matched.contains(&r.turn),
+                    turn: r.turn,
+                    role: r.role,
+                    timestamp: r.timestamp,
+                    message: r.message,
+                })
+                .collect();
+
+            conversations.push(Conversation {
+                session_id: first.session_id.clone(),
+                project: first.project.clone(),
+                file_path: first.file_path.clone(),
+                turns,
+            });
+        }
+
+        Ok(conversations)
+    }
+
+    /// Builds a Tantivy query from SearchQuery; without `with_text` only the filters apply
+    pub(super) fn build_query(&self, query: &SearchQuery, with_text: bool) -> Result<Box<dyn Query>> {
         let message_field = self.schema.get_field(FIELD_MESSAGE)?;
 
         // Main text query
         let mut subqueries: Vec<(Occur, Box<dyn Query>)> = Vec::new();
 
-        if !query.text.is_empty() {
+        if with_text && !query.text.is_empty() {
             if query.regex {
                 // Regex query
                 let regex_query = RegexQuery::from_pattern(&query.text, message_field)?;
                 subqueries.push((Occur::Must, Box::new(regex_query)));
+            } else if query.fuzzy {
+                // Every word must match, as typed so far and allowing a typo
+                for word in query.text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+                    let word = word.to_lowercase();
+                    let distance = if word.chars().count() >= FUZZY_TYPO_MIN_LEN { 1 } else { 0 };
+                    let term = Term::from_field_text(message_field, &word);
+                    let fuzzy = FuzzyTermQuery::new_prefix(term, distance, true);
+                    subqueries.push((Occur::Must, Box::new(fuzzy)));
+                }
             } else {
                 // Standard full-text query
                 let query_parser = QueryParser::for_index(&self.index, vec![message_field]);
@@ -212,13 +679,107 @@ impl QueryExecutor {
             subqueries.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
         }
 
-        // Date range filter (simplified - would need proper range query in production)
-        // TODO: Implement proper date range query using RangeQuery
+        if let Some(ref language) = query.language {
+            let language_field = self.schema.get_field(FIELD_LANGUAGE)?;
+            let term = Term::from_field_text(language_field, &normalize_language(language));
+            subqueries.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+
+        if let Some(ref symbol) = query.symbol {
+            // Indexed lowercase, whole and split, so `LogDiscovery` and `discovery` both match
+            let symbols_field = self.schema.get_field(FIELD_SYMBOLS)?;
+            let term = Term::from_field_text(symbols_field, &symbol.to_lowercase());
+            subqueries.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+
+        if let Some(ref tool_name) = query.tool_name {
+            let tool_name_field = self.schema.get_field(FIELD_TOOL_NAME)?;
+            let term = Term::from_field_text(tool_name_field, &tool_name.to_lowercase());
+            subqueries.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+
+        if let Some(ref file) = query.file {
+            // Every path suffix is indexed, so `src/main.rs` matches `/repo/src/main.rs`
+            let target_file_field = self.schema.get_field(FIELD_TARGET_FILE)?;
+            let term = Term::from_field_text(target_file_field, &normalize_path(file));
+            subqueries.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+
+        if let Some(exit_status) = query.exit_status {
+            let exit_status_field = self.schema.get_field(FIELD_EXIT_STATUS)?;
+            let filter: Box<dyn Query> = match exit_status {
+                ExitStatusFilter::Code(code) => Box::new(TermQuery::new(
+                    Term::from_field_i64(exit_status_field, code),
+                    IndexRecordOption::Basic,
+                )),
+                ExitStatusFilter::Success => Box::new(TermQuery::new(
+                    Term::from_field_i64(exit_status_field, 0),
+                    IndexRecordOption::Basic,
+                )),
+                // Nonzero, including negative codes from signals
+                ExitStatusFilter::Failure => {
+                    let range = |lower, upper| {
+                        Box::new(RangeQuery::new_i64_bounds(
+                            FIELD_EXIT_STATUS.to_string(),
+                            lower,
+                            upper,
+                        )) as Box<dyn Query>
+                    };
+                    Box::new(BooleanQuery::new(vec![
+                        (Occur::Should, range(Bound::Unbounded, Bound::Excluded(0))),
+                        (Occur::Should, range(Bound::Excluded(0), Bound::Unbounded)),
+                    ]))
+                }
+            };
+            subqueries.push((Occur::Must, filter));
+        }
+
+        if !query.facets.is_empty() {
+            let facets_field = self.schema.get_field(FIELD_FACETS)?;
+            let mut by_dimension: Vec<(&str, Vec<Box<dyn Query>>)> = Vec::new();
+            for (dimension, value) in &query.facets {
+                let term = Term::from_facet(facets_field, &Facet::from_path([dimension, value]));
+                let value_query = Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>;
+                match by_dimension.iter_mut().find(|(d, _)| d == dimension) {
+                    Some((_, values)) => values.push(value_query),
+                    None => by_dimension.push((dimension, vec![value_query])),
+                }
+            }
+            for (_, values) in by_dimension {
+                let any = values.into_iter().map(|q| (Occur::Should, q)).collect();
+                subqueries.push((Occur::Must, Box::new(BooleanQuery::new(any))));
+            }
+        }
+
+        if let Some(after) = query.after_doc_id {
+            let range = RangeQuery::new_u64_bounds(
+                FIELD_DOC_ID.to_string(),
+                Bound::Excluded(after),
+                Bound::Unbounded,
+            );
+            subqueries.push((Occur::Must, Box::new(range)));
+        }
+
+        // Date range filter on the timestamp fast field; entries without a
+        // timestamp never match a bounded range
+        if query.from_date.is_some() || query.to_date.is_some() {
+            let bound = |date: Option<DateTime<Utc>>| match date {
+                Some(date) => Bound::Included(tantivy::DateTime::from_timestamp_micros(
+                    date.timestamp_micros(),
+                )),
+                None => Bound::Unbounded,
+            };
+            let range = RangeQuery::new_date_bounds(
+                FIELD_TIMESTAMP.to_string(),
+                bound(query.from_date),
+                bound(query.to_date),
+            );
+            subqueries.push((Occur::Must, Box::new(range)));
+        }
 
         if subqueries.is_empty() {
             // Match all query if no filters
-            let all_query_parser = QueryParser::for_index(&self.index, vec![message_field]);
-            Ok(all_query_parser.parse_query("*")?)
+            Ok(Box::new(AllQuery))
         } else if subqueries.len() == 1 {
             Ok(subqueries.into_iter().next().unwrap().1)
         } else {
@@ -284,12 +845,56 @@ impl QueryExecutor {
             .unwrap_or("")
             .to_string();
 
+        let line = doc
+            .get_first(schema.get_field(FIELD_LINE)?)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+
         let project = doc
             .get_first(schema.get_field(FIELD_PROJECT)?)
             .and_then(|v| v.as_str())
             .unwrap_or("")
             .to_string();
 
+        let session_id = doc
+            .get_first(schema.get_field(FIELD_SESSION_ID)?)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let turn = doc
+            .get_first(schema.get_field(FIELD_TURN)?)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+
+        let role = doc
+            .get_first(schema.get_field(FIELD_ROLE)?)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let language = doc
+            .get_first(schema.get_field(FIELD_LANGUAGE)?)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let target_file = doc
+            .get_first(schema.get_field(FIELD_TARGET_FILE)?)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let tool_name = doc
+            .get_first(schema.get_field(FIELD_TOOL_NAME)?)
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+
+        let exit_status = doc
+            .get_first(schema.get_field(FIELD_EXIT_STATUS)?)
+            .and_then(|v| v.as_i64());
+
         Ok(SearchResult {
             doc_id,
             tool,
@@ -299,7 +904,15 @@ impl QueryExecutor {
             category,
             message,
             file_path,
+            line,
             project,
+            session_id,
+            turn,
+            role,
+            language,
+            target_file,
+            tool_name,
+            exit_status,
             score,
         })
     }
@@ -335,13 +948,89 @@ impl QueryExecutor {
                 output.push_str(&format!("- **Time**: {}\n", ts));
             }
             output.push_str(&format!("- **Project**: {}\n", result.project));
-            output.push_str(&format!("\n```\n{}\n```\n\n", result.message));
+            if !result.tool_name.is_empty() {
+                output.push_str(&format!("- **Tool call**: {}\n", result.tool_name));
+            }
+            if let Some(status) = result.exit_status {
+                output.push_str(&format!("- **Exit status**: {}\n", status));
+            }
+            if !result.target_file.is_empty() {
+                output.push_str(&format!("- **File**: {}\n", result.target_file));
+            }
+            output.push_str(&format!(
+                "\n```{}\n{}\n```\n\n",
+                result.language, result.message
+            ));
+        }
+
+        for conversation in &results.conversations {
+            output.push_str(&conversation_markdown(conversation, "##"));
         }
 
         output
     }
 
+    /// Renders conversations as transcripts with matches highlighted
+    fn format_conversations(&self, conversations: &[Conversation]) -> String {
+        use colored::Colorize;
+
+        let mut output = String::new();
+        for conversation in conversations {
+            output.push_str(&format!(
+                "{} {} {}\n",
+                "──".dimmed(),
+                conversation.project.cyan().bold(),
+                format!("session {}", conversation.session_id).dimmed()
+            ));
+
+            let mut previous: Option<u64> = None;
+            for turn in &conversation.turns {
+                if previous.is_some_and(|p| turn.turn > p + 1) {
+                    output.push_str(&format!("   {}\n", "⋮".dimmed()));
+                }
+                previous = Some(turn.turn);
+
+                let time = turn
+                    .timestamp
+                    .as_ref()
+                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                    .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                let header = format!("#{:<4} {:<9} {}", turn.turn, turn.role, time);
+                // Context turns are trimmed, matches are shown in full
+                let message: String = if turn.is_match || turn.message.chars().count() <= 300 {
+                    turn.message.clone()
+                } else {
+                    format!("{}…", turn.message.chars().take(300).collect::<String>())
+                };
+
+                if turn.is_match {
+                    output.push_str(&format!("{} {}\n", "▶".green().bold(), header.green().bold()));
+                } else {
+                    output.push_str(&format!("  {}\n", header.dimmed()));
+                }
+                for line in message.lines() {
+                    output.push_str(&format!("    {}\n", line));
+                }
+            }
+            output.push('\n');
+        }
+        output
+    }
+
     fn format_table(&self, results: &SearchResults) -> String {
+        let mut output = self.format_conversations(&results.conversations);
+
+        // Matches outside conversations still get the table
+        let standalone: Vec<&SearchResult> = results
+            .results
+            .iter()
+            .filter(|r| results.conversations.is_empty() || r.session_id.is_empty())
+            .collect();
+        if standalone.is_empty() {
+            return output;
+        }
+
         let mut table = Table::new();
         table
             .load_preset(UTF8_FULL)
@@ -353,7 +1042,7 @@ impl QueryExecutor {
                 Cell::new("Message").fg(Color::Cyan),
             ]);
 
-        for result in &results.results {
+        for result in standalone {
             let date = result
                 .timestamp
                 .as_ref()
@@ -361,24 +1050,89 @@ impl QueryExecutor {
                 .map(|dt| dt.format("%Y-%m-%d").to_string())
                 .unwrap_or_else(|| "N/A".to_string());
 
-            let message = if result.message.len() > 60 {
-                format!("{}...", &result.message[..57])
+            // Code spans several lines; the table shows it flattened
+            let flattened = result.message.split_whitespace().collect::<Vec<_>>().join(" ");
+            let message = if flattened.chars().count() > 60 {
+                format!("{}...", flattened.chars().take(57).collect::<String>())
             } else {
-                result.message.clone()
+                flattened
+            };
+
+            let category = if result.category == CODE_BLOCK_CATEGORY {
+                match (result.language.as_str(), result.target_file.as_str()) {
+                    ("", "") => "code".to_string(),
+                    (language, "") => format!("code: {}", language),
+                    ("", file) => format!("code: {}", file),
+                    (language, file) => format!("code: {} ({})", language, file),
+                }
+            } else if result.category == TOOL_CALL_CATEGORY {
+                match result.exit_status {
+                    Some(status) => format!("tool: {} (exit {})", result.tool_name, status),
+                    None => format!("tool: {}", result.tool_name),
+                }
+            } else {
+                result.category.clone()
             };
 
             table.add_row(vec![
                 Cell::new(&result.tool),
                 Cell::new(date),
-                Cell::new(&result.category),
+                Cell::new(category),
                 Cell::new(message),
             ]);
         }
 
-        table.to_string()
+        output.push_str(&table.to_string());
+        output
     }
 }
 
+/// Renders a conversation as markdown under a heading of the given level
+/// (`#`, `##`, ...); gaps between shown turns are marked with an ellipsis
+pub fn conversation_markdown(conversation: &Conversation, heading: &str) -> String {
+    let mut output = format!(
+        "{} Conversation {} ({})\n\n",
+        heading, conversation.session_id, conversation.project
+    );
+    let mut previous: Option<u64> = None;
+    for turn in &conversation.turns {
+        if previous.is_some_and(|p| turn.turn > p + 1) {
+            output.push_str("*…*\n\n");
+        }
+        previous = Some(turn.turn);
+        let marker = if turn.is_match { " ← match" } else { "" };
+        output.push_str(&format!("**{}** (turn {}){}\n\n", turn.role, turn.turn, marker));
+        for line in turn.message.lines() {
+            output.push_str(&format!("> {}\n", line));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Merges rankings by reciprocal rank fusion: each document scores the sum
+/// of `1 / (RRF_K + rank)` over the rankings it appears in
+fn fuse_rankings(rankings: Vec<Vec<SearchResult>>) -> Vec<SearchResult> {
+    let mut fused: Vec<SearchResult> = Vec::new();
+    let mut positions: HashMap<u64, usize> = HashMap::new();
+
+    for ranking in rankings {
+        for (rank, result) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match positions.get(&result.doc_id) {
+                Some(&i) => fused[i].score += score,
+                None => {
+                    positions.insert(result.doc_id, fused.len());
+                    fused.push(SearchResult { score, ..result });
+                }
+            }
+        }
+    }
+
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused
+}
+
 /// Parses a relative date string like "7d" or "1m" into DateTime
 pub fn parse_relative_date(s: &str) -> Result<DateTime<Utc>> {
     let now = Utc::now();
@@ -402,6 +1156,18 @@ pub fn parse_relative_date(s: &str) -> Result<DateTime<Utc>> {
     }
 }
 
+/// Parses the end of a date range: like `parse_relative_date`, but a plain
+/// YYYY-MM-DD date covers the whole day
+pub fn parse_end_date(s: &str) -> Result<DateTime<Utc>> {
+    match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(date) => Ok(DateTime::from_naive_utc_and_offset(
+            date.and_hms_micro_opt(23, 59, 59, 999_999).unwrap(),
+            Utc,
+        )),
+        Err(_) => parse_relative_date(s),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -426,4 +1192,412 @@ mod tests {
         assert_eq!(query.offset, 0);
         assert!(!query.regex);
     }
+
+    fn entry_doc(i: usize, message: &str, timestamp: Option<&str>) -> crate::search::LogEntryDocument {
+        use crate::models::AiTool;
+        use crate::parsers::{EntryCategory, LogEntry, LogLevel};
+
+        let entry = LogEntry {
+            timestamp: timestamp.map(|t| t.parse().unwrap()),
+            level: LogLevel::Error,
+            message: message.to_string(),
+            category: if i % 2 == 0 {
+                EntryCategory::UserPrompt
+            } else {
+                EntryCategory::AssistantResponse
+            },
+        };
+        crate::search::LogEntryDocument::from_log_entry(
+            &entry,
+            i as u64,
+            &AiTool::ClaudeCode,
+            "Session",
+            Path::new("/home/u/.claude/projects/app/log.jsonl"),
+        )
+    }
+
+    fn executor_for(docs: Vec<crate::search::LogEntryDocument>) -> (tempfile::TempDir, QueryExecutor) {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = build_schema();
+        let index = Index::create_in_dir(dir.path(), schema.clone()).unwrap();
+        register_tokenizers(&index);
+        let mut writer = index.writer(15_000_000).unwrap();
+        for doc in docs {
+            writer.add_document(doc.to_tantivy_document(&schema)).unwrap();
+        }
+        writer.commit().unwrap();
+
+        let executor = QueryExecutor::new(dir.path()).unwrap();
+        (dir, executor)
+    }
+
+    fn executor_with(entries: &[(&str, Option<&str>)]) -> (tempfile::TempDir, QueryExecutor) {
+        executor_for(
+            entries
+                .iter()
+                .enumerate()
+                .map(|(i, (message, timestamp))| entry_doc(i, message, *timestamp))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_date_range_and_total() {
+        let (_dir, executor) = executor_with(&[
+            ("thread panic in parser", Some("2026-01-01T10:00:00Z")),
+            ("panic while indexing", Some("2026-01-05T10:00:00Z")),
+            ("another panic", Some("2026-01-10T10:00:00Z")),
+            ("panic with no time", None),
+            ("unrelated", Some("2026-01-05T11:00:00Z")),
+        ]);
+
+        let all = executor
+            .execute(&SearchQuery {
+                text: "panic".into(),
+                limit: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(all.total_found, 4);
+        assert_eq!(all.showing, 1);
+
+        let ranged = executor
+            .execute(&SearchQuery {
+                text: "panic".into(),
+                from_date: Some(parse_relative_date("2026-01-02").unwrap()),
+                to_date: Some(parse_end_date("2026-01-10").unwrap()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(ranged.total_found, 2);
+        assert!(ranged.results.iter().all(|r| r.message != "thread panic in parser"));
+    }
+
+    #[test]
+    fn test_sort_by_time() {
+        let (_dir, executor) = executor_with(&[
+            ("panic b", Some("2026-01-05T10:00:00Z")),
+            ("panic c", Some("2026-01-10T10:00:00Z")),
+            ("panic a", Some("2026-01-01T10:00:00Z")),
+        ]);
+        let messages = |sort| {
+            executor
+                .execute(&SearchQuery {
+                    text: "panic".into(),
+                    sort,
+                    ..Default::default()
+                })
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| r.message)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(messages(SortOrder::Newest), ["panic c", "panic b", "panic a"]);
+        assert_eq!(messages(SortOrder::Oldest), ["panic a", "panic b", "panic c"]);
+
+        let page = executor
+            .execute(&SearchQuery {
+                text: "panic".into(),
+                sort: SortOrder::Newest,
+                limit: 1,
+                offset: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(page.results[0].message, "panic b");
+        assert_eq!(page.total_found, 3);
+    }
+
+    #[test]
+    fn test_context_groups_conversation_turns() {
+        let session = [
+            "how do I fix this lifetime issue",
+            "can you show the error",
+            "error[E0502]: cannot borrow `items` as mutable",
+            "clone the key before calling insert",
+            "that worked, thanks",
+            "now add tests",
+            "added three tests",
+        ];
+        let mut docs: Vec<_> = session
+            .iter()
+            .enumerate()
+            .map(|(turn, m)| entry_doc(turn, m, None).with_turn("session-a", turn as u64))
+            .collect();
+        docs.push(entry_doc(7, "unrelated borrow note", None));
+        docs.push(entry_doc(8, "other chat", None).with_turn("session-b", 0));
+        let (_dir, executor) = executor_for(docs);
+
+        let results = executor
+            .execute(&SearchQuery {
+                text: "borrow".into(),
+                context: 1,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(results.total_found, 2);
+        assert_eq!(results.conversations.len(), 1);
+        let conversation = &results.conversations[0];
+        assert_eq!(conversation.session_id, "session-a");
+        let turns: Vec<_> = conversation.turns.iter().map(|t| (t.turn, t.is_match)).collect();
+        assert_eq!(turns, [(1, false), (2, true), (3, false)]);
+        assert_eq!(conversation.turns[0].role, "assistant");
+
+        let table = executor.format_results(&results, OutputFormat::Table);
+        assert!(table.contains("clone the key"));
+        assert!(table.contains("unrelated borrow note"));
+
+        let plain = executor
+            .execute(&SearchQuery {
+                text: "borrow".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(plain.conversations.is_empty());
+    }
+
+    /// Embeds words by concept, so synonyms land close together
+    struct ConceptEmbedder;
+
+    impl TextEmbedder for ConceptEmbedder {
+        fn model_id(&self) -> &str {
+            "concepts"
+        }
+
+        fn dimensions(&self) -> usize {
+            4
+        }
+
+        fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+            let concepts: [&[&str]; 3] = [
+                &["panic", "crash", "crashed", "segfault", "abort"],
+                &["borrow", "lifetime", "borrowed", "mutable"],
+                &["test", "tests", "coverage", "assert"],
+            ];
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let mut v = vec![0.0f32; 4];
+                    for word in text.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+                        match concepts.iter().position(|c| c.contains(&word)) {
+                            Some(i) => v[i] += 1.0,
+                            None if !word.is_empty() => v[3] += 0.1,
+                            None => {}
+                        }
+                    }
+                    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt().max(1e-6);
+                    v.into_iter().map(|x| x / norm).collect()
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_semantic_and_hybrid_search() {
+        let messages = [
+            "the app crashed with a segfault on startup",
+            "cannot borrow items as mutable",
+            "add tests for the parser",
+            "panic in parser tests",
+        ];
+        let docs: Vec<_> = messages
+            .iter()
+            .enumerate()
+            .map(|(i, m)| entry_doc(i, m, Some("2026-01-01T10:00:00Z")))
+            .collect();
+        let (_dir, executor) = executor_for(docs);
+
+        let mut vectors = VectorIndex::new("concepts", 4);
+        assert_eq!(vectors.sync(executor.index(), &ConceptEmbedder, false).unwrap(), 4);
+        assert_eq!(vectors.sync(executor.index(), &ConceptEmbedder, false).unwrap(), 0);
+
+        // Lexical search misses the reworded crash report
+        let lexical = executor
+            .execute(&SearchQuery {
+                text: "abort".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(lexical.total_found, 0);
+
+        let semantic = executor
+            .execute_semantic(
+                &SearchQuery {
+                    text: "abort".into(),
+                    mode: SearchMode::Semantic,
+                    limit: 1,
+                    ..Default::default()
+                },
+                &vectors,
+                &ConceptEmbedder,
+            )
+            .unwrap();
+        assert_eq!(semantic.results[0].message, messages[0]);
+        assert!(semantic.results[0].score > 0.9);
+
+        // Filters still apply: only even doc ids are user prompts
+        let filtered = executor
+            .execute_semantic(
+                &SearchQuery {
+                    text: "borrow".into(),
+                    mode: SearchMode::Semantic,
+                    category: Some("userprompt".into()),
+                    ..Default::default()
+                },
+                &vectors,
+                &ConceptEmbedder,
+            )
+            .unwrap();
+        assert!(filtered.results.iter().all(|r| r.category == "UserPrompt"));
+        assert_eq!(filtered.total_found, 2);
+
+        // The message matching both words and meaning ranks first
+        let hybrid = executor
+            .execute_semantic(
+                &SearchQuery {
+                    text: "parser crash".into(),
+                    mode: SearchMode::Hybrid,
+                    ..Default::default()
+                },
+                &vectors,
+                &ConceptEmbedder,
+            )
+            .unwrap();
+        assert_eq!(hybrid.results[0].message, messages[3]);
+    }
+
+    #[test]
+    fn test_code_block_filters() {
+        use crate::search::code::extract_code_blocks;
+
+        let response = entry_doc(1, "", None);
+        let message = concat!(
+            "```rust\nlet discovery = LogDiscovery::new(home_dir, true);\n```\n",
+            "```python\ndef log_discovery(): pass\n```\n",
+            "[Write] {\"file_path\":\"src/scan.rs\",\"content\":\"fn scan_all() { parse_history_entry(); }\"}",
+        );
+        let mut docs = vec![entry_doc(0, "where is LogDiscovery created", None)];
+        for (i, block) in extract_code_blocks(message).iter().enumerate() {
+            docs.push(response.code_block(block, 10 + i as u64));
+        }
+        let (_dir, executor) = executor_for(docs);
+
+        let search = |language: Option<&str>, symbol: Option<&str>| {
+            executor
+                .execute(&SearchQuery {
+                    language: language.map(String::from),
+                    symbol: symbol.map(String::from),
+                    ..Default::default()
+                })
+                .unwrap()
+                .results
+        };
+
+        // The whole identifier, any case
+        let found = search(None, Some("LogDiscovery"));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].language, "rust");
+        assert_eq!(found[0].category, CODE_BLOCK_CATEGORY);
+
+        // Parts of camelCase and snake_case identifiers
+        assert_eq!(search(None, Some("discovery")).len(), 2);
+        assert_eq!(search(Some("rs"), Some("discovery")).len(), 1);
+        assert_eq!(search(None, Some("history")).len(), 1);
+
+        let written = search(Some("rust"), None);
+        assert_eq!(written.len(), 2);
+        assert!(written.iter().any(|r| r.target_file == "src/scan.rs"));
+
+        // Prompts mentioning the symbol are not code
+        assert!(search(Some("python"), Some("LogDiscovery")).is_empty());
+    }
+
+    #[test]
+    fn test_tool_call_filters() {
+        use crate::search::tool_calls::ToolCallRecord;
+
+        let response = entry_doc(1, "", None);
+        let call = |name: &str, arguments: &str, file: Option<&str>, status: Option<i64>| {
+            ToolCallRecord {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+                target_file: file.map(String::from),
+                exit_status: status,
+                requested_at: None,
+            }
+        };
+        let calls = [
+            call("Bash", "rm -rf target/", None, Some(1)),
+            call("Bash", "cargo build", None, Some(0)),
+            call("Edit", "{\"file_path\":\"/repo/src/main.rs\"}", Some("/repo/src/main.rs"), Some(0)),
+            call("Read", "{\"file_path\":\"./README.md\"}", Some("./README.md"), None),
+        ];
+        let mut docs = vec![entry_doc(0, "please rm -rf the build", None)];
+        for (i, c) in calls.iter().enumerate() {
+            docs.push(response.tool_call(c, 10 + i as u64));
+        }
+        let (_dir, executor) = executor_for(docs);
+
+        let search = |query: SearchQuery| executor.execute(&query).unwrap().results;
+
+        let bash = search(SearchQuery {
+            tool_name: Some("bash".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(bash.len(), 2);
+        assert!(bash.iter().all(|r| r.tool_name == "Bash"));
+
+        // Phrase search on the command, limited to tool calls
+        let removed = search(SearchQuery {
+            text: "\"rm -rf\"".to_string(),
+            tool_name: Some("Bash".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].exit_status, Some(1));
+
+        let failed = search(SearchQuery {
+            exit_status: Some(ExitStatusFilter::Failure),
+            ..Default::default()
+        });
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].message, "rm -rf target/");
+        assert_eq!(failed[0].level, "Error");
+
+        let succeeded = search(SearchQuery {
+            exit_status: Some(ExitStatusFilter::Success),
+            ..Default::default()
+        });
+        assert_eq!(succeeded.len(), 2);
+
+        // Relative paths and file names match absolute targets
+        for file in ["src/main.rs", "./src/main.rs", "main.rs", "/repo/src/main.rs"] {
+            let edits = search(SearchQuery {
+                file: Some(file.to_string()),
+                ..Default::default()
+            });
+            assert_eq!(edits.len(), 1, "{}", file);
+            assert_eq!(edits[0].tool_name, "Edit");
+        }
+        assert_eq!(
+            search(SearchQuery {
+                file: Some("README.md".to_string()),
+                ..Default::default()
+            })
+            .len(),
+            1
+        );
+        assert!(search(SearchQuery {
+            file: Some("rc/main.rs".to_string()),
+            ..Default::default()
+        })
+        .is_empty());
+
+        assert_eq!("0".parse::<ExitStatusFilter>().unwrap(), ExitStatusFilter::Success);
+        assert_eq!("127".parse::<ExitStatusFilter>().unwrap(), ExitStatusFilter::Code(127));
+        assert!("maybe".parse::<ExitStatusFilter>().is_err());
+    }
 }
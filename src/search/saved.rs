@@ -0,0 +1,383 @@
+// Named searches kept in the user's config, and alert rules built on them
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::metadata::{AlertLine, IndexMetadata};
+use super::query_executor::{
+    parse_end_date, parse_relative_date, ExitStatusFilter, QueryExecutor, SearchQuery,
+    SearchResult, SortOrder,
+};
+
+/// A named query with the same filters as `claudev search`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSearch {
+    pub name: String,
+    /// Search query text
+    pub query: String,
+    pub tool: Option<String>,
+    pub log_type: Option<String>,
+    pub category: Option<String>,
+    pub level: Option<String>,
+    pub project: Option<String>,
+    pub language: Option<String>,
+    pub symbol: Option<String>,
+    pub tool_name: Option<String>,
+    pub file: Option<String>,
+    pub exit_status: Option<String>,
+    /// Start date, usually relative (7d, 1m) so it moves with time
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub regex: bool,
+    pub fuzzy: bool,
+    /// Checked by `claudev alerts check` when no rules are named
+    pub alert: bool,
+}
+
+impl SavedSearch {
+    /// The search this describes, with dates resolved against today
+    pub fn to_query(&self) -> Result<SearchQuery> {
+        if self.regex && self.fuzzy {
+            anyhow::bail!("Saved search {} cannot be both regex and fuzzy", self.name);
+        }
+        Ok(SearchQuery {
+            text: self.query.clone(),
+            tool: self.tool.clone(),
+            log_type: self.log_type.clone(),
+            category: self.category.clone(),
+            level: self.level.clone(),
+            project: self.project.clone(),
+            language: self.language.clone(),
+            symbol: self.symbol.clone(),
+            tool_name: self.tool_name.clone(),
+            file: self.file.clone(),
+            exit_status: self
+                .exit_status
+                .as_deref()
+                .map(str::parse::<ExitStatusFilter>)
+                .transpose()?,
+            from_date: self.from.as_deref().map(parse_relative_date).transpose()?,
+            to_date: self.to.as_deref().map(parse_end_date).transpose()?,
+            regex: self.regex,
+            fuzzy: self.fuzzy,
+            ..Default::default()
+        })
+    }
+}
+
+/// The user's saved searches
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedSearches {
+    #[serde(default)]
+    pub searches: Vec<SavedSearch>,
+}
+
+impl SavedSearches {
+    /// Load the user's saved searches, or none if there is no file
+    pub fn load() -> Result<Self> {
+        Self::load_from(&saved_searches_path())
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&saved_searches_path())
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn find(&self, name: &str) -> Option<&SavedSearch> {
+        self.searches.iter().find(|search| search.name == name)
+    }
+
+    /// Like `find`, but an unknown name is an error listing the known ones
+    pub fn get(&self, name: &str) -> Result<&SavedSearch> {
+        self.find(name).with_context(|| {
+            let known: Vec<&str> = self.searches.iter().map(|s| s.name.as_str()).collect();
+            if known.is_empty() {
+                format!("No saved search named {} (none saved yet; use --save)", name)
+            } else {
+                format!("No saved search named {} (saved: {})", name, known.join(", "))
+            }
+        })
+    }
+
+    /// Replaces the saved search with the same name, or adds it
+    pub fn upsert(&mut self, search: SavedSearch) {
+        match self.searches.iter_mut().find(|s| s.name == search.name) {
+            Some(existing) => *existing = search,
+            None => self.searches.push(search),
+        }
+    }
+
+    /// Saved searches marked as alert rules
+    pub fn alerts(&self) -> impl Iterator<Item = &SavedSearch> {
+        self.searches.iter().filter(|search| search.alert)
+    }
+}
+
+/// Path of the saved searches file
+pub fn saved_searches_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("claudev")
+        .join("searches.json")
+}
+
+/// Outcome of checking one alert rule
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertReport {
+    pub name: String,
+    /// Whether the rule had never been checked; nothing counts as new then
+    pub first_check: bool,
+    /// Matches indexed since the last check, or every match on the first check
+    pub hits: usize,
+    /// The newest of the new matches
+    pub results: Vec<SearchResult>,
+}
+
+/// Finds matches of `search` indexed since it was last checked, and records
+/// in `metadata` that everything indexed so far has been seen. The first
+/// check only records the starting point, so existing history is not
+/// reported as new. A line indexed again is reported once, however many
+/// times it gets a new document id.
+pub fn check_alert(
+    executor: &QueryExecutor,
+    metadata: &mut IndexMetadata,
+    search: &SavedSearch,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> Result<AlertReport> {
+    let last_doc_id = executor
+        .last_doc_id()?
+        .max(metadata.next_doc_id.saturating_sub(1));
+    let checkpoint = metadata.find_alert(&search.name).cloned();
+
+    let mut query = search.to_query()?;
+    query.after_doc_id = checkpoint.as_ref().map(|alert| alert.last_doc_id);
+    query.sort = SortOrder::Newest;
+    query.limit = limit;
+    let mut found = executor.execute(&query)?;
+
+    let Some(checkpoint) = checkpoint else {
+        metadata.record_alert(&search.name, last_doc_id, Vec::new(), now);
+        return Ok(AlertReport {
+            name: search.name.clone(),
+            first_check: true,
+            hits: found.total_found,
+            results: Vec::new(),
+        });
+    };
+
+    // Every match is needed to tell lines read again from new ones
+    if found.total_found > found.results.len() {
+        query.limit = found.total_found;
+        found = executor.execute(&query)?;
+    }
+    let mut seen = checkpoint.seen;
+    let mut results = Vec::new();
+    for result in found.results {
+        let line = AlertLine {
+            file_path: result.file_path.clone(),
+            line: result.line,
+        };
+        if !seen.contains(&line) {
+            seen.push(line);
+            results.push(result);
+        }
+    }
+
+    metadata.record_alert(&search.name, last_doc_id, seen, now);
+    Ok(AlertReport {
+        name: search.name.clone(),
+        first_check: false,
+        hits: results.len(),
+        results: results.into_iter().take(limit).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AiTool;
+    use crate::parsers::{EntryCategory, LogEntry, LogLevel};
+    use crate::search::metadata::FileCheckpoint;
+    use crate::search::schema::{build_schema, register_tokenizers};
+    use crate::search::LogEntryDocument;
+    use tantivy::Index;
+
+    const SOURCE: &str = "/home/u/.claude/projects/app/s.jsonl";
+
+    /// Indexes `messages` as consecutive lines, starting at `first_line`
+    /// with ids from `first_id`
+    fn add_messages(index: &Index, first_id: u64, first_line: u64, messages: &[&str]) {
+        let schema = build_schema();
+        let mut writer = index.writer::<tantivy::TantivyDocument>(15_000_000).unwrap();
+        for (i, message) in messages.iter().enumerate() {
+            let entry = LogEntry {
+                timestamp: Some(Utc::now()),
+                level: LogLevel::Info,
+                message: message.to_string(),
+                category: EntryCategory::AssistantResponse,
+            };
+            let doc = LogEntryDocument::from_log_entry(
+                &entry,
+                first_id + i as u64,
+                &AiTool::ClaudeCode,
+                "Session",
+                Path::new(SOURCE),
+            )
+            .at_line(first_line + i as u64);
+            writer.add_document(doc.to_tantivy_document(&schema)).unwrap();
+        }
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn test_saved_searches_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("searches.json");
+        assert!(SavedSearches::load_from(&path).unwrap().searches.is_empty());
+
+        let mut saved = SavedSearches::default();
+        saved.upsert(SavedSearch {
+            name: "tool-errors".into(),
+            project: Some("x".into()),
+            exit_status: Some("failure".into()),
+            from: Some("7d".into()),
+            alert: true,
+            ..Default::default()
+        });
+        saved.upsert(SavedSearch {
+            name: "rate-limits".into(),
+            query: "\"rate limit\"".into(),
+            ..Default::default()
+        });
+        saved.save_to(&path).unwrap();
+
+        let loaded = SavedSearches::load_from(&path).unwrap();
+        assert_eq!(loaded.searches, saved.searches);
+        assert_eq!(loaded.alerts().count(), 1);
+        let err = loaded.get("nope").unwrap_err().to_string();
+        assert!(err.contains("tool-errors, rate-limits"));
+
+        let query = loaded.get("tool-errors").unwrap().to_query().unwrap();
+        assert_eq!(query.exit_status, Some(ExitStatusFilter::Failure));
+        assert!(query.from_date.unwrap() < Utc::now());
+
+        // Hand-written files may leave out everything but the name
+        let minimal: SavedSearches =
+            serde_json::from_str(r#"{"searches": [{"name": "all", "alert": true}]}"#).unwrap();
+        assert_eq!(minimal.searches[0].query, "");
+    }
+
+    #[test]
+    fn test_alert_reports_only_new_hits() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = Index::create_in_dir(dir.path(), build_schema()).unwrap();
+        register_tokenizers(&index);
+        add_messages(&index, 1, 1, &["hit a rate limit", "all good"]);
+
+        let executor = QueryExecutor::new(dir.path()).unwrap();
+        let mut metadata = IndexMetadata::new();
+        let rule = SavedSearch {
+            name: "rate-limits".into(),
+            query: "\"rate limit\"".into(),
+            alert: true,
+            ..Default::default()
+        };
+        let check = |metadata: &mut IndexMetadata| {
+            check_alert(&executor, metadata, &rule, 10, Utc::now()).unwrap()
+        };
+
+        // Existing history is the starting point, not news
+        let first = check(&mut metadata);
+        assert!(first.first_check);
+        assert_eq!(first.hits, 1);
+        assert!(first.results.is_empty());
+        assert_eq!(metadata.find_alert("rate-limits").unwrap().last_doc_id, 2);
+
+        assert_eq!(check(&mut metadata).hits, 0);
+
+        add_messages(&index, 3, 3, &["another rate limit", "rate limit again", "fine"]);
+        let report = check(&mut metadata);
+        assert!(!report.first_check);
+        assert_eq!(report.hits, 2);
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(metadata.find_alert("rate-limits").unwrap().last_doc_id, 5);
+
+        assert_eq!(check(&mut metadata).hits, 0);
+    }
+
+    #[test]
+    fn test_alert_skips_lines_indexed_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = Index::create_in_dir(dir.path(), build_schema()).unwrap();
+        register_tokenizers(&index);
+        add_messages(&index, 1, 1, &["all good"]);
+
+        let executor = QueryExecutor::new(dir.path()).unwrap();
+        let mut metadata = IndexMetadata::new();
+        // Line 2 on may be read again, as when a tool call there is running
+        metadata.upsert_file(FileCheckpoint {
+            path: PathBuf::from(SOURCE),
+            size_bytes: 0,
+            last_modified: Utc::now(),
+            offset: 0,
+            line: 2,
+            turn: 0,
+            tail_hash: String::new(),
+            doc_count: 0,
+        });
+        let rule = SavedSearch {
+            name: "rate-limits".into(),
+            query: "\"rate limit\"".into(),
+            alert: true,
+            ..Default::default()
+        };
+        let check = |metadata: &mut IndexMetadata| {
+            check_alert(&executor, metadata, &rule, 10, Utc::now()).unwrap()
+        };
+        assert!(check(&mut metadata).first_check);
+
+        add_messages(&index, 2, 2, &["hit a rate limit"]);
+        assert_eq!(check(&mut metadata).hits, 1);
+
+        // The same line indexed again under a new id is not news
+        let mut writer = index.writer::<tantivy::TantivyDocument>(15_000_000).unwrap();
+        writer.delete_all_documents().unwrap();
+        writer.commit().unwrap();
+        drop(writer);
+        add_messages(&index, 3, 1, &["all good", "hit a rate limit", "rate limit again"]);
+        let report = check(&mut metadata);
+        assert_eq!(report.hits, 1);
+        assert_eq!(report.results[0].message, "rate limit again");
+        assert_eq!(metadata.find_alert("rate-limits").unwrap().seen.len(), 2);
+
+        // Lines before the file's checkpoint are not kept
+        metadata.upsert_file(FileCheckpoint {
+            line: 4,
+            ..metadata.find_file(Path::new(SOURCE)).unwrap().clone()
+        });
+        check(&mut metadata);
+        assert!(metadata.find_alert("rate-limits").unwrap().seen.is_empty());
+    }
+}
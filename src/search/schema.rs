@@ -2,8 +2,13 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use std::path::Path;
 use tantivy::schema::*;
-use tantivy::TantivyDocument;
+use tantivy::{Index, Searcher, TantivyDocument};
 
+use super::code::{register_code_tokenizer, CodeBlock, CODE_TOKENIZER};
+use super::tool_calls::{
+    register_tool_call_tokenizers, ToolCallRecord, PATH_TOKENIZER, TOOL_NAME_TOKENIZER,
+};
+use crate::extraction_utils::estimate_tokens;
 use crate::models::AiTool;
 use crate::parsers::{EntryCategory, LogEntry, LogLevel};
 
@@ -17,6 +22,30 @@ pub const FIELD_CATEGORY: &str = "category";
 pub const FIELD_MESSAGE: &str = "message";
 pub const FIELD_FILE_PATH: &str = "file_path";
 pub const FIELD_PROJECT: &str = "project";
+pub const FIELD_SESSION_ID: &str = "session_id";
+pub const FIELD_TURN: &str = "turn";
+pub const FIELD_LINE: &str = "line";
+pub const FIELD_ROLE: &str = "role";
+pub const FIELD_LANGUAGE: &str = "language";
+pub const FIELD_TARGET_FILE: &str = "target_file";
+pub const FIELD_SYMBOLS: &str = "symbols";
+pub const FIELD_TOOL_NAME: &str = "tool_name";
+pub const FIELD_EXIT_STATUS: &str = "exit_status";
+pub const FIELD_FACETS: &str = "facets";
+pub const FIELD_TOKENS: &str = "tokens";
+pub const FIELD_MONTH: &str = "month";
+
+/// Facet dimensions every document is counted under, e.g. `/tool/Cursor`
+pub const FACET_DIMENSIONS: [&str; 4] = ["tool", "project", "category", "month"];
+
+/// Category of documents holding a single code block
+pub const CODE_BLOCK_CATEGORY: &str = "CodeBlock";
+
+/// Category of documents holding a single tool call
+pub const TOOL_CALL_CATEGORY: &str = "ToolCall";
+
+/// Bumped whenever fields change; indexes with another version are rebuilt
+pub const SCHEMA_VERSION: &str = "7.0";
 
 /// Builds the Tantivy schema for indexing log entries
 pub fn build_schema() -> Schema {
@@ -25,14 +54,16 @@ pub fn build_schema() -> Schema {
     // doc_id: u64 (unique identifier)
     schema_builder.add_u64_field(FIELD_DOC_ID, STORED | INDEXED | FAST);
 
-    // tool: TEXT (AI tool name like "Claude Code", "Cursor")
+    // tool: TEXT (AI tool name like "Claude Code", "Cursor"); FAST with the
+    // value as is, so matches can be grouped by it
     let text_options = TextOptions::default()
         .set_indexing_options(
             TextFieldIndexing::default()
                 .set_tokenizer("default")
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         )
-        .set_stored();
+        .set_stored()
+        .set_fast(None);
     schema_builder.add_text_field(FIELD_TOOL, text_options.clone());
 
     // log_type: TEXT (Debug, History, Session, etc.)
@@ -57,15 +88,83 @@ pub fn build_schema() -> Schema {
         .set_stored();
     schema_builder.add_text_field(FIELD_MESSAGE, message_options);
 
-    // file_path: TEXT (source file path)
-    schema_builder.add_text_field(FIELD_FILE_PATH, text_options.clone());
+    // file_path: STRING (source file path, indexed whole so a file's documents
+    // can be deleted when it is reindexed)
+    schema_builder.add_text_field(FIELD_FILE_PATH, STRING | STORED);
 
     // project: TEXT (project name extracted from path)
     schema_builder.add_text_field(FIELD_PROJECT, text_options);
 
+    // session_id: STRING (conversation identity, matched exactly; empty for non-conversation logs)
+    schema_builder.add_text_field(FIELD_SESSION_ID, STRING | STORED);
+
+    // turn: u64 (position of the message within its conversation)
+    schema_builder.add_u64_field(FIELD_TURN, STORED | INDEXED | FAST);
+
+    // line: u64 (line of the source file the entry was parsed from, or its
+    // position for sources that are not line-oriented)
+    schema_builder.add_u64_field(FIELD_LINE, STORED | INDEXED | FAST);
+
+    // role: STRING (user, assistant, tool, system)
+    schema_builder.add_text_field(FIELD_ROLE, STRING | STORED);
+
+    // language: STRING (normalized language of a code block; empty for messages)
+    schema_builder.add_text_field(FIELD_LANGUAGE, STRING | STORED);
+
+    // target_file: TEXT (file a code block was written to or a tool call
+    // operated on; indexed with every path suffix)
+    let target_file_options = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(PATH_TOKENIZER)
+                .set_index_option(IndexRecordOption::Basic),
+        )
+        .set_stored();
+    schema_builder.add_text_field(FIELD_TARGET_FILE, target_file_options);
+
+    // symbols: TEXT (identifiers of a code block, split at camelCase and snake_case)
+    let symbol_options = TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(CODE_TOKENIZER)
+            .set_index_option(IndexRecordOption::WithFreqs),
+    );
+    schema_builder.add_text_field(FIELD_SYMBOLS, symbol_options);
+
+    // tool_name: TEXT (name of a tool call, matched whole and case-insensitively)
+    let tool_name_options = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TOOL_NAME_TOKENIZER)
+                .set_index_option(IndexRecordOption::Basic),
+        )
+        .set_stored();
+    schema_builder.add_text_field(FIELD_TOOL_NAME, tool_name_options);
+
+    // exit_status: I64 (0 for a successful tool call, the exit code otherwise)
+    schema_builder.add_i64_field(FIELD_EXIT_STATUS, STORED | INDEXED | FAST);
+
+    // facets: FACET (exact tool, project, category and month, for counting
+    // matches per value and filtering on one)
+    schema_builder.add_facet_field(FIELD_FACETS, FacetOptions::default());
+
+    // tokens: u64 (estimated tokens of a message, for statistics; absent on
+    // code blocks and tool calls, whose text is part of a message already)
+    schema_builder.add_u64_field(FIELD_TOKENS, STORED | FAST);
+
+    // month: STRING (YYYY-MM of the timestamp; months vary in length, so
+    // monthly histograms group by this instead of by a fixed interval)
+    schema_builder.add_text_field(FIELD_MONTH, STRING | FAST);
+
     schema_builder.build()
 }
 
+/// Registers the custom tokenizers the schema refers to; needed whenever an
+/// index is opened or created, since tokenizers are not persisted
+pub fn register_tokenizers(index: &Index) {
+    register_code_tokenizer(index);
+    register_tool_call_tokenizers(index);
+}
+
 /// Represents a log entry document in the Tantivy index
 pub struct LogEntryDocument {
     pub doc_id: u64,
@@ -77,6 +176,14 @@ pub struct LogEntryDocument {
     pub message: String,
     pub file_path: String,
     pub project: String,
+    pub session_id: String,
+    pub turn: u64,
+    pub line: u64,
+    pub role: String,
+    pub language: String,
+    pub target_file: String,
+    pub tool_name: String,
+    pub exit_status: Option<i64>,
 }
 
 impl LogEntryDocument {
@@ -100,9 +207,91 @@ impl LogEntryDocument {
             message: log_entry.message.clone(),
             file_path: file_path.to_string_lossy().to_string(),
             project,
+            session_id: String::new(),
+            turn: 0,
+            line: 0,
+            role: role_for(&log_entry.category).to_string(),
+            language: String::new(),
+            target_file: String::new(),
+            tool_name: String::new(),
+            exit_status: None,
         }
     }
 
+    /// A document for one code block of this entry, in the same conversation turn
+    pub fn code_block(&self, block: &CodeBlock, doc_id: u64) -> Self {
+        LogEntryDocument {
+            doc_id,
+            tool: self.tool.clone(),
+            log_type: self.log_type.clone(),
+            timestamp: self.timestamp,
+            level: self.level.clone(),
+            category: CODE_BLOCK_CATEGORY.to_string(),
+            message: block.code.clone(),
+            file_path: self.file_path.clone(),
+            project: self.project.clone(),
+            session_id: self.session_id.clone(),
+            turn: self.turn,
+            line: self.line,
+            role: self.role.clone(),
+            language: block.language.clone(),
+            target_file: block.file_path.clone().unwrap_or_default(),
+            tool_name: String::new(),
+            exit_status: None,
+        }
+    }
+
+    /// A document for a tool call requested by this entry, in the same
+    /// conversation turn; failed calls are indexed at error level
+    pub fn tool_call(&self, call: &ToolCallRecord, doc_id: u64) -> Self {
+        let failed = call.exit_status.is_some_and(|status| status != 0);
+        LogEntryDocument {
+            doc_id,
+            tool: self.tool.clone(),
+            log_type: self.log_type.clone(),
+            timestamp: call.requested_at.or(self.timestamp),
+            level: if failed { "Error".to_string() } else { self.level.clone() },
+            category: TOOL_CALL_CATEGORY.to_string(),
+            message: call.arguments.clone(),
+            file_path: self.file_path.clone(),
+            project: self.project.clone(),
+            session_id: self.session_id.clone(),
+            turn: self.turn,
+            line: self.line,
+            role: self.role.clone(),
+            language: String::new(),
+            target_file: call.target_file.clone().unwrap_or_default(),
+            tool_name: call.name.clone(),
+            exit_status: call.exit_status,
+        }
+    }
+
+    /// Places this entry at `turn` of the conversation `session_id`
+    pub fn with_turn(mut self, session_id: &str, turn: u64) -> Self {
+        self.session_id = session_id.to_string();
+        self.turn = turn;
+        self
+    }
+
+    /// Records the source line this entry was parsed from
+    pub fn at_line(mut self, line: u64) -> Self {
+        self.line = line;
+        self
+    }
+
+    /// Value of each of the `FACET_DIMENSIONS` for this entry
+    pub fn facet_values(&self) -> Vec<(&'static str, String)> {
+        let mut values = vec![
+            ("tool", self.tool.clone()),
+            ("project", self.project.clone()),
+            ("category", self.category.clone()),
+        ];
+        if let Some(timestamp) = self.timestamp {
+            values.push(("month", timestamp.format("%Y-%m").to_string()));
+        }
+        values
+    }
+
     /// Converts this LogEntryDocument to a Tantivy Document
     pub fn to_tantivy_document(&self, schema: &Schema) -> TantivyDocument {
         let mut doc = TantivyDocument::default();
@@ -122,6 +311,9 @@ impl LogEntryDocument {
             // Convert chrono DateTime to tantivy DateTime (Unix timestamp in microseconds)
             let tantivy_dt = tantivy::DateTime::from_timestamp_micros(timestamp.timestamp_micros());
             doc.add_date(timestamp_field, tantivy_dt);
+
+            let month_field = schema.get_field(FIELD_MONTH).unwrap();
+            doc.add_text(month_field, timestamp.format("%Y-%m").to_string());
         }
 
         let level_field = schema.get_field(FIELD_LEVEL).unwrap();
@@ -139,10 +331,82 @@ impl LogEntryDocument {
         let project_field = schema.get_field(FIELD_PROJECT).unwrap();
         doc.add_text(project_field, &self.project);
 
+        let session_id_field = schema.get_field(FIELD_SESSION_ID).unwrap();
+        doc.add_text(session_id_field, &self.session_id);
+
+        let turn_field = schema.get_field(FIELD_TURN).unwrap();
+        doc.add_u64(turn_field, self.turn);
+
+        let line_field = schema.get_field(FIELD_LINE).unwrap();
+        doc.add_u64(line_field, self.line);
+
+        let role_field = schema.get_field(FIELD_ROLE).unwrap();
+        doc.add_text(role_field, &self.role);
+
+        let facets_field = schema.get_field(FIELD_FACETS).unwrap();
+        for (dimension, value) in self.facet_values() {
+            if !value.is_empty() {
+                doc.add_facet(facets_field, Facet::from_path([dimension, value.as_str()]));
+            }
+        }
+
+        if self.category != CODE_BLOCK_CATEGORY && self.category != TOOL_CALL_CATEGORY {
+            let tokens_field = schema.get_field(FIELD_TOKENS).unwrap();
+            doc.add_u64(tokens_field, estimate_tokens(&self.message) as u64);
+        }
+
+        if self.category == CODE_BLOCK_CATEGORY {
+            let language_field = schema.get_field(FIELD_LANGUAGE).unwrap();
+            doc.add_text(language_field, &self.language);
+
+            let target_file_field = schema.get_field(FIELD_TARGET_FILE).unwrap();
+            doc.add_text(target_file_field, &self.target_file);
+
+            let symbols_field = schema.get_field(FIELD_SYMBOLS).unwrap();
+            doc.add_text(symbols_field, &self.message);
+        }
+
+        if self.category == TOOL_CALL_CATEGORY {
+            let tool_name_field = schema.get_field(FIELD_TOOL_NAME).unwrap();
+            doc.add_text(tool_name_field, &self.tool_name);
+
+            if !self.target_file.is_empty() {
+                let target_file_field = schema.get_field(FIELD_TARGET_FILE).unwrap();
+                doc.add_text(target_file_field, &self.target_file);
+            }
+
+            if let Some(exit_status) = self.exit_status {
+                let exit_status_field = schema.get_field(FIELD_EXIT_STATUS).unwrap();
+                doc.add_i64(exit_status_field, exit_status);
+            }
+        }
+
         doc
     }
 }
 
+/// Conversation role of an entry category
+fn role_for(category: &EntryCategory) -> &'static str {
+    match category {
+        EntryCategory::UserPrompt => "user",
+        EntryCategory::AssistantResponse => "assistant",
+        EntryCategory::ToolUse | EntryCategory::FileOperation => "tool",
+        _ => "system",
+    }
+}
+
+/// Highest document id in the index, 0 when it is empty. Ids are handed
+/// out in increasing order, so documents above an earlier maximum are newer.
+pub fn max_doc_id(searcher: &Searcher) -> Result<u64> {
+    // Deleted documents leave gaps, so the document count is no bound
+    let mut max_doc_id = 0;
+    for segment in searcher.segment_readers() {
+        let doc_ids = segment.fast_fields().u64(FIELD_DOC_ID)?;
+        max_doc_id = max_doc_id.max(doc_ids.max_value());
+    }
+    Ok(max_doc_id)
+}
+
 /// Extracts project name from a file path
 /// Examples:
 /// - ~/.config/Code/User/globalStorage/saoudrizwan.claude-dev/tasks/vibedev/task.json -> "vibedev"
@@ -197,6 +461,15 @@ mod tests {
         assert!(schema.get_field(FIELD_MESSAGE).is_ok());
         assert!(schema.get_field(FIELD_FILE_PATH).is_ok());
         assert!(schema.get_field(FIELD_PROJECT).is_ok());
+        assert!(schema.get_field(FIELD_SESSION_ID).is_ok());
+        assert!(schema.get_field(FIELD_TURN).is_ok());
+        assert!(schema.get_field(FIELD_LINE).is_ok());
+        assert!(schema.get_field(FIELD_FACETS).is_ok());
+        assert!(schema.get_field(FIELD_TOKENS).is_ok());
+        assert!(schema.get_field(FIELD_MONTH).is_ok());
+        assert!(schema.get_field(FIELD_ROLE).is_ok());
+        assert!(schema.get_field(FIELD_TOOL_NAME).is_ok());
+        assert!(schema.get_field(FIELD_EXIT_STATUS).is_ok());
     }
 
     #[test]
@@ -221,6 +494,36 @@ mod tests {
         assert_eq!(doc.category, "UserPrompt");
         assert_eq!(doc.message, "Test message");
         assert_eq!(doc.project, "my-app");
+        assert_eq!(doc.role, "user");
+        assert_eq!(doc.session_id, "");
+
+        let doc = doc.with_turn("abc-123", 4).at_line(9);
+        assert_eq!(doc.session_id, "abc-123");
+        assert_eq!((doc.turn, doc.line), (4, 9));
+
+        let block = CodeBlock {
+            language: "rust".to_string(),
+            file_path: Some("src/lib.rs".to_string()),
+            code: "fn main() {}".to_string(),
+        };
+        let code = doc.code_block(&block, 2);
+        assert_eq!(code.category, CODE_BLOCK_CATEGORY);
+        assert_eq!(code.message, "fn main() {}");
+        assert_eq!((code.session_id.as_str(), code.turn, code.line), ("abc-123", 4, 9));
+        assert_eq!(code.target_file, "src/lib.rs");
+
+        let call = ToolCallRecord {
+            name: "Bash".to_string(),
+            arguments: "cargo test".to_string(),
+            target_file: None,
+            exit_status: Some(101),
+            requested_at: None,
+        };
+        let call = doc.tool_call(&call, 3);
+        assert_eq!(call.category, TOOL_CALL_CATEGORY);
+        assert_eq!(call.message, "cargo test");
+        assert_eq!(call.level, "Error");
+        assert_eq!((call.tool_name.as_str(), call.exit_status), ("Bash", Some(101)));
     }
 
     #[test]
@@ -255,6 +558,6 @@ mod tests {
         let tantivy_doc = entry_doc.to_tantivy_document(&schema);
 
         // Verify document was created (basic check)
-        assert!(!tantivy_doc.is_empty());
+        assert!(!tantivy_doc.field_values().is_empty());
     }
 }
@@ -0,0 +1,240 @@
+// Tool calls in Claude Code transcripts as structured search records
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+use tantivy::tokenizer::{LowerCaser, RawTokenizer, TextAnalyzer, Token, TokenStream, Tokenizer};
+use tantivy::Index;
+
+use crate::parsers::LogEntry;
+use crate::tool_analytics::{pair_tool_calls, transcript_events, ToolInvocation};
+
+/// Name the path tokenizer is registered under
+pub const PATH_TOKENIZER: &str = "path";
+
+/// Name of the whole-value, case-insensitive tokenizer used for tool names
+pub const TOOL_NAME_TOKENIZER: &str = "raw_lowercase";
+
+lazy_static! {
+    /// Claude Code prefixes failed Bash results with the exit code
+    static ref EXIT_CODE: Regex = Regex::new(r"^\s*Exit code (-?\d+)").unwrap();
+}
+
+/// One tool call with what it did and how it ended
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolCallRecord {
+    pub name: String,
+    /// Searchable form of the input: the command for Bash, the JSON input otherwise
+    pub arguments: String,
+    /// File or directory the call operated on
+    pub target_file: Option<String>,
+    /// 0 on success; the process exit code for failed commands, 1 for other
+    /// failures; `None` when no result was recorded
+    pub exit_status: Option<i64>,
+    pub requested_at: Option<DateTime<Utc>>,
+}
+
+impl ToolCallRecord {
+    pub fn from_invocation(invocation: &ToolInvocation) -> Self {
+        let input = &invocation.input;
+        let arguments = match input.get("command").and_then(Value::as_str) {
+            Some(command) if invocation.name == "Bash" => command.to_string(),
+            _ => input.to_string(),
+        };
+        let target_file = ["file_path", "notebook_path", "path"]
+            .iter()
+            .find_map(|key| input.get(*key).and_then(Value::as_str))
+            .map(String::from);
+
+        let exit_status = invocation.completed.then(|| {
+            if !invocation.is_error {
+                return 0;
+            }
+            invocation
+                .result_preview
+                .as_deref()
+                .and_then(|preview| EXIT_CODE.captures(preview))
+                .and_then(|c| c[1].parse().ok())
+                .unwrap_or(1)
+        });
+
+        Self {
+            name: invocation.name.clone(),
+            arguments,
+            target_file,
+            exit_status,
+            requested_at: invocation.requested_at,
+        }
+    }
+}
+
+/// Tool calls in a transcript, each with the index of the parsed entry
+/// (conversation turn) that requested it. `entries` are the transcript's
+/// entries as parsed by the Claude parser, where tool uses appear as
+/// `[Name] {input}` lines.
+pub fn tool_calls_by_turn(content: &str, entries: &[LogEntry]) -> Vec<(usize, ToolCallRecord)> {
+    let mut next_turn = 0;
+    pair_tool_calls(transcript_events(content))
+        .iter()
+        .filter_map(|invocation| {
+            let marker = format!("[{}] ", invocation.name);
+            // Calls come in transcript order, so search forward from the last match
+            let turn = (next_turn..entries.len()).find(|&i| {
+                entries[i].timestamp == invocation.requested_at && entries[i].message.contains(&marker)
+            })?;
+            next_turn = turn;
+            Some((turn, ToolCallRecord::from_invocation(invocation)))
+        })
+        .collect()
+}
+
+/// Path as written in tool inputs and as given to `--file`: relative
+/// paths lose a leading `./`
+pub fn normalize_path(path: &str) -> String {
+    let mut path = path.trim();
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    path.to_string()
+}
+
+/// Tokenizer for file paths: the whole path plus every suffix starting after
+/// a `/`, so `/repo/src/main.rs` matches `src/main.rs` and `main.rs`
+#[derive(Clone, Default)]
+pub struct PathTokenizer {
+    token: Token,
+}
+
+pub struct PathTokenStream<'a> {
+    tokens: std::vec::IntoIter<Token>,
+    current: &'a mut Token,
+}
+
+impl Tokenizer for PathTokenizer {
+    type TokenStream<'a> = PathTokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> PathTokenStream<'a> {
+        let path = normalize_path(text);
+        let starts = std::iter::once(0).chain(path.match_indices('/').map(|(i, _)| i + 1));
+        let tokens: Vec<Token> = starts
+            .filter(|&start| start < path.len())
+            .map(|start| Token {
+                offset_from: start,
+                offset_to: path.len(),
+                position: 0,
+                text: path[start..].to_string(),
+                position_length: 1,
+            })
+            .collect();
+
+        self.token = Token::default();
+        PathTokenStream {
+            tokens: tokens.into_iter(),
+            current: &mut self.token,
+        }
+    }
+}
+
+impl TokenStream for PathTokenStream<'_> {
+    fn advance(&mut self) -> bool {
+        match self.tokens.next() {
+            Some(token) => {
+                *self.current = token;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn token(&self) -> &Token {
+        self.current
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        self.current
+    }
+}
+
+/// Makes the path and tool-name tokenizers available to an index
+pub fn register_tool_call_tokenizers(index: &Index) {
+    let tokenizers = index.tokenizers();
+    tokenizers.register(PATH_TOKENIZER, PathTokenizer::default());
+    tokenizers.register(
+        TOOL_NAME_TOKENIZER,
+        TextAnalyzer::builder(RawTokenizer::default())
+            .filter(LowerCaser)
+            .build(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::claude::ClaudeParser;
+    use crate::parsers::LogParser;
+    use serde_json::json;
+
+    #[test]
+    fn test_tool_calls_by_turn() {
+        let lines = [
+            json!({"type": "user", "timestamp": "2026-03-01T10:00:00Z",
+                "message": {"role": "user", "content": "clean the build and fix main"}}),
+            json!({"type": "assistant", "timestamp": "2026-03-01T10:00:05Z",
+                "message": {"role": "assistant", "content": [
+                    {"type": "text", "text": "Cleaning up"},
+                    {"type": "tool_use", "id": "a", "name": "Bash", "input": {"command": "rm -rf target/"}}]}}),
+            json!({"type": "user", "timestamp": "2026-03-01T10:00:09Z",
+                "message": {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "a", "content": "Exit code 2\nrm: cannot remove", "is_error": true}]}}),
+            json!({"type": "assistant", "timestamp": "2026-03-01T10:00:12Z",
+                "message": {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "b", "name": "Edit",
+                     "input": {"file_path": "/repo/src/main.rs", "old_string": "a", "new_string": "b"}}]}}),
+            json!({"type": "user", "timestamp": "2026-03-01T10:00:13Z",
+                "message": {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "b", "content": "ok"}]}}),
+            json!({"type": "assistant", "timestamp": "2026-03-01T10:00:20Z",
+                "message": {"role": "assistant", "content": [
+                    {"type": "tool_use", "id": "c", "name": "Read", "input": {"file_path": "/repo/README.md"}}]}}),
+        ];
+        let content = lines.iter().map(|l| l.to_string()).collect::<Vec<_>>().join("\n");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".claude/projects/-repo/s1.jsonl");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &content).unwrap();
+        let entries = ClaudeParser.parse(&path).unwrap().entries;
+
+        let calls = tool_calls_by_turn(&content, &entries);
+        assert_eq!(calls.len(), 3);
+
+        let (turn, bash) = &calls[0];
+        assert_eq!(*turn, 1);
+        assert_eq!(bash.arguments, "rm -rf target/");
+        assert_eq!(bash.exit_status, Some(2));
+
+        let (turn, edit) = &calls[1];
+        assert_eq!(*turn, 3);
+        assert_eq!(edit.target_file.as_deref(), Some("/repo/src/main.rs"));
+        assert_eq!(edit.exit_status, Some(0));
+
+        // No result recorded
+        assert_eq!(calls[2].1.exit_status, None);
+    }
+
+    #[test]
+    fn test_path_tokenizer() {
+        let mut tokenizer = PathTokenizer::default();
+        let mut stream = tokenizer.token_stream("/repo/src/main.rs");
+        let mut texts = Vec::new();
+        while stream.advance() {
+            texts.push(stream.token().text.clone());
+        }
+        assert_eq!(
+            texts,
+            ["/repo/src/main.rs", "repo/src/main.rs", "src/main.rs", "main.rs"]
+        );
+        assert_eq!(normalize_path("./src/lib.rs"), "src/lib.rs");
+    }
+}
@@ -0,0 +1,782 @@
+//! Interactive search
+//!
+//! Fuzzy, live-as-you-type search over the index using ratatui, with facet
+//! sidebars to narrow results and a preview of the whole conversation.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::Frame;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::query_executor::{
+    conversation_markdown, Conversation, FacetCounts, QueryExecutor, SearchQuery, SearchResult,
+    SortOrder,
+};
+use super::schema::FACET_DIMENSIONS;
+
+const UPDATE_INTERVAL_MS: u64 = 50;
+
+/// Keystrokes within this long of each other are searched once
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Results fetched per query
+const RESULT_LIMIT: usize = 200;
+
+/// Lines PgUp/PgDn scroll the preview by
+const PREVIEW_PAGE: isize = 10;
+
+/// Clipboard programs tried in order; OSC 52 is the fallback
+const CLIPBOARD_COMMANDS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Which panel the arrow keys and Enter act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Results,
+    /// Facet sidebar, by index into `FACET_DIMENSIONS`
+    Facet(usize),
+}
+
+/// Interactive search application state
+pub struct SearchApp {
+    executor: QueryExecutor,
+    input: String,
+    /// When the input last changed, if it has not been searched yet
+    pending_since: Option<Instant>,
+    results: Vec<SearchResult>,
+    total_found: usize,
+    search_time_ms: u64,
+    facets: Vec<FacetCounts>,
+    /// Active `(dimension, value)` filters
+    selected_facets: Vec<(String, String)>,
+    focus: Focus,
+    selected: usize,
+    /// Cursor per facet sidebar
+    facet_cursor: [usize; FACET_DIMENSIONS.len()],
+    /// Conversation of the selected result
+    preview: Option<Conversation>,
+    /// Preview scroll relative to the selected turn
+    preview_scroll: isize,
+    /// Last message for the status bar, and whether it reports a failure
+    status: Option<(String, bool)>,
+    /// Where conversations are exported
+    export_dir: PathBuf,
+    should_quit: bool,
+}
+
+impl SearchApp {
+    pub fn new(executor: QueryExecutor) -> Self {
+        Self {
+            executor,
+            input: String::new(),
+            pending_since: None,
+            results: Vec::new(),
+            total_found: 0,
+            search_time_ms: 0,
+            facets: Vec::new(),
+            selected_facets: Vec::new(),
+            focus: Focus::Results,
+            selected: 0,
+            facet_cursor: [0; FACET_DIMENSIONS.len()],
+            preview: None,
+            preview_scroll: 0,
+            status: None,
+            export_dir: PathBuf::from("."),
+            should_quit: false,
+        }
+    }
+
+    fn query(&self) -> SearchQuery {
+        let text = self.input.trim().to_string();
+        // Without words to rank by, show the latest entries first
+        let sort = if text.is_empty() {
+            SortOrder::Newest
+        } else {
+            SortOrder::Relevance
+        };
+        SearchQuery {
+            text,
+            fuzzy: true,
+            facets: self.selected_facets.clone(),
+            limit: RESULT_LIMIT,
+            sort,
+            ..Default::default()
+        }
+    }
+
+    /// Runs the current query, keeping the previous results if it fails
+    fn refresh(&mut self) {
+        self.pending_since = None;
+        match self.search() {
+            Ok(()) => self.status = None,
+            Err(e) => self.status = Some((format!("Query failed: {:#}", e), true)),
+        }
+        self.load_preview();
+    }
+
+    fn search(&mut self) -> Result<()> {
+        let query = self.query();
+        let results = self.executor.execute(&query)?;
+        let mut facets = self.executor.facet_counts(&query)?;
+
+        // A selected dimension is counted without its own filter, so
+        // alternative values stay visible with the counts they would add
+        for counts in facets.iter_mut() {
+            if self.selected_facets.iter().any(|(d, _)| *d == counts.dimension) {
+                let mut others = query.clone();
+                others.facets.retain(|(d, _)| *d != counts.dimension);
+                if let Some(own) = self
+                    .executor
+                    .facet_counts(&others)?
+                    .into_iter()
+                    .find(|c| c.dimension == counts.dimension)
+                {
+                    *counts = own;
+                }
+            }
+        }
+
+        self.results = results.results;
+        self.total_found = results.total_found;
+        self.search_time_ms = results.search_time_ms;
+        self.facets = facets;
+        self.selected = self.selected.min(self.results.len().saturating_sub(1));
+        for (cursor, counts) in self.facet_cursor.iter_mut().zip(&self.facets) {
+            *cursor = (*cursor).min(counts.values.len().saturating_sub(1));
+        }
+        Ok(())
+    }
+
+    fn selected_result(&self) -> Option<&SearchResult> {
+        self.results.get(self.selected)
+    }
+
+    /// Loads the conversation of the selected result, unless already shown
+    fn load_preview(&mut self) {
+        self.preview_scroll = 0;
+        let Some(session_id) = self
+            .selected_result()
+            .map(|r| r.session_id.clone())
+            .filter(|s| !s.is_empty())
+        else {
+            self.preview = None;
+            return;
+        };
+        if self.preview.as_ref().is_some_and(|c| c.session_id == session_id) {
+            return;
+        }
+        self.preview = match self.executor.conversation(&session_id) {
+            Ok(conversation) => conversation,
+            Err(e) => {
+                self.status = Some((format!("Could not load conversation: {:#}", e), true));
+                None
+            }
+        };
+    }
+
+    /// Run the TUI event loop
+    pub fn run(&mut self, terminal: &mut ratatui::Terminal<impl ratatui::backend::Backend>) -> Result<()> {
+        self.refresh();
+        loop {
+            terminal.draw(|f| self.draw(f))?;
+
+            if event::poll(Duration::from_millis(UPDATE_INTERVAL_MS))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        self.handle_key(key);
+                    }
+                }
+            }
+
+            if self.pending_since.is_some_and(|since| since.elapsed() >= DEBOUNCE) {
+                self.refresh();
+            }
+
+            if self.should_quit {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('c') if ctrl => self.should_quit = true,
+            KeyCode::Char('y') if ctrl => self.copy_prompt(),
+            KeyCode::Char('o') if ctrl => self.open_project(),
+            KeyCode::Char('e') if ctrl => self.export_conversation(),
+            KeyCode::Char('u') if ctrl => self.edit_input(String::clear),
+            KeyCode::Char(c) if !ctrl => self.edit_input(|input| input.push(c)),
+            KeyCode::Backspace => self.edit_input(|input| {
+                input.pop();
+            }),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Results => Focus::Facet(0),
+                    Focus::Facet(i) if i + 1 < FACET_DIMENSIONS.len() => Focus::Facet(i + 1),
+                    Focus::Facet(_) => Focus::Results,
+                };
+            }
+            KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Results => Focus::Facet(FACET_DIMENSIONS.len() - 1),
+                    Focus::Facet(0) => Focus::Results,
+                    Focus::Facet(i) => Focus::Facet(i - 1),
+                };
+            }
+            KeyCode::Up => self.move_cursor(-1),
+            KeyCode::Down => self.move_cursor(1),
+            KeyCode::PageUp => self.preview_scroll -= PREVIEW_PAGE,
+            KeyCode::PageDown => self.preview_scroll += PREVIEW_PAGE,
+            KeyCode::Enter => {
+                if let Focus::Facet(dimension) = self.focus {
+                    self.toggle_facet(dimension);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn edit_input(&mut self, edit: impl FnOnce(&mut String)) {
+        edit(&mut self.input);
+        self.pending_since = Some(Instant::now());
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        let step = |position: usize, len: usize| {
+            position
+                .saturating_add_signed(delta)
+                .min(len.saturating_sub(1))
+        };
+        match self.focus {
+            Focus::Results => {
+                let selected = step(self.selected, self.results.len());
+                if selected != self.selected {
+                    self.selected = selected;
+                    self.load_preview();
+                }
+            }
+            Focus::Facet(dimension) => {
+                let len = self.facets.get(dimension).map_or(0, |c| c.values.len());
+                self.facet_cursor[dimension] = step(self.facet_cursor[dimension], len);
+            }
+        }
+    }
+
+    /// Adds or removes the facet value under the cursor as a filter
+    fn toggle_facet(&mut self, dimension: usize) {
+        let Some((value, _)) = self
+            .facets
+            .get(dimension)
+            .and_then(|c| c.values.get(self.facet_cursor[dimension]))
+        else {
+            return;
+        };
+        let facet = (FACET_DIMENSIONS[dimension].to_string(), value.clone());
+        match self.selected_facets.iter().position(|f| *f == facet) {
+            Some(i) => {
+                self.selected_facets.remove(i);
+            }
+            None => self.selected_facets.push(facet),
+        }
+        self.selected = 0;
+        self.refresh();
+    }
+
+    /// The prompt behind the selected result: the result itself when it is
+    /// a prompt, otherwise the last prompt before it in the conversation
+    fn selected_prompt(&self) -> Option<String> {
+        let result = self.selected_result()?;
+        if result.role == "user" || self.preview.is_none() {
+            return Some(result.message.clone());
+        }
+        self.preview
+            .iter()
+            .flat_map(|c| c.turns.iter())
+            .rfind(|t| t.role == "user" && t.turn <= result.turn)
+            .map(|t| t.message.clone())
+            .or_else(|| Some(result.message.clone()))
+    }
+
+    fn copy_prompt(&mut self) {
+        let Some(prompt) = self.selected_prompt() else {
+            self.status = Some(("Nothing selected".to_string(), true));
+            return;
+        };
+        self.status = Some(match copy_to_clipboard(&prompt) {
+            Ok(via) => (format!("Copied prompt ({} chars) via {}", prompt.chars().count(), via), false),
+            Err(e) => (format!("Copy failed: {:#}", e), true),
+        });
+    }
+
+    fn open_project(&mut self) {
+        let Some(result) = self.selected_result() else {
+            self.status = Some(("Nothing selected".to_string(), true));
+            return;
+        };
+        let Some(dir) = project_dir(result) else {
+            self.status = Some((format!("Directory of {} not found", result.project), true));
+            return;
+        };
+        self.status = Some(match open::that(&dir) {
+            Ok(()) => (format!("Opened {}", dir.display()), false),
+            Err(e) => (format!("Could not open {}: {}", dir.display(), e), true),
+        });
+    }
+
+    fn export_conversation(&mut self) {
+        let Some(conversation) = &self.preview else {
+            self.status = Some(("The selected result is not part of a conversation".to_string(), true));
+            return;
+        };
+        let path = self.export_dir.join(format!("{}.md", conversation.session_id));
+        self.status = Some(match std::fs::write(&path, conversation_markdown(conversation, "#")) {
+            Ok(()) => (format!("Exported {} turns to {}", conversation.turns.len(), path.display()), false),
+            Err(e) => (format!("Could not write {}: {}", path.display(), e), true),
+        });
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Query
+                Constraint::Min(10),   // Content
+                Constraint::Length(3), // Status bar
+            ])
+            .split(f.area());
+
+        self.draw_input(f, chunks[0]);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(28),
+                Constraint::Percentage(40),
+                Constraint::Min(30),
+            ])
+            .split(chunks[1]);
+        self.draw_facets(f, columns[0]);
+        self.draw_results(f, columns[1]);
+        self.draw_preview(f, columns[2]);
+
+        self.draw_status_bar(f, chunks[2]);
+    }
+
+    fn draw_input(&self, f: &mut Frame, area: Rect) {
+        let mut spans = vec![Span::styled("› ", Style::default().fg(Color::Cyan)), Span::raw(&self.input)];
+        for (dimension, value) in &self.selected_facets {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                format!("[{}: {}]", dimension, value),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let input = Paragraph::new(Line::from(spans))
+            .block(Block::default().borders(Borders::ALL).title("Search (fuzzy)"));
+        f.render_widget(input, area);
+        // Cursor after the query text, inside the border
+        let cursor_x = area.x + 3 + self.input.chars().count() as u16;
+        f.set_cursor_position((cursor_x.min(area.right().saturating_sub(2)), area.y + 1));
+    }
+
+    fn draw_facets(&self, f: &mut Frame, area: Rect) {
+        let panels = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Ratio(1, FACET_DIMENSIONS.len() as u32); FACET_DIMENSIONS.len()])
+            .split(area);
+
+        for (i, dimension) in FACET_DIMENSIONS.iter().enumerate() {
+            let focused = self.focus == Focus::Facet(i);
+            let values = self
+                .facets
+                .iter()
+                .find(|c| c.dimension == *dimension)
+                .map(|c| c.values.as_slice())
+                .unwrap_or_default();
+
+            let rows: Vec<Row> = values
+                .iter()
+                .enumerate()
+                .map(|(j, (value, count))| {
+                    let active = self
+                        .selected_facets
+                        .iter()
+                        .any(|(d, v)| d == dimension && v == value);
+                    let mut style = Style::default();
+                    if active {
+                        style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
+                    }
+                    if focused && j == self.facet_cursor[i] {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Row::new(vec![
+                        Cell::from(if active { "✓" } else { " " }),
+                        Cell::from(value.clone()),
+                        Cell::from(count.to_string()).style(Style::default().fg(Color::DarkGray)),
+                    ])
+                    .style(style)
+                })
+                .collect();
+
+            let border = if focused {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            let table = Table::new(
+                rows,
+                [Constraint::Length(1), Constraint::Min(10), Constraint::Length(6)],
+            )
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(border)
+                    .title(dimension.to_string()),
+            );
+            let mut state = TableState::default().with_selected(focused.then_some(self.facet_cursor[i]));
+            f.render_stateful_widget(table, panels[i], &mut state);
+        }
+    }
+
+    fn draw_results(&self, f: &mut Frame, area: Rect) {
+        let rows: Vec<Row> = self
+            .results
+            .iter()
+            .map(|result| {
+                let time = result
+                    .timestamp
+                    .as_ref()
+                    .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+                    .map(|dt| dt.format("%m-%d %H:%M").to_string())
+                    .unwrap_or_default();
+                let message = result.message.split_whitespace().collect::<Vec<_>>().join(" ");
+                let kind = if result.role.is_empty() { &result.category } else { &result.role };
+                Row::new(vec![
+                    Cell::from(time).style(Style::default().fg(Color::DarkGray)),
+                    Cell::from(result.project.clone()).style(Style::default().fg(Color::Cyan)),
+                    Cell::from(kind.clone()).style(Style::default().fg(Color::Green)),
+                    Cell::from(message),
+                ])
+            })
+            .collect();
+
+        let border = if self.focus == Focus::Results {
+            Style::default().fg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+        let title = format!(
+            "Results ({} of {}, {}ms)",
+            self.results.len(),
+            self.total_found,
+            self.search_time_ms
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(11),
+                Constraint::Length(14),
+                Constraint::Length(9),
+                Constraint::Min(10),
+            ],
+        )
+        .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .block(Block::default().borders(Borders::ALL).border_style(border).title(title));
+
+        let mut state = TableState::default().with_selected((!self.results.is_empty()).then_some(self.selected));
+        f.render_stateful_widget(table, area, &mut state);
+    }
+
+    fn draw_preview(&self, f: &mut Frame, area: Rect) {
+        let width = area.width.saturating_sub(2).max(1) as usize;
+        let selected_turn = self.selected_result().map(|r| r.turn);
+
+        let mut lines: Vec<Line> = Vec::new();
+        let mut anchor = 0;
+        let title = match (&self.preview, self.selected_result()) {
+            (Some(conversation), _) => {
+                for turn in &conversation.turns {
+                    let is_selected = Some(turn.turn) == selected_turn;
+                    if is_selected {
+                        anchor = lines.len();
+                    }
+                    let header_style = if is_selected {
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::Green)
+                    };
+                    let marker = if is_selected { "▶" } else { " " };
+                    lines.push(Line::from(Span::styled(
+                        format!("{} #{} {}", marker, turn.turn, turn.role),
+                        header_style,
+                    )));
+                    for line in turn.message.lines() {
+                        lines.extend(wrap(line, width).into_iter().map(Line::from));
+                    }
+                    lines.push(Line::from(""));
+                }
+                format!("{} · session {}", conversation.project, conversation.session_id)
+            }
+            (None, Some(result)) => {
+                for line in result.message.lines() {
+                    lines.extend(wrap(line, width).into_iter().map(Line::from));
+                }
+                result.file_path.clone()
+            }
+            (None, None) => "Preview".to_string(),
+        };
+
+        // Keep the selected turn near the top unless scrolled away from it
+        let scroll = (anchor as isize + self.preview_scroll)
+            .clamp(0, lines.len().saturating_sub(1) as isize) as u16;
+        let preview = Paragraph::new(lines)
+            .scroll((scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(preview, area);
+    }
+
+    fn draw_status_bar(&self, f: &mut Frame, area: Rect) {
+        let status_line = match &self.status {
+            Some((message, is_error)) => Line::from(Span::styled(
+                format!(" {}", message),
+                Style::default().fg(if *is_error { Color::Red } else { Color::Green }),
+            )),
+            None => Line::from(Span::raw(
+                "Esc: quit  ↑↓: select  Tab: facets  Enter: toggle facet  PgUp/PgDn: scroll  \
+                 ^Y: copy prompt  ^O: open project  ^E: export",
+            )),
+        };
+        let status_widget = Paragraph::new(status_line).block(Block::default().borders(Borders::ALL));
+        f.render_widget(status_widget, area);
+    }
+}
+
+/// Splits a line into pieces of at most `width` characters
+fn wrap(line: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+/// Copies text with the first clipboard program found, or through the
+/// terminal with an OSC 52 escape; returns how it was copied
+fn copy_to_clipboard(text: &str) -> Result<&'static str> {
+    for (program, args) in CLIPBOARD_COMMANDS {
+        let Ok(mut child) = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        if child.wait()?.success() {
+            return Ok(program);
+        }
+    }
+
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()?;
+    Ok("terminal")
+}
+
+/// Directory the conversation of a result ran in: the `cwd` Claude Code
+/// records in transcripts, else the project directory itself
+fn project_dir(result: &SearchResult) -> Option<PathBuf> {
+    let file_path = Path::new(&result.file_path);
+    if let Ok(content) = std::fs::read_to_string(file_path) {
+        let cwd = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .find_map(|value| value.get("cwd").and_then(|c| c.as_str()).map(PathBuf::from));
+        if let Some(cwd) = cwd.filter(|dir| dir.is_dir()) {
+            return Some(cwd);
+        }
+    }
+
+    file_path
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == result.project.as_str()))
+        .filter(|dir| dir.is_dir())
+        .map(Path::to_path_buf)
+}
+
+/// Initialize and run interactive search over the index in `index_dir`
+pub fn run_search_tui(index_dir: &Path) -> Result<()> {
+    let executor = QueryExecutor::new(index_dir).context("Failed to open search index")?;
+
+    // Setup terminal
+    crossterm::terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
+
+    let backend = ratatui::backend::CrosstermBackend::new(stdout);
+    let mut terminal = ratatui::Terminal::new(backend)?;
+
+    // Run app
+    let mut app = SearchApp::new(executor);
+    let result = app.run(&mut terminal);
+
+    // Restore terminal
+    crossterm::terminal::disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), crossterm::terminal::LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AiTool;
+    use crate::parsers::{EntryCategory, LogEntry, LogLevel};
+    use crate::search::schema::{build_schema, register_tokenizers};
+    use crate::search::LogEntryDocument;
+    use ratatui::backend::TestBackend;
+    use tantivy::Index;
+
+    fn render(app: &SearchApp) -> String {
+        let mut terminal = ratatui::Terminal::new(TestBackend::new(160, 40)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    fn press(app: &mut SearchApp, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    /// Project, session id, and the timestamp and message of each turn
+    type Session<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+    fn app_with(sessions: &[Session]) -> (tempfile::TempDir, SearchApp) {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = build_schema();
+        let index = Index::create_in_dir(dir.path(), schema.clone()).unwrap();
+        register_tokenizers(&index);
+        let mut writer = index.writer(15_000_000).unwrap();
+
+        let mut doc_id = 0;
+        for (project, session, turns) in sessions {
+            let path = PathBuf::from(format!("/home/u/.claude/projects/{}/{}.jsonl", project, session));
+            for (turn, (timestamp, message)) in turns.iter().enumerate() {
+                let entry = LogEntry {
+                    timestamp: Some(timestamp.parse().unwrap()),
+                    level: LogLevel::Info,
+                    message: message.to_string(),
+                    category: if turn % 2 == 0 {
+                        EntryCategory::UserPrompt
+                    } else {
+                        EntryCategory::AssistantResponse
+                    },
+                };
+                let doc = LogEntryDocument::from_log_entry(&entry, doc_id, &AiTool::ClaudeCode, "Session", &path)
+                    .with_turn(session, turn as u64);
+                writer.add_document(doc.to_tantivy_document(&schema)).unwrap();
+                doc_id += 1;
+            }
+        }
+        writer.commit().unwrap();
+
+        let mut app = SearchApp::new(QueryExecutor::new(dir.path()).unwrap());
+        app.export_dir = dir.path().to_path_buf();
+        app.refresh();
+        (dir, app)
+    }
+
+    #[test]
+    fn test_live_search_facets_and_export() {
+        let (dir, mut app) = app_with(&[
+            ("shop", "s1", &[
+                ("2026-03-02T10:00:00Z", "why does the checkout test panic"),
+                ("2026-03-02T10:00:05Z", "the cart is empty when the handler runs"),
+            ]),
+            ("blog", "s2", &[
+                ("2026-04-10T09:00:00Z", "add a checkout link to the footer"),
+                ("2026-04-10T09:00:09Z", "done, the footer now links to the shop"),
+            ]),
+        ]);
+        assert_eq!(app.results.len(), 4);
+        // Newest first without a query
+        assert_eq!(app.results[0].session_id, "s2");
+
+        // Prefix of a word with a typo still matches both conversations
+        for c in "chekou".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert!(app.pending_since.is_some());
+        app.refresh();
+        assert_eq!(app.total_found, 2);
+        let screen = render(&app);
+        assert!(screen.contains("2026-03"));
+        assert!(screen.contains("2026-04"));
+        assert!(screen.contains("the cart is empty"));
+
+        // Filter on the first project listed, then remove the filter again
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.focus, Focus::Facet(1));
+        let project = app.facets[1].values[0].0.clone();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.selected_facets, [("project".to_string(), project.clone())]);
+        assert_eq!(app.total_found, 1);
+        assert!(app.results.iter().all(|r| r.project == project));
+        // The other project is still offered as an alternative
+        assert_eq!(app.facets[1].values.len(), 2);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.total_found, 2);
+
+        // The preview follows the selection, and the prompt is found from a response
+        press(&mut app, KeyCode::BackTab);
+        press(&mut app, KeyCode::BackTab);
+        press(&mut app, KeyCode::Down);
+        let session = app.selected_result().unwrap().session_id.clone();
+        assert_eq!(app.preview.as_ref().unwrap().session_id, session);
+        assert_eq!(app.preview.as_ref().unwrap().turns.len(), 2);
+        app.selected = app.results.iter().position(|r| r.role == "assistant").unwrap_or(app.selected);
+        app.load_preview();
+        assert_eq!(app.selected_prompt().unwrap(), app.preview.as_ref().unwrap().turns[0].message);
+
+        app.handle_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL));
+        let session = &app.preview.as_ref().unwrap().session_id;
+        let exported = std::fs::read_to_string(dir.path().join(format!("{}.md", session))).unwrap();
+        assert!(exported.starts_with(&format!("# Conversation {}", session)));
+        assert!(exported.contains("**assistant** (turn 1)"));
+        assert!(!app.status.as_ref().unwrap().1);
+    }
+
+    #[test]
+    fn test_unmatched_facet_filter() {
+        let (_dir, mut app) = app_with(&[("shop", "s1", &[("2026-03-02T10:00:00Z", "hello")])]);
+        app.selected_facets.push(("month".to_string(), "1999-01".to_string()));
+        app.refresh();
+        assert!(app.results.is_empty());
+        assert!(app.status.is_none());
+        assert!(render(&app).contains("[month: 1999-01]"));
+    }
+}
@@ -0,0 +1,413 @@
+// On-disk HNSW index of message embeddings, stored next to the tantivy index
+
+use anyhow::{Context, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::fs;
+use std::path::Path;
+use tantivy::collector::DocSetCollector;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
+use tantivy::schema::{IndexRecordOption, Value};
+use tantivy::{Index, TantivyDocument, Term};
+
+use super::embedder::TextEmbedder;
+use super::schema::{FIELD_CATEGORY, FIELD_DOC_ID, FIELD_MESSAGE};
+
+/// File name of the vector index inside the search index directory
+pub const VECTOR_INDEX_FILE: &str = "vectors.hnsw";
+
+/// Neighbours kept per node on upper layers (twice as many on layer 0)
+const MAX_LINKS: usize = 16;
+const EF_CONSTRUCTION: usize = 100;
+const EMBED_BATCH: usize = 32;
+
+/// Only prompts and responses are embedded; tool output and system noise
+/// would crowd out the conversations people look for
+const EMBEDDED_CATEGORIES: [&str; 2] = ["userprompt", "assistantresponse"];
+
+/// Distance between unit vectors, smaller is closer
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Distance(f32);
+
+impl Eq for Distance {}
+
+impl PartialOrd for Distance {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Distance {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    doc_id: u64,
+    vector: Vec<f32>,
+    /// Neighbour node indexes per layer, layer 0 first
+    links: Vec<Vec<u32>>,
+}
+
+/// Hierarchical navigable small world graph over unit-length vectors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndex {
+    model: String,
+    dimensions: usize,
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    /// Level generator state, kept so rebuilding the same inputs gives the same graph
+    rng_state: u64,
+    #[serde(skip)]
+    doc_ids: HashSet<u64>,
+}
+
+impl VectorIndex {
+    pub fn new(model: &str, dimensions: usize) -> Self {
+        Self {
+            model: model.to_string(),
+            dimensions,
+            nodes: Vec::new(),
+            entry_point: None,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+            doc_ids: HashSet::new(),
+        }
+    }
+
+    /// Loads the index in `dir`, or starts an empty one when it is missing
+    /// or was built with another model
+    pub fn open(dir: &Path, embedder: &dyn TextEmbedder) -> Result<Self> {
+        let path = dir.join(VECTOR_INDEX_FILE);
+        if path.exists() {
+            let bytes = fs::read(&path).context("Failed to read vector index")?;
+            if let Ok(mut index) = bincode::deserialize::<VectorIndex>(&bytes) {
+                if index.model == embedder.model_id() && index.dimensions == embedder.dimensions() {
+                    index.doc_ids = index.nodes.iter().map(|n| n.doc_id).collect();
+                    return Ok(index);
+                }
+            }
+        }
+        Ok(Self::new(embedder.model_id(), embedder.dimensions()))
+    }
+
+    /// Writes the index to `dir` atomically
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(VECTOR_INDEX_FILE);
+        let tmp = dir.join(format!("{}.tmp", VECTOR_INDEX_FILE));
+        fs::write(&tmp, bincode::serialize(self)?).context("Failed to write vector index")?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn contains(&self, doc_id: u64) -> bool {
+        self.doc_ids.contains(&doc_id)
+    }
+
+    /// Embeds prompts and responses of `index` that have no vector yet;
+    /// returns how many were added
+    pub fn sync(
+        &mut self,
+        index: &Index,
+        embedder: &dyn TextEmbedder,
+        show_progress: bool,
+    ) -> Result<usize> {
+        let schema = index.schema();
+        let doc_id_field = schema.get_field(FIELD_DOC_ID)?;
+        let message_field = schema.get_field(FIELD_MESSAGE)?;
+        let category_field = schema.get_field(FIELD_CATEGORY)?;
+
+        let searcher = index.reader()?.searcher();
+        let categories: Vec<(Occur, Box<dyn Query>)> = EMBEDDED_CATEGORIES
+            .iter()
+            .map(|c| {
+                let term = Term::from_field_text(category_field, c);
+                (
+                    Occur::Should,
+                    Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>,
+                )
+            })
+            .collect();
+        let addresses = searcher.search(&BooleanQuery::new(categories), &DocSetCollector)?;
+
+        let mut pending = Vec::new();
+        for address in addresses {
+            let doc: TantivyDocument = searcher.doc(address)?;
+            let Some(doc_id) = doc.get_first(doc_id_field).and_then(|v| v.as_u64()) else {
+                continue;
+            };
+            let message = doc
+                .get_first(message_field)
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            if !self.contains(doc_id) && !message.trim().is_empty() {
+                pending.push((doc_id, message.to_string()));
+            }
+        }
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        pending.sort_by_key(|(doc_id, _)| *doc_id);
+
+        let pb = show_progress.then(|| {
+            let pb = ProgressBar::new(pending.len() as u64);
+            pb.set_style(
+                ProgressStyle::default_bar()
+                    .template("   [{bar:40.cyan/blue}] {pos}/{len} messages embedded ETA: {eta}")
+                    .unwrap()
+                    .progress_chars("█▓▒░ "),
+            );
+            pb
+        });
+
+        for batch in pending.chunks(EMBED_BATCH) {
+            let texts: Vec<&str> = batch.iter().map(|(_, m)| m.as_str()).collect();
+            let vectors = embedder.embed(&texts)?;
+            for ((doc_id, _), vector) in batch.iter().zip(vectors) {
+                self.insert(*doc_id, vector);
+            }
+            if let Some(ref pb) = pb {
+                pb.inc(batch.len() as u64);
+            }
+        }
+        if let Some(pb) = pb {
+            pb.finish_and_clear();
+        }
+
+        Ok(pending.len())
+    }
+
+    /// Adds a unit-length vector for `doc_id`
+    pub fn insert(&mut self, doc_id: u64, vector: Vec<f32>) {
+        debug_assert_eq!(vector.len(), self.dimensions);
+        let id = self.nodes.len() as u32;
+        let level = self.random_level();
+        self.nodes.push(Node {
+            doc_id,
+            vector,
+            links: vec![Vec::new(); level + 1],
+        });
+        self.doc_ids.insert(doc_id);
+
+        let Some(entry) = self.entry_point else {
+            self.entry_point = Some(id);
+            return;
+        };
+
+        let query = self.nodes[id as usize].vector.clone();
+        let top = self.nodes[entry as usize].links.len() - 1;
+
+        // Descend greedily through the layers above the new node
+        let mut nearest = entry;
+        for layer in (level + 1..=top).rev() {
+            nearest = self.search_layer(&query, &[nearest], 1, layer)[0].1;
+        }
+
+        let mut entry_points = vec![nearest];
+        for layer in (0..=level.min(top)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, EF_CONSTRUCTION, layer);
+            let max_links = Self::max_links(layer);
+            let neighbours: Vec<u32> = candidates.iter().take(max_links).map(|(_, n)| *n).collect();
+
+            for &neighbour in &neighbours {
+                let links = &mut self.nodes[neighbour as usize].links[layer];
+                links.push(id);
+                if links.len() > max_links {
+                    self.prune(neighbour, layer, max_links);
+                }
+            }
+            self.nodes[id as usize].links[layer] = neighbours;
+            entry_points = candidates.into_iter().map(|(_, n)| n).collect();
+        }
+
+        if level > top {
+            self.entry_point = Some(id);
+        }
+    }
+
+    /// The `k` nearest documents to `query`, most similar first, with cosine similarity
+    pub fn search(&self, query: &[f32], k: usize, ef: usize) -> Vec<(u64, f32)> {
+        let Some(entry) = self.entry_point else {
+            return Vec::new();
+        };
+        let top = self.nodes[entry as usize].links.len() - 1;
+
+        let mut nearest = entry;
+        for layer in (1..=top).rev() {
+            nearest = self.search_layer(query, &[nearest], 1, layer)[0].1;
+        }
+
+        self.search_layer(query, &[nearest], ef.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|(distance, node)| (self.nodes[node as usize].doc_id, 1.0 - distance.0))
+            .collect()
+    }
+
+    fn max_links(layer: usize) -> usize {
+        if layer == 0 {
+            MAX_LINKS * 2
+        } else {
+            MAX_LINKS
+        }
+    }
+
+    fn distance(&self, query: &[f32], node: u32) -> Distance {
+        let vector = &self.nodes[node as usize].vector;
+        let dot: f32 = query.iter().zip(vector).map(|(a, b)| a * b).sum();
+        Distance(1.0 - dot)
+    }
+
+    /// Best-first search of one layer; returns up to `ef` nodes, closest first
+    fn search_layer(
+        &self,
+        query: &[f32],
+        entry_points: &[u32],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(Distance, u32)> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<(Distance, u32)>> = BinaryHeap::new();
+        let mut found: BinaryHeap<(Distance, u32)> = BinaryHeap::new();
+
+        for &point in entry_points {
+            let distance = self.distance(query, point);
+            candidates.push(Reverse((distance, point)));
+            found.push((distance, point));
+        }
+
+        while let Some(Reverse((distance, node))) = candidates.pop() {
+            if found.len() >= ef && found.peek().is_some_and(|(worst, _)| distance > *worst) {
+                break;
+            }
+            let Some(links) = self.nodes[node as usize].links.get(layer) else {
+                continue;
+            };
+            for &neighbour in links {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let distance = self.distance(query, neighbour);
+                if found.len() < ef || found.peek().is_some_and(|(worst, _)| distance < *worst) {
+                    candidates.push(Reverse((distance, neighbour)));
+                    found.push((distance, neighbour));
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Keeps the `max_links` closest neighbours of `node` on `layer`
+    fn prune(&mut self, node: u32, layer: usize, max_links: usize) {
+        let vector = self.nodes[node as usize].vector.clone();
+        let mut links: Vec<(Distance, u32)> = self.nodes[node as usize].links[layer]
+            .iter()
+            .map(|&n| (self.distance(&vector, n), n))
+            .collect();
+        links.sort();
+        links.truncate(max_links);
+        self.nodes[node as usize].links[layer] = links.into_iter().map(|(_, n)| n).collect();
+    }
+
+    /// Exponentially distributed level, as in the HNSW paper
+    fn random_level(&mut self) -> usize {
+        // xorshift64*
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11;
+        let uniform = (bits as f64 + 1.0) / (1u64 << 53) as f64;
+        let level_mult = 1.0 / (MAX_LINKS as f64).ln();
+        ((-uniform.ln() * level_mult) as usize).min(16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_vectors(count: usize, dimensions: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                let v: Vec<f32> = (0..dimensions)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect();
+                let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+                v.into_iter().map(|x| x / norm).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_search_matches_brute_force() {
+        let vectors = unit_vectors(600, 24);
+        let mut index = VectorIndex::new("test", 24);
+        for (i, v) in vectors.iter().enumerate() {
+            index.insert(i as u64, v.clone());
+        }
+
+        let mut hits = 0;
+        for query in vectors.iter().step_by(20) {
+            let mut exact: Vec<(u64, f32)> = vectors
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (i as u64, query.iter().zip(v).map(|(a, b)| a * b).sum()))
+                .collect();
+            exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let expected: HashSet<u64> = exact.iter().take(10).map(|(i, _)| *i).collect();
+
+            let found = index.search(query, 10, 64);
+            assert_eq!(found.len(), 10);
+            assert!(found.windows(2).all(|w| w[0].1 >= w[1].1));
+            hits += found.iter().filter(|(i, _)| expected.contains(i)).count();
+        }
+        // Recall@10 over 30 queries
+        assert!(hits as f64 / 300.0 > 0.9, "recall too low: {}", hits);
+    }
+
+    #[test]
+    fn test_save_and_reopen() {
+        struct Fixed;
+        impl TextEmbedder for Fixed {
+            fn model_id(&self) -> &str {
+                "test"
+            }
+            fn dimensions(&self) -> usize {
+                8
+            }
+            fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+                Ok(texts.iter().map(|_| vec![0.0; 8]).collect())
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut index = VectorIndex::new("test", 8);
+        for (i, v) in unit_vectors(20, 8).into_iter().enumerate() {
+            index.insert(i as u64 + 100, v);
+        }
+        index.save(dir.path()).unwrap();
+
+        let reopened = VectorIndex::open(dir.path(), &Fixed).unwrap();
+        assert_eq!(reopened.len(), 20);
+        assert!(reopened.contains(105));
+        let query = unit_vectors(20, 8)[3].clone();
+        assert_eq!(reopened.search(&query, 1, 16)[0].0, 103);
+    }
+}
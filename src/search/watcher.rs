@@ -0,0 +1,71 @@
+// Keeps the search index current while logs are being written
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::models::LogLocation;
+
+use super::index_builder::{discover_logs, IndexBuilder, IndexStats};
+
+/// Discovery walks the whole home directory, so new log locations are only
+/// looked for every this many polls; known files are checked on every poll
+const REDISCOVER_EVERY: u32 = 12;
+
+/// How often a sleeping watcher checks whether it should stop
+const STOP_CHECK: Duration = Duration::from_millis(200);
+
+/// Polls log files and indexes lines as they are appended
+pub struct IndexWatcher {
+    builder: IndexBuilder,
+    interval: Duration,
+    locations: Vec<LogLocation>,
+    polls: u32,
+}
+
+impl IndexWatcher {
+    /// Creates a watcher for the index in `index_dir`
+    pub fn new(index_dir: &Path, interval: Duration) -> Result<Self> {
+        Ok(Self {
+            builder: IndexBuilder::new(index_dir)?,
+            interval,
+            locations: Vec::new(),
+            polls: 0,
+        })
+    }
+
+    /// Indexes whatever changed since the last poll
+    pub fn poll(&mut self) -> Result<IndexStats> {
+        if self.polls % REDISCOVER_EVERY == 0 {
+            self.locations = discover_logs()?.locations;
+        }
+        self.polls += 1;
+        self.builder.update_locations(&self.locations)
+    }
+
+    /// Polls until `stop` is set, reporting each update; errors are reported
+    /// and retried on the next poll
+    pub fn run(&mut self, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            match self.poll() {
+                Ok(stats) if stats.total_files > 0 => println!(
+                    "{} {} Indexed {} entries from {} files",
+                    chrono::Local::now().format("%H:%M:%S").to_string().dimmed(),
+                    "✓".green(),
+                    stats.total_docs.to_string().cyan(),
+                    stats.total_files
+                ),
+                Ok(_) => {}
+                Err(e) => eprintln!("{} Index update failed: {:#}", "✗".red(), e),
+            }
+
+            // Sleep in slices so a stop request is noticed promptly
+            let started = Instant::now();
+            while started.elapsed() < self.interval && !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(STOP_CHECK.min(self.interval));
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Secret leak detection on outbound API traffic
+//!
+//! Every string in an outbound request body (prompts, file contents, tool
+//! results) is checked with [`Sanitizer::detect_sensitive_data`]. Each secret
+//! is reported the first time it leaves the machine and recorded in
+//! `~/.claudev/secret_audit.jsonl` by type, location and fingerprint — never
+//! the secret itself. In redact mode secrets are replaced before forwarding.
+
+use crate::sanitizer::{Sanitizer, SensitiveDataType};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A secret seen in an outbound request
+#[derive(Debug, Clone)]
+pub struct SecretFinding {
+    pub data_type: SensitiveDataType,
+    /// JSON path of the string it was found in, e.g. `messages[4].content[0].content`
+    pub location: String,
+    /// Hash identifying the secret across requests
+    pub fingerprint: String,
+    /// Masked prefix, safe to display
+    pub preview: String,
+}
+
+/// Outcome of scanning one request body
+#[derive(Debug, Clone, Default)]
+pub struct ScanResult {
+    /// Secrets not seen in earlier requests
+    pub findings: Vec<SecretFinding>,
+    /// Body with every secret replaced, in redact mode when anything matched
+    pub redacted_body: Option<String>,
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub model: Option<String>,
+    pub data_type: String,
+    pub location: String,
+    pub fingerprint: String,
+    pub preview: String,
+    pub redacted: bool,
+}
+
+/// Scans outbound request bodies for secrets
+pub struct SecretScanner {
+    sanitizer: Sanitizer,
+    redact: bool,
+    include_emails: bool,
+    audit_path: Option<PathBuf>,
+    seen: Mutex<HashSet<u64>>,
+}
+
+impl SecretScanner {
+    pub fn new(redact: bool) -> Self {
+        Self {
+            sanitizer: Sanitizer::new(),
+            redact,
+            include_emails: false,
+            audit_path: Some(audit_log_path()),
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Scanner that keeps no audit log
+    #[cfg(test)]
+    pub fn without_audit(redact: bool) -> Self {
+        Self {
+            audit_path: None,
+            ..Self::new(redact)
+        }
+    }
+
+    pub fn redacts(&self) -> bool {
+        self.redact
+    }
+
+    fn is_secret(&self, data_type: &SensitiveDataType) -> bool {
+        // Emails show up in every git log and commit, so they are opt-in
+        *data_type != SensitiveDataType::Email || self.include_emails
+    }
+
+    /// Scan a JSON request body, auditing newly seen secrets
+    pub fn scan(&self, body: &str) -> Result<ScanResult> {
+        let mut value: Value = serde_json::from_str(body).context("Request body is not JSON")?;
+        let mut result = ScanResult::default();
+        let mut matched = false;
+
+        {
+            let mut seen = self.seen.lock().unwrap();
+            visit_strings(&mut value, "", &mut |path, text| {
+                let spans = self.secret_spans(text);
+                if spans.is_empty() {
+                    return;
+                }
+                matched = true;
+
+                for (start, end, data_type) in &spans {
+                    let secret = &text[*start..*end];
+                    let hash = fingerprint(secret);
+                    if seen.insert(hash) {
+                        result.findings.push(SecretFinding {
+                            data_type: data_type.clone(),
+                            location: path.to_string(),
+                            fingerprint: format!("{:016x}", hash),
+                            preview: preview(secret),
+                        });
+                    }
+                }
+
+                if self.redact {
+                    *text = redact(text, &spans);
+                }
+            });
+        }
+
+        if self.redact && matched {
+            result.redacted_body = Some(serde_json::to_string(&value)?);
+        }
+
+        if !result.findings.is_empty() {
+            let model = value.get("model").and_then(|m| m.as_str());
+            if let Err(e) = self.audit(model, &result.findings) {
+                tracing::warn!("Failed to write secret audit log: {}", e);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Non-overlapping `(start, end, type)` byte spans of secrets in a string
+    fn secret_spans(&self, text: &str) -> Vec<(usize, usize, SensitiveDataType)> {
+        let mut matches: Vec<_> = self
+            .sanitizer
+            .detect_sensitive_data(text)
+            .into_iter()
+            .filter(|m| self.is_secret(&m.data_type))
+            .collect();
+        // Longest match first at each position so nested patterns collapse
+        matches.sort_by_key(|m| (m.position, std::cmp::Reverse(m.length)));
+
+        let mut spans: Vec<(usize, usize, SensitiveDataType)> = Vec::new();
+        for m in matches {
+            let end = m.position + m.length;
+            match spans.last_mut() {
+                Some(last) if m.position < last.1 => last.1 = last.1.max(end),
+                _ => spans.push((m.position, end, m.data_type)),
+            }
+        }
+        spans
+    }
+
+    fn audit(&self, model: Option<&str>, findings: &[SecretFinding]) -> Result<()> {
+        let Some(path) = &self.audit_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let now = Utc::now();
+        for finding in findings {
+            let entry = AuditEntry {
+                timestamp: now,
+                model: model.map(String::from),
+                data_type: finding.data_type.to_string(),
+                location: finding.location.clone(),
+                fingerprint: finding.fingerprint.clone(),
+                preview: finding.preview.clone(),
+                redacted: self.redact,
+            };
+            writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Call `f` with the path and value of every string in a JSON value
+fn visit_strings(value: &mut Value, path: &str, f: &mut dyn FnMut(&str, &mut String)) {
+    match value {
+        Value::String(text) => f(path, text),
+        Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                visit_strings(item, &format!("{}[{}]", path, i), f);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                visit_strings(item, &child, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn fingerprint(secret: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    secret.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn preview(secret: &str) -> String {
+    let prefix: String = secret.chars().take(4).collect();
+    format!("{}… ({} chars)", prefix, secret.chars().count())
+}
+
+fn redact(text: &str, spans: &[(usize, usize, SensitiveDataType)]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, data_type) in spans {
+        out.push_str(&text[last..*start]);
+        let label = data_type.to_string().to_uppercase().replace(' ', "_");
+        out.push_str(&format!("[REDACTED_{}]", label));
+        last = *end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Path of the secret audit log
+pub fn audit_log_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claudev")
+        .join("secret_audit.jsonl")
+}
+
+/// Print a summary of the audit log for `claudev monitor --secrets`
+pub fn print_audit_summary() -> Result<()> {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    let path = audit_log_path();
+    println!("{}", "Secret Audit Log".cyan().bold());
+    println!("Log: {}\n", path.display());
+
+    if !path.exists() {
+        println!("No secrets have been detected in outbound traffic.");
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let entries: Vec<AuditEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Time").fg(Color::Cyan),
+            Cell::new("Type").fg(Color::Cyan),
+            Cell::new("Preview").fg(Color::Cyan),
+            Cell::new("Location").fg(Color::Cyan),
+            Cell::new("Sent").fg(Color::Cyan),
+        ]);
+
+    for entry in &entries {
+        let sent = if entry.redacted {
+            Cell::new("redacted").fg(Color::Green)
+        } else {
+            Cell::new("yes").fg(Color::Red)
+        };
+        table.add_row(vec![
+            Cell::new(entry.timestamp.format("%Y-%m-%d %H:%M")),
+            Cell::new(&entry.data_type),
+            Cell::new(&entry.preview),
+            Cell::new(&entry.location),
+            sent,
+        ]);
+    }
+
+    println!("{}", table);
+    let leaked = entries.iter().filter(|e| !e.redacted).count();
+    println!(
+        "\n{} secrets detected, {} sent unredacted",
+        entries.len(),
+        leaked.to_string().red().bold()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{
+        "model": "claude-sonnet-4",
+        "messages": [
+            {"role": "user", "content": "deploy it, author is dev@example.com"},
+            {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "t1", "content": "OPENAI_API_KEY=sk-abcdefghijklmnopqrstuvwxyz123456\nPATH=/usr/bin"}
+            ]}
+        ]
+    }"#;
+
+    #[test]
+    fn test_reports_each_secret_once() {
+        let scanner = SecretScanner::without_audit(false);
+        let result = scanner.scan(BODY).unwrap();
+        assert_eq!(result.findings.len(), 1);
+        assert_eq!(result.findings[0].location, "messages[1].content[0].content");
+        assert!(result.redacted_body.is_none());
+
+        // Same history resent on the next turn
+        assert!(scanner.scan(BODY).unwrap().findings.is_empty());
+    }
+
+    #[test]
+    fn test_redacts_in_flight() {
+        let scanner = SecretScanner::without_audit(true);
+        let result = scanner.scan(BODY).unwrap();
+        let redacted = result.redacted_body.unwrap();
+        assert!(!redacted.contains("sk-abcdefghijklmnop"));
+        assert!(redacted.contains("PATH=/usr/bin"));
+        assert!(redacted.contains("dev@example.com"));
+
+        let value: Value = serde_json::from_str(&redacted).unwrap();
+        assert!(value["messages"][1]["content"][0]["content"]
+            .as_str()
+            .unwrap()
+            .starts_with("[REDACTED_"));
+    }
+}
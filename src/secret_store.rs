@@ -0,0 +1,391 @@
+// Secret storage for API keys
+// Keys live in the OS keyring (Secret Service on Linux) or, where no keyring
+// is reachable (headless boxes, CI), in a passphrase-encrypted age file.
+// Config files only ever hold a handle such as `keyring:claude/z.ai`.
+
+use age::secrecy::SecretString;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Keyring service name all claudev secrets are filed under
+pub const SERVICE: &str = "claudev";
+/// Passphrase for the encrypted file backend, for non-interactive use
+pub const PASSPHRASE_ENV: &str = "CLAUDEV_SECRET_PASSPHRASE";
+/// Forces a backend (`keyring` or `file`)
+pub const BACKEND_ENV: &str = "CLAUDEV_SECRET_BACKEND";
+
+/// Where a secret is kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Keyring,
+    EncryptedFile,
+}
+
+impl Backend {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "keyring" => Ok(Backend::Keyring),
+            "file" => Ok(Backend::EncryptedFile),
+            _ => Err(anyhow!(
+                "Unknown secret backend: {}. Supported: keyring, file",
+                s
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Backend::Keyring => "keyring",
+            Backend::EncryptedFile => "file",
+        }
+    }
+}
+
+/// Reference to a stored secret, e.g. `keyring:claude/z.ai`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SecretHandle {
+    pub backend: Backend,
+    pub name: String,
+}
+
+impl SecretHandle {
+    pub fn parse(s: &str) -> Result<Self> {
+        let (backend, name) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid secret handle '{}': expected <backend>:<name>", s))?;
+        if name.is_empty() {
+            return Err(anyhow!("Invalid secret handle '{}': empty name", s));
+        }
+        Ok(Self {
+            backend: Backend::from_str(backend)?,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for SecretHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.backend.name(), self.name)
+    }
+}
+
+impl TryFrom<String> for SecretHandle {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        Self::parse(&s)
+    }
+}
+
+impl From<SecretHandle> for String {
+    fn from(handle: SecretHandle) -> String {
+        handle.to_string()
+    }
+}
+
+/// Keyring and encrypted-file backed secret storage
+pub struct SecretStore {
+    preferred: Backend,
+    file: EncryptedFile,
+}
+
+impl SecretStore {
+    /// Use the keyring when one is reachable, the encrypted file otherwise.
+    /// `CLAUDEV_SECRET_BACKEND` overrides the choice.
+    pub fn open_default() -> Result<Self> {
+        let preferred = match std::env::var(BACKEND_ENV) {
+            Ok(name) => Backend::from_str(&name)?,
+            Err(_) if keyring_available() => Backend::Keyring,
+            Err(_) => Backend::EncryptedFile,
+        };
+        Ok(Self {
+            preferred,
+            file: EncryptedFile::new(secrets_file_path()),
+        })
+    }
+
+    /// File-backed store with a known passphrase and a cheap scrypt work factor
+    #[cfg(test)]
+    pub fn for_tests(path: PathBuf, passphrase: &str) -> Self {
+        let mut file = EncryptedFile::new(path);
+        file.passphrase = RefCell::new(Some(SecretString::from(passphrase.to_string())));
+        file.work_factor = Some(2);
+        Self {
+            preferred: Backend::EncryptedFile,
+            file,
+        }
+    }
+
+    pub fn set_preferred(&mut self, backend: Backend) {
+        self.preferred = backend;
+    }
+
+    pub fn preferred(&self) -> Backend {
+        self.preferred
+    }
+
+    pub fn file_path(&self) -> &Path {
+        &self.file.path
+    }
+
+    /// Store a secret in the preferred backend and return its handle
+    pub fn store(&self, name: &str, value: &str) -> Result<SecretHandle> {
+        let handle = SecretHandle {
+            backend: self.preferred,
+            name: name.to_string(),
+        };
+        match handle.backend {
+            Backend::Keyring => keyring_set(name, value)?,
+            Backend::EncryptedFile => self.file.set(name, Some(value))?,
+        }
+        Ok(handle)
+    }
+
+    pub fn get(&self, handle: &SecretHandle) -> Result<String> {
+        let value = match handle.backend {
+            Backend::Keyring => keyring_get(&handle.name)?,
+            Backend::EncryptedFile => self.file.get(&handle.name)?,
+        };
+        value.ok_or_else(|| anyhow!("Secret {} not found", handle))
+    }
+
+    pub fn delete(&self, handle: &SecretHandle) -> Result<()> {
+        match handle.backend {
+            Backend::Keyring => keyring_delete(&handle.name),
+            Backend::EncryptedFile => self.file.set(&handle.name, None),
+        }
+    }
+}
+
+/// Default location of the encrypted secrets file
+pub fn secrets_file_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claudev")
+        .join("secrets.age")
+}
+
+/// JSON map of secrets, encrypted to a passphrase with age
+struct EncryptedFile {
+    path: PathBuf,
+    passphrase: RefCell<Option<SecretString>>,
+    /// scrypt work factor (log2); lowered in tests
+    work_factor: Option<u8>,
+}
+
+impl EncryptedFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            passphrase: RefCell::new(None),
+            work_factor: None,
+        }
+    }
+
+    /// Passphrase from the environment, or asked for once on the terminal
+    fn passphrase(&self) -> Result<SecretString> {
+        if let Some(passphrase) = self.passphrase.borrow().as_ref() {
+            return Ok(passphrase.clone());
+        }
+        let passphrase = match std::env::var(PASSPHRASE_ENV) {
+            Ok(p) if !p.is_empty() => p,
+            _ if std::io::stdin().is_terminal() => {
+                rpassword::prompt_password(format!("Passphrase for {}: ", self.path.display()))?
+            }
+            _ => {
+                return Err(anyhow!(
+                    "No keyring available and {} is not set; cannot unlock {}",
+                    PASSPHRASE_ENV,
+                    self.path.display()
+                ))
+            }
+        };
+        if passphrase.is_empty() {
+            return Err(anyhow!("Empty passphrase"));
+        }
+        let passphrase = SecretString::from(passphrase);
+        *self.passphrase.borrow_mut() = Some(passphrase.clone());
+        Ok(passphrase)
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let encrypted = fs::read(&self.path)?;
+        let decryptor = age::Decryptor::new_buffered(&encrypted[..])
+            .with_context(|| format!("{} is not an age file", self.path.display()))?;
+        let identity = age::scrypt::Identity::new(self.passphrase()?);
+        let mut reader = decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .map_err(|e| anyhow!("Failed to decrypt {}: {}", self.path.display(), e))?;
+        let mut plaintext = String::new();
+        reader.read_to_string(&mut plaintext)?;
+        Ok(serde_json::from_str(&plaintext)?)
+    }
+
+    fn write_all(&self, secrets: &BTreeMap<String, String>) -> Result<()> {
+        let mut recipient = age::scrypt::Recipient::new(self.passphrase()?);
+        if let Some(log_n) = self.work_factor {
+            recipient.set_work_factor(log_n);
+        }
+        let encryptor =
+            age::Encryptor::with_recipients(std::iter::once(&recipient as &dyn age::Recipient))?;
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut encrypted)?;
+        writer.write_all(serde_json::to_string(secrets)?.as_bytes())?;
+        writer.finish()?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, &encrypted)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        Ok(self.read_all()?.remove(name))
+    }
+
+    /// Insert or, with `None`, remove a secret
+    fn set(&self, name: &str, value: Option<&str>) -> Result<()> {
+        let mut secrets = self.read_all()?;
+        match value {
+            Some(value) => secrets.insert(name.to_string(), value.to_string()),
+            None => secrets.remove(name),
+        };
+        self.write_all(&secrets)
+    }
+}
+
+/// Run a keyring call off the async runtime: the Secret Service client
+/// deadlocks when driven from a tokio worker thread
+fn on_keyring_thread<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    std::thread::scope(|s| s.spawn(f).join().expect("keyring thread panicked"))
+}
+
+/// Whether an OS keyring answers. Only Linux has a persistent backend
+/// compiled in; elsewhere keyring would fall back to an in-memory mock.
+pub fn keyring_available() -> bool {
+    if !cfg!(any(target_os = "linux", target_os = "freebsd")) {
+        return false;
+    }
+    on_keyring_thread(|| {
+        let entry = keyring::Entry::new(SERVICE, "__probe__");
+        matches!(
+            entry.and_then(|e| e.get_password()),
+            Ok(_) | Err(keyring::Error::NoEntry)
+        )
+    })
+}
+
+fn keyring_get(name: &str) -> Result<Option<String>> {
+    on_keyring_thread(
+        || match keyring::Entry::new(SERVICE, name).and_then(|e| e.get_password()) {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Keyring lookup of '{}' failed: {}", name, e)),
+        },
+    )
+}
+
+fn keyring_set(name: &str, value: &str) -> Result<()> {
+    on_keyring_thread(|| {
+        keyring::Entry::new(SERVICE, name)
+            .and_then(|e| e.set_password(value))
+            .map_err(|e| anyhow!("Failed to store '{}' in the keyring: {}", name, e))
+    })
+}
+
+fn keyring_delete(name: &str) -> Result<()> {
+    on_keyring_thread(|| {
+        match keyring::Entry::new(SERVICE, name).and_then(|e| e.delete_credential()) {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow!(
+                "Failed to delete '{}' from the keyring: {}",
+                name,
+                e
+            )),
+        }
+    })
+}
+
+/// Read a secret value from the terminal without echo, or from piped stdin
+pub fn read_secret_value(prompt: &str) -> Result<String> {
+    let value = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(prompt)?
+    } else {
+        let mut value = String::new();
+        std::io::stdin().read_to_string(&mut value)?;
+        value
+    };
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        return Err(anyhow!("No secret given"));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_round_trip() {
+        let handle = SecretHandle::parse("keyring:claude/z.ai").unwrap();
+        assert_eq!(handle.backend, Backend::Keyring);
+        assert_eq!(handle.name, "claude/z.ai");
+        assert_eq!(
+            serde_json::to_string(&handle).unwrap(),
+            "\"keyring:claude/z.ai\""
+        );
+        assert!(SecretHandle::parse("sk-ant-plaintext").is_err());
+        assert!(serde_json::from_str::<SecretHandle>("\"vault:x\"").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_file_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.age");
+        let store = SecretStore::for_tests(path.clone(), "correct horse");
+
+        let a = store.store("claude/z.ai", "sk-zai-secret").unwrap();
+        let b = store.store("claude/openrouter", "sk-or-secret").unwrap();
+        assert_eq!(a.to_string(), "file:claude/z.ai");
+        assert_eq!(store.get(&a).unwrap(), "sk-zai-secret");
+        assert_eq!(store.get(&b).unwrap(), "sk-or-secret");
+
+        let raw = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("sk-zai-secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        assert!(SecretStore::for_tests(path.clone(), "wrong")
+            .get(&a)
+            .is_err());
+
+        store.delete(&a).unwrap();
+        assert!(store.get(&a).is_err());
+        assert_eq!(store.get(&b).unwrap(), "sk-or-secret");
+    }
+}
@@ -0,0 +1,519 @@
+//! Tool-call latency and failure analytics
+//!
+//! Pairs each `tool_use` block with the `tool_result` that answers it. In
+//! captured traffic the tool runs between the end of the response that asked
+//! for it and the start of the request carrying its result; in Claude Code
+//! transcripts both blocks are timestamped lines. From the pairs we derive
+//! per-tool execution time, error counts, and retry loops (the same tool
+//! called again and again with the same input or right after failing).
+
+use crate::context_composition::{load_traffic_file, value_text_len};
+use crate::traffic::{ContentBlock, MessageContent, TrafficEntry};
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Consecutive attempts that make a retry loop
+const RETRY_LOOP_MIN_ATTEMPTS: usize = 3;
+/// Characters of each tool result kept for display
+const RESULT_PREVIEW_CHARS: usize = 200;
+
+/// Something that happened to a tool call
+#[derive(Debug, Clone)]
+pub enum ToolEvent {
+    /// The model asked for a tool
+    Use {
+        id: String,
+        name: String,
+        input: Value,
+        at: Option<DateTime<Utc>>,
+    },
+    /// The client sent the tool's result back
+    Result {
+        id: String,
+        is_error: bool,
+        content: Option<Value>,
+        at: Option<DateTime<Utc>>,
+    },
+}
+
+/// A tool call paired with its result
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolInvocation {
+    pub id: String,
+    pub name: String,
+    pub input: Value,
+    pub requested_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// False until a result has been seen
+    pub completed: bool,
+    pub is_error: bool,
+    pub result_preview: Option<String>,
+    /// Length of the full result text
+    pub result_chars: usize,
+}
+
+impl ToolInvocation {
+    /// Execution time, when both ends are timestamped
+    pub fn duration_ms(&self) -> Option<u64> {
+        let ms = (self.completed_at? - self.requested_at?).num_milliseconds();
+        Some(ms.max(0) as u64)
+    }
+}
+
+/// Aggregates for one tool
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ToolStats {
+    pub name: String,
+    pub calls: usize,
+    pub errors: usize,
+    pub avg_ms: f64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl ToolStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.calls as f64
+        }
+    }
+}
+
+/// A run of repeated calls to the same tool
+#[derive(Debug, Clone, Serialize)]
+pub struct RetryLoop {
+    pub tool: String,
+    pub attempts: usize,
+    pub failures: usize,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// Tool analytics over a set of invocations
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ToolReport {
+    /// In the order the tools were requested
+    pub invocations: Vec<ToolInvocation>,
+    pub tools: Vec<ToolStats>,
+    pub retry_loops: Vec<RetryLoop>,
+}
+
+impl ToolReport {
+    pub fn from_invocations(invocations: Vec<ToolInvocation>) -> Self {
+        let tools = tool_stats(&invocations);
+        let retry_loops = detect_retry_loops(&invocations);
+        Self {
+            invocations,
+            tools,
+            retry_loops,
+        }
+    }
+
+    /// Tools by average execution time, slowest first
+    pub fn slowest(&self, limit: usize) -> Vec<&ToolStats> {
+        let mut tools: Vec<&ToolStats> = self.tools.iter().filter(|t| t.avg_ms > 0.0).collect();
+        tools.sort_by(|a, b| b.avg_ms.total_cmp(&a.avg_ms));
+        tools.truncate(limit);
+        tools
+    }
+
+    /// Tools by error count, most failing first
+    pub fn most_failing(&self, limit: usize) -> Vec<&ToolStats> {
+        let mut tools: Vec<&ToolStats> = self.tools.iter().filter(|t| t.errors > 0).collect();
+        tools.sort_by(|a, b| {
+            b.errors
+                .cmp(&a.errors)
+                .then(b.error_rate().total_cmp(&a.error_rate()))
+        });
+        tools.truncate(limit);
+        tools
+    }
+}
+
+/// Pair tool uses with their results, in the order the uses appear.
+/// Only the first result for each id counts, since requests resend history.
+pub fn pair_tool_calls(events: impl IntoIterator<Item = ToolEvent>) -> Vec<ToolInvocation> {
+    let mut invocations: Vec<ToolInvocation> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for event in events {
+        match event {
+            ToolEvent::Use {
+                id,
+                name,
+                input,
+                at,
+            } => {
+                if index.contains_key(&id) {
+                    continue;
+                }
+                index.insert(id.clone(), invocations.len());
+                invocations.push(ToolInvocation {
+                    id,
+                    name,
+                    input,
+                    requested_at: at,
+                    completed_at: None,
+                    completed: false,
+                    is_error: false,
+                    result_preview: None,
+                    result_chars: 0,
+                });
+            }
+            ToolEvent::Result {
+                id,
+                is_error,
+                content,
+                at,
+            } => {
+                let Some(invocation) = index.get(&id).map(|i| &mut invocations[*i]) else {
+                    continue;
+                };
+                if invocation.completed {
+                    continue;
+                }
+                invocation.completed = true;
+                invocation.completed_at = at;
+                invocation.is_error = is_error;
+                invocation.result_preview = content.as_ref().map(result_preview);
+                invocation.result_chars = content.as_ref().map(value_text_len).unwrap_or(0);
+            }
+        }
+    }
+
+    invocations
+}
+
+fn result_preview(content: &Value) -> String {
+    let text = match content {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(|item| item.get("text").and_then(|t| t.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        other => other.to_string(),
+    };
+    text.chars().take(RESULT_PREVIEW_CHARS).collect()
+}
+
+fn block_events(blocks: &[ContentBlock], at: Option<DateTime<Utc>>) -> Vec<ToolEvent> {
+    blocks
+        .iter()
+        .filter_map(|block| match block.block_type.as_str() {
+            "tool_use" => Some(ToolEvent::Use {
+                id: block.id.clone()?,
+                name: block.name.clone().unwrap_or_default(),
+                input: block.input.clone().unwrap_or(Value::Null),
+                at,
+            }),
+            "tool_result" => Some(ToolEvent::Result {
+                id: block.tool_use_id.clone()?,
+                is_error: block.is_error.unwrap_or(false),
+                content: block.content.clone(),
+                at,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Tool events in captured traffic. A tool is requested when its response
+/// finishes and completed when the request carrying its result starts.
+pub fn traffic_events(entries: &[TrafficEntry]) -> Vec<ToolEvent> {
+    let mut sorted: Vec<&TrafficEntry> = entries.iter().collect();
+    sorted.sort_by_key(|e| e.timestamp);
+
+    let mut events = Vec::new();
+    for entry in sorted {
+        for message in &entry.request.messages {
+            if let MessageContent::Blocks(blocks) = &message.content {
+                events.extend(
+                    block_events(blocks, Some(entry.timestamp))
+                        .into_iter()
+                        .filter(|e| matches!(e, ToolEvent::Result { .. })),
+                );
+            }
+        }
+        if let Some(response) = &entry.response {
+            let finished =
+                entry.timestamp + Duration::milliseconds(entry.latency_ms.unwrap_or(0) as i64);
+            events.extend(block_events(&response.content, Some(finished)));
+        }
+    }
+    events
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptLine {
+    #[serde(default)]
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(default)]
+    message: Option<TranscriptMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptMessage {
+    content: MessageContent,
+}
+
+/// Tool events in a Claude Code transcript (one JSON object per line)
+pub fn transcript_events(content: &str) -> Vec<ToolEvent> {
+    content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<TranscriptLine>(line).ok())
+        .flat_map(|line| match line.message.map(|m| m.content) {
+            Some(MessageContent::Blocks(blocks)) => block_events(&blocks, line.timestamp),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Analyze tool calls in captured traffic
+pub fn analyze_traffic(entries: &[TrafficEntry]) -> ToolReport {
+    ToolReport::from_invocations(pair_tool_calls(traffic_events(entries)))
+}
+
+/// Analyze tool calls in a Claude Code transcript
+pub fn analyze_transcript(content: &str) -> ToolReport {
+    ToolReport::from_invocations(pair_tool_calls(transcript_events(content)))
+}
+
+/// Analyze a traffic log (JSONL) or a Claude Code transcript
+pub fn analyze_file(path: &Path) -> Result<ToolReport> {
+    let (content, entries) = load_traffic_file(path)?;
+
+    if entries.is_empty() {
+        Ok(analyze_transcript(&content))
+    } else {
+        Ok(analyze_traffic(&entries))
+    }
+}
+
+/// Print a report as tables
+pub fn print_report(report: &ToolReport, limit: usize) {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Tool").fg(Color::Cyan),
+            Cell::new("Calls").fg(Color::Cyan),
+            Cell::new("Errors").fg(Color::Cyan),
+            Cell::new("Avg").fg(Color::Cyan),
+            Cell::new("P95").fg(Color::Cyan),
+            Cell::new("Max").fg(Color::Cyan),
+        ]);
+
+    for tool in report.tools.iter().take(limit) {
+        let errors = Cell::new(format!(
+            "{} ({:.0}%)",
+            tool.errors,
+            tool.error_rate() * 100.0
+        ));
+        table.add_row(vec![
+            Cell::new(&tool.name),
+            Cell::new(tool.calls),
+            if tool.errors > 0 {
+                errors.fg(Color::Red)
+            } else {
+                errors
+            },
+            Cell::new(format!("{:.0}ms", tool.avg_ms)),
+            Cell::new(format!("{}ms", tool.p95_ms)),
+            Cell::new(format!("{}ms", tool.max_ms)),
+        ]);
+    }
+
+    println!("{}", table);
+    println!();
+
+    if let Some(slowest) = report.slowest(1).first() {
+        println!(
+            "{} {} ({:.0}ms avg)",
+            "Slowest:".cyan().bold(),
+            slowest.name,
+            slowest.avg_ms
+        );
+    }
+    if let Some(failing) = report.most_failing(1).first() {
+        println!(
+            "{} {} ({} errors)",
+            "Most failing:".cyan().bold(),
+            failing.name,
+            failing.errors.to_string().red()
+        );
+    }
+
+    for retry in &report.retry_loops {
+        println!(
+            "{} {} called {} times in a row, {} failed",
+            "Retry loop:".yellow().bold(),
+            retry.tool,
+            retry.attempts,
+            retry.failures
+        );
+    }
+}
+
+fn tool_stats(invocations: &[ToolInvocation]) -> Vec<ToolStats> {
+    let mut by_tool: HashMap<&str, (ToolStats, Vec<u64>)> = HashMap::new();
+
+    for invocation in invocations.iter().filter(|i| i.completed) {
+        let (stats, durations) = by_tool.entry(&invocation.name).or_default();
+        stats.calls += 1;
+        if invocation.is_error {
+            stats.errors += 1;
+        }
+        if let Some(ms) = invocation.duration_ms() {
+            durations.push(ms);
+        }
+    }
+
+    let mut tools: Vec<ToolStats> = by_tool
+        .into_iter()
+        .map(|(name, (mut stats, mut durations))| {
+            stats.name = name.to_string();
+            if !durations.is_empty() {
+                durations.sort_unstable();
+                stats.avg_ms = durations.iter().sum::<u64>() as f64 / durations.len() as f64;
+                stats.max_ms = *durations.last().unwrap();
+                let p95 =
+                    ((durations.len() as f64 * 0.95).ceil() as usize).clamp(1, durations.len());
+                stats.p95_ms = durations[p95 - 1];
+            }
+            stats
+        })
+        .collect();
+    tools.sort_by(|a, b| b.calls.cmp(&a.calls).then(a.name.cmp(&b.name)));
+    tools
+}
+
+/// Runs where the same tool is called again with the same input, or again after failing
+fn detect_retry_loops(invocations: &[ToolInvocation]) -> Vec<RetryLoop> {
+    let mut loops = Vec::new();
+    let mut run: Vec<&ToolInvocation> = Vec::new();
+
+    let mut close = |run: &mut Vec<&ToolInvocation>| {
+        if run.len() >= RETRY_LOOP_MIN_ATTEMPTS {
+            loops.push(RetryLoop {
+                tool: run[0].name.clone(),
+                attempts: run.len(),
+                failures: run.iter().filter(|i| i.is_error).count(),
+                started_at: run[0].requested_at,
+            });
+        }
+        run.clear();
+    };
+
+    for invocation in invocations {
+        let continues = run.last().is_some_and(|prev| {
+            prev.name == invocation.name && (prev.input == invocation.input || prev.is_error)
+        });
+        if !continues {
+            close(&mut run);
+        }
+        run.push(invocation);
+    }
+    close(&mut run);
+
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn at(secs: i64) -> Option<DateTime<Utc>> {
+        Some(Utc.timestamp_opt(1_700_000_000 + secs, 0).unwrap())
+    }
+
+    fn call(id: &str, name: &str, input: Value, start: i64) -> ToolEvent {
+        ToolEvent::Use {
+            id: id.to_string(),
+            name: name.to_string(),
+            input,
+            at: at(start),
+        }
+    }
+
+    fn result(id: &str, is_error: bool, end: i64) -> ToolEvent {
+        ToolEvent::Result {
+            id: id.to_string(),
+            is_error,
+            content: Some(json!("output")),
+            at: at(end),
+        }
+    }
+
+    #[test]
+    fn test_pairs_first_result_only() {
+        let invocations = pair_tool_calls(vec![
+            call("t1", "Bash", json!({"command": "cargo build"}), 0),
+            result("t1", false, 12),
+            // Later requests resend the same result
+            result("t1", false, 40),
+            call("t2", "Read", json!({}), 41),
+        ]);
+        assert_eq!(invocations.len(), 2);
+        assert_eq!(invocations[0].duration_ms(), Some(12_000));
+        assert!(!invocations[1].completed);
+    }
+
+    #[test]
+    fn test_stats_and_retry_loops() {
+        let edit = json!({"file_path": "a.rs", "old_string": "x"});
+        let report = ToolReport::from_invocations(pair_tool_calls(vec![
+            call("e1", "Edit", edit.clone(), 0),
+            result("e1", true, 1),
+            call(
+                "e2",
+                "Edit",
+                json!({"file_path": "a.rs", "old_string": "y"}),
+                2,
+            ),
+            result("e2", true, 3),
+            call("e3", "Edit", edit, 4),
+            result("e3", false, 5),
+            call("b1", "Bash", json!({"command": "ls"}), 6),
+            result("b1", false, 16),
+        ]));
+
+        assert_eq!(report.retry_loops.len(), 1);
+        assert_eq!(report.retry_loops[0].tool, "Edit");
+        assert_eq!(report.retry_loops[0].attempts, 3);
+        assert_eq!(report.retry_loops[0].failures, 2);
+
+        assert_eq!(report.most_failing(5)[0].name, "Edit");
+        assert_eq!(report.slowest(1)[0].name, "Bash");
+        assert_eq!(report.slowest(1)[0].max_ms, 10_000);
+    }
+
+    #[test]
+    fn test_transcript_events() {
+        let transcript = [
+            r#"{"type":"assistant","timestamp":"2025-01-01T10:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"make"}}]}}"#,
+            r#"{"type":"user","timestamp":"2025-01-01T10:00:30Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","is_error":true,"content":"make: *** No targets"}]}}"#,
+        ]
+        .join("\n");
+
+        let report = analyze_transcript(&transcript);
+        assert_eq!(report.invocations.len(), 1);
+        assert!(report.invocations[0].is_error);
+        assert_eq!(report.invocations[0].duration_ms(), Some(30_000));
+        assert_eq!(
+            report.invocations[0].result_preview.as_deref(),
+            Some("make: *** No targets")
+        );
+    }
+}
@@ -2,25 +2,21 @@
 //!
 //! Parses Claude API request/response format and tracks usage metrics.
 
+use crate::client_id::ClientInfo;
+use crate::pricing;
+use crate::traffic_store::TrafficStore;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 
 /// Maximum number of traffic entries to keep in memory
 const MAX_TRAFFIC_HISTORY: usize = 1000;
 
-/// Claude API pricing (per million tokens)
-const CLAUDE_SONNET_INPUT_COST: f64 = 3.0;
-const CLAUDE_SONNET_OUTPUT_COST: f64 = 15.0;
-const CLAUDE_OPUS_INPUT_COST: f64 = 15.0;
-const CLAUDE_OPUS_OUTPUT_COST: f64 = 75.0;
-const CLAUDE_HAIKU_INPUT_COST: f64 = 0.25;
-const CLAUDE_HAIKU_OUTPUT_COST: f64 = 1.25;
-
 /// A single API traffic entry (request + response pair)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrafficEntry {
@@ -30,6 +26,9 @@ pub struct TrafficEntry {
     pub response: Option<ApiResponse>,
     pub latency_ms: Option<u64>,
     pub status: TrafficStatus,
+    /// Process or tool that sent the request, when it could be identified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<ClientInfo>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -46,7 +45,10 @@ pub struct ApiRequest {
     pub model: String,
     pub max_tokens: Option<u64>,
     pub messages: Vec<Message>,
-    pub system: Option<String>,
+    /// System prompt, sent either as a plain string or as text blocks
+    pub system: Option<MessageContent>,
+    /// Optional in the API, defaulting to a non-streaming response
+    #[serde(default)]
     pub stream: bool,
     pub tools: Option<Vec<serde_json::Value>>,
     /// Raw request body for debugging
@@ -82,6 +84,20 @@ pub enum MessageContent {
     Blocks(Vec<ContentBlock>),
 }
 
+impl MessageContent {
+    /// Concatenated text of the content (text blocks only)
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|b| b.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
 /// Content block in message or response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContentBlock {
@@ -95,12 +111,23 @@ pub struct ContentBlock {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub input: Option<serde_json::Value>,
+    /// For `tool_result` blocks: the `tool_use` block being answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_use_id: Option<String>,
+    /// For `tool_result` blocks: result text or nested content blocks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
 }
 
 /// Token usage from API response
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Usage {
+    /// Absent from the `message_delta` usage of some streamed responses
+    #[serde(default)]
     pub input_tokens: u64,
+    #[serde(default)]
     pub output_tokens: u64,
     #[serde(default)]
     pub cache_creation_input_tokens: u64,
@@ -124,6 +151,48 @@ pub struct TrafficStats {
     pub models_used: std::collections::HashMap<String, u64>,
 }
 
+impl TrafficStats {
+    /// Compute stats over a set of entries, e.g. the in-memory entries of one client
+    pub fn from_entries(entries: &[TrafficEntry]) -> Self {
+        let mut stats = TrafficStats::default();
+        let mut latency_sum = 0u64;
+        let mut latency_count = 0u64;
+
+        for entry in entries {
+            stats.total_requests += 1;
+            match entry.status {
+                TrafficStatus::Success => stats.successful_requests += 1,
+                TrafficStatus::Error(_) => stats.failed_requests += 1,
+                _ => {}
+            }
+            if entry.request.stream {
+                stats.streaming_requests += 1;
+            }
+            if let Some(latency) = entry.latency_ms {
+                latency_sum += latency;
+                latency_count += 1;
+            }
+            if let Some(response) = &entry.response {
+                if let Some(usage) = &response.usage {
+                    stats.total_input_tokens += usage.input_tokens;
+                    stats.total_output_tokens += usage.output_tokens;
+                    stats.total_cache_read_tokens += usage.cache_read_input_tokens;
+                    stats.total_cache_write_tokens += usage.cache_creation_input_tokens;
+                }
+                if let Some(model) = response.model.as_ref().filter(|m| !m.is_empty()) {
+                    *stats.models_used.entry(model.clone()).or_insert(0) += 1;
+                }
+            }
+            stats.total_cost_usd += crate::traffic_store::entry_cost(entry);
+        }
+
+        if latency_count > 0 {
+            stats.avg_latency_ms = latency_sum as f64 / latency_count as f64;
+        }
+        stats
+    }
+}
+
 /// Get the default log file path
 pub fn get_log_file_path() -> PathBuf {
     dirs::home_dir()
@@ -140,6 +209,9 @@ pub struct TrafficLog {
     stats: Arc<Mutex<TrafficStats>>,
     log_file: Arc<Mutex<Option<File>>>,
     log_path: PathBuf,
+    store: Arc<Mutex<Option<TrafficStore>>>,
+    /// Bumped on every change to the in-memory entries
+    generation: Arc<AtomicU64>,
 }
 
 impl TrafficLog {
@@ -151,6 +223,8 @@ impl TrafficLog {
             stats: Arc::new(Mutex::new(TrafficStats::default())),
             log_file: Arc::new(Mutex::new(None)),
             log_path,
+            store: Arc::new(Mutex::new(None)),
+            generation: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -183,18 +257,37 @@ impl TrafficLog {
         }
     }
 
+    /// Persist finished entries to a rolling on-disk store
+    pub fn enable_store(&self, store: TrafficStore) {
+        tracing::info!("Storing traffic in {:?}", store.dir());
+        *self.store.lock().unwrap() = Some(store);
+    }
+
+    /// Append a finished entry to the store, if enabled
+    fn persist(&self, entry: &TrafficEntry) {
+        if let Some(store) = self.store.lock().unwrap().as_ref() {
+            if let Err(e) = store.append(entry) {
+                tracing::warn!("Failed to store traffic entry #{}: {}", entry.id, e);
+            }
+        }
+    }
+
     /// Get the log file path
     pub fn get_log_path(&self) -> &PathBuf {
         &self.log_path
     }
 
     /// Start tracking a new request, returns entry ID
-    pub fn start_request(&self, request: ApiRequest) -> u64 {
+    pub fn start_request(&self, request: ApiRequest, client: Option<ClientInfo>) -> u64 {
         let mut next_id = self.next_id.lock().unwrap();
         let id = *next_id;
         *next_id += 1;
 
         let timestamp = Utc::now();
+        let client_label = client
+            .as_ref()
+            .map(|c| format!(" client={}", c.label()))
+            .unwrap_or_default();
         let entry = TrafficEntry {
             id,
             timestamp,
@@ -202,16 +295,18 @@ impl TrafficLog {
             response: None,
             latency_ms: None,
             status: TrafficStatus::Pending,
+            client,
         };
 
         // Log to file
         self.write_log(&format!(
-            "[{}] REQUEST #{} model={} stream={} messages={}",
+            "[{}] REQUEST #{} model={} stream={} messages={}{}",
             timestamp.format("%Y-%m-%d %H:%M:%S"),
             id,
             request.model,
             request.stream,
-            request.messages.len()
+            request.messages.len(),
+            client_label
         ));
 
         let mut entries = self.entries.lock().unwrap();
@@ -219,6 +314,7 @@ impl TrafficLog {
             entries.pop_front();
         }
         entries.push_back(entry);
+        self.generation.fetch_add(1, Ordering::Relaxed);
 
         let mut stats = self.stats.lock().unwrap();
         stats.total_requests += 1;
@@ -234,7 +330,9 @@ impl TrafficLog {
             entries.iter().find(|e| e.id == id).map(|entry| {
                 let usage = response.usage.as_ref();
                 let model = response.model.as_deref().unwrap_or(&entry.request.model);
-                let cost = usage.map(|u| calculate_cost(model, u)).unwrap_or(0.0);
+                let cost = usage
+                    .map(|u| calculate_cost_at(model, u, entry.timestamp))
+                    .unwrap_or(0.0);
                 (
                     model.to_string(),
                     usage.map(|u| u.input_tokens).unwrap_or(0),
@@ -264,6 +362,7 @@ impl TrafficLog {
             entry.response = Some(response.clone());
             entry.latency_ms = Some(latency_ms);
             entry.status = TrafficStatus::Success;
+            self.generation.fetch_add(1, Ordering::Relaxed);
         }
 
         // Update stats
@@ -279,7 +378,7 @@ impl TrafficLog {
             // Calculate cost based on model
             if let Some(entry) = entries.iter().find(|e| e.id == id) {
                 let model = response.model.as_deref().unwrap_or(&entry.request.model);
-                stats.total_cost_usd += calculate_cost(model, usage);
+                stats.total_cost_usd += calculate_cost_at(model, usage, entry.timestamp);
             }
         }
 
@@ -299,6 +398,13 @@ impl TrafficLog {
         if count > 0 {
             stats.avg_latency_ms = total_latency as f64 / count as f64;
         }
+
+        let finished = entries.iter().find(|e| e.id == id).cloned();
+        drop(stats);
+        drop(entries);
+        if let Some(entry) = finished {
+            self.persist(&entry);
+        }
     }
 
     /// Mark request as failed
@@ -311,12 +417,19 @@ impl TrafficLog {
             error
         ));
 
-        let mut entries = self.entries.lock().unwrap();
-        if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
-            entry.status = TrafficStatus::Error(error);
+        let finished = {
+            let mut entries = self.entries.lock().unwrap();
+            entries.iter_mut().find(|e| e.id == id).map(|entry| {
+                entry.status = TrafficStatus::Error(error);
+                self.generation.fetch_add(1, Ordering::Relaxed);
 
-            let mut stats = self.stats.lock().unwrap();
-            stats.failed_requests += 1;
+                let mut stats = self.stats.lock().unwrap();
+                stats.failed_requests += 1;
+                entry.clone()
+            })
+        };
+        if let Some(entry) = finished {
+            self.persist(&entry);
         }
     }
 
@@ -325,16 +438,22 @@ impl TrafficLog {
         let mut entries = self.entries.lock().unwrap();
         if let Some(entry) = entries.iter_mut().find(|e| e.id == id) {
             entry.status = TrafficStatus::Streaming;
+            self.generation.fetch_add(1, Ordering::Relaxed);
 
             let mut stats = self.stats.lock().unwrap();
             stats.streaming_requests += 1;
         }
     }
 
-    /// Get recent entries (newest first)
-    pub fn get_recent(&self, limit: usize) -> Vec<TrafficEntry> {
+    /// Get all entries currently held in memory (oldest first)
+    pub fn get_all(&self) -> Vec<TrafficEntry> {
         let entries = self.entries.lock().unwrap();
-        entries.iter().rev().take(limit).cloned().collect()
+        entries.iter().cloned().collect()
+    }
+
+    /// Counter that changes whenever entries are added or updated
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
     }
 
     /// Get current stats
@@ -359,21 +478,9 @@ impl Default for TrafficLog {
     }
 }
 
-/// Calculate cost for a request based on model and usage
-pub fn calculate_cost(model: &str, usage: &Usage) -> f64 {
-    let (input_rate, output_rate) = if model.contains("opus") {
-        (CLAUDE_OPUS_INPUT_COST, CLAUDE_OPUS_OUTPUT_COST)
-    } else if model.contains("haiku") {
-        (CLAUDE_HAIKU_INPUT_COST, CLAUDE_HAIKU_OUTPUT_COST)
-    } else {
-        // Default to Sonnet pricing
-        (CLAUDE_SONNET_INPUT_COST, CLAUDE_SONNET_OUTPUT_COST)
-    };
-
-    let input_cost = (usage.input_tokens as f64 / 1_000_000.0) * input_rate;
-    let output_cost = (usage.output_tokens as f64 / 1_000_000.0) * output_rate;
-
-    input_cost + output_cost
+/// Calculate cost for a request at the rates in effect at `at`
+pub fn calculate_cost_at(model: &str, usage: &Usage, at: DateTime<Utc>) -> f64 {
+    pricing::global().cost_at(model, usage, at)
 }
 
 /// Parse a request body into ApiRequest
@@ -401,12 +508,14 @@ pub fn parse_stream_event(line: &str) -> Option<StreamEvent> {
         return Some(StreamEvent::Done);
     }
 
-    serde_json::from_str(data).ok().map(StreamEvent::Data)
+    serde_json::from_str(data)
+        .ok()
+        .map(|data| StreamEvent::Data(Box::new(data)))
 }
 
 #[derive(Debug, Clone)]
 pub enum StreamEvent {
-    Data(StreamData),
+    Data(Box<StreamData>),
     Done,
 }
 
@@ -419,6 +528,8 @@ pub struct StreamData {
     pub delta: Option<StreamDelta>,
     pub usage: Option<Usage>,
     pub message: Option<serde_json::Value>,
+    #[serde(default)]
+    pub content_block: Option<ContentBlock>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -426,6 +537,87 @@ pub struct StreamDelta {
     #[serde(rename = "type")]
     pub delta_type: Option<String>,
     pub text: Option<String>,
+    #[serde(default)]
+    pub partial_json: Option<String>,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+}
+
+/// Rebuild a response from a streamed (SSE) response body
+pub fn response_from_stream(body: &str) -> ApiResponse {
+    let mut response = ApiResponse {
+        id: None,
+        model: None,
+        content: Vec::new(),
+        usage: None,
+        stop_reason: None,
+        raw_body: Some(body.to_string()),
+    };
+    // Tool inputs arrive as JSON fragments, one buffer per content block
+    let mut partial_json: Vec<String> = Vec::new();
+
+    for line in body.lines() {
+        let data = match parse_stream_event(line) {
+            Some(StreamEvent::Data(data)) => data,
+            _ => continue,
+        };
+        let index = data.index as usize;
+
+        match data.event_type.as_str() {
+            "message_start" => {
+                if let Some(message) = data.message {
+                    let field = |name: &str| {
+                        message.get(name).and_then(|v| v.as_str()).map(String::from)
+                    };
+                    response.id = field("id");
+                    response.model = field("model");
+                    response.usage = message
+                        .get("usage")
+                        .and_then(|u| serde_json::from_value(u.clone()).ok());
+                }
+            }
+            "content_block_start" => {
+                if let Some(block) = data.content_block {
+                    response.content.push(block);
+                    partial_json.push(String::new());
+                }
+            }
+            "content_block_delta" => {
+                if let Some(delta) = data.delta {
+                    if let (Some(text), Some(block)) = (delta.text, response.content.get_mut(index)) {
+                        block.text.get_or_insert_with(String::new).push_str(&text);
+                    }
+                    if let (Some(json), Some(buf)) = (delta.partial_json, partial_json.get_mut(index)) {
+                        buf.push_str(&json);
+                    }
+                }
+            }
+            "message_delta" => {
+                if let Some(stop_reason) = data.delta.and_then(|d| d.stop_reason) {
+                    response.stop_reason = Some(stop_reason);
+                }
+                if let Some(delta) = data.usage {
+                    let usage = response.usage.get_or_insert_with(Usage::default);
+                    usage.output_tokens = delta.output_tokens;
+                    usage.input_tokens = usage.input_tokens.max(delta.input_tokens);
+                    usage.cache_creation_input_tokens = usage
+                        .cache_creation_input_tokens
+                        .max(delta.cache_creation_input_tokens);
+                    usage.cache_read_input_tokens =
+                        usage.cache_read_input_tokens.max(delta.cache_read_input_tokens);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for (block, json) in response.content.iter_mut().zip(partial_json) {
+        if !json.is_empty() {
+            block.input = serde_json::from_str(&json).ok().or(block.input.take());
+        }
+    }
+
+    response
 }
 
 #[cfg(test)]
@@ -447,6 +639,55 @@ mod tests {
         assert!(request.stream);
     }
 
+    #[test]
+    fn test_parse_request_with_system_blocks_and_tool_results() {
+        let body = r#"{
+            "model": "claude-sonnet-4-20250514",
+            "system": [{"type": "text", "text": "You are Claude Code."}],
+            "messages": [{"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "ok", "is_error": false}
+            ]}],
+            "stream": false
+        }"#;
+
+        let request = parse_request(body).unwrap();
+        assert_eq!(request.system.unwrap().text(), "You are Claude Code.");
+        match &request.messages[0].content {
+            MessageContent::Blocks(blocks) => {
+                assert_eq!(blocks[0].tool_use_id.as_deref(), Some("toolu_1"));
+                assert_eq!(blocks[0].is_error, Some(false));
+            }
+            MessageContent::Text(_) => panic!("expected content blocks"),
+        }
+    }
+
+    #[test]
+    fn test_response_from_stream() {
+        let body = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"claude-sonnet-4-20250514\",\"usage\":{\"input_tokens\":12,\"cache_read_input_tokens\":900,\"output_tokens\":1}}}\n\n",
+            "data: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"text\",\"text\":\"\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hel\"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"lo\"}}\n",
+            "data: {\"type\":\"content_block_start\",\"index\":1,\"content_block\":{\"type\":\"tool_use\",\"id\":\"t1\",\"name\":\"Read\",\"input\":{}}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{\\\"file_path\\\": \"}}\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"\\\"a.rs\\\"}\"}}\n",
+            "data: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":42}}\n",
+            "data: {\"type\":\"message_stop\"}\n",
+        );
+
+        let response = response_from_stream(body);
+        assert_eq!(response.id.as_deref(), Some("msg_1"));
+        assert_eq!(response.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(response.content[0].text.as_deref(), Some("Hello"));
+        assert_eq!(response.content[1].input.as_ref().unwrap()["file_path"], "a.rs");
+
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.input_tokens, 12);
+        assert_eq!(usage.output_tokens, 42);
+        assert_eq!(usage.cache_read_input_tokens, 900);
+    }
+
     #[test]
     fn test_calculate_cost() {
         let usage = Usage {
@@ -455,9 +696,18 @@ mod tests {
             ..Default::default()
         };
 
-        let cost = calculate_cost("claude-sonnet-4-20250514", &usage);
+        let cost = calculate_cost_at("claude-sonnet-4-20250514", &usage, Utc::now());
         // 1000/1M * 3 + 500/1M * 15 = 0.003 + 0.0075 = 0.0105
         assert!((cost - 0.0105).abs() < 0.0001);
+
+        let cached = Usage {
+            cache_creation_input_tokens: 1000,
+            cache_read_input_tokens: 10_000,
+            ..usage
+        };
+        let cost = calculate_cost_at("claude-sonnet-4-20250514", &cached, Utc::now());
+        // + 1000/1M * 3.75 + 10000/1M * 0.30 = 0.0105 + 0.00375 + 0.003
+        assert!((cost - 0.01725).abs() < 0.0001);
     }
 
     #[test]
@@ -474,7 +724,7 @@ mod tests {
             raw_body: None,
         };
 
-        let id = log.start_request(request);
+        let id = log.start_request(request, None);
         assert_eq!(id, 1);
 
         let response = ApiResponse {
@@ -0,0 +1,399 @@
+//! Export captured traffic to HAR and OpenTelemetry
+//!
+//! HAR 1.2 files open in browser devtools. OTLP/JSON spans follow the
+//! OpenTelemetry GenAI semantic conventions (`gen_ai.*` attributes) and can be
+//! written to a file or posted to a collector's `/v1/traces` endpoint. Each
+//! conversation becomes one trace, so a session's requests line up together.
+
+use crate::prompt_cache::session_key;
+use crate::traffic::{TrafficEntry, TrafficStatus};
+use crate::traffic_store::entry_cost;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// URL recorded for exported Messages API requests
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const CREATOR: &str = "claudev";
+
+/// Export format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Har,
+    Otlp,
+    Jsonl,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "har" => Ok(ExportFormat::Har),
+            "otlp" | "otel" => Ok(ExportFormat::Otlp),
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            other => anyhow::bail!("Unknown export format: {} (expected har, otlp or jsonl)", other),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Guess the format from a file extension, defaulting to JSONL
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("har") => ExportFormat::Har,
+            Some("otlp") => ExportFormat::Otlp,
+            _ => ExportFormat::Jsonl,
+        }
+    }
+}
+
+/// HTTP status of an entry; failures recorded as `HTTP 429: ...` keep their code
+fn http_status(entry: &TrafficEntry) -> u16 {
+    match &entry.status {
+        TrafficStatus::Success => 200,
+        TrafficStatus::Error(message) => message
+            .strip_prefix("HTTP ")
+            .and_then(|rest| rest.split(':').next())
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0),
+        TrafficStatus::Pending | TrafficStatus::Streaming => 0,
+    }
+}
+
+fn request_body(entry: &TrafficEntry) -> String {
+    entry
+        .request
+        .raw_body
+        .clone()
+        .unwrap_or_else(|| serde_json::to_string(&entry.request).unwrap_or_default())
+}
+
+fn response_body(entry: &TrafficEntry) -> String {
+    match &entry.response {
+        Some(response) => response
+            .raw_body
+            .clone()
+            .unwrap_or_else(|| serde_json::to_string(response).unwrap_or_default()),
+        None => match &entry.status {
+            TrafficStatus::Error(message) => message.clone(),
+            _ => String::new(),
+        },
+    }
+}
+
+/// Build a HAR 1.2 document
+pub fn to_har(entries: &[TrafficEntry]) -> Value {
+    let har_entries: Vec<Value> = entries
+        .iter()
+        .map(|entry| {
+            let request_text = request_body(entry);
+            let response_text = response_body(entry);
+            let status = http_status(entry);
+            let latency = entry.latency_ms.unwrap_or(0);
+            let response_mime = if entry.request.stream {
+                "text/event-stream"
+            } else {
+                "application/json"
+            };
+
+            json!({
+                "startedDateTime": entry.timestamp.to_rfc3339(),
+                "time": latency,
+                "request": {
+                    "method": "POST",
+                    "url": MESSAGES_URL,
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": [{ "name": "content-type", "value": "application/json" }],
+                    "queryString": [],
+                    "postData": { "mimeType": "application/json", "text": request_text },
+                    "headersSize": -1,
+                    "bodySize": request_text.len(),
+                },
+                "response": {
+                    "status": status,
+                    "statusText": reqwest::StatusCode::from_u16(status)
+                        .ok()
+                        .and_then(|s| s.canonical_reason())
+                        .unwrap_or(""),
+                    "httpVersion": "HTTP/1.1",
+                    "cookies": [],
+                    "headers": [{ "name": "content-type", "value": response_mime }],
+                    "content": {
+                        "size": response_text.len(),
+                        "mimeType": response_mime,
+                        "text": response_text,
+                    },
+                    "redirectURL": "",
+                    "headersSize": -1,
+                    "bodySize": response_text.len(),
+                },
+                "cache": {},
+                "timings": { "send": 0, "wait": latency, "receive": 0 },
+                "comment": match &entry.client {
+                    Some(client) => format!("#{} {} from {}", entry.id, entry.request.model, client.label()),
+                    None => format!("#{} {}", entry.id, entry.request.model),
+                },
+            })
+        })
+        .collect();
+
+    json!({
+        "log": {
+            "version": "1.2",
+            "creator": { "name": CREATOR, "version": env!("CARGO_PKG_VERSION") },
+            "entries": har_entries,
+        }
+    })
+}
+
+fn hash_hex(parts: &[&str], seed: u64) -> String {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    for part in parts {
+        part.hash(&mut hasher);
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+fn string_attr(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/// OTLP/JSON encodes 64-bit integers as strings
+fn int_attr(key: &str, value: u64) -> Value {
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+fn span(entry: &TrafficEntry) -> Value {
+    let session = session_key(&entry.request);
+    let trace_id = format!("{}{}", hash_hex(&[&session], 0), hash_hex(&[&session], 1));
+    let span_id = hash_hex(&[&entry.id.to_string(), &entry.timestamp.to_rfc3339()], 2);
+
+    let start = entry.timestamp.timestamp_nanos_opt().unwrap_or(0) as u64;
+    let end = start + entry.latency_ms.unwrap_or(0) * 1_000_000;
+
+    let mut attributes = vec![
+        string_attr("gen_ai.operation.name", "chat"),
+        string_attr("gen_ai.system", "anthropic"),
+        string_attr("gen_ai.request.model", &entry.request.model),
+        json!({ "key": "gen_ai.request.stream", "value": { "boolValue": entry.request.stream } }),
+        string_attr("server.address", "api.anthropic.com"),
+        string_attr("claudev.session", &session),
+    ];
+    if let Some(max_tokens) = entry.request.max_tokens {
+        attributes.push(int_attr("gen_ai.request.max_tokens", max_tokens));
+    }
+
+    if let Some(response) = &entry.response {
+        if let Some(id) = &response.id {
+            attributes.push(string_attr("gen_ai.response.id", id));
+        }
+        if let Some(model) = &response.model {
+            attributes.push(string_attr("gen_ai.response.model", model));
+        }
+        if let Some(reason) = &response.stop_reason {
+            attributes.push(json!({
+                "key": "gen_ai.response.finish_reasons",
+                "value": { "arrayValue": { "values": [{ "stringValue": reason }] } }
+            }));
+        }
+        if let Some(usage) = &response.usage {
+            attributes.push(int_attr("gen_ai.usage.input_tokens", usage.input_tokens));
+            attributes.push(int_attr("gen_ai.usage.output_tokens", usage.output_tokens));
+            attributes.push(int_attr(
+                "gen_ai.usage.cache_read.input_tokens",
+                usage.cache_read_input_tokens,
+            ));
+            attributes.push(int_attr(
+                "gen_ai.usage.cache_creation.input_tokens",
+                usage.cache_creation_input_tokens,
+            ));
+        }
+        attributes.push(json!({
+            "key": "claudev.cost_usd",
+            "value": { "doubleValue": entry_cost(entry) }
+        }));
+    }
+
+    if let Some(client) = &entry.client {
+        attributes.push(string_attr("claudev.client", &client.label()));
+        if let Some(pid) = client.pid {
+            attributes.push(int_attr("process.pid", pid as u64));
+        }
+    }
+
+    let status = match &entry.status {
+        TrafficStatus::Error(message) => {
+            let status = http_status(entry);
+            attributes.push(string_attr(
+                "error.type",
+                &if status > 0 { status.to_string() } else { "_OTHER".to_string() },
+            ));
+            json!({ "code": 2, "message": message })
+        }
+        TrafficStatus::Success => json!({ "code": 1 }),
+        _ => json!({ "code": 0 }),
+    };
+
+    json!({
+        "traceId": trace_id,
+        "spanId": span_id,
+        "name": format!("chat {}", entry.request.model),
+        // SPAN_KIND_CLIENT
+        "kind": 3,
+        "startTimeUnixNano": start.to_string(),
+        "endTimeUnixNano": end.to_string(),
+        "attributes": attributes,
+        "status": status,
+    })
+}
+
+/// Build an OTLP/JSON `ExportTraceServiceRequest`
+pub fn to_otlp(entries: &[TrafficEntry]) -> Value {
+    let spans: Vec<Value> = entries.iter().map(span).collect();
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [string_attr("service.name", CREATOR)]
+            },
+            "scopeSpans": [{
+                "scope": { "name": CREATOR, "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }]
+        }]
+    })
+}
+
+/// Render entries in a format
+pub fn render(entries: &[TrafficEntry], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Har => serde_json::to_string_pretty(&to_har(entries))?,
+        ExportFormat::Otlp => serde_json::to_string(&to_otlp(entries))?,
+        ExportFormat::Jsonl => entries
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n"),
+    })
+}
+
+/// Post spans to an OTLP/HTTP collector (e.g. `http://localhost:4318`)
+pub async fn send_otlp(entries: &[TrafficEntry], endpoint: &str) -> Result<()> {
+    let url = if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint.trim_end_matches('/'))
+    };
+
+    let response = reqwest::Client::new()
+        .post(&url)
+        .json(&to_otlp(entries))
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach OTLP collector at {}", url))?;
+
+    if !response.status().is_success() {
+        anyhow::bail!(
+            "OTLP collector returned {}: {}",
+            response.status(),
+            response.text().await.unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{parse_request, ApiResponse, Usage};
+    use chrono::{TimeZone, Utc};
+
+    fn entries() -> Vec<TrafficEntry> {
+        let request = parse_request(
+            r#"{"model":"claude-sonnet-4","max_tokens":1024,"messages":[{"role":"user","content":"hi"}],"stream":false}"#,
+        )
+        .unwrap();
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 9, 0, 0).unwrap();
+        vec![
+            TrafficEntry {
+                id: 1,
+                timestamp: at,
+                request: request.clone(),
+                response: Some(ApiResponse {
+                    id: Some("msg_1".to_string()),
+                    model: Some("claude-sonnet-4-20250514".to_string()),
+                    content: vec![],
+                    usage: Some(Usage {
+                        input_tokens: 10,
+                        output_tokens: 5,
+                        ..Default::default()
+                    }),
+                    stop_reason: Some("end_turn".to_string()),
+                    raw_body: None,
+                }),
+                latency_ms: Some(1500),
+                status: TrafficStatus::Success,
+                client: None,
+            },
+            TrafficEntry {
+                id: 2,
+                timestamp: at,
+                request,
+                response: None,
+                latency_ms: Some(20),
+                status: TrafficStatus::Error("HTTP 529: overloaded".to_string()),
+                client: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_har_export() {
+        let har = to_har(&entries());
+        assert_eq!(har["log"]["version"], "1.2");
+        let first = &har["log"]["entries"][0];
+        assert_eq!(first["request"]["method"], "POST");
+        assert_eq!(first["response"]["status"], 200);
+        assert_eq!(first["time"], 1500);
+        assert!(first["request"]["postData"]["text"]
+            .as_str()
+            .unwrap()
+            .contains("claude-sonnet-4"));
+        assert_eq!(har["log"]["entries"][1]["response"]["status"], 529);
+    }
+
+    #[test]
+    fn test_otlp_spans() {
+        let otlp = to_otlp(&entries());
+        let spans = &otlp["resourceSpans"][0]["scopeSpans"][0]["spans"];
+        let ok = &spans[0];
+        assert_eq!(ok["name"], "chat claude-sonnet-4");
+        assert_eq!(ok["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(ok["spanId"].as_str().unwrap().len(), 16);
+        // Same conversation, same trace
+        assert_eq!(ok["traceId"], spans[1]["traceId"]);
+
+        let start: u64 = ok["startTimeUnixNano"].as_str().unwrap().parse().unwrap();
+        let end: u64 = ok["endTimeUnixNano"].as_str().unwrap().parse().unwrap();
+        assert_eq!(end - start, 1_500_000_000);
+
+        let attr = |span: &Value, key: &str| {
+            span["attributes"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|a| a["key"] == key)
+                .map(|a| a["value"].clone())
+        };
+        assert_eq!(attr(ok, "gen_ai.usage.input_tokens").unwrap()["intValue"], "10");
+        assert_eq!(
+            attr(ok, "gen_ai.response.finish_reasons").unwrap()["arrayValue"]["values"][0]["stringValue"],
+            "end_turn"
+        );
+        assert_eq!(spans[1]["status"]["code"], 2);
+        assert_eq!(attr(&spans[1], "error.type").unwrap()["stringValue"], "529");
+    }
+}
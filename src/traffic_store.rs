@@ -0,0 +1,626 @@
+//! Persistent traffic store
+//!
+//! Completed traffic entries are appended to one JSONL segment per day under
+//! `~/.claudev/traffic/`. When the day changes, older segments are gzipped
+//! (`2025-01-15.jsonl.gz`) and segments beyond the retention window are
+//! deleted. Segment file names double as the index: queries only open the
+//! days inside their time range.
+
+use crate::client_id::ClientInfo;
+use crate::traffic::{calculate_cost_at, TrafficEntry, TrafficStatus};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Days of traffic kept by default
+pub const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+const SEGMENT_EXT: &str = ".jsonl";
+const COMPRESSED_EXT: &str = ".jsonl.gz";
+
+/// Daily-segmented, compressed store of traffic entries
+pub struct TrafficStore {
+    dir: PathBuf,
+    retention_days: u32,
+    /// Day of the segment currently being appended to
+    current_day: Mutex<Option<NaiveDate>>,
+}
+
+impl TrafficStore {
+    pub fn new(dir: PathBuf, retention_days: u32) -> Self {
+        Self {
+            dir,
+            retention_days,
+            current_day: Mutex::new(None),
+        }
+    }
+
+    /// Store in the default location
+    pub fn open_default(retention_days: u32) -> Self {
+        Self::new(traffic_store_dir(), retention_days)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append a finished entry to the segment for its day. A straggler
+    /// started before midnight does not turn the store back to its day, so
+    /// it lands in a fresh segment next to that day's archive and is
+    /// compressed into it at the next rotation.
+    pub fn append(&self, entry: &TrafficEntry) -> Result<()> {
+        let day = entry.timestamp.date_naive();
+        {
+            let mut current = self.current_day.lock().unwrap();
+            if current.is_none_or(|current| day > current) {
+                *current = Some(day);
+                fs::create_dir_all(&self.dir)?;
+                self.rotate(day)?;
+            }
+        }
+
+        let path = self.segment_path(day);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Compress segments older than `today` and delete those past retention
+    pub fn rotate(&self, today: NaiveDate) -> Result<()> {
+        let cutoff = today - Duration::days(self.retention_days as i64);
+
+        for (day, path) in self.segments()? {
+            if day < cutoff {
+                fs::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            } else if day < today && !is_compressed(&path) {
+                compress_segment(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// All segments with their day, oldest first
+    fn segments(&self) -> Result<Vec<(NaiveDate, PathBuf)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut segments = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let stem = name
+                .strip_suffix(COMPRESSED_EXT)
+                .or_else(|| name.strip_suffix(SEGMENT_EXT));
+            if let Some(day) = stem.and_then(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()) {
+                segments.push((day, path));
+            }
+        }
+        segments.sort();
+        Ok(segments)
+    }
+
+    fn segment_path(&self, day: NaiveDate) -> PathBuf {
+        self.dir.join(format!("{}{}", day.format("%Y-%m-%d"), SEGMENT_EXT))
+    }
+
+    /// Load entries whose day falls within `[from, to]` (inclusive, either end open)
+    pub fn load(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Result<Vec<TrafficEntry>> {
+        let mut entries = Vec::new();
+        for (day, path) in self.segments()? {
+            if from.is_some_and(|f| day < f) || to.is_some_and(|t| day > t) {
+                continue;
+            }
+            read_segment(&path, &mut entries)?;
+        }
+        Ok(entries)
+    }
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.ends_with(COMPRESSED_EXT))
+}
+
+/// Gzips a segment, adding it as a new member of the day's archive when
+/// one exists already
+fn compress_segment(path: &Path) -> Result<()> {
+    let compressed = PathBuf::from(format!("{}.gz", path.display()));
+    let mut input = File::open(path)?;
+    let output = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&compressed)
+        .with_context(|| format!("Failed to open {}", compressed.display()))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    fs::remove_file(path)?;
+    tracing::debug!("Compressed traffic segment {}", compressed.display());
+    Ok(())
+}
+
+fn read_segment(path: &Path, entries: &mut Vec<TrafficEntry>) -> Result<()> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let reader: Box<dyn BufRead> = if is_compressed(path) {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    for line in reader.lines() {
+        let line = line?;
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// Read entries from a traffic JSONL file, gzipped or not
+pub fn read_file(path: &Path) -> Result<Vec<TrafficEntry>> {
+    let mut entries = Vec::new();
+    read_segment(path, &mut entries)?;
+    Ok(entries)
+}
+
+/// Default directory of the traffic store
+pub fn traffic_store_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".claudev")
+        .join("traffic")
+}
+
+/// Cost of an entry at the rates in effect when it was made
+pub fn entry_cost(entry: &TrafficEntry) -> f64 {
+    entry
+        .response
+        .as_ref()
+        .and_then(|r| r.usage.as_ref().map(|u| (r.model.as_deref(), u)))
+        .map(|(model, usage)| {
+            let model = model.filter(|m| !m.is_empty()).unwrap_or(&entry.request.model);
+            calculate_cost_at(model, usage, entry.timestamp)
+        })
+        .unwrap_or(0.0)
+}
+
+fn status_name(status: &TrafficStatus) -> &'static str {
+    match status {
+        TrafficStatus::Pending => "pending",
+        TrafficStatus::Success => "success",
+        TrafficStatus::Error(_) => "error",
+        TrafficStatus::Streaming => "streaming",
+    }
+}
+
+/// Filters for `claudev traffic query`
+#[derive(Debug, Clone, Default)]
+pub struct TrafficQuery {
+    /// Substring of the model id
+    pub model: Option<String>,
+    /// success, error, pending or streaming
+    pub status: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub min_cost: Option<f64>,
+    pub max_cost: Option<f64>,
+    pub min_latency_ms: Option<u64>,
+    pub max_latency_ms: Option<u64>,
+    /// Substring of the client label, see [`ClientInfo::label`]
+    pub client: Option<String>,
+}
+
+impl TrafficQuery {
+    pub fn matches(&self, entry: &TrafficEntry) -> bool {
+        if let Some(model) = &self.model {
+            if !entry.request.model.to_lowercase().contains(&model.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(client) = &self.client {
+            if !client_label(entry).to_lowercase().contains(&client.to_lowercase()) {
+                return false;
+            }
+        }
+        if let Some(status) = &self.status {
+            let status = if status == "ok" { "success" } else { status.as_str() };
+            if status_name(&entry.status) != status {
+                return false;
+            }
+        }
+        if self.since.is_some_and(|t| entry.timestamp < t)
+            || self.until.is_some_and(|t| entry.timestamp > t)
+        {
+            return false;
+        }
+        if self.min_cost.is_some() || self.max_cost.is_some() {
+            let cost = entry_cost(entry);
+            if self.min_cost.is_some_and(|c| cost < c) || self.max_cost.is_some_and(|c| cost > c) {
+                return false;
+            }
+        }
+        if self.min_latency_ms.is_some() || self.max_latency_ms.is_some() {
+            let Some(latency) = entry.latency_ms else {
+                return false;
+            };
+            if self.min_latency_ms.is_some_and(|l| latency < l)
+                || self.max_latency_ms.is_some_and(|l| latency > l)
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Load matching entries from a store
+    pub fn run(&self, store: &TrafficStore) -> Result<Vec<TrafficEntry>> {
+        let entries = store.load(
+            self.since.map(|t| t.date_naive()),
+            self.until.map(|t| t.date_naive()),
+        )?;
+        Ok(entries.into_iter().filter(|e| self.matches(e)).collect())
+    }
+}
+
+/// Field to aggregate query results by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Model,
+    Day,
+    Hour,
+    Status,
+    Client,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "model" => Ok(GroupBy::Model),
+            "day" => Ok(GroupBy::Day),
+            "hour" => Ok(GroupBy::Hour),
+            "status" => Ok(GroupBy::Status),
+            "client" => Ok(GroupBy::Client),
+            other => anyhow::bail!(
+                "Unknown group: {} (expected model, day, hour, status or client)",
+                other
+            ),
+        }
+    }
+}
+
+/// Aggregate of the entries sharing a group key
+#[derive(Debug, Clone, Default)]
+pub struct QueryGroup {
+    pub key: String,
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost_usd: f64,
+    pub avg_latency_ms: f64,
+}
+
+/// Client an entry is attributed to, `unknown` for entries captured before tagging
+pub fn client_label(entry: &TrafficEntry) -> String {
+    entry
+        .client
+        .as_ref()
+        .map(ClientInfo::label)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Aggregate entries by a key, sorted by key
+pub fn aggregate(entries: &[TrafficEntry], group_by: GroupBy) -> Vec<QueryGroup> {
+    let mut groups: BTreeMap<String, (QueryGroup, u64, u64)> = BTreeMap::new();
+
+    for entry in entries {
+        let key = match group_by {
+            GroupBy::Model => entry.request.model.clone(),
+            GroupBy::Day => entry.timestamp.format("%Y-%m-%d").to_string(),
+            GroupBy::Hour => entry.timestamp.format("%Y-%m-%d %H:00").to_string(),
+            GroupBy::Status => status_name(&entry.status).to_string(),
+            GroupBy::Client => client_label(entry),
+        };
+        let (group, latency_sum, latency_count) = groups.entry(key.clone()).or_default();
+        group.key = key;
+        group.requests += 1;
+        group.cost_usd += entry_cost(entry);
+        if let Some(usage) = entry.response.as_ref().and_then(|r| r.usage.as_ref()) {
+            group.input_tokens += usage.input_tokens;
+            group.output_tokens += usage.output_tokens;
+            group.cache_read_tokens += usage.cache_read_input_tokens;
+        }
+        if let Some(latency) = entry.latency_ms {
+            *latency_sum += latency;
+            *latency_count += 1;
+        }
+    }
+
+    groups
+        .into_values()
+        .map(|(mut group, sum, count)| {
+            if count > 0 {
+                group.avg_latency_ms = sum as f64 / count as f64;
+            }
+            group
+        })
+        .collect()
+}
+
+/// Parse `--since`/`--until` values: `YYYY-MM-DD`, RFC 3339, or relative (`30m`, `24h`, `7d`)
+pub fn parse_time(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(day.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    let Some(unit) = value.chars().last() else {
+        anyhow::bail!("Empty time (use YYYY-MM-DD or e.g. 7d, 24h)");
+    };
+    let amount: i64 = value
+        .strip_suffix(unit)
+        .unwrap_or_default()
+        .parse()
+        .with_context(|| format!("Invalid time: {} (use YYYY-MM-DD or e.g. 7d, 24h)", value))?;
+    let duration = match unit {
+        'm' => Duration::try_minutes(amount),
+        'h' => Duration::try_hours(amount),
+        'd' => Duration::try_days(amount),
+        'w' => Duration::try_weeks(amount),
+        _ => anyhow::bail!("Invalid time unit in {} (use m, h, d or w)", value),
+    };
+    duration
+        .and_then(|d| now.checked_sub_signed(d))
+        .with_context(|| format!("Time out of range: {}", value))
+}
+
+/// Print query results as a table of entries or of groups
+pub fn print_results(entries: &[TrafficEntry], group_by: Option<GroupBy>, limit: usize) {
+    use colored::Colorize;
+    use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+
+    match group_by {
+        Some(group_by) => {
+            table.set_header(vec![
+                Cell::new("Group").fg(Color::Cyan),
+                Cell::new("Requests").fg(Color::Cyan),
+                Cell::new("Input").fg(Color::Cyan),
+                Cell::new("Output").fg(Color::Cyan),
+                Cell::new("Cache read").fg(Color::Cyan),
+                Cell::new("Cost").fg(Color::Cyan),
+                Cell::new("Avg latency").fg(Color::Cyan),
+            ]);
+            for group in aggregate(entries, group_by) {
+                table.add_row(vec![
+                    Cell::new(&group.key),
+                    Cell::new(group.requests),
+                    Cell::new(group.input_tokens),
+                    Cell::new(group.output_tokens),
+                    Cell::new(group.cache_read_tokens),
+                    Cell::new(format!("${:.4}", group.cost_usd)).fg(Color::Yellow),
+                    Cell::new(format!("{:.0}ms", group.avg_latency_ms)),
+                ]);
+            }
+        }
+        None => {
+            table.set_header(vec![
+                Cell::new("Time").fg(Color::Cyan),
+                Cell::new("Model").fg(Color::Cyan),
+                Cell::new("Client").fg(Color::Cyan),
+                Cell::new("Status").fg(Color::Cyan),
+                Cell::new("Tokens").fg(Color::Cyan),
+                Cell::new("Cost").fg(Color::Cyan),
+                Cell::new("Latency").fg(Color::Cyan),
+            ]);
+            // Most recent first
+            for entry in entries.iter().rev().take(limit) {
+                let tokens = entry
+                    .response
+                    .as_ref()
+                    .and_then(|r| r.usage.as_ref())
+                    .map(|u| format!("{}/{}", u.input_tokens, u.output_tokens))
+                    .unwrap_or_else(|| "-".to_string());
+                let status = Cell::new(status_name(&entry.status));
+                table.add_row(vec![
+                    Cell::new(entry.timestamp.format("%Y-%m-%d %H:%M:%S")),
+                    Cell::new(&entry.request.model),
+                    Cell::new(client_label(entry)),
+                    if matches!(entry.status, TrafficStatus::Error(_)) {
+                        status.fg(Color::Red)
+                    } else {
+                        status
+                    },
+                    Cell::new(tokens),
+                    Cell::new(format!("${:.4}", entry_cost(entry))),
+                    Cell::new(
+                        entry
+                            .latency_ms
+                            .map(|l| format!("{}ms", l))
+                            .unwrap_or_else(|| "-".to_string()),
+                    ),
+                ]);
+            }
+        }
+    }
+
+    println!("{}", table);
+    let total: f64 = entries.iter().map(entry_cost).sum();
+    println!(
+        "\n{} requests, total cost {}",
+        entries.len(),
+        format!("${:.4}", total).yellow().bold()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{ApiRequest, ApiResponse, Usage};
+    use chrono::TimeZone;
+
+    fn entry(model: &str, at: DateTime<Utc>, status: TrafficStatus, latency: u64) -> TrafficEntry {
+        TrafficEntry {
+            id: 1,
+            timestamp: at,
+            request: ApiRequest {
+                model: model.to_string(),
+                max_tokens: None,
+                messages: vec![],
+                system: None,
+                stream: false,
+                tools: None,
+                raw_body: None,
+            },
+            response: Some(ApiResponse {
+                id: None,
+                model: Some(model.to_string()),
+                content: vec![],
+                usage: Some(Usage {
+                    input_tokens: 1_000_000,
+                    output_tokens: 0,
+                    ..Default::default()
+                }),
+                stop_reason: None,
+                raw_body: None,
+            }),
+            latency_ms: Some(latency),
+            status,
+            client: None,
+        }
+    }
+
+    #[test]
+    fn test_rotation_and_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TrafficStore::new(dir.path().to_path_buf(), 7);
+
+        let old = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let yesterday = Utc.with_ymd_and_hms(2025, 1, 9, 12, 0, 0).unwrap();
+        let today = Utc.with_ymd_and_hms(2025, 1, 10, 12, 0, 0).unwrap();
+        store.append(&entry("claude-sonnet-4", old, TrafficStatus::Success, 100)).unwrap();
+        store.append(&entry("claude-sonnet-4", yesterday, TrafficStatus::Success, 100)).unwrap();
+        store.append(&entry("claude-haiku-4-5", today, TrafficStatus::Success, 100)).unwrap();
+
+        assert!(!dir.path().join("2025-01-01.jsonl").exists());
+        assert!(!dir.path().join("2025-01-01.jsonl.gz").exists());
+        assert!(dir.path().join("2025-01-09.jsonl.gz").exists());
+        assert!(dir.path().join("2025-01-10.jsonl").exists());
+
+        let all = store.load(None, None).unwrap();
+        assert_eq!(all.len(), 2);
+        let only_today = store.load(Some(today.date_naive()), None).unwrap();
+        assert_eq!(only_today.len(), 1);
+    }
+
+    #[test]
+    fn test_straggler_keeps_compressed_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TrafficStore::new(dir.path().to_path_buf(), 30);
+
+        let day = Utc.with_ymd_and_hms(2025, 1, 9, 23, 59, 0).unwrap();
+        let next_day = Utc.with_ymd_and_hms(2025, 1, 10, 0, 1, 0).unwrap();
+        let day_after = Utc.with_ymd_and_hms(2025, 1, 11, 9, 0, 0).unwrap();
+        store.append(&entry("claude-sonnet-4", day, TrafficStatus::Success, 100)).unwrap();
+        store.append(&entry("claude-sonnet-4", next_day, TrafficStatus::Success, 100)).unwrap();
+        assert!(dir.path().join("2025-01-09.jsonl.gz").exists());
+
+        // Started before midnight, finished after the day was compressed
+        let straggler = Utc.with_ymd_and_hms(2025, 1, 9, 23, 59, 30).unwrap();
+        store.append(&entry("claude-sonnet-4", straggler, TrafficStatus::Success, 90_000)).unwrap();
+        store.append(&entry("claude-sonnet-4", next_day, TrafficStatus::Success, 100)).unwrap();
+        assert!(dir.path().join("2025-01-10.jsonl").exists());
+        assert_eq!(store.load(None, None).unwrap().len(), 4);
+
+        // The next rotation adds the straggler to the day's archive
+        store.append(&entry("claude-sonnet-4", day_after, TrafficStatus::Success, 100)).unwrap();
+        assert!(!dir.path().join("2025-01-09.jsonl").exists());
+        let first_day = store
+            .load(Some(day.date_naive()), Some(day.date_naive()))
+            .unwrap();
+        assert_eq!(first_day.len(), 2);
+        assert_eq!(store.load(None, None).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_query_filters_and_aggregates() {
+        let at = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let mut entries = vec![
+            entry("claude-sonnet-4", at, TrafficStatus::Success, 500),
+            entry("claude-sonnet-4", at, TrafficStatus::Error("overloaded".into()), 50),
+            entry("claude-opus-4", at, TrafficStatus::Success, 5000),
+        ];
+        entries[2].client = Some(ClientInfo {
+            session: Some("review".to_string()),
+            ..Default::default()
+        });
+
+        let query = TrafficQuery {
+            model: Some("sonnet".to_string()),
+            status: Some("ok".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(entries.iter().filter(|e| query.matches(e)).count(), 1);
+
+        let expensive = TrafficQuery {
+            min_cost: Some(10.0),
+            ..Default::default()
+        };
+        let matched: Vec<_> = entries.iter().filter(|e| expensive.matches(e)).collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].request.model, "claude-opus-4");
+
+        let groups = aggregate(&entries, GroupBy::Model);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].key, "claude-sonnet-4");
+        assert_eq!(groups[1].requests, 2);
+        assert!((groups[1].cost_usd - 6.0).abs() < 1e-9);
+        assert!((groups[1].avg_latency_ms - 275.0).abs() < 1e-9);
+
+        let by_client = TrafficQuery {
+            client: Some("REVIEW".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(entries.iter().filter(|e| by_client.matches(e)).count(), 1);
+        let clients: Vec<_> = aggregate(&entries, GroupBy::Client)
+            .into_iter()
+            .map(|g| (g.key, g.requests))
+            .collect();
+        assert_eq!(clients, vec![("review".to_string(), 1), ("unknown".to_string(), 2)]);
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 10, 0, 0, 0).unwrap();
+        assert_eq!(parse_time("7d", now).unwrap(), now - Duration::days(7));
+        assert_eq!(
+            parse_time("2025-06-01", now).unwrap(),
+            Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()
+        );
+        assert!(parse_time("soon", now).is_err());
+        assert!(parse_time("", now).is_err());
+        assert!(parse_time("d", now).is_err());
+        assert!(parse_time("99999999999999d", now).is_err());
+        assert!(parse_time("99999999999w", now).is_err());
+    }
+}
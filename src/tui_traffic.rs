@@ -2,8 +2,11 @@
 //!
 //! Real-time display of Claude API traffic using ratatui.
 
+use crate::prompt_cache::{analyze_cache, CacheReport};
 use crate::proxy::ProxyEvent;
-use crate::traffic::{TrafficLog, TrafficStatus};
+use crate::tool_analytics::{self, ToolReport};
+use crate::traffic::{TrafficEntry, TrafficLog, TrafficStats, TrafficStatus};
+use crate::traffic_store::{aggregate, client_label, GroupBy};
 use crossterm::event::{self, Event, KeyCode, KeyEventKind};
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
@@ -15,6 +18,26 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 
 const UPDATE_INTERVAL_MS: u64 = 100;
+const TAB_TITLES: [&str; 5] = ["Live", "Stats", "History", "Cache", "Tools"];
+
+/// Entries captured from the traffic log once per change, shared by every pane
+#[derive(Default)]
+struct Snapshot {
+    /// Log generation the snapshot was taken at
+    generation: Option<u64>,
+    /// Client labels in the order they were first seen, with request counts
+    clients: Vec<(String, u64)>,
+    /// Entries of the selected client (oldest first)
+    entries: Vec<TrafficEntry>,
+    /// Stats of the selected client, lifetime totals when showing every client
+    stats: TrafficStats,
+    /// Lifetime totals over every client
+    totals: TrafficStats,
+    /// Prompt cache analysis of `entries`
+    cache: CacheReport,
+    /// Tool analytics of `entries`
+    tools: ToolReport,
+}
 
 /// Traffic monitor application state
 pub struct TrafficMonitorApp {
@@ -26,6 +49,10 @@ pub struct TrafficMonitorApp {
     should_quit: bool,
     recent_events: Vec<String>,
     max_events: usize,
+    secret_alerts: usize,
+    /// 0 shows every client, n the n-th client of the snapshot
+    selected_client: usize,
+    snapshot: Snapshot,
 }
 
 impl TrafficMonitorApp {
@@ -42,13 +69,71 @@ impl TrafficMonitorApp {
             should_quit: false,
             recent_events: Vec::new(),
             max_events: 100,
+            secret_alerts: 0,
+            selected_client: 0,
+            snapshot: Snapshot::default(),
         }
     }
 
+    /// Re-read the traffic log if it changed since the last snapshot
+    fn refresh(&mut self) {
+        let generation = self.traffic_log.generation();
+        if self.snapshot.generation == Some(generation) {
+            return;
+        }
+
+        let all = self.traffic_log.get_all();
+        let mut clients: Vec<(String, u64)> = Vec::new();
+        for entry in &all {
+            let label = client_label(entry);
+            match clients.iter_mut().find(|(c, _)| *c == label) {
+                Some((_, count)) => *count += 1,
+                None => clients.push((label, 1)),
+            }
+        }
+        if self.selected_client > clients.len() {
+            self.selected_client = 0;
+        }
+
+        let totals = self.traffic_log.get_stats();
+        let (entries, stats) = match self.selected_client.checked_sub(1) {
+            Some(index) => {
+                let client = &clients[index].0;
+                let entries: Vec<TrafficEntry> = all
+                    .into_iter()
+                    .filter(|e| &client_label(e) == client)
+                    .collect();
+                let stats = TrafficStats::from_entries(&entries);
+                (entries, stats)
+            }
+            None => (all, totals.clone()),
+        };
+
+        self.snapshot = Snapshot {
+            generation: Some(generation),
+            clients,
+            cache: analyze_cache(&entries),
+            tools: tool_analytics::analyze_traffic(&entries),
+            entries,
+            stats,
+            totals,
+        };
+    }
+
+    /// Select another client tab, `step` is 1 for next and -1 for previous
+    fn switch_client(&mut self, step: isize) {
+        let count = self.snapshot.clients.len() as isize + 1;
+        self.selected_client = (self.selected_client as isize + step).rem_euclid(count) as usize;
+        self.scroll_offset = 0;
+        self.snapshot.generation = None;
+        self.refresh();
+    }
+
     /// Run the TUI event loop
     pub async fn run(&mut self, terminal: &mut ratatui::Terminal<impl ratatui::backend::Backend>) -> io::Result<()> {
         loop {
             // Draw UI
+            self.refresh();
             terminal.draw(|f| self.draw(f))?;
 
             // Poll for events with timeout
@@ -77,9 +162,13 @@ impl TrafficMonitorApp {
         match key {
             KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
             KeyCode::Char('p') | KeyCode::Char(' ') => self.paused = !self.paused,
-            KeyCode::Tab => self.selected_tab = (self.selected_tab + 1) % 3,
+            KeyCode::Tab => self.selected_tab = (self.selected_tab + 1) % TAB_TITLES.len(),
             KeyCode::BackTab => {
-                self.selected_tab = if self.selected_tab == 0 { 2 } else { self.selected_tab - 1 };
+                self.selected_tab = if self.selected_tab == 0 {
+                    TAB_TITLES.len() - 1
+                } else {
+                    self.selected_tab - 1
+                };
             }
             KeyCode::Up | KeyCode::Char('k') => {
                 self.scroll_offset = self.scroll_offset.saturating_sub(1);
@@ -90,6 +179,10 @@ impl TrafficMonitorApp {
             KeyCode::Char('1') => self.selected_tab = 0,
             KeyCode::Char('2') => self.selected_tab = 1,
             KeyCode::Char('3') => self.selected_tab = 2,
+            KeyCode::Char('4') => self.selected_tab = 3,
+            KeyCode::Char('5') => self.selected_tab = 4,
+            KeyCode::Char('c') => self.switch_client(1),
+            KeyCode::Char('C') => self.switch_client(-1),
             _ => {}
         }
     }
@@ -108,6 +201,18 @@ impl TrafficMonitorApp {
             ProxyEvent::StreamChunk { id, text } => {
                 format!("#{} chunk: {}...", id, text.chars().take(50).collect::<String>())
             }
+            ProxyEvent::SecretDetected { data_type, location, redacted } => {
+                self.secret_alerts += 1;
+                let action = if redacted { "redacted" } else { "sent" };
+                format!("SECRET {} in {} ({})", data_type, location, action)
+            }
+            ProxyEvent::BudgetAlert { message, blocked } => {
+                if blocked {
+                    format!("BUDGET BLOCKED: {}", message)
+                } else {
+                    format!("BUDGET: {}", message)
+                }
+            }
         };
 
         self.recent_events.push(msg);
@@ -121,26 +226,29 @@ impl TrafficMonitorApp {
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3), // Tabs
+                Constraint::Length(3), // Clients
                 Constraint::Min(10),   // Content
                 Constraint::Length(3), // Status bar
             ])
             .split(f.area());
 
         self.draw_tabs(f, chunks[0]);
+        self.draw_client_tabs(f, chunks[1]);
 
         match self.selected_tab {
-            0 => self.draw_live_tab(f, chunks[1]),
-            1 => self.draw_stats_tab(f, chunks[1]),
-            2 => self.draw_history_tab(f, chunks[1]),
+            0 => self.draw_live_tab(f, chunks[2]),
+            1 => self.draw_stats_tab(f, chunks[2]),
+            2 => self.draw_history_tab(f, chunks[2]),
+            3 => self.draw_cache_tab(f, chunks[2]),
+            4 => self.draw_tools_tab(f, chunks[2]),
             _ => {}
         }
 
-        self.draw_status_bar(f, chunks[2]);
+        self.draw_status_bar(f, chunks[3]);
     }
 
     fn draw_tabs(&self, f: &mut Frame, area: Rect) {
-        let titles = vec!["Live", "Stats", "History"];
-        let tabs = Tabs::new(titles)
+        let tabs = Tabs::new(TAB_TITLES.to_vec())
             .block(Block::default().borders(Borders::ALL).title("Claude Traffic Monitor"))
             .select(self.selected_tab)
             .style(Style::default().fg(Color::White))
@@ -148,6 +256,25 @@ impl TrafficMonitorApp {
         f.render_widget(tabs, area);
     }
 
+    fn draw_client_tabs(&self, f: &mut Frame, area: Rect) {
+        let clients = &self.snapshot.clients;
+        let total: u64 = clients.iter().map(|(_, requests)| requests).sum();
+
+        let mut titles = vec![format!("All ({})", total)];
+        titles.extend(
+            clients
+                .iter()
+                .map(|(client, requests)| format!("{} ({})", client, requests)),
+        );
+
+        let tabs = Tabs::new(titles)
+            .block(Block::default().borders(Borders::ALL).title("Clients (c: switch)"))
+            .select(self.selected_client)
+            .style(Style::default().fg(Color::White))
+            .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
+        f.render_widget(tabs, area);
+    }
+
     fn draw_live_tab(&self, f: &mut Frame, area: Rect) {
         let chunks = Layout::default()
             .direction(Direction::Horizontal)
@@ -161,8 +288,10 @@ impl TrafficMonitorApp {
             .rev()
             .take(area.height as usize - 2)
             .map(|e| {
-                let color = if e.contains("ERROR") {
+                let color = if e.contains("ERROR") || e.contains("SECRET") || e.contains("BLOCKED") {
                     Color::Red
+                } else if e.contains("BUDGET") {
+                    Color::Yellow
                 } else if e.contains("done") {
                     Color::Green
                 } else if e.contains("chunk") {
@@ -179,7 +308,7 @@ impl TrafficMonitorApp {
         f.render_widget(events_widget, chunks[0]);
 
         // Quick stats
-        let stats = self.traffic_log.get_stats();
+        let stats = &self.snapshot.stats;
         let stats_text = vec![
             Line::from(format!("Requests: {}", stats.total_requests)),
             Line::from(format!("Success: {}", stats.successful_requests)),
@@ -194,6 +323,15 @@ impl TrafficMonitorApp {
                 Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
             )),
             Line::from(format!("Avg latency: {:.0}ms", stats.avg_latency_ms)),
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("Secrets detected: {}", self.secret_alerts),
+                if self.secret_alerts > 0 {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                },
+            )),
         ];
 
         let stats_widget = Paragraph::new(stats_text)
@@ -202,11 +340,15 @@ impl TrafficMonitorApp {
     }
 
     fn draw_stats_tab(&self, f: &mut Frame, area: Rect) {
-        let stats = self.traffic_log.get_stats();
+        let stats = &self.snapshot.stats;
 
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .constraints([
+                Constraint::Percentage(40),
+                Constraint::Percentage(30),
+                Constraint::Percentage(30),
+            ])
             .split(area);
 
         // Token usage
@@ -255,13 +397,51 @@ impl TrafficMonitorApp {
             .header(Row::new(vec!["Model", "Requests"]).style(Style::default().add_modifier(Modifier::BOLD)))
             .block(Block::default().borders(Borders::ALL).title("Models Used"));
         f.render_widget(model_table, chunks[1]);
+
+        // Per-client totals
+        let mut client_rows: Vec<Row> = aggregate(&self.snapshot.entries, GroupBy::Client)
+            .into_iter()
+            .map(|group| {
+                Row::new(vec![
+                    Cell::from(group.key),
+                    Cell::from(group.requests.to_string()),
+                    Cell::from(format!("{}/{}", group.input_tokens, group.output_tokens)),
+                    Cell::from(format!("${:.4}", group.cost_usd))
+                        .style(Style::default().fg(Color::Yellow)),
+                    Cell::from(format!("{:.0}ms", group.avg_latency_ms)),
+                ])
+            })
+            .collect();
+
+        if client_rows.is_empty() {
+            client_rows.push(Row::new(vec![Cell::from("No requests yet")]));
+        }
+
+        let client_table = Table::new(
+            client_rows,
+            [
+                Constraint::Min(20),
+                Constraint::Length(9),
+                Constraint::Length(18),
+                Constraint::Length(10),
+                Constraint::Length(10),
+            ],
+        )
+        .header(
+            Row::new(vec!["Client", "Requests", "Tokens", "Cost", "Latency"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Clients"));
+        f.render_widget(client_table, chunks[2]);
     }
 
     fn draw_history_tab(&self, f: &mut Frame, area: Rect) {
-        let entries = self.traffic_log.get_recent(50);
-
-        let rows: Vec<Row> = entries
+        let rows: Vec<Row> = self
+            .snapshot
+            .entries
             .iter()
+            .rev()
+            .take(50)
             .enumerate()
             .skip(self.scroll_offset)
             .take(area.height as usize - 3)
@@ -296,6 +476,7 @@ impl TrafficMonitorApp {
                     Cell::from(format!("#{}", entry.id)),
                     Cell::from(entry.timestamp.format("%H:%M:%S").to_string()),
                     Cell::from(entry.request.model.clone()).style(Style::default().fg(Color::Cyan)),
+                    Cell::from(client_label(entry)),
                     Cell::from(status).style(status_style),
                     Cell::from(tokens),
                     Cell::from(latency),
@@ -309,13 +490,14 @@ impl TrafficMonitorApp {
                 Constraint::Length(6),  // ID
                 Constraint::Length(10), // Time
                 Constraint::Min(20),    // Model
+                Constraint::Min(16),    // Client
                 Constraint::Length(5),  // Status
                 Constraint::Length(15), // Tokens
                 Constraint::Length(10), // Latency
             ],
         )
         .header(
-            Row::new(vec!["ID", "Time", "Model", "Status", "Tokens", "Latency"])
+            Row::new(vec!["ID", "Time", "Model", "Client", "Status", "Tokens", "Latency"])
                 .style(Style::default().add_modifier(Modifier::BOLD)),
         )
         .block(Block::default().borders(Borders::ALL).title("Request History"));
@@ -323,8 +505,224 @@ impl TrafficMonitorApp {
         f.render_widget(table, area);
     }
 
+    fn draw_cache_tab(&self, f: &mut Frame, area: Rect) {
+        let report = &self.snapshot.cache;
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(8),
+                Constraint::Percentage(50),
+                Constraint::Min(5),
+            ])
+            .split(area);
+
+        // Summary
+        let summary = vec![
+            Line::from(format!(
+                "Hit ratio: {:.1}%",
+                report.overall_hit_ratio * 100.0
+            )),
+            Line::from(Span::styled(
+                format!("Saved by cache reads: ${:.4}", report.saved_usd),
+                Style::default().fg(Color::Green),
+            )),
+            Line::from(format!("Cache write premium: ${:.4}", report.write_premium_usd)),
+            Line::from(Span::styled(
+                format!(
+                    "Lost to cache breaks: ${:.4} ({} breaks)",
+                    report.lost_usd,
+                    report.breaks.len()
+                ),
+                Style::default().fg(Color::Red),
+            )),
+            Line::from(Span::styled(
+                format!("Net savings: ${:.4}", report.net_savings_usd()),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            )),
+        ];
+        let summary_widget = Paragraph::new(summary)
+            .block(Block::default().borders(Borders::ALL).title("Prompt Cache"));
+        f.render_widget(summary_widget, chunks[0]);
+
+        // Per-session hit ratios
+        let mut session_rows: Vec<Row> = report
+            .sessions
+            .iter()
+            .rev()
+            .skip(self.scroll_offset)
+            .map(|s| {
+                let ratio_color = if s.hit_ratio >= 0.7 {
+                    Color::Green
+                } else if s.hit_ratio >= 0.3 {
+                    Color::Yellow
+                } else {
+                    Color::Red
+                };
+                Row::new(vec![
+                    Cell::from(s.session.clone()),
+                    Cell::from(s.requests.to_string()),
+                    Cell::from(format!("{:.1}%", s.hit_ratio * 100.0))
+                        .style(Style::default().fg(ratio_color)),
+                    Cell::from(format!("${:.4}", s.saved_usd)),
+                    Cell::from(format!("${:.4}", s.lost_usd)),
+                    Cell::from(s.breaks.to_string()),
+                ])
+            })
+            .collect();
+
+        if session_rows.is_empty() {
+            session_rows.push(Row::new(vec![Cell::from("No completed requests yet")]));
+        }
+
+        let session_table = Table::new(
+            session_rows,
+            [
+                Constraint::Length(10),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Length(7),
+            ],
+        )
+        .header(
+            Row::new(vec!["Session", "Requests", "Hit", "Saved", "Lost", "Breaks"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Sessions"));
+        f.render_widget(session_table, chunks[1]);
+
+        // Most recent cache breaks
+        let breaks: Vec<Line> = report
+            .breaks
+            .iter()
+            .rev()
+            .take(chunks[2].height.saturating_sub(2) as usize)
+            .map(|b| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("#{} ", b.request_id),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(format!(
+                        "[{}] {} - {} tokens re-sent (${:.4})",
+                        b.session, b.cause, b.tokens_lost, b.cost_usd
+                    )),
+                ])
+            })
+            .collect();
+        let breaks_widget = Paragraph::new(breaks)
+            .block(Block::default().borders(Borders::ALL).title("Cache Breaks"));
+        f.render_widget(breaks_widget, chunks[2]);
+    }
+
+    fn draw_tools_tab(&self, f: &mut Frame, area: Rect) {
+        let report = &self.snapshot.tools;
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(70), Constraint::Min(5)])
+            .split(area);
+        let tables = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[0]);
+        let limit = tables[0].height.saturating_sub(3) as usize;
+
+        // Slowest tools by average execution time
+        let mut slow_rows: Vec<Row> = report
+            .slowest(limit)
+            .into_iter()
+            .map(|t| {
+                Row::new(vec![
+                    Cell::from(t.name.clone()),
+                    Cell::from(t.calls.to_string()),
+                    Cell::from(format!("{:.0}ms", t.avg_ms)),
+                    Cell::from(format!("{}ms", t.p95_ms)),
+                    Cell::from(format!("{}ms", t.max_ms)),
+                ])
+            })
+            .collect();
+        if slow_rows.is_empty() {
+            slow_rows.push(Row::new(vec![Cell::from("No tool results yet")]));
+        }
+        let slow_table = Table::new(
+            slow_rows,
+            [
+                Constraint::Min(12),
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+            ],
+        )
+        .header(
+            Row::new(vec!["Tool", "Calls", "Avg", "P95", "Max"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Slowest Tools"));
+        f.render_widget(slow_table, tables[0]);
+
+        // Tools with the most error results
+        let mut fail_rows: Vec<Row> = report
+            .most_failing(limit)
+            .into_iter()
+            .map(|t| {
+                Row::new(vec![
+                    Cell::from(t.name.clone()),
+                    Cell::from(t.calls.to_string()),
+                    Cell::from(t.errors.to_string()).style(Style::default().fg(Color::Red)),
+                    Cell::from(format!("{:.1}%", t.error_rate() * 100.0)),
+                ])
+            })
+            .collect();
+        if fail_rows.is_empty() {
+            fail_rows.push(Row::new(vec![Cell::from("No tool errors")]));
+        }
+        let fail_table = Table::new(
+            fail_rows,
+            [
+                Constraint::Min(12),
+                Constraint::Length(6),
+                Constraint::Length(7),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(vec!["Tool", "Calls", "Errors", "Rate"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Most Failing Tools"));
+        f.render_widget(fail_table, tables[1]);
+
+        // Retry loops, most recent first
+        let loops: Vec<Line> = report
+            .retry_loops
+            .iter()
+            .rev()
+            .take(chunks[1].height.saturating_sub(2) as usize)
+            .map(|l| {
+                let started = l
+                    .started_at
+                    .map(|t| t.format("%H:%M:%S").to_string())
+                    .unwrap_or_default();
+                Line::from(vec![
+                    Span::styled(format!("{} ", started), Style::default().fg(Color::Cyan)),
+                    Span::raw(format!(
+                        "{} called {} times in a row, {} failed",
+                        l.tool, l.attempts, l.failures
+                    )),
+                ])
+            })
+            .collect();
+        let loops_widget = Paragraph::new(loops)
+            .block(Block::default().borders(Borders::ALL).title("Retry Loops"));
+        f.render_widget(loops_widget, chunks[1]);
+    }
+
     fn draw_status_bar(&self, f: &mut Frame, area: Rect) {
-        let stats = self.traffic_log.get_stats();
+        let stats = &self.snapshot.totals;
         let status = if self.paused { "PAUSED" } else { "RUNNING" };
 
         let status_line = Line::from(vec![
@@ -342,7 +740,7 @@ impl TrafficMonitorApp {
                 Style::default().fg(Color::Yellow),
             ),
             Span::raw(" | "),
-            Span::raw("q: quit  p: pause  Tab: switch  ↑↓: scroll"),
+            Span::raw("q: quit  p: pause  Tab: switch  c: client  ↑↓: scroll"),
         ]);
 
         let status_widget = Paragraph::new(status_line)
@@ -383,3 +781,124 @@ pub async fn run_traffic_monitor(
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_id::ClientInfo;
+    use crate::mock_server::{self, MockConfig};
+    use crate::proxy::{MitmProxy, ProxyConfig};
+    use ratatui::backend::TestBackend;
+    use tokio::net::TcpListener;
+
+    fn render(app: &TrafficMonitorApp) -> String {
+        let mut terminal = ratatui::Terminal::new(TestBackend::new(140, 40)).unwrap();
+        terminal.draw(|f| app.draw(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_monitor_shows_proxied_traffic() {
+        let mock = mock_server::start(MockConfig {
+            listen_addr: "127.0.0.1:0".parse().unwrap(),
+            error_every: Some(2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://{}/api/v1/messages", listener.local_addr().unwrap());
+        let traffic_log = TrafficLog::new();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let proxy = MitmProxy::new(
+            ProxyConfig {
+                upstream: mock.url(),
+                ..Default::default()
+            },
+            traffic_log.clone(),
+            event_tx,
+        );
+        tokio::spawn(async move { proxy.serve(listener).await });
+
+        let client = reqwest::Client::new();
+        for _ in 0..2 {
+            client
+                .post(&proxy_url)
+                .header("user-agent", "claude-cli/2.0.0 (external, cli)")
+                .json(&serde_json::json!({
+                    "model": "claude-haiku-4-5",
+                    "max_tokens": 10,
+                    "messages": [{ "role": "user", "content": "ping" }],
+                }))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let mut app = TrafficMonitorApp::new(traffic_log, event_rx);
+        while app.recent_events.len() < 4 {
+            let event = app.event_rx.recv().await.unwrap();
+            app.handle_proxy_event(event);
+        }
+        app.refresh();
+        let live = render(&app);
+        assert!(live.contains("#1 done"));
+        assert!(live.contains("#2 ERROR: HTTP 529"));
+
+        app.handle_key(KeyCode::Char('3'));
+        let history = render(&app);
+        assert!(history.contains("claude-haiku-4-5"));
+        assert!(history.contains("claude-cli"));
+
+        // Switching to the only client keeps both requests
+        app.handle_key(KeyCode::Char('c'));
+        assert_eq!(app.snapshot.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_client_tab_filters_every_pane() {
+        let traffic_log = TrafficLog::new();
+        let request = crate::traffic::parse_request(
+            r#"{"model":"claude-haiku-4-5","messages":[{"role":"user","content":"hi"}]}"#,
+        )
+        .unwrap();
+        let response = crate::traffic::parse_response(
+            r#"{"model":"claude-haiku-4-5","content":[],"usage":{"input_tokens":1200,"output_tokens":34}}"#,
+        )
+        .unwrap();
+        let session = |name: &str| ClientInfo {
+            session: Some(name.to_string()),
+            ..Default::default()
+        };
+
+        let alpha = traffic_log.start_request(request.clone(), Some(session("alpha")));
+        traffic_log.complete_request(alpha, response, 250);
+        traffic_log.start_request(request, Some(session("beta")));
+
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        let mut app = TrafficMonitorApp::new(traffic_log.clone(), event_rx);
+        app.refresh();
+        assert_eq!(app.snapshot.stats.total_requests, 2);
+        assert!(render(&app).contains("beta (1)"));
+
+        // An unchanged log keeps the snapshot
+        let generation = app.snapshot.generation;
+        app.refresh();
+        assert_eq!(app.snapshot.generation, generation);
+
+        app.handle_key(KeyCode::Char('C'));
+        assert_eq!(app.snapshot.entries.len(), 1);
+        assert_eq!(app.snapshot.stats.total_requests, 1);
+        assert_eq!(app.snapshot.stats.total_input_tokens, 0);
+        assert!(render(&app).contains("Requests: 1"));
+
+        app.handle_key(KeyCode::Char('C'));
+        app.handle_key(KeyCode::Char('2'));
+        let stats = render(&app);
+        assert!(stats.contains("Input:  1200"));
+        assert!(!stats.contains("beta  "));
+        assert_eq!(app.snapshot.totals.total_requests, 2);
+    }
+}